@@ -0,0 +1,95 @@
+use anyhow::{Context, Result, bail};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Writes entries one at a time into a tar, gzipped tar or zip archive so nothing but the
+/// current entry is held in memory.
+pub enum ArchiveWriter {
+    Tar(tar::Builder<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+    Zip(ZipWriter<BufWriter<File>>),
+}
+
+impl ArchiveWriter {
+    /// Creates the archive at `path`, picking the format from its extension
+    /// (`.tar`, `.tar.gz`/`.tgz` or `.zip`).
+    pub fn create(path: &Path) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let make_file = || -> Result<BufWriter<File>> {
+            let file = File::create(path)
+                .with_context(|| format!("Could not create archive {:?}", path))?;
+            Ok(BufWriter::new(file))
+        };
+
+        if name.ends_with(".tar") {
+            Ok(ArchiveWriter::Tar(tar::Builder::new(make_file()?)))
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            let encoder = GzEncoder::new(make_file()?, Compression::default());
+            Ok(ArchiveWriter::TarGz(tar::Builder::new(encoder)))
+        } else if name.ends_with(".zip") {
+            Ok(ArchiveWriter::Zip(ZipWriter::new(make_file()?)))
+        } else {
+            bail!(
+                "Unsupported archive extension for {:?}, expected .tar, .tar.gz, .tgz or .zip",
+                path
+            )
+        }
+    }
+
+    /// Appends a regular file named `name` (a `/` separated path inside the archive)
+    pub fn append(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        match self {
+            ArchiveWriter::Tar(builder) => append_tar(builder, name, data),
+            ArchiveWriter::TarGz(builder) => append_tar(builder, name, data),
+            ArchiveWriter::Zip(zip) => {
+                // images are already compressed, only deflate everything else
+                let method = if name.ends_with(".json") {
+                    CompressionMethod::Deflated
+                } else {
+                    CompressionMethod::Stored
+                };
+                let options = SimpleFileOptions::default()
+                    .compression_method(method)
+                    .large_file(data.len() as u64 >= u32::MAX as u64);
+                zip.start_file(name, options)?;
+                zip.write_all(data)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the archive trailer and flushes everything to disk
+    pub fn finish(self) -> Result<()> {
+        match self {
+            ArchiveWriter::Tar(builder) => builder.into_inner()?.flush()?,
+            ArchiveWriter::TarGz(builder) => builder.into_inner()?.finish()?.flush()?,
+            ArchiveWriter::Zip(zip) => zip.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn append_tar<W: Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    builder
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("Could not append {} to archive", name))
+}
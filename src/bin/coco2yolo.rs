@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use cococrawl::export::{YoloExportOptions, to_yolo};
+use cococrawl::progress::progress_bar;
+use cococrawl::{CocoFile, CocoImage};
+use indicatif::ParallelProgressIterator;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ImageMode {
+    /// Copy the image files
+    Copy,
+    /// Symlink the original image files
+    Symlink,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// Dataset directory to write labels/, data.yaml and optionally images/ into
+    #[clap(short, long, default_value = "yolo")]
+    output: PathBuf,
+
+    /// Also put the images into images/, copied or as symlinks to the originals
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "copy")]
+    copy_images: Option<ImageMode>,
+
+    /// Export crowd annotations too, they are skipped by default
+    #[clap(long)]
+    include_crowd: bool,
+
+    /// Write normalized polygons for YOLO-seg instead of boxes
+    #[clap(long)]
+    segmentation: bool,
+
+    /// Don't draw progress bars
+    #[clap(short, long)]
+    quiet: bool,
+}
+
+#[cfg(unix)]
+fn symlink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(src, dest)
+}
+
+#[cfg(windows)]
+fn symlink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(src, dest)
+}
+
+/// Puts the images into `images_dir` under their own file names, so they keep the stems of
+/// their label files. [`to_yolo`] already made sure no two of them share a stem.
+fn export_images(
+    images: &[&CocoImage],
+    args: &Args,
+    mode: ImageMode,
+    images_dir: &Path,
+) -> Result<()> {
+    fs::create_dir_all(images_dir).with_context(|| format!("Could not create {:?}", images_dir))?;
+
+    images
+        .par_iter()
+        .progress_with(progress_bar(images.len() as u64, args.quiet))
+        .try_for_each(|image| {
+            let src = image.get_absolute_path(&args.coco_file)?;
+            let dest = images_dir.join(image.file_name.file_name().unwrap());
+            match mode {
+                ImageMode::Copy => fs::copy(&src, &dest).map(|_| ()),
+                ImageMode::Symlink => {
+                    let src = fs::canonicalize(&src)
+                        .with_context(|| format!("Could not resolve {:?}", src))?;
+                    if dest.symlink_metadata().is_ok() {
+                        fs::remove_file(&dest)
+                            .with_context(|| format!("Could not replace {:?}", dest))?;
+                    }
+                    symlink(&src, &dest)
+                }
+            }
+            .with_context(|| format!("Could not put {:?} at {:?}", src, dest))
+        })
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    let labels = to_yolo(
+        &coco_file,
+        &YoloExportOptions {
+            include_crowd: args.include_crowd,
+            segmentation: args.segmentation,
+        },
+    )?;
+    labels.skipped_images.iter().for_each(|image_id| {
+        eprintln!(
+            "Warning: skipping image id {} with zero width or height",
+            image_id
+        );
+    });
+
+    labels.write(&args.output.join("labels"))?;
+    let images_dir = args.copy_images.map(|_| Path::new("images"));
+    labels.write_data_yaml(&args.output, images_dir)?;
+
+    if let Some(mode) = args.copy_images {
+        let images: HashMap<i64, &CocoImage> = coco_file
+            .images
+            .iter()
+            .map(|image| (image.id, image))
+            .collect();
+        let exported: Vec<&CocoImage> = labels
+            .files
+            .iter()
+            .map(|file| images[&file.image_id])
+            .collect();
+        export_images(&exported, &args, mode, &args.output.join("images"))?;
+    }
+
+    println!(
+        "Wrote {} label file(s) with {} class(es) to {}",
+        labels.files.len(),
+        labels.classes.len(),
+        args.output.display()
+    );
+    println!(
+        "Skipped {} image(s) and {} annotation(s) that couldn't be exported",
+        labels.skipped_images.len(),
+        labels.skipped_annotations
+    );
+    Ok(())
+}
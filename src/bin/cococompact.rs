@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cococrawl::{CocoAnnotation, CocoFile};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "compacted.json")]
+    output: PathBuf,
+
+    /// First new image id
+    #[clap(long, default_value_t = 1)]
+    start_image_id: i64,
+
+    /// First new annotation id, panoptic segments are numbered along with the annotations
+    #[clap(long, default_value_t = 1)]
+    start_annotation_id: i64,
+
+    /// First new category id
+    #[clap(long, default_value_t = 1)]
+    start_category_id: i32,
+
+    /// Also write the old to new id mappings to this JSON file
+    #[clap(long)]
+    remap_output: Option<PathBuf>,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let mut coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    let report = coco_file.compact_ids(
+        args.start_image_id,
+        args.start_annotation_id,
+        args.start_category_id,
+    );
+    if coco_file
+        .annotations
+        .iter()
+        .any(|annotation| matches!(annotation, CocoAnnotation::PanopticSegmentation(_)))
+    {
+        eprintln!(
+            "Warning: panoptic segment ids were renumbered, the segment PNGs no longer match them"
+        );
+    }
+
+    coco_file.to_path(&args.output, !args.compact)?;
+    if let Some(remap_output) = &args.remap_output {
+        let remap_json = if args.compact {
+            serde_json::to_string(&report)?
+        } else {
+            serde_json::to_string_pretty(&report)?
+        };
+        fs::write(remap_output, remap_json)
+            .with_context(|| format!("Could not write {}", remap_output.display()))?;
+    }
+
+    println!(
+        "Renumbered {} images, {} annotations and {} categories, wrote {}",
+        coco_file.images.len(),
+        report.annotations.len(),
+        report.categories.len(),
+        args.output.display()
+    );
+    Ok(())
+}
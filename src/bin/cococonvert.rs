@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Parser, ValueEnum};
+use cococrawl::export::to_yolo_labels;
+use cococrawl::import::{from_yolo_labels, read_yolo_classes};
+use cococrawl::{CocoFile, CrawlOptions, crawl_directories};
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ConvertFormat {
+    Yolo,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[clap(group(ArgGroup::new("direction").required(true).args(["to", "from"])))]
+struct Args {
+    /// coco JSON file path to convert from (with --to)
+    #[clap(required_unless_present = "from", conflicts_with = "from")]
+    coco_file: Option<PathBuf>,
+
+    /// format to convert the dataset to
+    #[clap(long, value_enum)]
+    to: Option<ConvertFormat>,
+
+    /// format to convert into a COCO file
+    /// cococonvert --from yolo --images-dir imgs/ --labels-dir labels/ --classes classes.txt
+    #[clap(long, value_enum, requires_all = ["images_dir", "labels_dir", "classes"])]
+    from: Option<ConvertFormat>,
+
+    /// directory of images to crawl (with --from)
+    #[clap(long)]
+    images_dir: Option<PathBuf>,
+
+    /// directory of label files matched to images by file stem (with --from)
+    #[clap(long)]
+    labels_dir: Option<PathBuf>,
+
+    /// file with one class name per line, in class index order (with --from)
+    #[clap(long)]
+    classes: Option<PathBuf>,
+
+    /// output path, the labels directory with --to (default: labels) or the COCO JSON file
+    /// with --from (default: coco.json)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Force absolute paths for image file names when importing.
+    #[clap(short, long)]
+    absolute_paths: bool,
+
+    /// Write compact COCO JSON when importing without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match (args.to, args.from) {
+        (Some(ConvertFormat::Yolo), _) => export_yolo(&args),
+        (None, Some(ConvertFormat::Yolo)) => import_yolo(&args),
+        (None, None) => unreachable!("clap requires --to or --from"),
+    }
+}
+
+fn export_yolo(args: &Args) -> Result<()> {
+    let coco_file_path = args
+        .coco_file
+        .as_ref()
+        .expect("clap requires a COCO file with --to");
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("labels"));
+
+    let coco_json = fs::read_to_string(coco_file_path).context("Could not read COCO JSON file")?;
+    let coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    let labels = to_yolo_labels(&coco_file)?;
+    labels.skipped_images.iter().for_each(|image_id| {
+        eprintln!(
+            "Warning: skipping image id {} with zero width or height",
+            image_id
+        );
+    });
+    labels.write(&output)?;
+
+    println!(
+        "Wrote {} label file(s) with {} class(es) to {}",
+        labels.files.len(),
+        labels.classes.len(),
+        output.display()
+    );
+    println!(
+        "Skipped {} image(s) and {} annotation(s) that couldn't be exported",
+        labels.skipped_images.len(),
+        labels.skipped_annotations
+    );
+
+    Ok(())
+}
+
+fn import_yolo(args: &Args) -> Result<()> {
+    // clap enforces these alongside --from
+    let images_dir = args.images_dir.as_deref().unwrap();
+    let labels_dir = args.labels_dir.as_deref().unwrap();
+    let classes = read_yolo_classes(args.classes.as_deref().unwrap())?;
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("coco.json"));
+
+    let output_file = File::create(&output).context("Could not create output file")?;
+
+    let report = crawl_directories(
+        &[images_dir.to_path_buf()],
+        CrawlOptions {
+            output_path: output.clone(),
+            absolute_paths: args.absolute_paths,
+            ..CrawlOptions::default()
+        },
+    )?;
+    report.failures.iter().for_each(|failure| {
+        eprintln!("Warning: skipping {}", failure);
+    });
+
+    let coco_file = from_yolo_labels(report.images, labels_dir, &classes)?;
+    println!(
+        "Imported {} image(s) with {} annotation(s) into {}",
+        coco_file.images.len(),
+        coco_file.annotations.len(),
+        output.display()
+    );
+
+    coco_file
+        .to_writer(BufWriter::new(output_file), !args.compact)
+        .context("Could not write JSON to output file")?;
+
+    Ok(())
+}
@@ -1,71 +1,997 @@
-use clap::Parser;
-use cococrawl::{CocoAnnotation, CocoCategory};
-use serde_json;
+use clap::{Parser, ValueEnum};
+use cococrawl::export::csv_field;
+use cococrawl::validation::ValidationIssue;
+use cococrawl::{
+    CocoAnnotation, CocoCategory, CocoFile, DuplicateImageIdError, HasID, LvisFrequency,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Text,
+    /// an array with one object per file
+    Json,
+    /// one row per category and a totals row for each file
+    Csv,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// coco JSON file path
+    /// coco JSON file paths
     #[clap(required = true)]
-    coco_file: PathBuf,
+    coco_files: Vec<PathBuf>,
+
+    /// Check for duplicate ids, annotations referencing missing images or categories, bboxes
+    /// outside image bounds and malformed keypoints.
+    /// Exits with a non-zero status if any error-level issues are found.
+    #[clap(long)]
+    check: bool,
+
+    /// Only list the first N rows of the per category table
+    #[clap(long)]
+    top: Option<usize>,
+
+    /// Order of the per category table
+    #[clap(long, value_enum, default_value = "count")]
+    sort: CategorySort,
+
+    /// Output format
+    #[clap(short, long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Count annotations in the COCO small (< 32²), medium and large (>= 96²) area buckets,
+    /// overall and per category, and summarize image resolutions
+    #[clap(long)]
+    size_stats: bool,
+
+    /// Report unannotated images, annotations of missing images and how annotations are
+    /// spread over images
+    #[clap(long)]
+    coverage: bool,
+
+    /// Count visible, occluded and missing keypoints per keypoint name, flag keypoint
+    /// annotations of the wrong length and count crowd annotations
+    #[clap(long)]
+    keypoints: bool,
 }
 
-fn main() {
-    let args = Args::parse();
+/// Upper area bounds of the COCO small and medium object buckets
+const SMALL_AREA: f64 = 32.0 * 32.0;
+const MEDIUM_AREA: f64 = 96.0 * 96.0;
 
-    let coco_json = fs::read_to_string(&args.coco_file).expect("Could not read COCO JSON file");
-    let coco_json_file_name = args.coco_file.file_name().unwrap().to_string_lossy();
-    let coco_file: cococrawl::CocoFile =
-        serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
+/// Upper bounds of the width / height histogram bins, the last bin is unbounded
+const ASPECT_RATIO_BINS: [(f64, &str); 5] = [
+    (0.5, "<0.5"),
+    (0.8, "0.5-0.8"),
+    (1.25, "0.8-1.25"),
+    (2.0, "1.25-2"),
+    (f64::INFINITY, ">=2"),
+];
 
-    // Iterate over images and copy them to the output directory
-    let images_count = coco_file.images.len() as u64;
+/// Inclusive (min, max) annotation counts of the annotations per image histogram bins
+const ANNOTATIONS_PER_IMAGE_BINS: [(usize, usize, &str); 5] = [
+    (0, 0, "0"),
+    (1, 1, "1"),
+    (2, 5, "2-5"),
+    (6, 10, "6-10"),
+    (11, usize::MAX, "11+"),
+];
 
-    let annotations_count = coco_file.annotations.len() as u64;
-    let annotation_counts: &mut [u64] = &mut [0; 5];
-    coco_file
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CategorySort {
+    /// most annotations first
+    Count,
+    Name,
+    Id,
+}
+
+#[derive(Serialize, Default)]
+struct AnnotationCounts {
+    total: u64,
+    object_detection: u64,
+    keypoint_detection: u64,
+    panoptic_segmentation: u64,
+    image_captioning: u64,
+    dense_pose: u64,
+}
+
+#[derive(Serialize, Default)]
+struct CategoryTypeCounts {
+    total: u64,
+    object_detection: u64,
+    panoptic_segmentation: u64,
+    keypoint_detection: u64,
+}
+
+#[derive(Serialize, Default)]
+struct IssueCounts {
+    total: u64,
+    duplicate_image_ids: u64,
+    duplicate_annotation_ids: u64,
+    dangling_annotations: u64,
+    dangling_category_ids: u64,
+    non_panoptic_segment_categories: u64,
+    out_of_bounds_bboxes: u64,
+    keypoint_length_mismatches: u64,
+}
+
+/// Annotation and image counts for one category. `id` is `None` for the bucket of
+/// annotations referencing categories missing from the categories array.
+#[derive(Serialize)]
+struct CategoryCount {
+    id: Option<i32>,
+    name: String,
+    annotations: u64,
+    images: u64,
+}
+
+#[derive(Serialize)]
+struct FileCounts {
+    file: String,
+    images: u64,
+    annotations: AnnotationCounts,
+    categories: CategoryTypeCounts,
+    per_category: Vec<CategoryCount>,
+    /// categories left out of `per_category` by --top
+    #[serde(skip)]
+    hidden_categories: usize,
+    /// only for LVIS files, whose categories have a frequency
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_groups: Option<FrequencyGroups>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issues: Option<IssueCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_stats: Option<SizeStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coverage: Option<Coverage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keypoints: Option<KeypointStats>,
+}
+
+#[derive(Serialize, Default)]
+struct FrequencyGroupCounts {
+    categories: u64,
+    annotations: u64,
+}
+
+/// Categories and annotations of the LVIS rare, common and frequent groups
+#[derive(Serialize, Default)]
+struct FrequencyGroups {
+    rare: FrequencyGroupCounts,
+    common: FrequencyGroupCounts,
+    frequent: FrequencyGroupCounts,
+}
+
+impl FrequencyGroups {
+    fn group_mut(&mut self, frequency: LvisFrequency) -> &mut FrequencyGroupCounts {
+        match frequency {
+            LvisFrequency::Rare => &mut self.rare,
+            LvisFrequency::Common => &mut self.common,
+            LvisFrequency::Frequent => &mut self.frequent,
+        }
+    }
+}
+
+/// Keypoints of one name by their visibility flag
+#[derive(Serialize)]
+struct KeypointVisibility {
+    name: String,
+    /// v = 2
+    visible: u64,
+    /// v = 1
+    occluded: u64,
+    /// v = 0
+    missing: u64,
+}
+
+#[derive(Serialize)]
+struct CategoryKeypoints {
+    id: i32,
+    name: String,
+    keypoints: Vec<KeypointVisibility>,
+}
+
+#[derive(Serialize, Default)]
+struct CrowdCounts {
+    crowd: u64,
+    total: u64,
+}
+
+#[derive(Serialize, Default)]
+struct AnnotationCrowdCounts {
+    object_detection: CrowdCounts,
+    keypoint_detection: CrowdCounts,
+    dense_pose: CrowdCounts,
+}
+
+#[derive(Serialize, Default)]
+struct KeypointStats {
+    per_category: Vec<CategoryKeypoints>,
+    /// ids of keypoint annotations without 3 values per keypoint of their category, their
+    /// keypoints aren't counted
+    length_mismatches: Vec<i64>,
+    /// keypoint annotations whose category is missing or isn't a keypoint category
+    unknown_category: u64,
+    /// keypoints with a visibility flag other than 0, 1 or 2
+    invalid_visibility: u64,
+    crowd: AnnotationCrowdCounts,
+}
+
+/// Middle value of `sorted`, the mean of the two middle values for even lengths
+fn median(sorted: &[f64]) -> Option<f64> {
+    let count = sorted.len();
+    match count {
+        0 => None,
+        _ if count.is_multiple_of(2) => Some((sorted[count / 2 - 1] + sorted[count / 2]) / 2.0),
+        _ => Some(sorted[count / 2]),
+    }
+}
+
+#[derive(Serialize)]
+struct AnnotationsPerImage {
+    min: usize,
+    median: f64,
+    mean: f64,
+    max: usize,
+}
+
+#[derive(Serialize)]
+struct HistogramBin {
+    annotations: &'static str,
+    images: u64,
+}
+
+#[derive(Serialize)]
+struct Coverage {
+    unannotated_images: u64,
+    unannotated_percent: f64,
+    /// annotations whose image id doesn't match any image
+    dangling_annotations: u64,
+    /// `None` without images
+    annotations_per_image: Option<AnnotationsPerImage>,
+    histogram: Vec<HistogramBin>,
+}
+
+/// Count and mean/median area of the annotations in one size bucket, the averages are `None`
+/// for empty buckets
+#[derive(Serialize)]
+struct AreaStats {
+    count: u64,
+    mean_area: Option<f64>,
+    median_area: Option<f64>,
+}
+
+impl AreaStats {
+    fn new(mut areas: Vec<f64>) -> Self {
+        areas.sort_by(f64::total_cmp);
+        let count = areas.len();
+        AreaStats {
+            count: count as u64,
+            mean_area: (count > 0).then(|| areas.iter().sum::<f64>() / count as f64),
+            median_area: median(&areas),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SizeBuckets {
+    small: AreaStats,
+    medium: AreaStats,
+    large: AreaStats,
+}
+
+impl SizeBuckets {
+    fn new(areas: impl IntoIterator<Item = f64>) -> Self {
+        let (mut small, mut medium, mut large) = (Vec::new(), Vec::new(), Vec::new());
+        for area in areas {
+            if area < SMALL_AREA {
+                small.push(area);
+            } else if area < MEDIUM_AREA {
+                medium.push(area);
+            } else {
+                large.push(area);
+            }
+        }
+        SizeBuckets {
+            small: AreaStats::new(small),
+            medium: AreaStats::new(medium),
+            large: AreaStats::new(large),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CategorySizeStats {
+    id: Option<i32>,
+    name: String,
+    #[serde(flatten)]
+    buckets: SizeBuckets,
+}
+
+#[derive(Serialize)]
+struct AspectRatioCount {
+    /// width / height range
+    range: &'static str,
+    images: u64,
+}
+
+#[derive(Serialize)]
+struct ResolutionStats {
+    min_width: u32,
+    max_width: u32,
+    mean_width: f64,
+    min_height: u32,
+    max_height: u32,
+    mean_height: f64,
+    /// images with a zero dimension are left out
+    aspect_ratios: Vec<AspectRatioCount>,
+}
+
+#[derive(Serialize)]
+struct SizeStats {
+    overall: SizeBuckets,
+    per_category: Vec<CategorySizeStats>,
+    /// `None` without images
+    resolution: Option<ResolutionStats>,
+}
+
+/// Counts annotations per category, with every segment of a panoptic annotation counted
+/// separately. Categories without annotations are left out.
+fn count_categories(coco_file: &CocoFile) -> Vec<CategoryCount> {
+    let category_map = coco_file.make_category_map();
+    let mut counts: HashMap<Option<i32>, (CategoryCount, HashSet<i64>)> = HashMap::new();
+    for annotation in &coco_file.annotations {
+        for category_id in annotation.category_ids() {
+            let category = category_map.get(&category_id);
+            let key = category.map(|category| category.id());
+            let (count, images) = counts.entry(key).or_insert_with(|| {
+                let count = CategoryCount {
+                    id: key,
+                    name: category
+                        .map_or("unknown", |category| category.name())
+                        .to_string(),
+                    annotations: 0,
+                    images: 0,
+                };
+                (count, HashSet::new())
+            });
+            count.annotations += 1;
+            images.insert(annotation.image_id());
+        }
+    }
+    counts
+        .into_values()
+        .map(|(count, images)| CategoryCount {
+            images: images.len() as u64,
+            ..count
+        })
+        .collect()
+}
+
+/// `None` unless some category has an LVIS frequency, annotations of categories without one
+/// aren't counted
+fn frequency_groups(coco_file: &CocoFile) -> Option<FrequencyGroups> {
+    let frequencies: HashMap<i32, LvisFrequency> = coco_file
+        .categories
+        .iter()
+        .flatten()
+        .filter_map(|category| match category {
+            CocoCategory::ObjectDetection(cat) => {
+                cat.frequency.map(|frequency| (cat.id, frequency))
+            }
+            _ => None,
+        })
+        .collect();
+    if frequencies.is_empty() {
+        return None;
+    }
+
+    let mut groups = FrequencyGroups::default();
+    frequencies
+        .values()
+        .for_each(|&frequency| groups.group_mut(frequency).categories += 1);
+    for annotation in &coco_file.annotations {
+        for category_id in annotation.category_ids() {
+            if let Some(&frequency) = frequencies.get(&category_id) {
+                groups.group_mut(frequency).annotations += 1;
+            }
+        }
+    }
+    Some(groups)
+}
+
+/// (category id, area) of every annotation that has an area, one per panoptic segment
+fn annotation_areas(annotation: &CocoAnnotation) -> Vec<(i32, f64)> {
+    match annotation {
+        CocoAnnotation::ObjectDetection(ann) => vec![(ann.category_id, f64::from(ann.area))],
+        CocoAnnotation::KeypointDetection(ann) => vec![(ann.category_id, f64::from(ann.area))],
+        CocoAnnotation::DensePose(ann) => vec![(ann.category_id, f64::from(ann.area))],
+        CocoAnnotation::PanopticSegmentation(ann) => ann
+            .segments_info
+            .iter()
+            .map(|segment| (segment.category_id, f64::from(segment.area)))
+            .collect(),
+        CocoAnnotation::ImageCaptioning(_) => Vec::new(),
+    }
+}
+
+fn size_stats(coco_file: &CocoFile) -> SizeStats {
+    let areas: Vec<(i32, f64)> = coco_file
         .annotations
         .iter()
-        .for_each(|annotation: &CocoAnnotation| match *annotation {
-            CocoAnnotation::ObjectDetection(_) => annotation_counts[0] += 1,
-            CocoAnnotation::KeypointDetection(_) => annotation_counts[1] += 1,
-            CocoAnnotation::PanopticSegmentation(_) => annotation_counts[2] += 1,
-            CocoAnnotation::ImageCaptioning(_) => annotation_counts[3] += 1,
-            CocoAnnotation::DensePose(_) => annotation_counts[4] += 1,
-        });
+        .flat_map(annotation_areas)
+        .collect();
+
+    // annotations of missing categories are bucketed together, like in the category table
+    let category_map = coco_file.make_category_map();
+    let mut category_areas: HashMap<Option<i32>, Vec<f64>> = HashMap::new();
+    for &(category_id, area) in &areas {
+        let key = category_map
+            .contains_key(&category_id)
+            .then_some(category_id);
+        category_areas.entry(key).or_default().push(area);
+    }
+    let mut per_category: Vec<CategorySizeStats> = category_areas
+        .into_iter()
+        .map(|(id, areas)| CategorySizeStats {
+            id,
+            name: id
+                .map_or("unknown", |id| category_map[&id].name())
+                .to_string(),
+            buckets: SizeBuckets::new(areas),
+        })
+        .collect();
+    per_category.sort_by_key(|stats| (stats.id.is_none(), stats.id));
 
-    let categories_count: &mut [u64] = &mut [0; 3];
-    let category_count = coco_file.categories.clone().unwrap_or_default().len() as u64;
+    SizeStats {
+        overall: SizeBuckets::new(areas.iter().map(|&(_, area)| area)),
+        per_category,
+        resolution: resolution_stats(coco_file),
+    }
+}
+
+fn resolution_stats(coco_file: &CocoFile) -> Option<ResolutionStats> {
+    let images = &coco_file.images;
+    let mean = |total: u64| total as f64 / images.len() as f64;
+    let mut aspect_ratios: Vec<AspectRatioCount> = ASPECT_RATIO_BINS
+        .iter()
+        .map(|&(_, range)| AspectRatioCount { range, images: 0 })
+        .collect();
+    for image in images
+        .iter()
+        .filter(|image| image.width > 0 && image.height > 0)
+    {
+        let ratio = f64::from(image.width) / f64::from(image.height);
+        let bin = ASPECT_RATIO_BINS
+            .iter()
+            .position(|&(upper, _)| ratio < upper)
+            .unwrap();
+        aspect_ratios[bin].images += 1;
+    }
+
+    Some(ResolutionStats {
+        min_width: images.iter().map(|image| image.width).min()?,
+        max_width: images.iter().map(|image| image.width).max()?,
+        mean_width: mean(images.iter().map(|image| u64::from(image.width)).sum()),
+        min_height: images.iter().map(|image| image.height).min()?,
+        max_height: images.iter().map(|image| image.height).max()?,
+        mean_height: mean(images.iter().map(|image| u64::from(image.height)).sum()),
+        aspect_ratios,
+    })
+}
+
+/// Fails if several images share an id, since their annotations can't be told apart
+fn coverage(coco_file: &CocoFile) -> Result<Coverage, DuplicateImageIdError> {
+    let image_id_map = coco_file.make_image_id_map_quiet()?;
+    let dangling_annotations = coco_file.dangling_annotations(&image_id_map).len() as u64;
+
+    let mut per_image: Vec<usize> = image_id_map
+        .values()
+        .map(|entry| entry.annotations.len())
+        .collect();
+    per_image.sort();
+
+    let unannotated_images = per_image.iter().filter(|&&count| count == 0).count() as u64;
+    let histogram = ANNOTATIONS_PER_IMAGE_BINS
+        .iter()
+        .map(|&(min, max, annotations)| HistogramBin {
+            annotations,
+            images: per_image
+                .iter()
+                .filter(|&&count| (min..=max).contains(&count))
+                .count() as u64,
+        })
+        .collect();
+    let sorted: Vec<f64> = per_image.iter().map(|&count| count as f64).collect();
+    let annotations_per_image = median(&sorted).map(|median| AnnotationsPerImage {
+        min: per_image[0],
+        median,
+        mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        max: per_image[per_image.len() - 1],
+    });
+
+    Ok(Coverage {
+        unannotated_images,
+        unannotated_percent: if per_image.is_empty() {
+            0.0
+        } else {
+            unannotated_images as f64 * 100.0 / per_image.len() as f64
+        },
+        dangling_annotations,
+        annotations_per_image,
+        histogram,
+    })
+}
+
+fn keypoint_stats(coco_file: &CocoFile) -> KeypointStats {
+    let category_map = coco_file.make_category_map();
+    let mut stats = KeypointStats::default();
+    let mut per_category: BTreeMap<i32, CategoryKeypoints> = BTreeMap::new();
+
+    for annotation in &coco_file.annotations {
+        let (crowd_counts, iscrowd) = match annotation {
+            CocoAnnotation::ObjectDetection(ann) => {
+                (&mut stats.crowd.object_detection, ann.iscrowd)
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                (&mut stats.crowd.keypoint_detection, ann.iscrowd)
+            }
+            CocoAnnotation::DensePose(ann) => (&mut stats.crowd.dense_pose, ann.iscrowd),
+            _ => continue,
+        };
+        crowd_counts.total += 1;
+        crowd_counts.crowd += u64::from(iscrowd);
+
+        let CocoAnnotation::KeypointDetection(ann) = annotation else {
+            continue;
+        };
+        let Some(CocoCategory::KeypointDetection(category)) = category_map.get(&ann.category_id)
+        else {
+            stats.unknown_category += 1;
+            continue;
+        };
+        if ann.keypoints.len() != 3 * category.keypoints.len() {
+            stats.length_mismatches.push(ann.id);
+            continue;
+        }
+
+        let counts = per_category
+            .entry(category.id)
+            .or_insert_with(|| CategoryKeypoints {
+                id: category.id,
+                name: category.name.clone(),
+                keypoints: category
+                    .keypoints
+                    .iter()
+                    .map(|name| KeypointVisibility {
+                        name: name.clone(),
+                        visible: 0,
+                        occluded: 0,
+                        missing: 0,
+                    })
+                    .collect(),
+            });
+        for (keypoint, triplet) in counts.keypoints.iter_mut().zip(ann.keypoints.chunks(3)) {
+            match triplet[2] {
+                2.0 => keypoint.visible += 1,
+                1.0 => keypoint.occluded += 1,
+                0.0 => keypoint.missing += 1,
+                _ => stats.invalid_visibility += 1,
+            }
+        }
+    }
+
+    stats.per_category = per_category.into_values().collect();
+    stats
+}
+
+fn sort_categories(counts: &mut [CategoryCount], sort: CategorySort) {
+    match sort {
+        CategorySort::Count => counts.sort_by(|a, b| {
+            b.annotations
+                .cmp(&a.annotations)
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        CategorySort::Name => counts.sort_by(|a, b| a.name.cmp(&b.name)),
+        // unknown categories go last
+        CategorySort::Id => counts.sort_by_key(|count| (count.id.is_none(), count.id)),
+    }
+}
+
+fn count_issues(issues: &[ValidationIssue]) -> IssueCounts {
+    let mut counts = IssueCounts {
+        total: issues.len() as u64,
+        ..IssueCounts::default()
+    };
+    issues.iter().for_each(|issue| match issue {
+        ValidationIssue::DuplicateImageId { .. } => counts.duplicate_image_ids += 1,
+        ValidationIssue::DuplicateAnnotationId { .. } => counts.duplicate_annotation_ids += 1,
+        ValidationIssue::DanglingImageId { .. } => counts.dangling_annotations += 1,
+        ValidationIssue::DanglingCategoryId { .. } => counts.dangling_category_ids += 1,
+        ValidationIssue::NonPanopticCategory { .. } => counts.non_panoptic_segment_categories += 1,
+        ValidationIssue::BboxOutOfBounds { .. } => counts.out_of_bounds_bboxes += 1,
+        ValidationIssue::KeypointLengthMismatch { .. } => counts.keypoint_length_mismatches += 1,
+    });
+    counts
+}
+
+fn count_file(path: &PathBuf, args: &Args) -> FileCounts {
+    let coco_json = fs::read_to_string(path).expect("Could not read COCO JSON file");
+    let coco_file: CocoFile = serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
+
+    let mut annotations = AnnotationCounts {
+        total: coco_file.annotations.len() as u64,
+        ..AnnotationCounts::default()
+    };
     coco_file
-        .categories
-        .unwrap_or_default()
+        .annotations
         .iter()
-        .for_each(|category| match category {
-            CocoCategory::ObjectDetection(_) => categories_count[0] += 1,
-            CocoCategory::PanopticSegmentation(_) => categories_count[1] += 1,
-            CocoCategory::KeypointDetection(_) => categories_count[2] += 1,
+        .for_each(|annotation| match annotation {
+            CocoAnnotation::ObjectDetection(_) => annotations.object_detection += 1,
+            CocoAnnotation::KeypointDetection(_) => annotations.keypoint_detection += 1,
+            CocoAnnotation::PanopticSegmentation(_) => annotations.panoptic_segmentation += 1,
+            CocoAnnotation::ImageCaptioning(_) => annotations.image_captioning += 1,
+            CocoAnnotation::DensePose(_) => annotations.dense_pose += 1,
         });
 
-    println!("Coco File: {}", coco_json_file_name);
-    println!("Images: {}", images_count);
-    println!("Annotations: {}", annotations_count);
+    let mut categories = CategoryTypeCounts::default();
+    coco_file.categories.iter().flatten().for_each(|category| {
+        categories.total += 1;
+        match category {
+            CocoCategory::ObjectDetection(_) => categories.object_detection += 1,
+            CocoCategory::PanopticSegmentation(_) => categories.panoptic_segmentation += 1,
+            CocoCategory::KeypointDetection(_) => categories.keypoint_detection += 1,
+        }
+    });
+
+    let mut per_category = count_categories(&coco_file);
+    sort_categories(&mut per_category, args.sort);
+    let category_count = per_category.len();
+    per_category.truncate(args.top.unwrap_or(category_count));
+
+    FileCounts {
+        file: path.file_name().unwrap().to_string_lossy().to_string(),
+        images: coco_file.images.len() as u64,
+        annotations,
+        categories,
+        hidden_categories: category_count - per_category.len(),
+        per_category,
+        frequency_groups: frequency_groups(&coco_file),
+        issues: args.check.then(|| count_issues(&coco_file.validate())),
+        size_stats: args.size_stats.then(|| size_stats(&coco_file)),
+        coverage: args
+            .coverage
+            .then(|| coverage(&coco_file))
+            .transpose()
+            .unwrap_or_else(|err| {
+                eprintln!("Warning: skipping coverage of {:?}: {}", path, err);
+                None
+            }),
+        keypoints: args.keypoints.then(|| keypoint_stats(&coco_file)),
+    }
+}
+
+fn print_totals(images: u64, annotations: &AnnotationCounts, categories: &CategoryTypeCounts) {
+    println!("Images: {}", images);
+    println!("Annotations: {}", annotations.total);
 
-    println!("  Object Detection Annotations: {}", annotation_counts[0]);
-    println!("  Keypoint Detection Annotations: {}", annotation_counts[1]);
+    println!(
+        "  Object Detection Annotations: {}",
+        annotations.object_detection
+    );
+    println!(
+        "  Keypoint Detection Annotations: {}",
+        annotations.keypoint_detection
+    );
     println!(
         "  Panoptic Segmentation Annotations: {}",
-        annotation_counts[2]
+        annotations.panoptic_segmentation
     );
-    println!("  Image Captioning Annotations: {}", annotation_counts[3]);
-    println!("  DensePose Annotations: {}", annotation_counts[4]);
+    println!(
+        "  Image Captioning Annotations: {}",
+        annotations.image_captioning
+    );
+    println!("  DensePose Annotations: {}", annotations.dense_pose);
 
-    println!("Categories: {}", category_count);
-    println!("  Object Detection Categories: {}", categories_count[0]);
+    println!("Categories: {}", categories.total);
+    println!(
+        "  Object Detection Categories: {}",
+        categories.object_detection
+    );
     println!(
         "  Panoptic Segmentation Categories: {}",
-        categories_count[1]
+        categories.panoptic_segmentation
+    );
+    println!(
+        "  Keypoint Detection Categories: {}",
+        categories.keypoint_detection
+    );
+}
+
+/// Prints the per category table, with a note on how many categories `--top` left out
+fn print_category_table(counts: &[CategoryCount], hidden: usize) {
+    let ids: Vec<String> = counts
+        .iter()
+        .map(|count| count.id.map_or("-".to_string(), |id| id.to_string()))
+        .collect();
+    let id_width = ids.iter().map(String::len).chain([2]).max().unwrap();
+    let name_width = counts
+        .iter()
+        .map(|count| count.name.len())
+        .chain([4])
+        .max()
+        .unwrap();
+
+    println!("Per Category:");
+    println!(
+        "  {:>id_width$}  {:<name_width$}  {:>11}  {:>8}",
+        "ID", "Name", "Annotations", "Images"
+    );
+    counts.iter().zip(&ids).for_each(|(count, id)| {
+        println!(
+            "  {:>id_width$}  {:<name_width$}  {:>11}  {:>8}",
+            id, count.name, count.annotations, count.images
+        );
+    });
+    if hidden > 0 {
+        println!("  ... {} more", hidden);
+    }
+}
+
+fn print_frequency_groups(groups: &FrequencyGroups) {
+    println!("LVIS Frequency Groups:");
+    for (label, counts) in [
+        ("Rare", &groups.rare),
+        ("Common", &groups.common),
+        ("Frequent", &groups.frequent),
+    ] {
+        println!(
+            "  {}: {} categories, {} annotations",
+            label, counts.categories, counts.annotations
+        );
+    }
+}
+
+fn print_issues(issues: &IssueCounts) {
+    println!("Issues: {}", issues.total);
+    println!("  Duplicate Image IDs: {}", issues.duplicate_image_ids);
+    println!(
+        "  Duplicate Annotation IDs: {}",
+        issues.duplicate_annotation_ids
+    );
+    println!("  Dangling Annotations: {}", issues.dangling_annotations);
+    println!("  Dangling Category IDs: {}", issues.dangling_category_ids);
+    println!(
+        "  Non-Panoptic Segment Categories: {}",
+        issues.non_panoptic_segment_categories
+    );
+    println!("  Out of Bounds BBoxes: {}", issues.out_of_bounds_bboxes);
+    println!(
+        "  Keypoint Length Mismatches: {}",
+        issues.keypoint_length_mismatches
+    );
+}
+
+fn print_size_stats(stats: &SizeStats) {
+    let format_area =
+        |area: Option<f64>| area.map_or("-".to_string(), |area| format!("{:.1}", area));
+    let mut rows = vec![("all".to_string(), &stats.overall)];
+    rows.extend(stats.per_category.iter().map(|category| {
+        let id = category.id.map_or("-".to_string(), |id| id.to_string());
+        (format!("{} {}", id, category.name), &category.buckets)
+    }));
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.len())
+        .chain([8])
+        .max()
+        .unwrap();
+
+    println!("Object Sizes:");
+    println!(
+        "  {:<label_width$}  {:<6}  {:>8}  {:>11}  {:>11}",
+        "Category", "Size", "Count", "Mean Area", "Median Area"
     );
-    println!("  Keypoint Detection Categories: {}", categories_count[2]);
+    for (label, buckets) in &rows {
+        for (size, bucket) in [
+            ("small", &buckets.small),
+            ("medium", &buckets.medium),
+            ("large", &buckets.large),
+        ] {
+            println!(
+                "  {:<label_width$}  {:<6}  {:>8}  {:>11}  {:>11}",
+                label,
+                size,
+                bucket.count,
+                format_area(bucket.mean_area),
+                format_area(bucket.median_area)
+            );
+        }
+    }
+
+    if let Some(resolution) = &stats.resolution {
+        println!("Image Resolution:");
+        println!(
+            "  Width: min {}, max {}, mean {:.1}",
+            resolution.min_width, resolution.max_width, resolution.mean_width
+        );
+        println!(
+            "  Height: min {}, max {}, mean {:.1}",
+            resolution.min_height, resolution.max_height, resolution.mean_height
+        );
+        println!("  Aspect Ratios (width / height):");
+        for bin in &resolution.aspect_ratios {
+            println!("    {}: {}", bin.range, bin.images);
+        }
+    }
+}
+
+fn print_coverage(coverage: &Coverage) {
+    println!("Coverage:");
+    println!(
+        "  Unannotated Images: {} ({:.1}%)",
+        coverage.unannotated_images, coverage.unannotated_percent
+    );
+    println!("  Dangling Annotations: {}", coverage.dangling_annotations);
+    if let Some(per_image) = &coverage.annotations_per_image {
+        println!(
+            "  Annotations per Image: min {}, median {:.1}, mean {:.1}, max {}",
+            per_image.min, per_image.median, per_image.mean, per_image.max
+        );
+    }
+    println!("  Images by Annotation Count:");
+    for bin in &coverage.histogram {
+        println!("    {}: {}", bin.annotations, bin.images);
+    }
+}
+
+fn print_keypoint_stats(stats: &KeypointStats) {
+    println!("Keypoints:");
+    for category in &stats.per_category {
+        let name_width = category
+            .keypoints
+            .iter()
+            .map(|keypoint| keypoint.name.len())
+            .chain([8])
+            .max()
+            .unwrap();
+        println!("  {} (id {}):", category.name, category.id);
+        println!(
+            "    {:<name_width$}  {:>8}  {:>8}  {:>8}",
+            "Keypoint", "Visible", "Occluded", "Missing"
+        );
+        for keypoint in &category.keypoints {
+            println!(
+                "    {:<name_width$}  {:>8}  {:>8}  {:>8}",
+                keypoint.name, keypoint.visible, keypoint.occluded, keypoint.missing
+            );
+        }
+    }
+    println!("  Length Mismatches: {}", stats.length_mismatches.len());
+    println!(
+        "  Annotations Without Keypoint Category: {}",
+        stats.unknown_category
+    );
+    println!("  Invalid Visibility Flags: {}", stats.invalid_visibility);
+
+    println!("Crowd Annotations:");
+    for (label, counts) in [
+        ("Object Detection", &stats.crowd.object_detection),
+        ("Keypoint Detection", &stats.crowd.keypoint_detection),
+        ("DensePose", &stats.crowd.dense_pose),
+    ] {
+        let percent = if counts.total == 0 {
+            0.0
+        } else {
+            counts.crowd as f64 * 100.0 / counts.total as f64
+        };
+        println!(
+            "  {}: {} of {} ({:.1}%)",
+            label, counts.crowd, counts.total, percent
+        );
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let file_counts: Vec<FileCounts> = args
+        .coco_files
+        .iter()
+        .map(|path| count_file(path, &args))
+        .collect();
+
+    match args.format {
+        OutputFormat::Text => {
+            file_counts.iter().enumerate().for_each(|(i, counts)| {
+                if i > 0 {
+                    println!();
+                }
+                println!("Coco File: {}", counts.file);
+                print_totals(counts.images, &counts.annotations, &counts.categories);
+                if !counts.per_category.is_empty() {
+                    print_category_table(&counts.per_category, counts.hidden_categories);
+                }
+                if let Some(groups) = &counts.frequency_groups {
+                    print_frequency_groups(groups);
+                }
+                if let Some(stats) = &counts.size_stats {
+                    print_size_stats(stats);
+                }
+                if let Some(coverage) = &counts.coverage {
+                    print_coverage(coverage);
+                }
+                if let Some(stats) = &counts.keypoints {
+                    print_keypoint_stats(stats);
+                }
+                if let Some(issues) = &counts.issues {
+                    print_issues(issues);
+                }
+            });
+
+            if file_counts.len() > 1 {
+                let mut annotations = AnnotationCounts::default();
+                let mut categories = CategoryTypeCounts::default();
+                for counts in &file_counts {
+                    annotations.total += counts.annotations.total;
+                    annotations.object_detection += counts.annotations.object_detection;
+                    annotations.keypoint_detection += counts.annotations.keypoint_detection;
+                    annotations.panoptic_segmentation += counts.annotations.panoptic_segmentation;
+                    annotations.image_captioning += counts.annotations.image_captioning;
+                    annotations.dense_pose += counts.annotations.dense_pose;
+                    categories.total += counts.categories.total;
+                    categories.object_detection += counts.categories.object_detection;
+                    categories.panoptic_segmentation += counts.categories.panoptic_segmentation;
+                    categories.keypoint_detection += counts.categories.keypoint_detection;
+                }
+                println!();
+                println!("Total: {} files", file_counts.len());
+                print_totals(
+                    file_counts.iter().map(|counts| counts.images).sum(),
+                    &annotations,
+                    &categories,
+                );
+            }
+        }
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&file_counts).expect("Could not serialize counts")
+            );
+        }
+        OutputFormat::Csv => {
+            println!("file,category_id,category_name,annotations,images");
+            for counts in &file_counts {
+                let file = csv_field(&counts.file);
+                for category in &counts.per_category {
+                    println!(
+                        "{},{},{},{},{}",
+                        file,
+                        category.id.map(|id| id.to_string()).unwrap_or_default(),
+                        csv_field(&category.name),
+                        category.annotations,
+                        category.images
+                    );
+                }
+                println!(
+                    "{},,total,{},{}",
+                    file, counts.annotations.total, counts.images
+                );
+            }
+        }
+    }
+
+    // unlike cocovalidate, warnings such as out of bounds bboxes fail --check too
+    if file_counts.iter().any(|counts| {
+        counts
+            .issues
+            .as_ref()
+            .is_some_and(|issues| issues.total > 0)
+    }) {
+        std::process::exit(1);
+    }
 }
@@ -1,12 +1,33 @@
-use clap::Parser;
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{Parser, ValueEnum};
+use cococrawl::CocoImage;
+use cococrawl::archive::ArchiveWriter;
+use cococrawl::dedupe::sha256_file;
+use cococrawl::export::csv_field;
+use cococrawl::geometry::Transform;
+use cococrawl::jobs::thread_pool;
+use cococrawl::progress::progress_bar;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
 use indicatif::ParallelProgressIterator;
 use rayon::prelude::*;
-use serde_json;
-use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{SyncSender, sync_channel};
+use std::thread::{self, JoinHandle};
+
+/// Number of encoded images that may wait for the archive writer before workers block
+const ARCHIVE_QUEUE_SIZE: usize = 64;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputImageFormat {
+    Jpg,
+    Png,
+    Keep,
+}
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -22,90 +43,616 @@ struct Args {
     /// Force absolute paths for copied image file names. By default, relative paths are used.
     #[clap(short, long)]
     absolute_paths: bool,
+
+    /// Downscale images whose longest side exceeds this many pixels, preserving aspect ratio.
+    /// Annotation coordinates and areas are scaled to match.
+    #[clap(long)]
+    max_side: Option<u32>,
+
+    /// Image format for copied images. `keep` preserves the source format; anything else
+    /// re-encodes images that aren't already in that format.
+    #[clap(short, long, value_enum, default_value = "keep")]
+    format: OutputImageFormat,
+
+    /// Write the dataset into a single .tar, .tar.gz or .zip archive instead of a directory.
+    /// The archive has the same layout as the output directory.
+    #[clap(long, conflicts_with_all = ["output_dir_path", "absolute_paths"])]
+    archive: Option<PathBuf>,
+
+    /// Read each image's real dimensions from its header and report images whose recorded
+    /// width/height differ
+    #[clap(long)]
+    verify_dims: bool,
+
+    /// Write the real dimensions of mismatched images to the output JSON
+    #[clap(long, requires = "verify_dims")]
+    fix_dims: bool,
+
+    /// Exit non-zero if any image has mismatched dimensions that weren't fixed
+    #[clap(long, requires = "verify_dims")]
+    strict: bool,
+
+    /// Don't draw progress bars
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Don't copy images whose destination already exists with the same size, so an
+    /// interrupted copy can be resumed. Re-encoded images are always written.
+    #[clap(long, conflicts_with = "archive")]
+    skip_existing: bool,
+
+    /// With --skip-existing, compare content hashes instead of sizes
+    #[clap(long, requires = "skip_existing")]
+    verify: bool,
+
+    /// Decode every copy after writing it and fail the image if its dimensions don't match the
+    /// width/height of the output JSON, to catch truncated copies on flaky storage
+    #[clap(long, conflicts_with = "archive")]
+    verify_copies: bool,
+
+    /// Re-encode every image without its EXIF and other metadata. Formats that can't be
+    /// re-encoded faithfully are copied unchanged with a warning.
+    #[clap(long)]
+    strip_metadata: bool,
+
+    /// JPEG quality (1-100) for re-encoded images
+    #[clap(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// Write a CSV manifest with the source and destination path and the outcome of every image
+    #[clap(long)]
+    manifest: Option<PathBuf>,
+
+    /// Number of worker threads, one per CPU by default
+    #[clap(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+/// Result of copying a single image
+struct CopiedImage {
+    id: i64,
+    /// (sx, sy) factors the image was resized by
+    scale: Option<(f32, f32)>,
+    dimension_mismatch: Option<DimensionMismatch>,
+    /// the destination already held the same file
+    skipped: bool,
+}
+
+/// Manifest row of a single image
+struct ManifestEntry {
+    image_id: i64,
+    source_path: PathBuf,
+    /// file name written to the output JSON, `None` unless the image was copied or skipped
+    dest_path: Option<PathBuf>,
+    status: &'static str,
+}
+
+struct DimensionMismatch {
+    image_id: i64,
+    file_name: PathBuf,
+    /// (width, height) from the input JSON
+    recorded: (u32, u32),
+    /// (width, height) from the image header
+    actual: (u32, u32),
+}
+
+/// Where copied images and the rewritten JSON end up
+enum Output {
+    /// canonicalized output directory
+    Directory(PathBuf),
+    /// entries are handed to a writer thread so the archive is written sequentially
+    Archive(ArchiveSink),
+}
+
+struct ArchiveSink {
+    sender: SyncSender<(String, Vec<u8>)>,
+    writer: JoinHandle<Result<()>>,
+}
+
+impl ArchiveSink {
+    fn spawn(path: &Path) -> Result<Self> {
+        let mut archive = ArchiveWriter::create(path)?;
+        let (sender, receiver) = sync_channel::<(String, Vec<u8>)>(ARCHIVE_QUEUE_SIZE);
+        let writer = thread::spawn(move || {
+            for (name, data) in receiver {
+                archive.append(&name, &data)?;
+            }
+            archive.finish()
+        });
+        Ok(ArchiveSink { sender, writer })
+    }
+
+    fn finish(self) -> Result<()> {
+        drop(self.sender);
+        self.writer
+            .join()
+            .map_err(|_| anyhow!("Archive writer thread panicked"))?
+    }
+}
+
+impl Output {
+    /// Writes `data` to `relative_path` inside the output
+    fn write(&self, relative_path: &Path, data: Vec<u8>) -> Result<()> {
+        match self {
+            Output::Directory(dir) => {
+                let dest_path = dir.join(relative_path);
+                fs::write(&dest_path, data)
+                    .with_context(|| format!("Could not write {:?}", dest_path))
+            }
+            Output::Archive(sink) => {
+                // archive entry names always use forward slashes
+                let name = relative_path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                sink.sender
+                    .send((name, data))
+                    .map_err(|_| anyhow!("Archive writer stopped unexpectedly"))
+            }
+        }
+    }
+
+    /// Whether `relative_path` inside the output already holds a copy of `src_path`, judged by
+    /// size or, with `compare_hashes`, by content. Always false for archives.
+    fn has_copy(
+        &self,
+        src_path: &Path,
+        relative_path: &Path,
+        compare_hashes: bool,
+    ) -> Result<bool> {
+        let Output::Directory(dir) = self else {
+            return Ok(false);
+        };
+        let dest_path = dir.join(relative_path);
+        let Ok(dest_metadata) = fs::metadata(&dest_path) else {
+            return Ok(false);
+        };
+        let src_metadata = fs::metadata(src_path)
+            .with_context(|| format!("Could not read metadata of {:?}", src_path))?;
+        if !dest_metadata.is_file() || dest_metadata.len() != src_metadata.len() {
+            return Ok(false);
+        }
+        if !compare_hashes {
+            return Ok(true);
+        }
+
+        let hash =
+            |path: &Path| sha256_file(path).with_context(|| format!("Could not hash {:?}", path));
+        Ok(hash(src_path)? == hash(&dest_path)?)
+    }
+
+    /// Decodes the copy at `relative_path` inside the output and checks that it is `width` x
+    /// `height`. Archives aren't read back.
+    fn verify_copy(&self, relative_path: &Path, (width, height): (u32, u32)) -> Result<()> {
+        let Output::Directory(dir) = self else {
+            return Ok(());
+        };
+        let dest_path = dir.join(relative_path);
+        let copy = image::open(&dest_path)
+            .with_context(|| format!("Could not decode copied image {:?}", dest_path))?;
+        if (copy.width(), copy.height()) != (width, height) {
+            bail!(
+                "copied image {:?} is {}x{} instead of {}x{}",
+                dest_path,
+                copy.width(),
+                copy.height(),
+                width,
+                height
+            );
+        }
+        Ok(())
+    }
+
+    /// Copies `src_path` to `relative_path` inside the output without decoding it
+    fn copy(&self, src_path: &Path, relative_path: &Path) -> Result<()> {
+        match self {
+            Output::Directory(dir) => {
+                let dest_path = dir.join(relative_path);
+                fs::copy(src_path, &dest_path).with_context(|| {
+                    format!(
+                        "Could not copy image from {:?} to {:?}",
+                        src_path, dest_path
+                    )
+                })?;
+                Ok(())
+            }
+            Output::Archive(_) => {
+                let data = fs::read(src_path)
+                    .with_context(|| format!("Could not read image {:?}", src_path))?;
+                self.write(relative_path, data)
+            }
+        }
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Whether decoding and re-encoding `path` keeps everything but its metadata. Animated GIFs
+/// would lose all but their first frame, and anything else has no encoder.
+fn can_strip_metadata(path: &Path) -> bool {
+    matches!(
+        ImageFormat::from_path(path),
+        Ok(ImageFormat::Jpeg
+            | ImageFormat::Png
+            | ImageFormat::Bmp
+            | ImageFormat::Tiff
+            | ImageFormat::WebP)
+    )
+}
+
+struct ReencodedImage {
+    data: Vec<u8>,
+    /// (width, height) of the source image
+    original_dimensions: (u32, u32),
+    /// (width, height) after resizing
+    dimensions: (u32, u32),
+}
+
+/// Decodes `src_path`, downscales it to fit within `max_side` if given and encodes it in the
+/// format implied by the extension of `dest_path`. Metadata of the source isn't carried over.
+fn reencode_image(
+    src_path: &Path,
+    dest_path: &Path,
+    max_side: Option<u32>,
+    quality: Option<u8>,
+) -> Result<ReencodedImage> {
+    let mut img =
+        image::open(src_path).with_context(|| format!("Could not decode image {:?}", src_path))?;
+    let original_dimensions = (img.width(), img.height());
+
+    if let Some(max_side) = max_side
+        && img.width().max(img.height()) > max_side
+    {
+        img = img.resize(max_side, max_side, FilterType::Lanczos3);
+    }
+
+    // jpeg has no alpha channel
+    let format = ImageFormat::from_path(dest_path)?;
+    if format == ImageFormat::Jpeg {
+        img = DynamicImage::ImageRgb8(img.to_rgb8());
+    }
+
+    let mut encoded = Cursor::new(Vec::new());
+    match quality {
+        Some(quality) if format == ImageFormat::Jpeg => {
+            img.write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, quality))
+        }
+        _ => img.write_to(&mut encoded, format),
+    }
+    .with_context(|| format!("Could not encode image {:?}", dest_path))?;
+
+    Ok(ReencodedImage {
+        data: encoded.into_inner(),
+        original_dimensions,
+        dimensions: (img.width(), img.height()),
+    })
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    let output = match &args.archive {
+        Some(archive_path) => Output::Archive(ArchiveSink::spawn(archive_path)?),
+        None => {
+            // Make directory for output if it doesn't exist
+            fs::create_dir_all(args.output_dir_path.join("images"))
+                .expect("Could not create images output directory");
+            Output::Directory(args.output_dir_path.canonicalize()?)
+        }
+    };
+
+    let result =
+        thread_pool(args.jobs.map(|jobs| jobs as usize))?.install(|| copy_dataset(&args, &output));
+
+    // a failed archive writer makes the copy fail too, so report the writer's error first
+    if let Output::Archive(sink) = output {
+        sink.finish()?;
+    }
+    result
+}
+
+/// Path of the source image, relative to the input coco json file location unless it's an
+/// absolute path
+fn source_path(image: &CocoImage, coco_file: &Path) -> PathBuf {
+    if PathBuf::from(&image.file_name).is_absolute() {
+        PathBuf::from(&image.file_name)
+    } else {
+        coco_file.parent().unwrap().join(&image.file_name)
+    }
+}
+
+fn write_manifest(path: &Path, entries: &[ManifestEntry]) -> Result<()> {
+    let mut manifest = String::from("image_id,source_path,dest_path,status\n");
+    for entry in entries {
+        let dest_path = entry
+            .dest_path
+            .as_ref()
+            .map(|path| path.to_string_lossy())
+            .unwrap_or_default();
+        manifest.push_str(&format!(
+            "{},{},{},{}\n",
+            entry.image_id,
+            csv_field(&entry.source_path.to_string_lossy()),
+            csv_field(&dest_path),
+            entry.status
+        ));
+    }
+    fs::write(path, manifest).with_context(|| format!("Could not write manifest {:?}", path))
+}
+
+/// Copies or re-encodes a single image from `src_path` into the output and points its file
+/// name at the copy. Returns `None` if the source image is missing.
+fn copy_image(
+    image: &mut CocoImage,
+    src_path: &Path,
+    args: &Args,
+    output: &Output,
+) -> Result<Option<CopiedImage>> {
+    if !(src_path.exists() && src_path.is_file()) {
+        eprintln!(
+            "Warning: Source image file does not exist or is not a file: {:?}",
+            src_path
+        );
+        return Ok(None);
+    }
+
+    // output file name is the original basename inside the images directory
+    let file_name = src_path.file_name().unwrap_or_else(|| {
+        panic!(
+            "Could not get file name for source image path {:?}",
+            src_path
+        )
+    });
+
+    let dest_path = match args.format {
+        OutputImageFormat::Keep => Path::new("images").join(file_name),
+        OutputImageFormat::Jpg => Path::new("images").join(file_name).with_extension("jpg"),
+        OutputImageFormat::Png => Path::new("images").join(file_name).with_extension("png"),
+    };
+
+    let format_changes = match args.format {
+        OutputImageFormat::Keep => false,
+        OutputImageFormat::Jpg => !has_extension(src_path, &["jpg", "jpeg"]),
+        OutputImageFormat::Png => !has_extension(src_path, &["png"]),
+    };
+
+    // header only read, shared by the size check and dimension verification
+    let actual_dimensions = if args.max_side.is_some() || args.verify_dims {
+        Some(
+            ImageReader::open(src_path)?
+                .with_guessed_format()?
+                .into_dimensions()
+                .with_context(|| format!("Could not read dimensions of {:?}", src_path))?,
+        )
+    } else {
+        None
+    };
+
+    let too_large = match (args.max_side, actual_dimensions) {
+        (Some(max_side), Some((width, height))) => width.max(height) > max_side,
+        _ => false,
+    };
+
+    let dimension_mismatch = match actual_dimensions {
+        Some(actual) if args.verify_dims && actual != (image.width, image.height) => {
+            let mismatch = DimensionMismatch {
+                image_id: image.id,
+                file_name: image.file_name.clone(),
+                recorded: (image.width, image.height),
+                actual,
+            };
+            if args.fix_dims {
+                (image.width, image.height) = actual;
+            }
+            Some(mismatch)
+        }
+        _ => None,
+    };
+
+    let strip_metadata = args.strip_metadata && can_strip_metadata(src_path);
+    if args.strip_metadata && !(strip_metadata || format_changes || too_large) {
+        eprintln!(
+            "Warning: can't strip metadata from {:?}, copying it unchanged",
+            src_path
+        );
+    }
+
+    let mut scale = None;
+    let mut skipped = false;
+    if too_large || format_changes || strip_metadata {
+        let reencoded = reencode_image(src_path, &dest_path, args.max_side, args.quality)?;
+        let (old_width, old_height) = reencoded.original_dimensions;
+        let (new_width, new_height) = reencoded.dimensions;
+        output.write(&dest_path, reencoded.data)?;
+        if (new_width, new_height) != (old_width, old_height) {
+            scale = Some((
+                new_width as f32 / old_width as f32,
+                new_height as f32 / old_height as f32,
+            ));
+        }
+        image.width = new_width;
+        image.height = new_height;
+    } else if args.skip_existing && output.has_copy(src_path, &dest_path, args.verify)? {
+        skipped = true;
+    } else {
+        output.copy(src_path, &dest_path)?;
+    }
+
+    if args.verify_copies {
+        output.verify_copy(&dest_path, (image.width, image.height))?;
+    }
+
+    // written path is relative to the output coco json file location
+    // unless absolute_paths is set
+    image.file_name = match output {
+        Output::Directory(dir) if args.absolute_paths => dir.join(&dest_path),
+        _ => dest_path,
+    };
+
+    Ok(Some(CopiedImage {
+        id: image.id,
+        scale,
+        dimension_mismatch,
+        skipped,
+    }))
+}
+
+fn copy_dataset(args: &Args, output: &Output) -> Result<()> {
     let coco_json = fs::read_to_string(&args.coco_file).expect("Could not read COCO JSON file");
     let coco_json_file_name = args.coco_file.file_name().unwrap().to_string_lossy();
-    
-    // Make directory for output if it doesn't exist
-    fs::create_dir_all(&args.output_dir_path).expect("Could not create output directory");
-    let output_dir_path = args.output_dir_path.canonicalize()?;
-
-    let images_output_path = output_dir_path.join("images");
-    fs::create_dir_all(&images_output_path).expect("Could not create images output directory");
-    let images_output_path = images_output_path.canonicalize()?;
 
     let mut coco_file: cococrawl::CocoFile =
         serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
 
     // Iterate over images and copy them to the output directory
     let images_count = coco_file.images.len() as u64;
-    coco_file
+    let results: Vec<(ManifestEntry, Result<Option<CopiedImage>>)> = coco_file
         .images
         .par_iter_mut()
-        .progress_count(images_count)
-        .for_each(|image| {
-            // src_path is relative to the input coco json file location
-            // unless it's an absolute path
-            let src_path = if PathBuf::from(&image.file_name).is_absolute() {
-                PathBuf::from(&image.file_name)
-            } else {
-                args.coco_file.parent().unwrap().join(&image.file_name)
+        .progress_with(progress_bar(images_count, args.quiet))
+        .map(|image| {
+            let src_path = source_path(image, &args.coco_file);
+            let result = copy_image(image, &src_path, args, output);
+            let status = match &result {
+                Ok(Some(copied)) if copied.skipped => "skipped",
+                Ok(Some(_)) => "copied",
+                Ok(None) => "missing",
+                Err(_) => "failed",
             };
-            if src_path.exists() && src_path.is_file() {
-                // output file name is the original basename prefixed with the path to the
-                // images_output_path
-                let file_name = src_path
-                    .file_name()
-                    .expect(format!(
-                        "Could not get file name for source image path {:?}",
-                        src_path
-                    ).as_str());
-
-                let dest_path = images_output_path
-                    .join(file_name);
-
-                fs::copy(&src_path, &dest_path)
-                    .expect(format!(
-                        "Could not copy image from {:?} to {:?}",
-                        src_path, dest_path
-                    ).as_str());
-
-                // written path is relative to the output coco json file location
-                // unless absolute_paths is set
-                let written_path = if args.absolute_paths {
-                    dest_path
-                } else {
-                    dest_path
-                        .strip_prefix(output_dir_path.clone())
-                        .expect(format!(
-                            "Could not strip prefix {:?} from destination path {:?}",
-                            output_dir_path, dest_path
-                        ).as_str())
-                        .to_path_buf()
-                };
-
-                image.file_name = written_path;
+            let entry = ManifestEntry {
+                image_id: image.id,
+                source_path: src_path,
+                dest_path: matches!(result, Ok(Some(_))).then(|| image.file_name.clone()),
+                status,
+            };
+            (entry, result)
+        })
+        .collect();
 
+    let mut manifest: Vec<ManifestEntry> = Vec::new();
+    let mut copied_images: Vec<CopiedImage> = Vec::new();
+    let mut failures: Vec<(i64, anyhow::Error)> = Vec::new();
+    let mut missing_count = 0;
+    for (entry, result) in results {
+        match result {
+            Ok(Some(copied)) => copied_images.push(copied),
+            Ok(None) => missing_count += 1,
+            Err(err) => failures.push((entry.image_id, err)),
+        }
+        manifest.push(entry);
+    }
+
+    // images that failed to copy are left out of the output JSON along with their annotations
+    let failed_ids: HashSet<i64> = failures.iter().map(|(image_id, _)| *image_id).collect();
+    coco_file
+        .images
+        .retain(|image| !failed_ids.contains(&image.id));
+    coco_file
+        .annotations
+        .retain(|annotation| !failed_ids.contains(&annotation.image_id()));
+
+    let image_scales: HashMap<i64, (f32, f32)> = copied_images
+        .iter()
+        .filter_map(|copied| copied.scale.map(|scale| (copied.id, scale)))
+        .collect();
+
+    // Scale annotations of resized images to the new pixel grid
+    coco_file
+        .annotations
+        .par_iter_mut()
+        .filter_map(|annotation| {
+            image_scales
+                .get(&annotation.image_id())
+                .map(|scale| (annotation, *scale))
+        })
+        .try_for_each(|(annotation, (sx, sy))| {
+            let image_id = annotation.image_id();
+            // RLE masks, such as those of crowd annotations, are resampled onto the new grid
+            annotation
+                .transform(&Transform::scale(sx, sy))
+                .with_context(|| format!("Could not rescale annotations for image id {}", image_id))
+        })?;
+
+    // Write updated COCO JSON next to the images
+    match output {
+        Output::Directory(dir) => {
+            let output_coco_path = dir.join(coco_json_file_name.to_string());
+            coco_file.to_path(&output_coco_path, !args.compact)?;
+        }
+        Output::Archive(_) => {
+            let mut coco_json = Vec::new();
+            coco_file.to_writer(&mut coco_json, !args.compact)?;
+            output.write(Path::new(coco_json_file_name.as_ref()), coco_json)?;
+        }
+    }
+    if let Some(manifest_path) = &args.manifest {
+        write_manifest(manifest_path, &manifest)?;
+    }
+
+    let skipped_count = copied_images.iter().filter(|copied| copied.skipped).count();
+    if args.skip_existing {
+        println!(
+            "Skipped {} existing image(s), copied {}",
+            skipped_count,
+            copied_images.len() - skipped_count
+        );
+    }
+
+    failures.sort_by_key(|(image_id, _)| *image_id);
+    for (image_id, err) in &failures {
+        eprintln!("Error: Could not copy image id {}: {:#}", image_id, err);
+    }
+    println!(
+        "Copied {}, failed {}, skipped {}",
+        copied_images.len() - skipped_count,
+        failures.len(),
+        skipped_count + missing_count
+    );
+
+    if args.verify_dims {
+        let mut mismatches: Vec<&DimensionMismatch> = copied_images
+            .iter()
+            .filter_map(|copied| copied.dimension_mismatch.as_ref())
+            .collect();
+        mismatches.sort_by_key(|mismatch| mismatch.image_id);
+
+        mismatches.iter().for_each(|mismatch| {
+            eprintln!(
+                "Dimension mismatch: image id {} ({}) recorded {}x{}, actual {}x{}",
+                mismatch.image_id,
+                mismatch.file_name.to_string_lossy(),
+                mismatch.recorded.0,
+                mismatch.recorded.1,
+                mismatch.actual.0,
+                mismatch.actual.1
+            );
+        });
+        eprintln!(
+            "{} image(s) with mismatched dimensions{}",
+            mismatches.len(),
+            if args.fix_dims && !mismatches.is_empty() {
+                ", fixed in output"
             } else {
-                eprintln!(
-                    "Warning: Source image file does not exist or is not a file: {:?}",
-                    src_path
-                );
+                ""
             }
-        });
+        );
 
-    // Write updated COCO JSON to output directory
-    let output_coco_path =
-        PathBuf::from(&args.output_dir_path).join(coco_json_file_name.to_string());
-    let output_file =
-        File::create(&output_coco_path).expect("Could not create output COCO JSON file");
-    let writer = BufWriter::new(output_file);
-    serde_json::to_writer_pretty(writer, &coco_file)
-        .expect("Could not write COCO JSON to output file");
+        if args.strict && !args.fix_dims && !mismatches.is_empty() {
+            bail!("{} image(s) have mismatched dimensions", mismatches.len());
+        }
+    }
 
+    if !failures.is_empty() {
+        bail!("{} image(s) could not be copied", failures.len());
+    }
     Ok(())
 }
@@ -1,26 +1,37 @@
-use chrono::{DateTime, Datelike, Utc};
-use clap::Parser;
-use image::ImageReader;
-use anyhow::Result;
-use indicatif::ParallelProgressIterator;
-use rayon::prelude::*;
-use serde_json;
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, Utc};
+use clap::{Parser, ValueEnum};
 use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
 use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use cococrawl::{CocoFile, CocoImage, CocoInfo, path_utils::create_coco_image_path};
+use cococrawl::crawl::{CrawlReport, DimensionFilter, categorize_by_directory};
+use cococrawl::dedupe::{DEFAULT_PHASH_THRESHOLD, DedupeMode};
+use cococrawl::path_utils::create_coco_image_path;
+use cococrawl::probe::ProbeMode;
+use cococrawl::{CocoFile, CocoInfo, CocoLicense, CrawlOptions, build_coco_file, crawl_new_images};
 
-const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "bmp", "tiff", "svg", "webp"];
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ProbeArg {
+    Fast,
+    Full,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DedupeArg {
+    None,
+    Sha256,
+    Phash,
+}
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
     /// Directories to crawl positional arguments
     #[clap(required = true)]
-    directories: Vec<String>,
+    directories: Vec<PathBuf>,
 
     /// JSON output path
     #[clap(short, long, default_value = "coco.json")]
@@ -30,88 +41,385 @@ struct Args {
     #[clap(short, long, default_value = "1.0.0")]
     version_string: String,
 
+    /// Description for the COCO info section
+    #[clap(long, default_value = "", conflicts_with = "append")]
+    description: String,
+
+    /// Contributor for the COCO info section
+    #[clap(long, default_value = "", conflicts_with = "append")]
+    contributor: String,
+
+    /// URL for the COCO info section
+    #[clap(long, default_value = "", conflicts_with = "append")]
+    url: String,
+
+    /// Name of a license to add with id 1 and apply to every crawled image
+    #[clap(long, conflicts_with = "append")]
+    license_name: Option<String>,
+
+    /// URL of the --license-name license
+    #[clap(long, requires = "license_name")]
+    license_url: Option<String>,
+
+    /// JSON file with an array of licenses to add, instead of --license-name
+    #[clap(long, conflicts_with_all = ["append", "license_name"])]
+    licenses_file: Option<PathBuf>,
+
+    /// Id of the license from --licenses-file to apply to every crawled image
+    #[clap(long, requires = "licenses_file")]
+    default_license_id: Option<i32>,
+
     /// Force absolute paths for image file names. By default, relative paths are used if the image
     /// is located within the same directory tree as the output JSON file. Otherwise, absolute paths are used.
     #[clap(short, long)]
     absolute_paths: bool,
+
+    /// Swap width and height for images whose EXIF orientation (5-8) rotates them by 90 degrees,
+    /// so the recorded dimensions match how the image is displayed.
+    #[clap(long)]
+    respect_exif_orientation: bool,
+
+    /// Comma separated image extensions to crawl, replacing the default set (case insensitive)
+    /// cococrawl ./images --extensions jpg,png,heic
+    #[clap(long, value_delimiter = ',')]
+    extensions: Option<Vec<String>>,
+
+    /// Skip files matching this glob, e.g. "**/thumbnails/**". Can be repeated.
+    #[clap(long)]
+    exclude: Vec<String>,
+
+    /// Maximum directory depth to descend below each crawled directory, 1 only crawls the
+    /// directory's own files
+    #[clap(long)]
+    max_depth: Option<usize>,
+
+    /// Follow symbolic links while crawling. Links back to a parent directory are skipped, so
+    /// symlink cycles don't loop.
+    #[clap(long)]
+    follow_symlinks: bool,
+
+    /// Existing COCO JSON file to add newly discovered images to. Its images and annotations are
+    /// kept with their ids, new images get ids after the current maximum. Images are matched by
+    /// their resolved path, so differently written file names of the same file aren't added twice.
+    /// cococrawl ./images --append coco.json -o coco.json
+    #[clap(long, visible_alias = "merge-into")]
+    append: Option<PathBuf>,
+
+    /// With --append, drop images whose file no longer exists along with their annotations
+    #[clap(long, requires = "append")]
+    prune_missing: bool,
+
+    /// Record unreadable or corrupt images with 0x0 dimensions instead of skipping them
+    #[clap(long)]
+    keep_broken: bool,
+
+    /// Exit non-zero if any image couldn't be read. The output file is still written.
+    #[clap(long)]
+    fail_on_error: bool,
+
+    /// How to read image dimensions. `fast` parses just the header of JPEG, PNG, GIF, BMP and
+    /// WebP files and falls back to `full` decoding for everything else.
+    #[clap(long, value_enum, default_value = "fast")]
+    probe: ProbeArg,
+
+    /// Drop duplicate images, keeping the first path found. `sha256` matches identical files,
+    /// `phash` matches perceptually similar images.
+    #[clap(long, value_enum, default_value = "none")]
+    dedupe: DedupeArg,
+
+    /// Maximum Hamming distance between 64 bit perceptual hashes for `--dedupe phash`
+    #[clap(long, default_value_t = DEFAULT_PHASH_THRESHOLD)]
+    phash_threshold: u32,
+
+    /// Store the SHA-256 digest of each image file as `sha256` so later corruption can be
+    /// detected. Reads every file in full.
+    #[clap(long)]
+    checksums: bool,
+
+    /// Write a JSON report mapping each kept image path to the duplicate paths dropped for it
+    #[clap(long)]
+    dedupe_report: Option<PathBuf>,
+
+    /// Name each image's category after its directory this many levels below the crawl root
+    /// (1 for dataset/cat/a.jpg) and add a full image bbox annotation for it
+    /// cococrawl ./dataset --category-from-dir 1
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "append")]
+    category_from_dir: Option<u64>,
+
+    /// Create a category per immediate subdirectory of the crawl roots, same as
+    /// --category-from-dir 1
+    #[clap(long, conflicts_with_all = ["category_from_dir", "append"])]
+    categorize_by_dir: bool,
+
+    /// Skip images whose shorter side is below this many pixels
+    #[clap(long)]
+    min_dim: Option<u32>,
+
+    /// Skip images whose longer side exceeds this many pixels
+    #[clap(long)]
+    max_dim: Option<u32>,
+
+    /// Skip images with fewer than this many pixels in total
+    #[clap(long)]
+    min_pixels: Option<u64>,
+
+    /// Skip images with more than this many pixels in total
+    #[clap(long)]
+    max_pixels: Option<u64>,
+
+    /// Number of threads reading images, one per CPU by default
+    #[clap(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let output_file = File::create(&args.output).expect("Could not create output file");
+    let existing: Option<CocoFile> = match &args.append {
+        Some(path) => {
+            let coco_json = fs::read_to_string(path)
+                .with_context(|| format!("Could not read COCO JSON file {:?}", path))?;
+            Some(serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?)
+        }
+        None => None,
+    };
 
-    let extension_set: HashSet<&str> = IMAGE_EXTENSIONS.iter().cloned().collect();
+    let (licenses, default_license_id) = read_licenses(&args)?;
 
-    let entries: Vec<_> =  args
-        .directories
-        .iter()
-        .flat_map(|dir| {
-            walkdir::WalkDir::new(dir)
-                .into_iter()
-                .filter_map(Result::ok)
-                .filter(|entry| entry.file_type().is_file())
-                .filter(|entry| {
-                    entry
-                        .path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map_or(false, |ext_str| {
-                            extension_set.contains(&ext_str.to_lowercase().as_str())
-                        })
-                })
-        }).collect();
-
-    let images: Vec<CocoImage> = entries
-        .par_iter()
-        .progress_count(entries.len() as u64)
-        .enumerate()
-        .map(|(id, entry)| {
-            let written_path = create_coco_image_path(args.output.as_path(), entry.path(), args.absolute_paths).expect("Could not create COCO image path");
-            let metadata = fs::metadata(entry.path()).unwrap();
-            let date_created = metadata.created().ok();
-            let date_created = date_created.map(|dt| DateTime::<Utc>::from(dt));
-
-            let (width, height) = ImageReader::open(&entry.path())
-                .unwrap()
-                .with_guessed_format()
-                .unwrap()
-                .into_dimensions()
-                .unwrap_or((0, 0));
-
-            CocoImage {
-                id: id as i64,
-                width,
-                height,
-                file_name: written_path,
-                license: None,
-                flickr_url: None,
-                coco_url: None,
-                date_captured: date_created,
-            }
-        })
-        .collect();
+    let mut crawl_options = CrawlOptions::default().with_exclude_patterns(&args.exclude)?;
+    if let Some(extensions) = &args.extensions {
+        crawl_options = crawl_options.with_extensions(extensions);
+    }
+    crawl_options.max_depth = args.max_depth;
+    crawl_options.follow_symlinks = args.follow_symlinks;
+    crawl_options.output_path = args.output.clone();
+    crawl_options.absolute_paths = args.absolute_paths;
+    crawl_options.respect_exif_orientation = args.respect_exif_orientation;
+    crawl_options.keep_broken = args.keep_broken;
+    crawl_options.probe = match args.probe {
+        ProbeArg::Fast => ProbeMode::Fast,
+        ProbeArg::Full => ProbeMode::Full,
+    };
+    crawl_options.dedupe = match args.dedupe {
+        DedupeArg::None => DedupeMode::None,
+        DedupeArg::Sha256 => DedupeMode::Sha256,
+        DedupeArg::Phash => DedupeMode::Phash,
+    };
+    crawl_options.phash_threshold = args.phash_threshold;
+    crawl_options.checksums = args.checksums;
+    crawl_options.jobs = args.jobs.map(|jobs| jobs as usize);
+    crawl_options.dimension_filter = DimensionFilter {
+        min_dim: args.min_dim,
+        max_dim: args.max_dim,
+        min_pixels: args.min_pixels,
+        max_pixels: args.max_pixels,
+    };
 
     let coco_info = CocoInfo {
         year: Utc::now().year(),
-        version: args.version_string,
-        description: "".to_string(),
-        contributor: "".to_string(),
-        url: "".to_string(),
+        version: args.version_string.clone(),
+        description: args.description.clone(),
+        contributor: args.contributor.clone(),
+        url: args.url.clone(),
         date_created: Utc::now(),
+        extra: Default::default(),
     };
 
-    let coco_file = CocoFile {
-        info: Some(coco_info),
-        images,
-        annotations: Vec::new(),
-        categories: None,
-        licenses: None,
+    let (coco_file, report) = match (existing, &args.append) {
+        (Some(existing), Some(existing_path)) => {
+            append_images(&args, existing, existing_path, crawl_options)?
+        }
+        _ => {
+            let (mut coco_file, report) =
+                build_coco_file(&args.directories, crawl_options, coco_info)?;
+            coco_file.licenses = licenses;
+            if default_license_id.is_some() {
+                coco_file
+                    .images
+                    .iter_mut()
+                    .for_each(|image| image.license = default_license_id);
+            }
+            let category_depth = args
+                .category_from_dir
+                .or(args.categorize_by_dir.then_some(1));
+            if let Some(depth) = category_depth {
+                let (categories, annotations, uncategorized) = categorize_by_directory(
+                    &coco_file.images,
+                    &args.directories,
+                    &args.output,
+                    depth as usize,
+                )?;
+                if !uncategorized.is_empty() {
+                    eprintln!(
+                        "Warning: {} image(s) are not nested {} directory level(s) deep and have no category",
+                        uncategorized.len(),
+                        depth
+                    );
+                }
+                coco_file.categories = Some(categories);
+                coco_file.annotations = annotations;
+            }
+            (coco_file, report)
+        }
     };
 
-    let writer = BufWriter::new(output_file);
+    // --append may read from the output file, which is only replaced once everything succeeded
+    write_output(&coco_file, &args.output, !args.compact)?;
+
+    if let Some(dedupe_report) = &args.dedupe_report {
+        let writer = BufWriter::new(
+            File::create(dedupe_report).context("Could not create dedupe report file")?,
+        );
+        serde_json::to_writer_pretty(writer, &report.duplicates)
+            .context("Could not write dedupe report")?;
+    }
+    if !report.duplicates.is_empty() {
+        eprintln!(
+            "Dropped {} duplicate image(s)",
+            report.duplicates.values().map(Vec::len).sum::<usize>()
+        );
+    }
+    if !report.filtered.is_empty() {
+        eprintln!(
+            "Skipped {} image(s) outside the dimension bounds",
+            report.filtered.len()
+        );
+    }
 
-    serde_json::to_writer_pretty(writer, &coco_file).expect("Could not write JSON to output file");
+    report_failures(&report, args.keep_broken);
+    if args.fail_on_error && !report.failures.is_empty() {
+        bail!("{} image(s) could not be read", report.failures.len());
+    }
 
     Ok(())
 }
+
+/// Writes `coco_file` to a temporary file next to `output` and renames it over `output`, so a
+/// failed run leaves an existing output untouched
+fn write_output(coco_file: &CocoFile, output: &Path, pretty: bool) -> Result<()> {
+    let file_name = output
+        .file_name()
+        .with_context(|| format!("Output path {:?} has no file name", output))?;
+    let temp_path = output.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let result = coco_file.to_path(&temp_path, pretty).and_then(|()| {
+        fs::rename(&temp_path, output)
+            .with_context(|| format!("Could not write output file {:?}", output))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// The licenses to write and the license id to apply to every image, from either
+/// --license-name or --licenses-file
+fn read_licenses(args: &Args) -> Result<(Option<Vec<CocoLicense>>, Option<i32>)> {
+    if let Some(name) = &args.license_name {
+        let license = CocoLicense {
+            id: 1,
+            name: name.clone(),
+            url: args.license_url.clone().unwrap_or_default(),
+            extra: Default::default(),
+        };
+        return Ok((Some(vec![license]), Some(1)));
+    }
+
+    let Some(licenses_file) = &args.licenses_file else {
+        return Ok((None, None));
+    };
+    let licenses_json = fs::read_to_string(licenses_file)
+        .with_context(|| format!("Could not read licenses file {:?}", licenses_file))?;
+    let licenses: Vec<CocoLicense> =
+        serde_json::from_str(&licenses_json).context("Could not parse licenses file")?;
+    if let Some(id) = args.default_license_id
+        && !licenses.iter().any(|license| license.id == id)
+    {
+        bail!("No license with id {} in {:?}", id, licenses_file);
+    }
+
+    Ok((Some(licenses), args.default_license_id))
+}
+
+fn report_failures(report: &CrawlReport, keep_broken: bool) {
+    if report.failures.is_empty() {
+        return;
+    }
+
+    for failure in &report.failures {
+        eprintln!("Warning: {}", failure);
+    }
+    let summary: Vec<String> = report
+        .failure_counts()
+        .iter()
+        .map(|(reason, count)| format!("{} {}", count, reason))
+        .collect();
+    eprintln!(
+        "{} {} image(s): {}",
+        if keep_broken { "Kept" } else { "Skipped" },
+        report.failures.len(),
+        summary.join(", ")
+    );
+}
+
+/// Adds the files under the crawled directories that `existing` doesn't reference yet, keeping
+/// existing image ids and annotations
+fn append_images(
+    args: &Args,
+    mut existing: CocoFile,
+    existing_path: &Path,
+    crawl_options: CrawlOptions,
+) -> Result<(CocoFile, CrawlReport)> {
+    // new ids start after every existing id, including pruned ones, so they are never reused
+    let first_id = existing
+        .images
+        .iter()
+        .map(|image| image.id + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut known_paths: HashSet<PathBuf> = HashSet::new();
+    let mut pruned_ids: HashSet<i64> = HashSet::new();
+    let mut kept_images = Vec::with_capacity(existing.images.len());
+
+    for mut image in existing.images {
+        let absolute_path = image.get_absolute_path(existing_path)?;
+        match absolute_path.canonicalize() {
+            Ok(canonical) => {
+                // rewrite relative to the output file, which may live elsewhere
+                image.file_name =
+                    create_coco_image_path(&args.output, &canonical, args.absolute_paths)?;
+                known_paths.insert(canonical);
+                kept_images.push(image);
+            }
+            Err(_) if args.prune_missing => {
+                pruned_ids.insert(image.id);
+            }
+            Err(_) => {
+                known_paths.insert(absolute_path);
+                kept_images.push(image);
+            }
+        }
+    }
+
+    let mut report = crawl_new_images(&args.directories, crawl_options, &known_paths, first_id)?;
+    let new_images = std::mem::take(&mut report.images);
+
+    println!(
+        "Kept {} image(s), added {}, pruned {}",
+        kept_images.len(),
+        new_images.len(),
+        pruned_ids.len()
+    );
+
+    existing
+        .annotations
+        .retain(|annotation| !pruned_ids.contains(&annotation.image_id()));
+    kept_images.extend(new_images);
+    existing.images = kept_images;
+
+    Ok((existing, report))
+}
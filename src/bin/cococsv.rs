@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use cococrawl::export::to_csv;
+use cococrawl::import::from_csv;
+use cococrawl::{CocoAnnotation, CocoFile};
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the annotations of a COCO file as a CSV table
+    /// cococsv export dataset.json -o ann.csv
+    Export {
+        /// coco JSON file path
+        coco_file: PathBuf,
+
+        /// CSV output path
+        #[clap(short, long, default_value = "annotations.csv")]
+        output: PathBuf,
+    },
+    /// Build a COCO file from a CSV table and the images of an existing COCO file
+    /// cococsv import ann.csv --images-from dataset.json -o out.json
+    Import {
+        /// CSV file path
+        csv_file: PathBuf,
+
+        /// coco JSON file to take the images, categories, info and licenses from
+        #[clap(long)]
+        images_from: PathBuf,
+
+        /// JSON output path
+        #[clap(short, long, default_value = "coco.json")]
+        output: PathBuf,
+
+        /// Write compact JSON without indentation, which is much smaller for large datasets
+        #[clap(long)]
+        compact: bool,
+    },
+}
+
+fn read_coco_file(path: &Path) -> Result<CocoFile> {
+    let coco_json =
+        fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+    serde_json::from_str(&coco_json).with_context(|| format!("Could not parse {:?}", path))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match args.command {
+        Command::Export { coco_file, output } => {
+            let coco_file = read_coco_file(&coco_file)?;
+            let csv = to_csv(&coco_file);
+            fs::write(&output, &csv).with_context(|| format!("Could not write {:?}", output))?;
+
+            let skipped = coco_file
+                .annotations
+                .iter()
+                .filter(|annotation| matches!(annotation, CocoAnnotation::PanopticSegmentation(_)))
+                .count();
+            println!(
+                "Wrote {} annotation(s) to {}, skipped {} panoptic annotation(s)",
+                coco_file.annotations.len() - skipped,
+                output.display(),
+                skipped
+            );
+        }
+        Command::Import {
+            csv_file,
+            images_from,
+            output,
+            compact,
+        } => {
+            let csv = fs::read_to_string(&csv_file)
+                .with_context(|| format!("Could not read {:?}", csv_file))?;
+            let images_from = read_coco_file(&images_from)?;
+            let coco_file = from_csv(&csv, &images_from)
+                .with_context(|| format!("Could not import {:?}", csv_file))?;
+
+            let output_file = File::create(&output).context("Could not create output file")?;
+            coco_file
+                .to_writer(BufWriter::new(output_file), !compact)
+                .context("Could not write JSON to output file")?;
+            println!(
+                "Imported {} annotation(s) on {} image(s) into {}",
+                coco_file.annotations.len(),
+                coco_file.images.len(),
+                output.display()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use cococrawl::CocoFile;
+use cococrawl::dedupe::KeepPolicy;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum KeepArg {
+    /// the annotation with the largest area
+    LargestArea,
+    LowestId,
+    /// the annotation earliest in the file
+    First,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "deduped.json")]
+    output: PathBuf,
+
+    /// Annotations of the same image and category overlapping by more than this IoU are
+    /// duplicates. Masks are compared when both have a segmentation, bboxes otherwise.
+    #[clap(long, default_value_t = 0.9)]
+    iou_threshold: f32,
+
+    /// Which annotation of a group of duplicates to keep
+    #[clap(long, value_enum, default_value = "largest-area")]
+    keep: KeepArg,
+
+    /// Only list the duplicate pairs, without writing anything
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let mut coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    if args.dry_run {
+        let pairs = coco_file.find_duplicate_annotations(args.iou_threshold);
+        for (a, b) in &pairs {
+            println!("Annotations {} and {} are duplicates", a, b);
+        }
+        println!("{} duplicate pair(s)", pairs.len());
+        return Ok(());
+    }
+
+    let keep = match args.keep {
+        KeepArg::LargestArea => KeepPolicy::LargestArea,
+        KeepArg::LowestId => KeepPolicy::LowestId,
+        KeepArg::First => KeepPolicy::First,
+    };
+    let removed = coco_file.dedupe_annotations(args.iou_threshold, keep);
+    coco_file.to_path(&args.output, !args.compact)?;
+    println!(
+        "Removed {} duplicate annotation(s), wrote {}",
+        removed.len(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use cococrawl::CocoFile;
+use cococrawl::diff::{DiffOptions, DiffReport};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(ValueEnum, Clone, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// old coco JSON file path
+    #[clap(required = true)]
+    old: PathBuf,
+
+    /// new coco JSON file path
+    #[clap(required = true)]
+    new: PathBuf,
+
+    /// Match images by id instead of file name
+    #[clap(long)]
+    by_id: bool,
+
+    /// Annotations of the same category match when their bbox IoU is above this
+    #[clap(long, default_value = "0.5")]
+    iou_threshold: f32,
+
+    /// Output format for the report
+    #[clap(short, long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+fn read_coco_file(path: &Path) -> Result<CocoFile> {
+    let coco_json =
+        fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+    serde_json::from_str(&coco_json).with_context(|| format!("Could not parse {:?}", path))
+}
+
+fn print_text(report: &DiffReport) {
+    for image in &report.added_images {
+        println!("+ image {}", image);
+    }
+    for image in &report.removed_images {
+        println!("- image {}", image);
+    }
+    for change in &report.changed_images {
+        println!("~ image {}: {}", change.image, change.fields.join(", "));
+    }
+    for changes in &report.annotations {
+        let ids = |ids: &[i64]| {
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if !changes.added.is_empty() {
+            println!(
+                "+ annotations {} on image {}",
+                ids(&changes.added),
+                changes.image
+            );
+        }
+        if !changes.removed.is_empty() {
+            println!(
+                "- annotations {} on image {}",
+                ids(&changes.removed),
+                changes.image
+            );
+        }
+    }
+    for category in &report.added_categories {
+        println!("+ category {} {:?}", category.id, category.name);
+    }
+    for category in &report.removed_categories {
+        println!("- category {} {:?}", category.id, category.name);
+    }
+    for change in &report.changed_categories {
+        println!(
+            "~ category {} {:?} -> {:?}",
+            change.id, change.old_name, change.new_name
+        );
+    }
+
+    let added_annotations: usize = report.annotations.iter().map(|c| c.added.len()).sum();
+    let removed_annotations: usize = report.annotations.iter().map(|c| c.removed.len()).sum();
+    println!(
+        "Images: {} added, {} removed, {} changed. Annotations: {} added, {} removed. \
+         Categories: {} added, {} removed, {} changed",
+        report.added_images.len(),
+        report.removed_images.len(),
+        report.changed_images.len(),
+        added_annotations,
+        removed_annotations,
+        report.added_categories.len(),
+        report.removed_categories.len(),
+        report.changed_categories.len()
+    );
+}
+
+/// Whether the files differ
+fn run(args: &Args) -> Result<bool> {
+    let old = read_coco_file(&args.old)?;
+    let new = read_coco_file(&args.new)?;
+    let options = DiffOptions {
+        by_id: args.by_id,
+        iou_threshold: args.iou_threshold,
+    };
+    let report = old.diff(&new, &options);
+
+    match args.format {
+        OutputFormat::Text => print_text(&report),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(!report.is_empty())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    // like diff(1): 0 for identical files, 1 for differences and 2 for trouble
+    match run(&args) {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use cococrawl::eval::{CategoryEval, EvalParams, EvalStat, IouType, evaluate};
+use cococrawl::export::csv_field;
+use cococrawl::results::CocoResults;
+use cococrawl::{CocoFile, HasID};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum IouTypeArg {
+    /// Box IoU
+    Bbox,
+    /// Mask IoU
+    Segm,
+    /// Object keypoint similarity
+    Keypoints,
+}
+
+impl From<IouTypeArg> for IouType {
+    fn from(iou_type: IouTypeArg) -> Self {
+        match iou_type {
+            IouTypeArg::Bbox => IouType::Bbox,
+            IouTypeArg::Segm => IouType::Segm,
+            IouTypeArg::Keypoints => IouType::Keypoints,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Ground truth coco JSON file path
+    #[clap(required = true)]
+    ground_truth: PathBuf,
+
+    /// Predictions in the COCO results format
+    #[clap(required = true)]
+    predictions: PathBuf,
+
+    /// What predictions are matched to the ground truth by
+    #[clap(long, value_enum, default_value = "bbox")]
+    iou_type: IouTypeArg,
+
+    /// Print the metrics as JSON instead of tables
+    #[clap(long)]
+    json: bool,
+
+    /// Also write the per category metrics to this CSV file
+    #[clap(long)]
+    per_category_csv: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct EvalReport<'a> {
+    iou_type: &'a str,
+    stats: Vec<EvalStat>,
+    per_category: Vec<CategoryEval>,
+}
+
+fn write_per_category_csv(
+    path: &Path,
+    per_category: &[CategoryEval],
+    names: &HashMap<i32, &str>,
+) -> Result<()> {
+    let mut csv = String::from("category_id,name,ap,ap50,ap75,ar\n");
+    for category in per_category {
+        let name = names
+            .get(&category.category_id)
+            .copied()
+            .unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{:.6},{:.6},{:.6},{:.6}\n",
+            category.category_id,
+            csv_field(name),
+            category.ap,
+            category.ap50,
+            category.ap75,
+            category.ar
+        ));
+    }
+    fs::write(path, csv).with_context(|| format!("Could not write {:?}", path))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json =
+        fs::read_to_string(&args.ground_truth).context("Could not read COCO JSON file")?;
+    let ground_truth: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+    let results = CocoResults::from_path(&args.predictions)?;
+
+    let params = EvalParams::new(args.iou_type.into());
+    let evaluation = evaluate(&ground_truth, &results, &params)?;
+    let stats = evaluation.stats();
+    let per_category = evaluation.per_category();
+
+    let names: HashMap<i32, &str> = ground_truth
+        .categories
+        .iter()
+        .flatten()
+        .map(|category| (category.id(), category.name()))
+        .collect();
+
+    if let Some(path) = &args.per_category_csv {
+        write_per_category_csv(path, &per_category, &names)?;
+    }
+
+    if args.json {
+        let iou_type = args
+            .iou_type
+            .to_possible_value()
+            .expect("IoU types have names");
+        let report = EvalReport {
+            iou_type: iou_type.get_name(),
+            stats,
+            per_category,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    for stat in &stats {
+        println!("{}", stat);
+    }
+    println!("\nPer Category:");
+    println!(
+        "{:>8}  {:<24} {:>7} {:>7} {:>7}",
+        "ID", "Name", "AP", "AP50", "AP75"
+    );
+    for category in &per_category {
+        let name = names
+            .get(&category.category_id)
+            .copied()
+            .unwrap_or_default();
+        println!(
+            "{:>8}  {:<24} {:>7.3} {:>7.3} {:>7.3}",
+            category.category_id, name, category.ap, category.ap50, category.ap75
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use cococrawl::path_utils::create_coco_image_path;
+use cococrawl::{CocoAnnotation, CocoFile, HasID};
+use std::collections::HashSet;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "filtered.json")]
+    output: PathBuf,
+
+    /// comma separated category names to keep
+    /// cocofilter dataset.json -o subset.json --categories person,car
+    #[clap(short, long, required = true, value_delimiter = ',')]
+    categories: Vec<String>,
+
+    /// treat --categories as category ids instead of names
+    #[clap(long)]
+    by_id: bool,
+
+    /// drop images that have no annotations left after filtering
+    #[clap(long)]
+    drop_empty: bool,
+
+    /// Force absolute paths for image file names in the output file.
+    #[clap(short, long)]
+    absolute_paths: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    if coco_file.make_category_map().is_empty() {
+        bail!(
+            "{} has no categories to filter by",
+            args.coco_file.display()
+        );
+    }
+
+    let keep_category_ids = coco_file.find_category_ids(&args.categories, args.by_id)?;
+
+    let annotations: Vec<CocoAnnotation> = coco_file
+        .annotations
+        .iter()
+        .filter_map(|annotation| match annotation {
+            CocoAnnotation::PanopticSegmentation(ann) => {
+                // keep only the matching segments, dropping the annotation if none are left
+                let mut ann = ann.clone();
+                ann.segments_info
+                    .retain(|segment| keep_category_ids.contains(&segment.category_id));
+                (!ann.segments_info.is_empty()).then_some(CocoAnnotation::PanopticSegmentation(ann))
+            }
+            // captions have no category so they stay with their image
+            CocoAnnotation::ImageCaptioning(_) => Some(annotation.clone()),
+            _ => annotation
+                .category_id()
+                .filter(|id| keep_category_ids.contains(id))
+                .map(|_| annotation.clone()),
+        })
+        .collect();
+
+    let annotated_image_ids: HashSet<i64> = annotations
+        .iter()
+        .filter(|annotation| !matches!(annotation, CocoAnnotation::ImageCaptioning(_)))
+        .map(|annotation| annotation.image_id())
+        .collect();
+
+    // create output file upfront so canonicalize works
+    let output_file = File::create(&args.output).context("Could not create output file")?;
+
+    let images = coco_file
+        .images
+        .iter()
+        .filter(|image| !args.drop_empty || annotated_image_ids.contains(&image.id))
+        .map(|image| {
+            let mut new_image = image.clone();
+            new_image.file_name = create_coco_image_path(
+                args.output.as_path(),
+                new_image.get_absolute_path(&args.coco_file)?.as_path(),
+                args.absolute_paths,
+            )
+            .with_context(|| {
+                format!("Could not create COCO image path for image id {}", image.id)
+            })?;
+            Ok(new_image)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let kept_image_ids: HashSet<i64> = images.iter().map(|image| image.id).collect();
+    let annotations: Vec<CocoAnnotation> = annotations
+        .into_iter()
+        .filter(|annotation| kept_image_ids.contains(&annotation.image_id()))
+        .collect();
+
+    eprintln!(
+        "Kept {} of {} images and {} of {} annotations",
+        images.len(),
+        coco_file.images.len(),
+        annotations.len(),
+        coco_file.annotations.len()
+    );
+
+    let output_coco_file = CocoFile {
+        info: coco_file.info.clone(),
+        images,
+        annotations,
+        categories: coco_file.categories.as_ref().map(|categories| {
+            categories
+                .iter()
+                .filter(|category| keep_category_ids.contains(&category.id()))
+                .cloned()
+                .collect()
+        }),
+        licenses: coco_file.licenses.clone(),
+    };
+
+    output_coco_file
+        .to_writer(BufWriter::new(output_file), !args.compact)
+        .context("Could not write JSON to output file")?;
+
+    Ok(())
+}
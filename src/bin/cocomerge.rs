@@ -1,18 +1,70 @@
-use chrono::{Datelike, Utc};
-use clap::Parser;
+use chrono::{DateTime, Datelike, Utc};
+use clap::{Parser, ValueEnum};
 use cococrawl::path_utils::create_coco_image_path;
 use cococrawl::{
     CocoAnnotation, CocoCategory, CocoFile, CocoImage, CocoInfo, CocoLicense, HasCategoryID, HasID,
 };
 // use indicatif::ParallelProgressIterator;
 // use rayon::prelude::*;
-use serde_json;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::io::BufWriter;
+use std::mem::Discriminant;
 use std::path::PathBuf;
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum KeepInfo {
+    /// take description, contributor, url and any unknown keys from the first input
+    First,
+    /// join the distinct non-empty values of all inputs, unknown keys come from the first
+    /// input that has them
+    Concat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OnClash {
+    /// drop the image (and its annotations) that clashes with an earlier image id
+    Ignore,
+    /// exit with an error
+    Error,
+    /// give the clashing image a new unique id
+    Reassign,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum IdSpace {
+    /// one id sequence shared by every annotation type and panoptic segment, so an id names a
+    /// single annotation of the whole file
+    Global,
+    /// a separate id sequence per annotation type, so object detection and caption ids stay
+    /// independent, but an id alone no longer identifies an annotation
+    PerType,
+}
+
+/// The annotation ids taken so far in one id space
+#[derive(Default)]
+struct AnnotationIds {
+    seen: HashSet<i64>,
+    next_unseen: i64,
+}
+
+impl AnnotationIds {
+    /// Takes `id` if it's still free, otherwise the next unused id, and returns the one taken
+    fn claim(&mut self, id: i64) -> i64 {
+        let id = if self.seen.contains(&id) {
+            self.next_unseen
+        } else {
+            id
+        };
+        if id >= self.next_unseen {
+            self.next_unseen = id + 1;
+        }
+        self.seen.insert(id);
+        id
+    }
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
@@ -26,10 +78,19 @@ struct Args {
 
     /// If files contain clashing image ids, reassign ids to new unique ids
     /// If not set then clashing ids will be ignored and the image id from the first file will be
-    /// used
-    #[clap(short, long)]
+    /// used. Same as `--on-clash reassign`.
+    #[clap(short, long, conflicts_with = "on_clash")]
     reassign_clashing_ids: bool,
 
+    /// What to do with images whose id clashes with an image of an earlier file
+    #[clap(long, value_enum, default_value = "ignore")]
+    on_clash: OnClash,
+
+    /// Whether clashing annotation ids are reassigned across all annotations or only among
+    /// annotations of the same type
+    #[clap(long, value_enum, default_value = "global")]
+    id_space: IdSpace,
+
     /// Version string for the COCO info section
     #[clap(short, long, default_value = "1.0.0")]
     version_string: String,
@@ -37,10 +98,44 @@ struct Args {
     /// Force absolute paths for image file names in the merged output file.
     #[clap(short, long)]
     absolute_paths: bool,
+
+    /// Prepend a directory named after each source file's stem to its images' relative file
+    /// names (coco1.json: images/0001.jpg -> coco1/images/0001.jpg), so same-named images from
+    /// different sources don't collide. Images with absolute paths are left as they are.
+    #[clap(long, conflicts_with = "absolute_paths")]
+    prefix_by_source: bool,
+
+    /// Keep the inputs' info sections instead of blanking them. The year is taken from the
+    /// earliest input either way.
+    #[clap(long, value_enum)]
+    keep_info: Option<KeepInfo>,
+
+    /// Recompute the area of every annotation from its segmentation, or its bbox when it has
+    /// none, instead of keeping the inputs' areas
+    #[clap(long)]
+    recompute_area: bool,
+
+    /// Simplify polygon segmentations with Douglas-Peucker, dropping points at most EPSILON
+    /// pixels off the outline. RLE masks are left untouched.
+    #[clap(long, value_name = "EPSILON")]
+    simplify_polygons: Option<f32>,
+
+    /// Don't print warnings about ignored clashing images or the merge summary
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
 }
 
 fn main() {
     let args = Args::parse();
+    let on_clash = if args.reassign_clashing_ids {
+        OnClash::Reassign
+    } else {
+        args.on_clash
+    };
 
     let coco_files: Vec<CocoFile> = args
         .coco_files
@@ -51,9 +146,9 @@ fn main() {
         })
         .collect();
 
-
     // create output file now so canonicalize doesn't fail later
-    let output_file = File::create(&args.output_path).expect("Could not create output COCO JSON file");
+    let output_file =
+        File::create(&args.output_path).expect("Could not create output COCO JSON file");
 
     // Categories don't hash on id but instead they hash on the everything else in the struct.
     // This allows us to use this as a ground truth for making sure all categories have the same id
@@ -61,74 +156,89 @@ fn main() {
     let mut category_set: HashSet<CocoCategory> = HashSet::new();
     let mut category_seen_ids: HashSet<i32> = HashSet::new();
     let mut next_unseen_category_id: i32 = 0; // this can technically start at any number but we start at 0 for simplicity
+    let mut unified_category_count: usize = 0;
 
     // Licenses work the same way as categories
     let mut license_set: HashSet<CocoLicense> = HashSet::new();
     let mut license_seen_ids: HashSet<i32> = HashSet::new();
     let mut next_unseen_license_id: i32 = 0;
+    let mut unified_license_count: usize = 0;
 
     let mut images: Vec<CocoImage> = Vec::new();
     let mut seen_image_ids: HashSet<i64> = HashSet::new();
     let mut next_unseen_image_id: i64 = 0;
+    let mut reassigned_image_count: usize = 0;
+    let mut dropped_image_count: usize = 0;
 
     let mut annotations: Vec<CocoAnnotation> = Vec::new();
-    let mut seen_annotation_ids: HashSet<i64> = HashSet::new();
-    let mut next_unseen_annotation_id: i64 = 0;
+    let mut annotation_ids: HashMap<Option<Discriminant<CocoAnnotation>>, AnnotationIds> =
+        HashMap::new();
+    let mut dropped_annotation_count: usize = 0;
+
+    let source_prefixes = source_prefixes(&args.coco_files);
 
     coco_files.iter().enumerate().for_each(|(file_index, coco_file)| {
         let coco_file_path = &args.coco_files[file_index];
 
         // categories logic
         let mut category_id_remap: HashMap<i32, i32> = HashMap::new();
-        coco_file.categories.as_ref().map(|categories| categories.iter().for_each(|category| {
-            if let Some(entry) = category_set.get(category) {
-                // category id exists so we use the existing id
-                category_id_remap.insert(category.id(), entry.id());
-            } else {
-                if category_seen_ids.contains(&category.id()) {
-                    // category hasn't been seen yet and it's id clashes with an existing category
-                    let mut new_category = category.clone();
-                    new_category.set_id(next_unseen_category_id);
-                    next_unseen_category_id += 1;
-                    category_id_remap.insert(category.id(), new_category.id());
-                    category_set.insert(new_category);
+        if let Some(categories) = coco_file.categories.as_ref() {
+            categories.iter().for_each(|category| {
+                if let Some(entry) = category_set.get(category) {
+                    // category id exists so we use the existing id
+                    category_id_remap.insert(category.id(), entry.id());
+                    unified_category_count += 1;
                 } else {
-                    // category hasn't been seen yet and it's id doesn't clash
-                    category_seen_ids.insert(category.id());
-                    if category.id() >= next_unseen_category_id {
-                        next_unseen_category_id = category.id() + 1;
+                    if category_seen_ids.contains(&category.id()) {
+                        // category hasn't been seen yet and it's id clashes with an existing category
+                        let mut new_category = category.clone();
+                        new_category.set_id(next_unseen_category_id);
+                        next_unseen_category_id += 1;
+                        category_seen_ids.insert(new_category.id());
+                        category_id_remap.insert(category.id(), new_category.id());
+                        category_set.insert(new_category);
+                    } else {
+                        // category hasn't been seen yet and it's id doesn't clash
+                        category_seen_ids.insert(category.id());
+                        if category.id() >= next_unseen_category_id {
+                            next_unseen_category_id = category.id() + 1;
+                        }
+                        category_id_remap.insert(category.id(), category.id());
+                        category_set.insert(category.clone());
                     }
-                    category_id_remap.insert(category.id(), category.id());
-                    category_set.insert(category.clone());
                 }
-            }
-        }));
+            });
+        }
 
         // licenses logic
         let mut license_id_remap: HashMap<i32, i32> = HashMap::new();
-        coco_file.licenses.as_ref().map(|licenses| licenses.iter().for_each(|license| {
-            if let Some(entry) = license_set.get(license) {
-                // license id exists so we use the existing id
-                license_id_remap.insert(license.id(), entry.id());
-            } else {
-                if license_seen_ids.contains(&license.id()) {
-                    // license hasn't been seen yet and it's id clashes with an existing license
-                    let mut new_license = license.clone();
-                    new_license.set_id(next_unseen_license_id);
-                    next_unseen_license_id += 1;
-                    license_id_remap.insert(license.id(), new_license.id());
-                    license_set.insert(new_license);
+        if let Some(licenses) = coco_file.licenses.as_ref() {
+            licenses.iter().for_each(|license| {
+                if let Some(entry) = license_set.get(license) {
+                    // license id exists so we use the existing id
+                    license_id_remap.insert(license.id(), entry.id());
+                    unified_license_count += 1;
                 } else {
-                    // license hasn't been seen yet and it's id doesn't clash
-                    license_seen_ids.insert(license.id());
-                    if license.id() >= next_unseen_license_id {
-                        next_unseen_license_id = license.id() + 1;
+                    if license_seen_ids.contains(&license.id()) {
+                        // license hasn't been seen yet and it's id clashes with an existing license
+                        let mut new_license = license.clone();
+                        new_license.set_id(next_unseen_license_id);
+                        next_unseen_license_id += 1;
+                        license_seen_ids.insert(new_license.id());
+                        license_id_remap.insert(license.id(), new_license.id());
+                        license_set.insert(new_license);
+                    } else {
+                        // license hasn't been seen yet and it's id doesn't clash
+                        license_seen_ids.insert(license.id());
+                        if license.id() >= next_unseen_license_id {
+                            next_unseen_license_id = license.id() + 1;
+                        }
+                        license_id_remap.insert(license.id(), license.id());
+                        license_set.insert(license.clone());
                     }
-                    license_id_remap.insert(license.id(), license.id());
-                    license_set.insert(license.clone());
                 }
-            }
-        }));
+            });
+        }
 
         // images logic
         let mut image_id_remap: HashMap<i64, i64> = HashMap::new();
@@ -136,48 +246,67 @@ fn main() {
             let mut new_image = image.clone();
 
             // hanlde image path
-            new_image.file_name = create_coco_image_path(
-                &args.output_path.as_path(), 
-                new_image.get_absolute_path(&coco_file_path.as_path())
-                    .expect(format!(
-                        "Could not get absolute image path for image id {} in file {}",
-                        new_image.id(),
-                        coco_file_path.to_string_lossy(),
-                    ).as_str())
-                    .as_path(),
-                args.absolute_paths,
-            ).expect(format!(
-                "Could not create COCO image path for image id {} in file {}",
-                new_image.id(),
-                coco_file_path.to_string_lossy(),
-            ).as_str());
+            new_image.file_name = if args.prefix_by_source && !image.file_name.is_absolute() {
+                source_prefixes[file_index].join(&image.file_name)
+            } else {
+                create_coco_image_path(
+                    args.output_path.as_path(),
+                    new_image.get_absolute_path(coco_file_path.as_path())
+                        .unwrap_or_else(|_| panic!(
+                            "Could not get absolute image path for image id {} in file {}",
+                            new_image.id(),
+                            coco_file_path.to_string_lossy(),
+                        ))
+                        .as_path(),
+                    args.absolute_paths,
+                ).unwrap_or_else(|_| panic!(
+                    "Could not create COCO image path for image id {} in file {}",
+                    new_image.id(),
+                    coco_file_path.to_string_lossy(),
+                ))
+            };
 
             // handle license
             if let Some(new_license_id) = new_image.license {
-                new_image.license = Some(license_id_remap.get(&new_license_id)
-                    .expect(format!(
+                new_image.license = Some(*license_id_remap.get(&new_license_id)
+                    .unwrap_or_else(|| panic!(
                         "License id {} not found in remap for image id {} in file {}",
                         new_license_id,
                         new_image.id(),
                         coco_file_path.to_string_lossy(),
-                    ).as_str())
-                    .clone());
+                    )));
             }
 
             if seen_image_ids.contains(&image.id()) {
-                if args.reassign_clashing_ids {
-                    new_image.set_id(next_unseen_image_id);
-                    next_unseen_image_id += 1;
-                    seen_image_ids.insert(new_image.id());
-                    image_id_remap.insert(image.id(), new_image.id());
-                    images.push(new_image);
-                } else {
-                    // ignore clashing image
-                    eprintln!(
-                        "Warning: Image id {} in file {} clashes with an existing image id. Ignoring this image.",
-                        image.id(),
-                        coco_file_path.to_string_lossy(),
-                    );
+                match on_clash {
+                    OnClash::Reassign => {
+                        new_image.set_id(next_unseen_image_id);
+                        next_unseen_image_id += 1;
+                        seen_image_ids.insert(new_image.id());
+                        image_id_remap.insert(image.id(), new_image.id());
+                        images.push(new_image);
+                        reassigned_image_count += 1;
+                    }
+                    OnClash::Error => {
+                        eprintln!(
+                            "Error: Image id {} in file {} clashes with an existing image id",
+                            image.id(),
+                            coco_file_path.to_string_lossy(),
+                        );
+                        // don't leave the empty output file behind
+                        let _ = fs::remove_file(&args.output_path);
+                        std::process::exit(1);
+                    }
+                    OnClash::Ignore => {
+                        dropped_image_count += 1;
+                        if !args.quiet {
+                            eprintln!(
+                                "Warning: Image id {} in file {} clashes with an existing image id. Ignoring this image.",
+                                image.id(),
+                                coco_file_path.to_string_lossy(),
+                            );
+                        }
+                    }
                 }
             } else {
                 if new_image.id() >= next_unseen_image_id {
@@ -195,136 +324,234 @@ fn main() {
             if let Some(new_annotation_id) = image_id_remap.get(&annotation.image_id()) {
                 let mut new_annotation = annotation.clone();
                 new_annotation.set_image_id(*new_annotation_id);
+                let ids = annotation_ids
+                    .entry(match args.id_space {
+                        IdSpace::Global => None,
+                        IdSpace::PerType => Some(std::mem::discriminant(annotation)),
+                    })
+                    .or_default();
 
                 // handle category id remappings and annotation id remapping
                 match new_annotation {
                     CocoAnnotation::KeypointDetection(ref mut ann) => {
-                        let new_category_id = *category_id_remap.get(&ann.category_id()).expect(
-                            format!(
+                        let new_category_id = *category_id_remap.get(&ann.category_id()).unwrap_or_else(|| panic!(
                                 "Category id {} not found in remap for annotation id {} in file {}",
                                 ann.category_id(),
                                 ann.id(),
                                 coco_file_path.to_string_lossy(),
-                            ).as_str()
+                            )
                         );
                         ann.set_category_id(new_category_id);
 
-                        if seen_annotation_ids.contains(&ann.id()) {
-                            ann.set_id(next_unseen_annotation_id);
-                            next_unseen_annotation_id += 1;
-                            seen_annotation_ids.insert(ann.id());
-                        } else {
-                            if ann.id() >= next_unseen_annotation_id {
-                                next_unseen_annotation_id = ann.id() + 1;
-                            }
-                            seen_annotation_ids.insert(ann.id());
-                        }
+                        ann.set_id(ids.claim(ann.id()));
                     },
                     CocoAnnotation::PanopticSegmentation(ref mut ann) => {
                         ann.segments_info.iter_mut().for_each(|segment| {
-                            let new_category_id = *category_id_remap.get(&segment.category_id).expect(
-                                format!(
+                            let new_category_id = *category_id_remap.get(&segment.category_id).unwrap_or_else(|| panic!(
                                     "Category id {} not found in remap for segment info id {} in file {}",
                                     segment.category_id,
                                     segment.id(),
                                     coco_file_path.to_string_lossy(),
-                                ).as_str()
+                                )
                             );
                             segment.category_id = new_category_id;
 
-                            // special case. We want unique segment ids across the whole dataset
-                            // including other annotation types
-                            if seen_annotation_ids.contains(&segment.id()) {
-                                segment.set_id(next_unseen_annotation_id);
-                                next_unseen_annotation_id += 1;
-                                seen_annotation_ids.insert(segment.id());
-                            } else {
-                                if segment.id() >= next_unseen_annotation_id {
-                                    next_unseen_annotation_id = segment.id() + 1;
-                                }
-                                seen_annotation_ids.insert(segment.id());
-                            }
+                            // special case. Segment ids share the id space of the annotations,
+                            // across every type with --id-space global
+                            segment.set_id(ids.claim(segment.id()));
                         });
                     },
                     CocoAnnotation::ImageCaptioning(ref mut ann) => {
-                        if seen_annotation_ids.contains(&ann.id()) {
-                            ann.set_id(next_unseen_annotation_id);
-                            next_unseen_annotation_id += 1;
-                            seen_annotation_ids.insert(ann.id());
-                        } else {
-                            if ann.id() >= next_unseen_annotation_id {
-                                next_unseen_annotation_id = ann.id() + 1;
-                            }
-                            seen_annotation_ids.insert(ann.id());
-                        }
+                        ann.set_id(ids.claim(ann.id()));
                     },
                     CocoAnnotation::ObjectDetection(ref mut ann) => {
-                        let new_category_id = *category_id_remap.get(&ann.category_id()).expect(
-                            format!(
-                                "Category id {} not found in remap for annotation id {} in file {}", 
+                        let new_category_id = *category_id_remap.get(&ann.category_id()).unwrap_or_else(|| panic!(
+                                "Category id {} not found in remap for annotation id {} in file {}",
                                 ann.category_id(),
                                 ann.id(),
                                 coco_file_path.to_string_lossy(),
-                            ).as_str()
+                            )
                         );
                         ann.set_category_id(new_category_id);
 
-                        if seen_annotation_ids.contains(&ann.id()) {
-                            ann.set_id(next_unseen_annotation_id);
-                            next_unseen_annotation_id += 1;
-                            seen_annotation_ids.insert(ann.id());
-                        } else {
-                            if ann.id() >= next_unseen_annotation_id {
-                                next_unseen_annotation_id = ann.id() + 1;
-                            }
-                            seen_annotation_ids.insert(ann.id());
-                        }
+                        ann.set_id(ids.claim(ann.id()));
                     },
                     CocoAnnotation::DensePose(ref mut ann) => {
-                        let new_category_id = *category_id_remap.get(&ann.category_id()).expect(
-                            format!(
-                                "Category id {} not found in remap for annotation id {} in file {}", 
+                        let new_category_id = *category_id_remap.get(&ann.category_id()).unwrap_or_else(|| panic!(
+                                "Category id {} not found in remap for annotation id {} in file {}",
                                 ann.category_id(),
                                 ann.id(),
                                 coco_file_path.to_string_lossy(),
-                            ).as_str()
+                            )
                         );
                         ann.set_category_id(new_category_id);
 
-                        if seen_annotation_ids.contains(&ann.id()) {
-                            ann.set_id(next_unseen_annotation_id);
-                            next_unseen_annotation_id += 1;
-                            seen_annotation_ids.insert(ann.id());
-                        } else {
-                            if ann.id() >= next_unseen_annotation_id {
-                                next_unseen_annotation_id = ann.id() + 1;
-                            }
-                            seen_annotation_ids.insert(ann.id());
-                        }
+                        ann.set_id(ids.claim(ann.id()));
                     },
                 }
 
                 annotations.push(new_annotation);
+            } else {
+                dropped_annotation_count += 1;
             }
         });
     });
 
-    let merged_file = CocoFile {
-        info: Some(CocoInfo {
-            year: Utc::now().year(),
-            version: args.version_string,
-            description: "".to_string(),
-            contributor: "".to_string(),
-            url: "".to_string(),
-            date_created: Utc::now(),
-        }),
-        licenses: Some(license_set.into_iter().collect()),
+    // the sets iterate in a different order every run, sort everything so identical inputs
+    // give identical output
+    let kept_image_count = images.len() - reassigned_image_count;
+    let kept_annotation_count = annotations.len();
+
+    let mut licenses: Vec<CocoLicense> = license_set.into_iter().collect();
+    licenses.sort_by_key(|license| license.id());
+    let mut categories: Vec<CocoCategory> = category_set.into_iter().collect();
+    categories.sort_by_key(|category| category.id());
+    images.sort_by_key(|image| image.id());
+    // panoptic annotations have no id and keep their relative order at the front
+    annotations.sort_by_key(|annotation| annotation.id());
+
+    let mut merged_file = CocoFile {
+        info: Some(merge_info(&coco_files, args.keep_info, args.version_string)),
+        licenses: Some(licenses),
         images,
         annotations,
-        categories: Some(category_set.into_iter().collect()),
+        categories: Some(categories),
     };
+    let removed_point_count = args
+        .simplify_polygons
+        .map(|epsilon| merged_file.simplify_polygons(epsilon));
+    if args.recompute_area {
+        merged_file.recompute_areas();
+    }
 
-    let writer = BufWriter::new(output_file);
-    serde_json::to_writer_pretty(writer, &merged_file)
+    merged_file
+        .to_writer(BufWriter::new(output_file), !args.compact)
         .expect("Could not write COCO JSON to output file");
+
+    // printed even with --quiet so dropped images never go unnoticed
+    if dropped_image_count > 0 {
+        eprintln!(
+            "Warning: Dropped {} image(s) with clashing ids and their annotations",
+            dropped_image_count
+        );
+    }
+
+    if !args.quiet {
+        eprintln!("Merged {} file(s)", coco_files.len());
+        eprintln!(
+            "  Images: {} kept, {} reassigned, {} dropped",
+            kept_image_count, reassigned_image_count, dropped_image_count
+        );
+        eprintln!(
+            "  Annotations: {} kept, {} dropped",
+            kept_annotation_count, dropped_annotation_count
+        );
+        eprintln!(
+            "  Categories: {} unified, {} in total",
+            unified_category_count,
+            merged_file.categories.as_ref().map_or(0, Vec::len)
+        );
+        eprintln!(
+            "  Licenses: {} unified, {} in total",
+            unified_license_count,
+            merged_file.licenses.as_ref().map_or(0, Vec::len)
+        );
+        if let Some(removed_point_count) = removed_point_count {
+            eprintln!("  Polygon points removed: {}", removed_point_count);
+        }
+    }
+}
+
+/// The current time, or `SOURCE_DATE_EPOCH` if it's set so reruns can reproduce an output
+/// byte for byte
+fn creation_time() -> DateTime<Utc> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .unwrap_or_else(Utc::now)
+}
+
+fn merge_info(coco_files: &[CocoFile], keep_info: Option<KeepInfo>, version: String) -> CocoInfo {
+    let infos: Vec<&CocoInfo> = coco_files
+        .iter()
+        .filter_map(|file| file.info.as_ref())
+        .collect();
+    let now = creation_time();
+    let Some(keep_info) = keep_info else {
+        return CocoInfo {
+            year: now.year(),
+            version,
+            description: "".to_string(),
+            contributor: "".to_string(),
+            url: "".to_string(),
+            date_created: now,
+            extra: Default::default(),
+        };
+    };
+
+    let merge_field = |field: fn(&CocoInfo) -> &String| -> String {
+        match keep_info {
+            KeepInfo::First => infos
+                .first()
+                .map(|info| field(info).clone())
+                .unwrap_or_default(),
+            KeepInfo::Concat => {
+                let mut values: Vec<&str> = Vec::new();
+                for info in &infos {
+                    let value = field(info).as_str();
+                    if !value.is_empty() && !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                values.join("; ")
+            }
+        }
+    };
+
+    // unknown keys come from the first file, concat adds keys only later files have
+    let mut extra = HashMap::new();
+    for info in infos.iter().take(match keep_info {
+        KeepInfo::First => 1,
+        KeepInfo::Concat => infos.len(),
+    }) {
+        for (key, value) in &info.extra {
+            extra.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    CocoInfo {
+        year: infos
+            .iter()
+            .map(|info| info.year)
+            .min()
+            .unwrap_or_else(|| now.year()),
+        version,
+        description: merge_field(|info| &info.description),
+        contributor: merge_field(|info| &info.contributor),
+        url: merge_field(|info| &info.url),
+        date_created: now,
+        extra,
+    }
+}
+
+/// One directory name per source file, its file stem with a numeric suffix for repeated stems
+/// (data/coco.json, other/coco.json -> coco, coco-2)
+fn source_prefixes(coco_file_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut stem_counts: HashMap<String, usize> = HashMap::new();
+    coco_file_paths
+        .iter()
+        .map(|path| {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "source".to_string());
+            let count = stem_counts.entry(stem.clone()).or_insert(0);
+            *count += 1;
+            match *count {
+                1 => PathBuf::from(stem),
+                n => PathBuf::from(format!("{}-{}", stem, n)),
+            }
+        })
+        .collect()
 }
@@ -0,0 +1,71 @@
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use cococrawl::CocoFile;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "pruned.json")]
+    output: PathBuf,
+
+    /// Remove categories with fewer annotations than this, together with their annotations.
+    /// Panoptic segments are counted and removed one by one.
+    #[clap(long)]
+    min_annotations: Option<usize>,
+
+    /// Remove images left without annotations, captions don't count
+    #[clap(long)]
+    drop_empty_images: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.min_annotations.is_none() && !args.drop_empty_images {
+        bail!("Nothing to do, pass --min-annotations and/or --drop-empty-images");
+    }
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let mut coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    if let Some(min_annotations) = args.min_annotations {
+        let category_count = coco_file.categories.iter().flatten().count();
+        let report = coco_file.prune_categories(min_annotations);
+        println!(
+            "Removed {} of {} categories with fewer than {} annotations and their {} annotation(s)",
+            report.categories.len(),
+            category_count,
+            min_annotations,
+            report.annotations
+        );
+    }
+    if args.drop_empty_images {
+        let report = coco_file.prune_unannotated_images();
+        println!(
+            "Removed {} image(s) without annotations and {} caption(s)",
+            report.images.len(),
+            report.annotations
+        );
+    }
+
+    coco_file.to_path(&args.output, !args.compact)?;
+    println!(
+        "Kept {} images, {} annotations and {} categories, wrote {}",
+        coco_file.images.len(),
+        coco_file.annotations.len(),
+        coco_file.categories.iter().flatten().count(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use cococrawl::CocoFile;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "remapped.json")]
+    output: PathBuf,
+
+    /// JSON mapping file with optional "drop", "merge" and "rename" rules, applied in that
+    /// order and all referring to the category names of the input
+    #[clap(short, long)]
+    map: Option<PathBuf>,
+
+    /// Finally renumber the categories 1, 2, 3, ... in the order of their ids
+    #[clap(long)]
+    contiguous: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Mapping {
+    /// old name -> new name
+    #[serde(default)]
+    rename: BTreeMap<String, String>,
+
+    /// target name -> names merged into it
+    #[serde(default)]
+    merge: BTreeMap<String, Vec<String>>,
+
+    /// names whose categories and annotations are removed
+    #[serde(default)]
+    drop: Vec<String>,
+}
+
+fn category_id(coco_file: &CocoFile, name: &str) -> Result<i32> {
+    let ids = coco_file.find_category_ids(&[name], false)?;
+    Ok(*ids.iter().next().unwrap())
+}
+
+/// Number of annotations referring to category `id`
+fn annotation_count(coco_file: &CocoFile, id: i32) -> usize {
+    coco_file
+        .annotations
+        .iter()
+        .filter(|annotation| annotation.category_ids().contains(&id))
+        .count()
+}
+
+fn print_categories(title: &str, coco_file: &CocoFile) {
+    let category_map = coco_file.make_category_id_map();
+    let mut entries: Vec<_> = category_map.values().collect();
+    entries.sort_by_key(|entry| entry.id);
+
+    println!("{}:", title);
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.category.name().len())
+        .chain([4])
+        .max()
+        .unwrap();
+    println!(
+        "  {:>6}  {:<name_width$}  {:>11}",
+        "ID", "Name", "Annotations"
+    );
+    for entry in entries {
+        println!(
+            "  {:>6}  {:<name_width$}  {:>11}",
+            entry.id,
+            entry.category.name(),
+            entry.annotations.len()
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let mut coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    let mapping: Mapping = match &args.map {
+        Some(path) => {
+            let mapping_json = fs::read_to_string(path).context("Could not read mapping file")?;
+            serde_json::from_str(&mapping_json).context("Could not parse mapping file")?
+        }
+        None => Mapping::default(),
+    };
+    if args.map.is_none() && !args.contiguous {
+        bail!("Nothing to do, pass --map and/or --contiguous");
+    }
+
+    // resolve every name up front so a typo fails before anything is changed
+    let drop_ids = mapping
+        .drop
+        .iter()
+        .map(|name| category_id(&coco_file, name).context("Invalid drop rule"))
+        .collect::<Result<Vec<i32>>>()?;
+    let merges = mapping
+        .merge
+        .iter()
+        .map(|(into, from)| {
+            let into_id = category_id(&coco_file, into)
+                .with_context(|| format!("Invalid merge rule for {:?}", into))?;
+            let from_ids = from
+                .iter()
+                .map(|name| category_id(&coco_file, name))
+                .collect::<Result<Vec<i32>>>()
+                .with_context(|| format!("Invalid merge rule for {:?}", into))?;
+            Ok((into, into_id, from_ids))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let renames = mapping
+        .rename
+        .iter()
+        .map(|(old, new)| {
+            let id = category_id(&coco_file, old)
+                .with_context(|| format!("Invalid rename rule for {:?}", old))?;
+            Ok((old, new, id))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    print_categories("Before", &coco_file);
+
+    let mut rules: Vec<String> = Vec::new();
+    if !drop_ids.is_empty() {
+        let removed = coco_file.drop_categories(&drop_ids)?;
+        rules.push(format!(
+            "drop {}: {} removed",
+            mapping.drop.join(", "),
+            removed
+        ));
+    }
+    for (into, into_id, from_ids) in merges {
+        let count: usize = from_ids
+            .iter()
+            .filter(|&&id| id != into_id)
+            .map(|&id| annotation_count(&coco_file, id))
+            .sum();
+        coco_file
+            .merge_categories(&from_ids, into_id)
+            .with_context(|| format!("Could not merge into {:?}", into))?;
+        rules.push(format!("merge into {}: {} moved", into, count));
+    }
+    for (old, new, id) in renames {
+        coco_file
+            .rename_category(id, new)
+            .with_context(|| format!("Could not rename {:?}", old))?;
+        let count = annotation_count(&coco_file, id);
+        rules.push(format!("rename {} -> {}: {} moved", old, new, count));
+    }
+    if args.contiguous {
+        let remap = coco_file.remap_category_ids_contiguous(1);
+        let changed = remap.iter().filter(|(old, new)| old != new).count();
+        rules.push(format!("contiguous ids: {} categories renumbered", changed));
+    }
+
+    print_categories("After", &coco_file);
+    println!("Rules:");
+    for line in &rules {
+        println!("  {}", line);
+    }
+
+    coco_file.to_path(&args.output, !args.compact)?;
+    Ok(())
+}
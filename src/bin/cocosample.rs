@@ -0,0 +1,117 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cococrawl::{CocoCategory, CocoFile, HasID};
+use rand::{Rng, rng};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    #[clap(required = true)]
+    coco_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "sample.json")]
+    output: PathBuf,
+
+    /// Pick images until every category is in up to this many of them, rarest categories
+    /// first. An image counts toward every category it contains.
+    #[clap(long, required_unless_present = "images", conflicts_with = "images")]
+    per_category: Option<usize>,
+
+    /// Pick this many images at random
+    #[clap(long)]
+    images: Option<usize>,
+
+    /// Seed for reproducible samples. Without a seed one is drawn at random and printed so the
+    /// sample can be reproduced.
+    #[clap(short, long)]
+    seed: Option<u64>,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+/// Number of images with at least one annotation of each category
+fn images_per_category(coco_file: &CocoFile) -> HashMap<i32, usize> {
+    let pairs: HashSet<(i32, i64)> = coco_file
+        .annotations
+        .iter()
+        .flat_map(|annotation| {
+            let image_id = annotation.image_id();
+            annotation
+                .category_ids()
+                .into_iter()
+                .map(move |category_id| (category_id, image_id))
+        })
+        .collect();
+    let mut counts = HashMap::new();
+    for (category_id, _) in pairs {
+        *counts.entry(category_id).or_default() += 1;
+    }
+    counts
+}
+
+fn print_coverage(input: &CocoFile, sample: &CocoFile) {
+    let available = images_per_category(input);
+    let sampled = images_per_category(sample);
+    let mut categories: Vec<&CocoCategory> = input.categories.iter().flatten().collect();
+    categories.sort_by_key(|category| category.id());
+
+    println!("Per Category:");
+    let name_width = categories
+        .iter()
+        .map(|category| category.name().len())
+        .chain([4])
+        .max()
+        .unwrap();
+    println!(
+        "  {:>6}  {:<name_width$}  {:>8}  {:>9}",
+        "ID", "Name", "Sampled", "Available"
+    );
+    for category in categories {
+        let id = category.id();
+        println!(
+            "  {:>6}  {:<name_width$}  {:>8}  {:>9}",
+            id,
+            category.name(),
+            sampled.get(&id).copied().unwrap_or(0),
+            available.get(&id).copied().unwrap_or(0)
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let coco_json = fs::read_to_string(&args.coco_file).context("Could not read COCO JSON file")?;
+    let coco_file: CocoFile =
+        serde_json::from_str(&coco_json).context("Could not parse COCO JSON")?;
+
+    let seed = args.seed.unwrap_or_else(|| {
+        let seed = rng().random();
+        eprintln!("Sampling with random seed {}", seed);
+        seed
+    });
+    let sample = match (args.per_category, args.images) {
+        (Some(per_category), _) => coco_file.subsample_per_category(per_category, seed),
+        (None, Some(images)) => coco_file.sample_images(images, seed),
+        (None, None) => unreachable!("clap requires --per-category or --images"),
+    }
+    .context("Could not index images")?;
+
+    print_coverage(&coco_file, &sample);
+    sample.to_path(&args.output, !args.compact)?;
+    println!(
+        "Sampled {} of {} images with {} annotations, wrote {}",
+        sample.images.len(),
+        coco_file.images.len(),
+        sample.annotations.len(),
+        args.output.display()
+    );
+    Ok(())
+}
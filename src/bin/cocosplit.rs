@@ -1,16 +1,35 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use cococrawl::jobs::thread_pool;
 use cococrawl::path_utils::create_coco_image_path;
+use cococrawl::progress::progress_bar;
 use cococrawl::{CocoFile, IDMapEntry};
 use indicatif::ParallelProgressIterator;
-use rand::{SeedableRng, rng, rngs::StdRng, seq::SliceRandom};
+use rand::{Rng, SeedableRng, rng, rngs::StdRng, seq::SliceRandom};
 use rayon::prelude::*;
-use serde_json;
 use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
 use std::io::BufWriter;
+use std::path::Path;
 use std::path::PathBuf;
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum MatchMode {
+    /// images with at least one of the required categories
+    Any,
+    /// images with every required category
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum CountUnit {
+    /// --count and --percent count images
+    Images,
+    /// --count and --percent count annotations, images are taken until their annotations
+    /// reach the target, including the image that crosses it
+    Annotations,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
@@ -22,11 +41,27 @@ struct Args {
     #[clap(short, long, default_value = "split.json")]
     output: PathBuf,
 
-    /// number of images to create the split with
+    /// number of images, or annotations with --count-unit annotations, to create the split with
     /// if not provided, all images not in the blacklisted sets will be used
     #[clap(short, long)]
     count: Option<usize>,
 
+    /// what --count and --percent measure the split in
+    /// cocosplit dataset.json -o batch.json --count 5000 --count-unit annotations --shuffle
+    #[clap(long, value_enum, default_value = "images")]
+    count_unit: CountUnit,
+
+    /// fail instead of warning when --count is more than the images left after filtering
+    #[clap(long, requires = "count")]
+    strict: bool,
+
+    /// percentage (0-100) of the available images, or annotations with --count-unit
+    /// annotations, to put in the split, counted after the blacklist, --annotated-only and
+    /// --offset are applied
+    /// cocosplit dataset.json -o val-set.json --percent 10
+    #[clap(long, conflicts_with = "count", value_parser = parse_percent)]
+    percent: Option<f64>,
+
     /// blacklist dataset JSON file paths
     /// cocosplit dataset.json -o val-set.json -c 10000
     /// cocosplit dataset.json -o test-set.json -c 20000 -b val-set.json
@@ -38,9 +73,15 @@ struct Args {
     #[clap(long)]
     shuffle: Option<Option<u64>>,
 
+    /// seed for shuffling, implies --shuffle. Without a seed one is drawn at random and
+    /// printed so the split can be reproduced.
+    /// cocosplit dataset.json -o val-set.json -c 10000 --seed 42
+    #[clap(short, long)]
+    seed: Option<u64>,
+
     /// offset index to start at when splitting (only valid without shuffle)
     /// if offset is 0, starts from the lowest image_id
-    #[clap(long, conflicts_with = "shuffle")]
+    #[clap(long, conflicts_with_all = ["shuffle", "seed"])]
     offset: Option<usize>,
 
     /// annotated images only
@@ -48,21 +89,104 @@ struct Args {
     #[clap(long)]
     annotated_only: bool,
 
+    /// only include images with an annotation of this category (repeatable)
+    /// cocosplit dataset.json -o dogs.json --require-category dog
+    #[clap(long)]
+    require_category: Vec<String>,
+
+    /// treat --require-category as category ids instead of names
+    #[clap(long, requires = "require_category")]
+    by_id: bool,
+
+    /// whether images need any or all of the required categories
+    #[clap(long, value_enum, default_value = "any", requires = "require_category")]
+    match_mode: MatchMode,
+
+    /// drop annotations scored below this before splitting, annotations without a score are
+    /// kept. Combine with --annotated-only to build a pseudo-label training split.
+    /// cocosplit predictions.json -o pseudo-labels.json --min-score 0.8 --annotated-only
+    #[clap(long)]
+    min_score: Option<f32>,
+
+    /// treat the input as LVIS: images with verified negative categories count as annotated for
+    /// --annotated-only, and the image_count and instance_count of categories are recounted
+    /// for each output
+    /// cocosplit lvis_v1_train.json -o lvis-val.json --percent 5 --lvis --annotated-only
+    #[clap(long)]
+    lvis: bool,
+
+    /// also write every non-blacklisted image that didn't make it into the split to this path,
+    /// so a single run produces both halves
+    /// cocosplit dataset.json -o val-set.json --percent 10 --shuffle 42 --rest train-set.json
+    #[clap(long)]
+    rest: Option<PathBuf>,
+
     /// Force absolute paths for image file names in the split output file.
     #[clap(short, long)]
     absolute_paths: bool,
+
+    /// Don't draw progress bars or print status messages
+    #[clap(short, long)]
+    quiet: bool,
+
+    /// Number of worker threads, one per CPU by default
+    #[clap(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: Option<u64>,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
+    thread_pool(args.jobs.map(|jobs| jobs as usize))
+        .expect("Could not build thread pool")
+        .install(|| split(&args));
+}
+
+/// The seed to shuffle with, `None` without --shuffle or --seed
+fn shuffle_seed(args: &Args) -> Option<u64> {
+    let seed = match (args.shuffle, args.seed) {
+        (None, None) => return None,
+        (Some(Some(shuffle_seed)), Some(seed)) if shuffle_seed != seed => {
+            panic!("--shuffle {} and --seed {} disagree", shuffle_seed, seed)
+        }
+        (Some(Some(seed)), _) | (_, Some(seed)) => seed,
+        (Some(None), None) => {
+            let seed = rng().random();
+            // printed even with --quiet, it's the only way to reproduce this split
+            eprintln!("Shuffling with random seed {}", seed);
+            return Some(seed);
+        }
+    };
+    if !args.quiet {
+        eprintln!("Shuffling with seed {}", seed);
+    }
+    Some(seed)
+}
+
+fn split(args: &Args) {
     let coco_json = fs::read_to_string(&args.coco_file).expect("Could not read COCO JSON file");
-    let coco_file: cococrawl::CocoFile =
+    let mut coco_file: cococrawl::CocoFile =
         serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
+    if let Some(min_score) = args.min_score {
+        let removed = coco_file.filter_by_score(min_score);
+        if !args.quiet {
+            eprintln!(
+                "Dropped {} annotation(s) scored below {}",
+                removed, min_score
+            );
+        }
+    }
 
-
-    // create output file upfront so canonicalize works
+    // create output files upfront so canonicalize works
     let output_file = File::create(&args.output).expect("Could not create output file");
+    let rest_file = args
+        .rest
+        .as_ref()
+        .map(|path| File::create(path).expect("Could not create rest output file"));
 
     let blacklisted_image_ids: HashSet<i64> = args
         .blacklist_file
@@ -72,83 +196,195 @@ fn main() {
                 fs::read_to_string(path).expect("Could not read blacklist COCO JSON file");
             let blacklist_coco: cococrawl::CocoFile =
                 serde_json::from_str(&json_str).expect("Could not parse blacklist COCO JSON");
+            let images_count = blacklist_coco.images.len() as u64;
             blacklist_coco
                 .images
                 .into_par_iter()
-                .progress()
+                .progress_with(progress_bar(images_count, args.quiet))
                 .map(|img| img.id)
                 .collect::<HashSet<i64>>()
         })
         .collect();
 
-    let id_map = coco_file.make_image_id_map();
-    let mut id_map_entries: Vec<(&i64, &IDMapEntry<'_>)> = id_map
+    let id_map = if args.quiet {
+        coco_file.make_image_id_map_quiet()
+    } else {
+        coco_file.make_image_id_map()
+    }
+    .unwrap_or_else(|err| panic!("Could not index images: {}", err));
+    let mut pool: Vec<(&i64, &IDMapEntry<'_>)> = id_map
         .par_iter()
-        .progress_count(id_map.len() as u64)
+        .progress_with(progress_bar(id_map.len() as u64, args.quiet))
         .filter(|(id, _)| !blacklisted_image_ids.contains(id))
         .collect();
 
-
-    if args.shuffle.is_some() {
-        match args.shuffle.unwrap() {
-            Some(seed) => {
-                let mut rng = StdRng::seed_from_u64(seed);
-                id_map_entries.shuffle(&mut rng);
-            }
-            None => {
-                let mut rng = rng();
-                id_map_entries.shuffle(&mut rng);
-            }
-        }
-    } else {
-        id_map_entries.sort_by_key(|(id, _)| *id);
+    // the id map iterates in a different order every run, sort first so a seed always gives
+    // the same split
+    pool.sort_by_key(|(id, _)| *id);
+    if let Some(seed) = shuffle_seed(args) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        pool.shuffle(&mut rng);
     }
 
     // filter annotated only
     let id_map_entries: Vec<_> = if args.annotated_only {
-        eprintln!("Filtering to annotated images only...");
-        id_map_entries
-            .into_par_iter()
-            .progress()
-            .filter(|(_, entry)| !entry.annotations.is_empty())
+        if !args.quiet {
+            eprintln!("Filtering to annotated images only...");
+        }
+        let entries_count = pool.len() as u64;
+        pool.par_iter()
+            .copied()
+            .progress_with(progress_bar(entries_count, args.quiet))
+            .filter(|(_, entry)| {
+                !entry.annotations.is_empty()
+                    || (args.lvis
+                        && entry
+                            .image
+                            .neg_category_ids
+                            .as_ref()
+                            .is_some_and(|ids| !ids.is_empty()))
+            })
             .collect()
     } else {
+        pool.clone()
+    };
+
+    // filter to the required categories
+    let id_map_entries: Vec<_> = if args.require_category.is_empty() {
+        id_map_entries
+    } else {
+        let required_ids = coco_file
+            .find_category_ids(&args.require_category, args.by_id)
+            .unwrap_or_else(|err| panic!("Could not resolve required categories: {}", err));
         id_map_entries
+            .into_iter()
+            .filter(|(_, entry)| {
+                let image_category_ids: HashSet<i32> = entry
+                    .annotations
+                    .iter()
+                    .flat_map(|ann| ann.category_ids())
+                    .collect();
+                match args.match_mode {
+                    MatchMode::Any => !required_ids.is_disjoint(&image_category_ids),
+                    MatchMode::All => required_ids.is_subset(&image_category_ids),
+                }
+            })
+            .collect()
+    };
+
+    let unit_count = |entry: &IDMapEntry<'_>| match args.count_unit {
+        CountUnit::Images => 1,
+        CountUnit::Annotations => entry.annotations.len(),
+    };
+    let unit_name = match args.count_unit {
+        CountUnit::Images => "images",
+        CountUnit::Annotations => "annotations",
     };
 
     let offset = args.offset.unwrap_or(0);
-    let output_count = args
-        .count
-        .unwrap_or(id_map_entries.len().saturating_sub(offset));
+    let available_count: usize = id_map_entries
+        .iter()
+        .skip(offset)
+        .map(|(_, entry)| unit_count(entry))
+        .sum();
+    if let Some(count) = args.count.filter(|&count| count > available_count) {
+        let message = format!(
+            "requested {} {} but only {} are available after filtering",
+            count, unit_name, available_count
+        );
+        if args.strict {
+            panic!("{}", message);
+        }
+        // printed even with --quiet, the split is smaller than asked for
+        eprintln!(
+            "Warning: {}, the split will have {}",
+            message, available_count
+        );
+    }
+    let output_count = match (args.count, args.percent) {
+        (Some(count), _) => count,
+        (None, Some(percent)) => (percent / 100.0 * available_count as f64).round() as usize,
+        (None, None) => available_count,
+    };
 
+    // take images until the target is reached, the last one may overshoot it when counting
+    // annotations
+    let mut selected_count = 0;
     let id_map_entries: Vec<(&i64, &IDMapEntry<'_>)> = id_map_entries
         .into_iter()
         .skip(offset)
-        .take(output_count)
+        .take_while(|(_, entry)| {
+            if selected_count >= output_count {
+                return false;
+            }
+            selected_count += unit_count(entry);
+            true
+        })
         .collect();
+    if args.count_unit == CountUnit::Annotations && !args.quiet {
+        eprintln!(
+            "Split has {} annotation(s) on {} image(s)",
+            selected_count,
+            id_map_entries.len()
+        );
+    }
+
+    build_split(&coco_file, &id_map_entries, &args.output, args)
+        .to_writer(BufWriter::new(output_file), !args.compact)
+        .expect("Could not write JSON to output file");
 
-    // Write updated COCO JSON to output directory
-    let output_coco_file = CocoFile {
+    if let (Some(rest_path), Some(rest_file)) = (&args.rest, rest_file) {
+        // everything from the pool that wasn't selected, in the same order
+        let selected_ids: HashSet<i64> = id_map_entries.iter().map(|(id, _)| **id).collect();
+        let rest_entries: Vec<(&i64, &IDMapEntry<'_>)> = pool
+            .into_iter()
+            .filter(|(id, _)| !selected_ids.contains(id))
+            .collect();
+        build_split(&coco_file, &rest_entries, rest_path, args)
+            .to_writer(BufWriter::new(rest_file), !args.compact)
+            .expect("Could not write JSON to rest output file");
+    }
+}
+
+/// A COCO file with the given images and their annotations, with image paths relative to
+/// `output_path`
+fn build_split(
+    coco_file: &CocoFile,
+    id_map_entries: &[(&i64, &IDMapEntry<'_>)],
+    output_path: &Path,
+    args: &Args,
+) -> CocoFile {
+    let mut split = CocoFile {
         info: coco_file.info.clone(),
         images: id_map_entries
             .par_iter()
-            .progress()
+            .progress_with(progress_bar(id_map_entries.len() as u64, args.quiet))
             .map(|(_, entry)| {
                 let mut new_image = entry.image.clone();
+                // LVIS images have no file name to rewrite
+                if new_image.file_name.as_os_str().is_empty() {
+                    return new_image;
+                }
                 new_image.file_name = create_coco_image_path(
-                    args.output.as_path(),
-                    new_image.get_absolute_path(&args.coco_file).expect("Could not get absolute image path").as_path(),
+                    output_path,
+                    new_image
+                        .get_absolute_path(&args.coco_file)
+                        .expect("Could not get absolute image path")
+                        .as_path(),
                     args.absolute_paths,
-                ).expect(format!(
-                    "Could not create COCO image path for image id {}",
-                    new_image.id
-                ).as_str());
+                )
+                .unwrap_or_else(|_| {
+                    panic!(
+                        "Could not create COCO image path for image id {}",
+                        new_image.id
+                    )
+                });
                 new_image
             })
             .collect(),
         annotations: id_map_entries
             .par_iter()
-            .progress()
+            .progress_with(progress_bar(id_map_entries.len() as u64, args.quiet))
             .flat_map(|(_, entry)| {
                 entry
                     .annotations
@@ -160,9 +396,18 @@ fn main() {
         categories: coco_file.categories.clone(),
         licenses: coco_file.licenses.clone(),
     };
+    if args.lvis {
+        split.recount_lvis_categories();
+    }
+    split
+}
 
-    let writer = BufWriter::new(output_file);
-
-    serde_json::to_writer_pretty(writer, &output_coco_file)
-        .expect("Could not write JSON to output file");
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", value))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("{} is not between 0 and 100", percent));
+    }
+    Ok(percent)
 }
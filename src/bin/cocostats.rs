@@ -0,0 +1,296 @@
+use clap::{Parser, ValueEnum};
+use cococrawl::geometry::{BBox, HasBBox};
+use cococrawl::{CocoAnnotation, CocoFile, HasID};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file path
+    coco_file: PathBuf,
+
+    /// Output format
+    #[clap(short, long, value_enum, default_value = "text")]
+    format: OutputFormat,
+}
+
+/// Upper bounds of the bbox width / height histogram bins, the last bin is unbounded
+const ASPECT_RATIO_BINS: [(f64, &str); 5] = [
+    (0.5, "<0.5"),
+    (0.8, "0.5-0.8"),
+    (1.25, "0.8-1.25"),
+    (2.0, "1.25-2"),
+    (f64::INFINITY, ">=2"),
+];
+
+/// Inclusive (min, max) annotation counts of the annotations per image histogram bins
+const ANNOTATIONS_PER_IMAGE_BINS: [(usize, usize, &str); 5] = [
+    (0, 0, "0"),
+    (1, 1, "1"),
+    (2, 5, "2-5"),
+    (6, 10, "6-10"),
+    (11, usize::MAX, "11+"),
+];
+
+#[derive(Serialize)]
+struct Quartiles {
+    min: f64,
+    q1: f64,
+    median: f64,
+    q3: f64,
+    max: f64,
+}
+
+#[derive(Serialize)]
+struct BBoxAreaStats {
+    count: u64,
+    /// `None` without bboxes
+    quartiles: Option<Quartiles>,
+}
+
+#[derive(Serialize)]
+struct AspectRatioBin {
+    /// width / height range
+    range: &'static str,
+    annotations: u64,
+}
+
+#[derive(Serialize)]
+struct AnnotationsPerImageBin {
+    annotations: &'static str,
+    images: u64,
+}
+
+#[derive(Serialize)]
+struct ImageSizeCount {
+    width: u32,
+    height: u32,
+    images: u64,
+}
+
+#[derive(Serialize)]
+struct CategoryCount {
+    id: i32,
+    name: String,
+    annotations: u64,
+}
+
+#[derive(Serialize)]
+struct Stats {
+    file: String,
+    images: u64,
+    annotations: u64,
+    bbox_area: BBoxAreaStats,
+    /// bboxes with a zero height are left out
+    bbox_aspect_ratio: Vec<AspectRatioBin>,
+    ann_per_image: Vec<AnnotationsPerImageBin>,
+    /// most common size first
+    image_sizes: Vec<ImageSizeCount>,
+    /// annotations of categories missing from the categories array aren't listed
+    per_category: Vec<CategoryCount>,
+}
+
+/// Every bbox of the annotation, one per panoptic segment
+fn annotation_bboxes(annotation: &CocoAnnotation) -> Vec<BBox> {
+    match annotation {
+        CocoAnnotation::ObjectDetection(ann) => vec![ann.bbox()],
+        CocoAnnotation::KeypointDetection(ann) => vec![ann.bbox()],
+        CocoAnnotation::DensePose(ann) => vec![ann.bbox()],
+        CocoAnnotation::PanopticSegmentation(ann) => ann
+            .segments_info
+            .iter()
+            .map(|segment| segment.bbox())
+            .collect(),
+        CocoAnnotation::ImageCaptioning(_) => Vec::new(),
+    }
+}
+
+/// Linearly interpolated `q` quantile of `sorted`
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+}
+
+fn quartiles(mut values: Vec<f64>) -> Option<Quartiles> {
+    values.sort_by(f64::total_cmp);
+    Some(Quartiles {
+        min: *values.first()?,
+        q1: quantile(&values, 0.25),
+        median: quantile(&values, 0.5),
+        q3: quantile(&values, 0.75),
+        max: *values.last()?,
+    })
+}
+
+fn compute_stats(path: &PathBuf) -> Stats {
+    let coco_json = fs::read_to_string(path).expect("Could not read COCO JSON file");
+    let coco_file: CocoFile = serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
+
+    let bboxes: Vec<BBox> = coco_file
+        .annotations
+        .iter()
+        .flat_map(annotation_bboxes)
+        .collect();
+
+    let areas: Vec<f64> = bboxes.iter().map(|bbox| f64::from(bbox.area())).collect();
+    let bbox_area = BBoxAreaStats {
+        count: areas.len() as u64,
+        quartiles: quartiles(areas),
+    };
+
+    let mut bbox_aspect_ratio: Vec<AspectRatioBin> = ASPECT_RATIO_BINS
+        .iter()
+        .map(|&(_, range)| AspectRatioBin {
+            range,
+            annotations: 0,
+        })
+        .collect();
+    for bbox in bboxes.iter().filter(|bbox| bbox.height() > 0.0) {
+        let ratio = f64::from(bbox.width()) / f64::from(bbox.height());
+        let bin = ASPECT_RATIO_BINS
+            .iter()
+            .position(|&(upper, _)| ratio < upper)
+            .unwrap();
+        bbox_aspect_ratio[bin].annotations += 1;
+    }
+
+    let mut per_image: HashMap<i64, usize> = HashMap::new();
+    for annotation in &coco_file.annotations {
+        *per_image.entry(annotation.image_id()).or_default() += 1;
+    }
+    let counts: Vec<usize> = coco_file
+        .images
+        .iter()
+        .map(|image| per_image.get(&image.id).copied().unwrap_or(0))
+        .collect();
+    let ann_per_image = ANNOTATIONS_PER_IMAGE_BINS
+        .iter()
+        .map(|&(min, max, annotations)| AnnotationsPerImageBin {
+            annotations,
+            images: counts
+                .iter()
+                .filter(|&&count| (min..=max).contains(&count))
+                .count() as u64,
+        })
+        .collect();
+
+    let mut sizes: HashMap<(u32, u32), u64> = HashMap::new();
+    for image in &coco_file.images {
+        *sizes.entry((image.width, image.height)).or_default() += 1;
+    }
+    let mut image_sizes: Vec<ImageSizeCount> = sizes
+        .into_iter()
+        .map(|((width, height), images)| ImageSizeCount {
+            width,
+            height,
+            images,
+        })
+        .collect();
+    image_sizes.sort_by(|a, b| {
+        b.images
+            .cmp(&a.images)
+            .then_with(|| (a.width, a.height).cmp(&(b.width, b.height)))
+    });
+
+    let mut category_counts: HashMap<i32, u64> = HashMap::new();
+    for annotation in &coco_file.annotations {
+        for category_id in annotation.category_ids() {
+            *category_counts.entry(category_id).or_default() += 1;
+        }
+    }
+    let mut per_category: Vec<CategoryCount> = coco_file
+        .categories
+        .iter()
+        .flatten()
+        .map(|category| CategoryCount {
+            id: category.id(),
+            name: category.name().to_string(),
+            annotations: category_counts.get(&category.id()).copied().unwrap_or(0),
+        })
+        .collect();
+    per_category.sort_by_key(|count| count.id);
+
+    Stats {
+        file: path.file_name().unwrap().to_string_lossy().to_string(),
+        images: coco_file.images.len() as u64,
+        annotations: coco_file.annotations.len() as u64,
+        bbox_area,
+        bbox_aspect_ratio,
+        ann_per_image,
+        image_sizes,
+        per_category,
+    }
+}
+
+fn print_stats(stats: &Stats) {
+    println!("Coco File: {}", stats.file);
+    println!("Images: {}", stats.images);
+    println!("Annotations: {}", stats.annotations);
+
+    println!("BBox Areas: {}", stats.bbox_area.count);
+    if let Some(quartiles) = &stats.bbox_area.quartiles {
+        println!(
+            "  min {:.1}, q1 {:.1}, median {:.1}, q3 {:.1}, max {:.1}",
+            quartiles.min, quartiles.q1, quartiles.median, quartiles.q3, quartiles.max
+        );
+    }
+
+    println!("BBox Aspect Ratios (width / height):");
+    for bin in &stats.bbox_aspect_ratio {
+        println!("  {}: {}", bin.range, bin.annotations);
+    }
+
+    println!("Images by Annotation Count:");
+    for bin in &stats.ann_per_image {
+        println!("  {}: {}", bin.annotations, bin.images);
+    }
+
+    println!("Image Sizes:");
+    for size in &stats.image_sizes {
+        println!("  {}x{}: {}", size.width, size.height, size.images);
+    }
+
+    println!("Per Category:");
+    let name_width = stats
+        .per_category
+        .iter()
+        .map(|count| count.name.len())
+        .chain([4])
+        .max()
+        .unwrap();
+    println!(
+        "  {:>6}  {:<name_width$}  {:>11}",
+        "ID", "Name", "Annotations"
+    );
+    for count in &stats.per_category {
+        println!(
+            "  {:>6}  {:<name_width$}  {:>11}",
+            count.id, count.name, count.annotations
+        );
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let stats = compute_stats(&args.coco_file);
+
+    match args.format {
+        OutputFormat::Text => print_stats(&stats),
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("Could not serialize stats")
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use clap::{Parser, ValueEnum};
+use cococrawl::CocoFile;
+use cococrawl::validation::{Severity, ValidationIssue};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// coco JSON file paths to validate
+    #[clap(required = true)]
+    coco_files: Vec<PathBuf>,
+
+    /// Output format for the issue report
+    #[clap(short, long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Treat warnings as errors when deciding the exit status
+    #[clap(short, long)]
+    warnings_as_errors: bool,
+}
+
+#[derive(Serialize)]
+struct ReportEntry<'a> {
+    file: &'a PathBuf,
+    severity: Severity,
+    message: String,
+    #[serde(flatten)]
+    issue: &'a ValidationIssue,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let reports: Vec<(&PathBuf, Vec<ValidationIssue>)> = args
+        .coco_files
+        .iter()
+        .map(|path| {
+            let coco_json = fs::read_to_string(path).expect("Could not read COCO JSON file");
+            let coco_file: CocoFile =
+                serde_json::from_str(&coco_json).expect("Could not parse COCO JSON");
+            (path, coco_file.validate())
+        })
+        .collect();
+
+    let entries: Vec<ReportEntry> = reports
+        .iter()
+        .flat_map(|(path, issues)| {
+            issues.iter().map(|issue| ReportEntry {
+                file: path,
+                severity: issue.severity(),
+                message: issue.to_string(),
+                issue,
+            })
+        })
+        .collect();
+
+    let error_count = entries
+        .iter()
+        .filter(|entry| entry.severity == Severity::Error)
+        .count();
+    let warning_count = entries.len() - error_count;
+
+    match args.format {
+        OutputFormat::Text => {
+            entries.iter().for_each(|entry| {
+                println!(
+                    "{}: {}: {}",
+                    entry.file.to_string_lossy(),
+                    entry.severity,
+                    entry.message
+                );
+            });
+            println!(
+                "{} file(s) checked: {} error(s), {} warning(s)",
+                reports.len(),
+                error_count,
+                warning_count
+            );
+        }
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&entries).expect("Could not serialize issues")
+            );
+        }
+    }
+
+    if error_count > 0 || (args.warnings_as_errors && warning_count > 0) {
+        std::process::exit(1);
+    }
+}
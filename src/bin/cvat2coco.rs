@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cococrawl::import::from_cvat_xml;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// CVAT for images 1.1 XML file path
+    #[clap(required = true)]
+    cvat_file: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "coco.json")]
+    output: PathBuf,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let xml = fs::read_to_string(&args.cvat_file)
+        .with_context(|| format!("Could not read {:?}", args.cvat_file))?;
+    let import = from_cvat_xml(&xml)?;
+    import.skipped_shapes.iter().for_each(|(tag, count)| {
+        eprintln!(
+            "Warning: skipped {} <{}> shape(s) without a COCO counterpart",
+            count, tag
+        );
+    });
+
+    let coco_file = import.coco_file;
+    let output_file = File::create(&args.output).context("Could not create output file")?;
+    coco_file
+        .to_writer(BufWriter::new(output_file), !args.compact)
+        .context("Could not write JSON to output file")?;
+
+    println!(
+        "Imported {} image(s) with {} annotation(s) in {} categories into {}",
+        coco_file.images.len(),
+        coco_file.annotations.len(),
+        coco_file.categories.iter().flatten().count(),
+        args.output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cococrawl::CrawlOptions;
+use cococrawl::import::from_yolo;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// YOLO dataset directory with images/ and labels/
+    #[clap(required = true)]
+    dataset_dir: PathBuf,
+
+    /// JSON output path
+    #[clap(short, long, default_value = "coco.json")]
+    output: PathBuf,
+
+    /// Class names in class index order, instead of the ones in data.yaml or
+    /// labels/classes.txt
+    /// yolo2coco yolo/ --names person,car,bicycle
+    #[clap(long, value_delimiter = ',')]
+    names: Option<Vec<String>>,
+
+    /// Force absolute paths for image file names.
+    #[clap(short, long)]
+    absolute_paths: bool,
+
+    /// Write compact JSON without indentation, which is much smaller for large datasets
+    #[clap(long)]
+    compact: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let output_file = File::create(&args.output).context("Could not create output file")?;
+    let options = CrawlOptions {
+        output_path: args.output.clone(),
+        absolute_paths: args.absolute_paths,
+        ..CrawlOptions::default()
+    };
+    let (coco_file, report) = from_yolo(&args.dataset_dir, args.names.clone(), options)?;
+    report.failures.iter().for_each(|failure| {
+        eprintln!("Warning: skipping {}", failure);
+    });
+
+    println!(
+        "Imported {} image(s) with {} annotation(s) in {} categories into {}",
+        coco_file.images.len(),
+        coco_file.annotations.len(),
+        coco_file.categories.iter().flatten().count(),
+        args.output.display()
+    );
+
+    coco_file
+        .to_writer(BufWriter::new(output_file), !args.compact)
+        .context("Could not write JSON to output file")?;
+
+    Ok(())
+}
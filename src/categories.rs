@@ -0,0 +1,242 @@
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet};
+
+use crate::{CocoAnnotation, CocoCategory, CocoFile, HasCategoryID, HasID};
+
+/// Rewrites every category id of the annotation found in `remap`, including those of
+/// panoptic segments
+fn remap_annotation_categories(annotation: &mut CocoAnnotation, remap: &HashMap<i32, i32>) {
+    let remap_id = |category_id: i32| remap.get(&category_id).copied().unwrap_or(category_id);
+    match annotation {
+        CocoAnnotation::ObjectDetection(ann) => ann.set_category_id(remap_id(ann.category_id())),
+        CocoAnnotation::KeypointDetection(ann) => ann.set_category_id(remap_id(ann.category_id())),
+        CocoAnnotation::DensePose(ann) => ann.set_category_id(remap_id(ann.category_id())),
+        CocoAnnotation::PanopticSegmentation(ann) => {
+            for segment in &mut ann.segments_info {
+                segment.category_id = remap_id(segment.category_id);
+            }
+        }
+        CocoAnnotation::ImageCaptioning(_) => {}
+    }
+}
+
+fn category_name_mut(category: &mut CocoCategory) -> &mut String {
+    match category {
+        CocoCategory::ObjectDetection(cat) => &mut cat.name,
+        CocoCategory::KeypointDetection(cat) => &mut cat.name,
+        CocoCategory::PanopticSegmentation(cat) => &mut cat.name,
+    }
+}
+
+impl CocoFile {
+    /// Renames category `id`. Fails if there is no such category or another category already
+    /// has the name, use [`CocoFile::merge_categories`] to combine them instead.
+    pub fn rename_category(&mut self, id: i32, new_name: &str) -> Result<()> {
+        let categories = self.categories.iter_mut().flatten();
+        let mut target = None;
+        for category in categories {
+            if category.id() == id {
+                target = Some(category);
+            } else if *category_name_mut(category) == new_name {
+                bail!("Category {} is already named {:?}", category.id(), new_name);
+            }
+        }
+        let Some(category) = target else {
+            bail!("No category with id {}", id);
+        };
+        *category_name_mut(category) = new_name.to_string();
+        Ok(())
+    }
+
+    /// Points every annotation and panoptic segment of the categories `from_ids` at `into_id`
+    /// and removes those categories. `into_id` may be listed in `from_ids`, it is kept.
+    pub fn merge_categories(&mut self, from_ids: &[i32], into_id: i32) -> Result<()> {
+        let category_ids: HashSet<i32> = self.categories.iter().flatten().map(HasID::id).collect();
+        if !category_ids.contains(&into_id) {
+            bail!("No category with id {}", into_id);
+        }
+        if let Some(missing) = from_ids.iter().find(|id| !category_ids.contains(id)) {
+            bail!("No category with id {}", missing);
+        }
+
+        let remap: HashMap<i32, i32> = from_ids
+            .iter()
+            .filter(|&&id| id != into_id)
+            .map(|&id| (id, into_id))
+            .collect();
+        for annotation in &mut self.annotations {
+            remap_annotation_categories(annotation, &remap);
+        }
+        if let Some(categories) = &mut self.categories {
+            categories.retain(|category| !remap.contains_key(&category.id()));
+        }
+        Ok(())
+    }
+
+    /// Removes the categories `ids` together with their annotations and panoptic segments.
+    /// Panoptic annotations left without segments are removed too. Returns the number of
+    /// removed annotations and segments.
+    pub fn drop_categories(&mut self, ids: &[i32]) -> Result<usize> {
+        let category_ids: HashSet<i32> = self.categories.iter().flatten().map(HasID::id).collect();
+        if let Some(missing) = ids.iter().find(|id| !category_ids.contains(id)) {
+            bail!("No category with id {}", missing);
+        }
+
+        let mut removed = 0;
+        self.annotations.retain_mut(|annotation| match annotation {
+            CocoAnnotation::PanopticSegmentation(ann) => {
+                let segment_count = ann.segments_info.len();
+                ann.segments_info
+                    .retain(|segment| !ids.contains(&segment.category_id));
+                removed += segment_count - ann.segments_info.len();
+                !ann.segments_info.is_empty()
+            }
+            _ => {
+                let dropped = annotation.category_id().is_some_and(|id| ids.contains(&id));
+                removed += usize::from(dropped);
+                !dropped
+            }
+        });
+        if let Some(categories) = &mut self.categories {
+            categories.retain(|category| !ids.contains(&category.id()));
+        }
+        Ok(removed)
+    }
+
+    /// Renumbers the categories `start`, `start + 1`, ... in the order of their current ids
+    /// and updates the annotations to match. Returns the old to new id mapping. Annotations of
+    /// categories missing from the categories array keep their id.
+    pub fn remap_category_ids_contiguous(&mut self, start: i32) -> HashMap<i32, i32> {
+        let mut old_ids: Vec<i32> = self.categories.iter().flatten().map(HasID::id).collect();
+        old_ids.sort();
+        old_ids.dedup();
+        let remap: HashMap<i32, i32> = old_ids.into_iter().zip(start..).collect();
+
+        for category in self.categories.iter_mut().flatten() {
+            category.set_id(remap[&category.id()]);
+        }
+        for annotation in &mut self.annotations {
+            remap_annotation_categories(annotation, &remap);
+        }
+        remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories_file() -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 3, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 7, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 12, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"image_id": 1, "file_name": "a.png", "segments_info": [
+                    {"id": 1, "category_id": 7, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 2, "category_id": 12, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0}
+                ]},
+                {"id": 4, "image_id": 1, "caption": "a cat"}
+            ],
+            "categories": [
+                {"id": 12, "name": "dog", "supercategory": "animal"},
+                {"id": 3, "name": "cat", "supercategory": "animal"},
+                {"id": 7, "name": "kitten", "supercategory": "animal"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn referenced_category_ids(coco_file: &CocoFile) -> Vec<i32> {
+        let mut ids: Vec<i32> = coco_file
+            .annotations
+            .iter()
+            .flat_map(CocoAnnotation::category_ids)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    fn category_ids(coco_file: &CocoFile) -> Vec<i32> {
+        coco_file
+            .categories
+            .iter()
+            .flatten()
+            .map(HasID::id)
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_category() {
+        let mut coco_file = categories_file();
+        coco_file.rename_category(7, "kitty").unwrap();
+        assert!(
+            coco_file
+                .find_category_ids(&["kitty"], false)
+                .unwrap()
+                .contains(&7)
+        );
+        assert!(coco_file.rename_category(7, "cat").is_err());
+        assert!(coco_file.rename_category(8, "cow").is_err());
+        // renaming to its own name is fine
+        coco_file.rename_category(3, "cat").unwrap();
+    }
+
+    #[test]
+    fn test_merge_categories() {
+        let mut coco_file = categories_file();
+        coco_file.merge_categories(&[7, 3], 3).unwrap();
+        assert_eq!(category_ids(&coco_file), vec![12, 3]);
+        assert_eq!(referenced_category_ids(&coco_file), vec![3, 12]);
+        assert_eq!(
+            coco_file.annotations[3].category_ids(),
+            vec![3, 12],
+            "panoptic segments are merged too"
+        );
+
+        assert!(coco_file.merge_categories(&[7], 3).is_err());
+        assert!(coco_file.merge_categories(&[12], 7).is_err());
+        assert_eq!(category_ids(&coco_file), vec![12, 3]);
+    }
+
+    #[test]
+    fn test_drop_categories() {
+        let mut coco_file = categories_file();
+        assert_eq!(coco_file.drop_categories(&[7]).unwrap(), 2);
+        assert_eq!(category_ids(&coco_file), vec![12, 3]);
+        assert_eq!(referenced_category_ids(&coco_file), vec![3, 12]);
+        assert_eq!(coco_file.annotations.len(), 4);
+
+        // the panoptic annotation loses its last segment and goes as well
+        assert_eq!(coco_file.drop_categories(&[12]).unwrap(), 2);
+        assert_eq!(referenced_category_ids(&coco_file), vec![3]);
+        assert_eq!(coco_file.annotations.len(), 2);
+        assert!(coco_file.annotations[1].as_captioning().is_some());
+
+        assert!(coco_file.drop_categories(&[3, 12]).is_err());
+        assert_eq!(category_ids(&coco_file), vec![3]);
+    }
+
+    #[test]
+    fn test_remap_category_ids_contiguous() {
+        let mut coco_file = categories_file();
+        let remap = coco_file.remap_category_ids_contiguous(1);
+        assert_eq!(remap, HashMap::from([(3, 1), (7, 2), (12, 3)]));
+        assert_eq!(category_ids(&coco_file), vec![3, 1, 2]);
+        assert_eq!(referenced_category_ids(&coco_file), vec![1, 2, 3]);
+        assert_eq!(coco_file.annotations[3].category_ids(), vec![2, 3]);
+
+        let category_map = coco_file.make_category_id_map();
+        assert_eq!(category_map[&1].annotations.len(), 1);
+        assert_eq!(category_map[&2].annotations.len(), 2);
+
+        let remap = coco_file.remap_category_ids_contiguous(0);
+        assert_eq!(remap, HashMap::from([(1, 0), (2, 1), (3, 2)]));
+        assert_eq!(referenced_category_ids(&coco_file), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{CocoAnnotation, CocoFile};
+
+/// The old to new id mappings of [`CocoFile::compact_ids`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IdRemapReport {
+    pub images: BTreeMap<i64, i64>,
+    /// pairs in file order, panoptic segments included, since old annotation ids need not be
+    /// unique
+    pub annotations: Vec<(i64, i64)>,
+    pub categories: BTreeMap<i32, i32>,
+}
+
+impl CocoFile {
+    /// Renumbers images and annotations sequentially in file order, and categories in the
+    /// order of their current ids, starting at the given values. Every reference to an image
+    /// or category is rewritten to match. Images sharing an id keep sharing their new id, and
+    /// references to missing images get new ids after those of the images so they stay
+    /// dangling.
+    ///
+    /// Panoptic segment ids are renumbered along with the annotations, so the segment PNGs
+    /// have to be regenerated to match them.
+    pub fn compact_ids(
+        &mut self,
+        start_image_id: i64,
+        start_annotation_id: i64,
+        start_category_id: i32,
+    ) -> IdRemapReport {
+        let mut report = IdRemapReport {
+            categories: self
+                .remap_category_ids_contiguous(start_category_id)
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut next_image_id = start_image_id;
+        let mut remap_image_id = |old_id: i64| {
+            *report.images.entry(old_id).or_insert_with(|| {
+                next_image_id += 1;
+                next_image_id - 1
+            })
+        };
+        for image in &mut self.images {
+            image.id = remap_image_id(image.id);
+        }
+        for annotation in &mut self.annotations {
+            annotation.set_image_id(remap_image_id(annotation.image_id()));
+        }
+
+        let mut next_annotation_id = start_annotation_id;
+        for annotation in &mut self.annotations {
+            if let CocoAnnotation::PanopticSegmentation(ann) = annotation {
+                for segment in &mut ann.segments_info {
+                    report.annotations.push((segment.id, next_annotation_id));
+                    segment.id = next_annotation_id;
+                    next_annotation_id += 1;
+                }
+            } else if let Some(old_id) = annotation.id() {
+                report.annotations.push((old_id, next_annotation_id));
+                annotation.set_id(next_annotation_id);
+                next_annotation_id += 1;
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HasID;
+    use std::collections::HashSet;
+
+    /// Sparse ids across every annotation type, with a dangling annotation on image 7
+    fn sparse_file() -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [
+                {"id": 9000000000_i64, "width": 10, "height": 10, "file_name": "a.png"},
+                {"id": 42, "width": 10, "height": 10, "file_name": "b.png"}
+            ],
+            "annotations": [
+                {"id": 500, "image_id": 42, "category_id": 30, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 17, "image_id": 9000000000_i64, "caption": "two things"},
+                {"image_id": 9000000000_i64, "file_name": "a.png", "segments_info": [
+                    {"id": 3, "category_id": 30, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 8, "category_id": 5, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0}
+                ]},
+                {"id": 500, "image_id": 7, "category_id": 5, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0}
+            ],
+            "categories": [
+                {"id": 30, "name": "cat", "supercategory": "animal"},
+                {"id": 5, "name": "dog", "supercategory": "animal"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compact_ids() {
+        let mut coco_file = sparse_file();
+        let report = coco_file.compact_ids(1, 100, 0);
+
+        assert_eq!(
+            report.images,
+            BTreeMap::from([(9000000000, 1), (42, 2), (7, 3)])
+        );
+        assert_eq!(
+            report.annotations,
+            vec![(500, 100), (17, 101), (3, 102), (8, 103), (500, 104)]
+        );
+        assert_eq!(report.categories, BTreeMap::from([(5, 0), (30, 1)]));
+
+        let image_ids: Vec<i64> = coco_file.images.iter().map(|image| image.id).collect();
+        assert_eq!(image_ids, vec![1, 2]);
+        let category_ids: HashSet<i32> = coco_file
+            .categories
+            .iter()
+            .flatten()
+            .map(HasID::id)
+            .collect();
+        assert_eq!(category_ids, HashSet::from([0, 1]));
+
+        // every reference still points at the same image and category
+        let original = sparse_file();
+        for (old, new) in original.annotations.iter().zip(&coco_file.annotations) {
+            assert_eq!(report.images[&old.image_id()], new.image_id());
+            let old_categories: Vec<i32> = old
+                .category_ids()
+                .iter()
+                .map(|id| report.categories[id])
+                .collect();
+            assert_eq!(old_categories, new.category_ids());
+            assert!(
+                new.category_ids()
+                    .iter()
+                    .all(|id| category_ids.contains(id))
+            );
+        }
+        assert_eq!(coco_file.annotations[3].image_id(), 3);
+    }
+}
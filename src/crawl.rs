@@ -0,0 +1,812 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use image::ImageReader;
+#[cfg(feature = "rayon")]
+use indicatif::ParallelProgressIterator;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dedupe::{
+    DEFAULT_PHASH_THRESHOLD, DedupeMode, Deduplicator, ImageHash, hash_image, sha256_hex,
+};
+use crate::image_metadata::ExifMetadata;
+#[cfg(feature = "rayon")]
+use crate::jobs::thread_pool;
+use crate::path_utils::create_coco_image_path;
+use crate::probe::{ProbeMode, probe_dimensions};
+#[cfg(feature = "rayon")]
+use crate::progress::progress_bar;
+use crate::{
+    CocoAnnotation, CocoCategory, CocoFile, CocoImage, CocoInfo, CocoObjectDetectionAnnotation,
+    CocoObjectDetectionCategory, CocoSegmentation,
+};
+
+pub const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "bmp", "tiff", "svg", "webp"];
+
+/// Controls which files a crawl picks up and how their image entries are written
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    /// lowercase file extensions without the leading dot
+    pub extensions: HashSet<String>,
+    /// files matching any of these globs are skipped
+    pub exclude: GlobSet,
+    /// maximum directory depth below each crawl root, unlimited if `None`. 1 only crawls the
+    /// files directly inside the root.
+    pub max_depth: Option<usize>,
+    /// descend into symlinked directories and crawl symlinked files. Links pointing back to
+    /// one of their own ancestors are detected by walkdir and skipped, so cycles end the walk
+    /// of that branch instead of looping.
+    pub follow_symlinks: bool,
+    /// the COCO file the images will be written to, `file_name`s are relative to its directory
+    pub output_path: PathBuf,
+    /// write absolute `file_name`s even for images under the output directory
+    pub absolute_paths: bool,
+    /// swap width and height for images whose EXIF orientation rotates them by 90 degrees
+    pub respect_exif_orientation: bool,
+    /// record files that can't be read as 0x0 entries instead of skipping them
+    pub keep_broken: bool,
+    pub probe: ProbeMode,
+    /// drop files that duplicate one found earlier in the crawl
+    pub dedupe: DedupeMode,
+    /// maximum Hamming distance between perceptual hashes for `DedupeMode::Phash`
+    pub phash_threshold: u32,
+    /// number of threads reading images, one per CPU if `None`
+    pub jobs: Option<usize>,
+    /// images outside these bounds are left out of the crawl
+    pub dimension_filter: DimensionFilter,
+    /// hash every image file into [`CocoImage::sha256`]
+    pub checksums: bool,
+    /// don't draw a progress bar while reading images
+    pub quiet: bool,
+}
+
+/// Bounds on image dimensions, unbounded where `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DimensionFilter {
+    /// minimum length of the shorter side
+    pub min_dim: Option<u32>,
+    /// maximum length of the longer side
+    pub max_dim: Option<u32>,
+    /// minimum width * height
+    pub min_pixels: Option<u64>,
+    /// maximum width * height
+    pub max_pixels: Option<u64>,
+}
+
+impl DimensionFilter {
+    pub fn contains(&self, width: u32, height: u32) -> bool {
+        let pixels = u64::from(width) * u64::from(height);
+        self.min_dim.is_none_or(|min| width.min(height) >= min)
+            && self.max_dim.is_none_or(|max| width.max(height) <= max)
+            && self.min_pixels.is_none_or(|min| pixels >= min)
+            && self.max_pixels.is_none_or(|max| pixels <= max)
+    }
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            extensions: IMAGE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            exclude: GlobSet::empty(),
+            max_depth: None,
+            follow_symlinks: false,
+            output_path: PathBuf::from("coco.json"),
+            absolute_paths: false,
+            respect_exif_orientation: false,
+            keep_broken: false,
+            probe: ProbeMode::default(),
+            dedupe: DedupeMode::default(),
+            phash_threshold: DEFAULT_PHASH_THRESHOLD,
+            jobs: None,
+            dimension_filter: DimensionFilter::default(),
+            checksums: false,
+            quiet: false,
+        }
+    }
+}
+
+impl CrawlOptions {
+    /// Replaces the extension set, matching is case insensitive and a leading dot is ignored
+    pub fn with_extensions<S: AsRef<str>>(mut self, extensions: &[S]) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Skips files whose path, or path relative to its crawl root, matches any of `patterns`
+    /// (e.g. `**/thumbnails/**`)
+    pub fn with_exclude_patterns<S: AsRef<str>>(mut self, patterns: &[S]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(
+                Glob::new(pattern.as_ref())
+                    .with_context(|| format!("Invalid exclude pattern {:?}", pattern.as_ref()))?,
+            );
+        }
+        self.exclude = builder.build()?;
+        Ok(self)
+    }
+
+    fn matches_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext_str| self.extensions.contains(&ext_str.to_lowercase()))
+    }
+
+    fn is_excluded(&self, path: &Path, root: &Path) -> bool {
+        self.exclude.is_match(path)
+            || path
+                .strip_prefix(root)
+                .is_ok_and(|relative| self.exclude.is_match(relative))
+    }
+}
+
+/// Recursively collects the files matching `options` under each of `directories`. Every
+/// directory's entries are visited sorted by name, so the order doesn't depend on the file
+/// system. Symlink loops are skipped, any other entry that can't be walked, e.g. a directory
+/// that doesn't exist or can't be listed, fails the crawl.
+pub fn find_image_files<P: AsRef<Path>>(
+    directories: &[P],
+    options: &CrawlOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for dir in directories {
+        let dir = dir.as_ref();
+        let mut walker = walkdir::WalkDir::new(dir)
+            .follow_links(options.follow_symlinks)
+            .sort_by_file_name();
+        if let Some(max_depth) = options.max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // a followed link back to one of its ancestors, already being crawled
+                Err(err) if err.loop_ancestor().is_some() => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to crawl {}", dir.display()));
+                }
+            };
+            if entry.file_type().is_file()
+                && options.matches_extension(entry.path())
+                && !options.is_excluded(entry.path(), dir)
+            {
+                paths.push(entry.into_path());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Why a crawled file couldn't be turned into an image entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CrawlFailureReason {
+    /// the file or its metadata couldn't be read, e.g. permission denied
+    Unreadable,
+    /// the file was read but its image header couldn't be decoded, e.g. truncated or empty
+    Corrupt,
+}
+
+impl fmt::Display for CrawlFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrawlFailureReason::Unreadable => write!(f, "unreadable"),
+            CrawlFailureReason::Corrupt => write!(f, "corrupt"),
+        }
+    }
+}
+
+/// A crawled file that couldn't be read
+#[derive(Debug, Clone)]
+pub struct CrawlFailure {
+    pub path: PathBuf,
+    pub reason: CrawlFailureReason,
+    pub message: String,
+}
+
+impl CrawlFailure {
+    fn new(path: &Path, reason: CrawlFailureReason, error: impl fmt::Display) -> Self {
+        CrawlFailure {
+            path: path.to_path_buf(),
+            reason,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CrawlFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} image {:?}: {}", self.reason, self.path, self.message)
+    }
+}
+
+impl std::error::Error for CrawlFailure {}
+
+/// The image entries built by a crawl and the files that couldn't be read
+#[derive(Default)]
+pub struct CrawlReport {
+    pub images: Vec<CocoImage>,
+    /// every file that failed, including the ones kept with `keep_broken`
+    pub failures: Vec<CrawlFailure>,
+    /// kept path to the paths dropped as its duplicates
+    pub duplicates: BTreeMap<PathBuf, Vec<PathBuf>>,
+    /// readable images dropped by `options.dimension_filter`
+    pub filtered: Vec<PathBuf>,
+}
+
+impl CrawlReport {
+    /// Number of failures for each reason
+    pub fn failure_counts(&self) -> BTreeMap<CrawlFailureReason, usize> {
+        let mut counts = BTreeMap::new();
+        for failure in &self.failures {
+            *counts.entry(failure.reason).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// Crawls `directories` and builds an image entry for every matching file, with ids assigned
+/// from 0 in crawl order, see [`find_image_files`]
+pub fn crawl_directories(directories: &[PathBuf], options: CrawlOptions) -> Result<CrawlReport> {
+    crawl_new_images(directories, options, &HashSet::new(), 0)
+}
+
+/// Crawls `directories` into a COCO file holding `info` and the crawled images. The images are
+/// moved out of the returned report, which keeps the failures and duplicates.
+pub fn build_coco_file(
+    directories: &[PathBuf],
+    options: CrawlOptions,
+    info: CocoInfo,
+) -> Result<(CocoFile, CrawlReport)> {
+    let mut report = crawl_directories(directories, options)?;
+    let coco_file = CocoFile {
+        info: Some(info),
+        images: std::mem::take(&mut report.images),
+        annotations: Vec::new(),
+        categories: None,
+        licenses: None,
+    };
+    Ok((coco_file, report))
+}
+
+/// Like [`crawl_directories`], but skips files whose canonical path is in `known_paths` and
+/// assigns ids from `first_id`. Used to add newly discovered files to an existing dataset.
+pub fn crawl_new_images(
+    directories: &[PathBuf],
+    options: CrawlOptions,
+    known_paths: &HashSet<PathBuf>,
+    first_id: i64,
+) -> Result<CrawlReport> {
+    let paths: Vec<PathBuf> = find_image_files(directories, &options)?
+        .into_iter()
+        .filter(|path| {
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            !known_paths.contains(&canonical)
+        })
+        .collect();
+
+    // ids are assigned afterwards so skipped files don't leave gaps
+    let read = |path: &PathBuf| {
+        let result = read_coco_image(0, path, &options);
+        let hash = match result {
+            Ok(_) => hash_image(path, options.dedupe),
+            Err(_) => None,
+        };
+        (result, hash)
+    };
+    #[cfg(feature = "rayon")]
+    let results: Vec<(
+        std::result::Result<CocoImage, CrawlFailure>,
+        Option<ImageHash>,
+    )> = thread_pool(options.jobs)?.install(|| {
+        paths
+            .par_iter()
+            .progress_with(progress_bar(paths.len() as u64, options.quiet))
+            .map(read)
+            .collect()
+    });
+    // without rayon images are read one at a time and `jobs` has no effect
+    #[cfg(not(feature = "rayon"))]
+    let results: Vec<(
+        std::result::Result<CocoImage, CrawlFailure>,
+        Option<ImageHash>,
+    )> = paths.iter().map(read).collect();
+
+    let mut deduplicator = Deduplicator::new(options.phash_threshold);
+    let mut report = CrawlReport::default();
+    for (path, (result, hash)) in paths.iter().zip(results) {
+        // filtered images don't get to shadow later duplicates
+        if let Ok(image) = &result
+            && !options.dimension_filter.contains(image.width, image.height)
+        {
+            report.filtered.push(path.clone());
+            continue;
+        }
+        if let Some(hash) = hash
+            && deduplicator.is_duplicate(hash, path)
+        {
+            continue;
+        }
+
+        let image = match result {
+            Ok(image) => image,
+            Err(failure) => {
+                report.failures.push(failure);
+                if !options.keep_broken {
+                    continue;
+                }
+                broken_coco_image(path, &options)?
+            }
+        };
+        report.images.push(CocoImage {
+            id: first_id + report.images.len() as i64,
+            ..image
+        });
+    }
+    report.duplicates = deduplicator.duplicates;
+
+    Ok(report)
+}
+
+/// Builds the image entry for `image_path`. Dimensions come from the image header (see
+/// [`ProbeMode`]) and `date_captured` from EXIF, falling back to the file creation time.
+/// `file_name` is written relative to `options.output_path` where possible, see
+/// [`create_coco_image_path`].
+pub fn read_coco_image(
+    id: i64,
+    image_path: &Path,
+    options: &CrawlOptions,
+) -> std::result::Result<CocoImage, CrawlFailure> {
+    let unreadable = |error: &dyn fmt::Display| {
+        CrawlFailure::new(image_path, CrawlFailureReason::Unreadable, error)
+    };
+
+    let written_path =
+        create_coco_image_path(&options.output_path, image_path, options.absolute_paths)
+            .map_err(|error| unreadable(&error))?;
+    let metadata = fs::metadata(image_path).map_err(|error| unreadable(&error))?;
+    let exif = ExifMetadata::read(image_path).unwrap_or_default();
+
+    // prefer when the photo was taken over when the file was created
+    let date_captured = exif
+        .date_time_original
+        .or_else(|| metadata.created().ok().map(DateTime::<Utc>::from));
+
+    let probed = match options.probe {
+        ProbeMode::Fast => probe_dimensions(image_path),
+        ProbeMode::Full => None,
+    };
+    let (width, height) = match probed {
+        Some(dimensions) => dimensions,
+        None => ImageReader::open(image_path)
+            .map_err(|error| unreadable(&error))?
+            .with_guessed_format()
+            .map_err(|error| unreadable(&error))?
+            .into_dimensions()
+            .map_err(|error| CrawlFailure::new(image_path, CrawlFailureReason::Corrupt, error))?,
+    };
+    let (width, height) = if options.respect_exif_orientation && exif.swaps_dimensions() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let sha256 = if options.checksums {
+        Some(sha256_hex(image_path).map_err(|error| unreadable(&error))?)
+    } else {
+        None
+    };
+
+    Ok(CocoImage {
+        id,
+        width,
+        height,
+        file_name: written_path,
+        license: None,
+        flickr_url: None,
+        coco_url: None,
+        date_captured,
+        sha256,
+        neg_category_ids: None,
+        not_exhaustive_category_ids: None,
+        extra: Default::default(),
+    })
+}
+
+/// The 0x0 entry recorded for a file that couldn't be read when `keep_broken` is set
+fn broken_coco_image(image_path: &Path, options: &CrawlOptions) -> Result<CocoImage> {
+    Ok(CocoImage {
+        id: 0,
+        width: 0,
+        height: 0,
+        file_name: create_coco_image_path(&options.output_path, image_path, options.absolute_paths)
+            .context("Could not create COCO image path")?,
+        license: None,
+        flickr_url: None,
+        coco_url: None,
+        date_captured: None,
+        sha256: None,
+        neg_category_ids: None,
+        not_exhaustive_category_ids: None,
+        extra: Default::default(),
+    })
+}
+
+/// Names the category of an image found at `path` after the directory `depth` levels below
+/// the crawl root it is under (`root/cat/a.jpg` is "cat" at depth 1). `None` if the image isn't
+/// nested that deep under any of `directories`.
+pub fn category_from_dir(path: &Path, directories: &[PathBuf], depth: usize) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    directories.iter().find_map(|root| {
+        let relative = path.strip_prefix(root.canonicalize().ok()?).ok()?;
+        let mut components = relative.components();
+        // the last component is the file itself
+        components.next_back()?;
+        components
+            .nth(depth.checked_sub(1)?)
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+    })
+}
+
+/// Builds one category per directory name found by [`category_from_dir`], with ids from 1
+/// sorted by name, and a full image bbox annotation for every image that has a category.
+/// Returns the categories, the annotations and the ids of images left without a category.
+pub fn categorize_by_directory(
+    images: &[CocoImage],
+    directories: &[PathBuf],
+    output_path: &Path,
+    depth: usize,
+) -> Result<(Vec<CocoCategory>, Vec<CocoAnnotation>, Vec<i64>)> {
+    let mut image_categories = Vec::with_capacity(images.len());
+    for image in images {
+        let path = image.get_absolute_path(output_path)?;
+        image_categories.push(category_from_dir(&path, directories, depth));
+    }
+
+    let names: BTreeSet<&String> = image_categories.iter().flatten().collect();
+    let category_ids: HashMap<&String, i32> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (*name, index as i32 + 1))
+        .collect();
+    let categories = names
+        .iter()
+        .map(|name| {
+            CocoCategory::ObjectDetection(CocoObjectDetectionCategory {
+                id: category_ids[name],
+                name: name.to_string(),
+                supercategory: String::new(),
+                frequency: None,
+                image_count: None,
+                instance_count: None,
+                extra: Default::default(),
+            })
+        })
+        .collect();
+
+    let mut annotations = Vec::new();
+    let mut uncategorized = Vec::new();
+    for (image, category) in images.iter().zip(&image_categories) {
+        let Some(category) = category else {
+            uncategorized.push(image.id);
+            continue;
+        };
+        let bbox = [0.0, 0.0, image.width as f32, image.height as f32];
+        annotations.push(CocoAnnotation::ObjectDetection(
+            CocoObjectDetectionAnnotation {
+                id: annotations.len() as i64 + 1,
+                image_id: image.id,
+                category_id: category_ids[category],
+                segmentation: CocoSegmentation::from_bbox(&bbox),
+                area: bbox[2] * bbox[3],
+                bbox,
+                iscrowd: false,
+                score: None,
+                attributes: None,
+                extra: Default::default(),
+            },
+        ));
+    }
+
+    Ok((categories, annotations, uncategorized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"").unwrap();
+    }
+
+    fn found_names(directory: &Path, options: &CrawlOptions) -> Vec<String> {
+        let mut names: Vec<String> = find_image_files(&[directory], options)
+            .unwrap()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(directory)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_find_image_files_extensions_case_insensitive() {
+        let temp_dir = TempDir::new().unwrap();
+        touch(&temp_dir.path().join("a.JPG"));
+        touch(&temp_dir.path().join("b.heic"));
+        touch(&temp_dir.path().join("c.png"));
+        touch(&temp_dir.path().join("notes.txt"));
+
+        assert_eq!(
+            found_names(temp_dir.path(), &CrawlOptions::default()),
+            vec!["a.JPG", "c.png"]
+        );
+
+        let options = CrawlOptions::default().with_extensions(&["jpg", ".HEIC"]);
+        assert_eq!(
+            found_names(temp_dir.path(), &options),
+            vec!["a.JPG", "b.heic"]
+        );
+    }
+
+    #[test]
+    fn test_find_image_files_exclude_and_max_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        touch(&temp_dir.path().join("a.jpg"));
+        touch(&temp_dir.path().join("set/b.jpg"));
+        touch(&temp_dir.path().join("set/thumbnails/b.jpg"));
+        touch(&temp_dir.path().join("thumbnails/c.jpg"));
+
+        let options = CrawlOptions::default()
+            .with_exclude_patterns(&["**/thumbnails/**"])
+            .unwrap();
+        assert_eq!(
+            found_names(temp_dir.path(), &options),
+            vec!["a.jpg", "set/b.jpg"]
+        );
+
+        // patterns also match relative to the crawl root
+        let options = CrawlOptions::default()
+            .with_exclude_patterns(&["set/**"])
+            .unwrap();
+        assert_eq!(
+            found_names(temp_dir.path(), &options),
+            vec!["a.jpg", "thumbnails/c.jpg"]
+        );
+
+        let options = CrawlOptions {
+            max_depth: Some(1),
+            ..CrawlOptions::default()
+        };
+        assert_eq!(found_names(temp_dir.path(), &options), vec!["a.jpg"]);
+    }
+
+    #[test]
+    fn test_find_image_files_missing_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing = temp_dir.path().join("missing");
+        let err = find_image_files(&[missing], &CrawlOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_crawl_directories() {
+        use image::{ImageBuffer, Rgb};
+
+        let temp_dir = TempDir::new().unwrap();
+        let images_dir = temp_dir.path().join("images");
+        fs::create_dir(&images_dir).unwrap();
+        ImageBuffer::from_fn(30, 20, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+            .save(images_dir.join("a.png"))
+            .unwrap();
+        ImageBuffer::from_fn(8, 12, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+            .save(images_dir.join("b.jpg"))
+            .unwrap();
+
+        let options = CrawlOptions {
+            output_path: temp_dir.path().join("coco.json"),
+            ..CrawlOptions::default()
+        };
+        let mut images = crawl_directories(std::slice::from_ref(&images_dir), options)
+            .unwrap()
+            .images;
+        images.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].file_name, PathBuf::from("images/a.png"));
+        assert_eq!((images[0].width, images[0].height), (30, 20));
+        assert_eq!((images[1].width, images[1].height), (8, 12));
+        let mut ids: Vec<i64> = images.iter().map(|image| image.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1]);
+
+        let options = CrawlOptions {
+            output_path: temp_dir.path().join("coco.json"),
+            absolute_paths: true,
+            ..CrawlOptions::default()
+        };
+        let images = crawl_directories(&[images_dir], options).unwrap().images;
+        assert!(images.iter().all(|image| image.file_name.is_absolute()));
+    }
+
+    #[test]
+    fn test_crawl_new_images_skips_known_paths() {
+        use image::{ImageBuffer, Rgb};
+
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.png", "b.png"] {
+            ImageBuffer::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+                .save(temp_dir.path().join(name))
+                .unwrap();
+        }
+
+        let known_paths: HashSet<PathBuf> =
+            [temp_dir.path().join("a.png").canonicalize().unwrap()].into();
+        let options = CrawlOptions {
+            output_path: temp_dir.path().join("coco.json"),
+            ..CrawlOptions::default()
+        };
+        let images = crawl_new_images(&[temp_dir.path().to_path_buf()], options, &known_paths, 10)
+            .unwrap()
+            .images;
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, 10);
+        assert_eq!(images[0].file_name, PathBuf::from("b.png"));
+    }
+
+    #[test]
+    fn test_crawl_directories_reports_broken_files() {
+        use image::{ImageBuffer, Rgb};
+
+        let temp_dir = TempDir::new().unwrap();
+        ImageBuffer::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+            .save(temp_dir.path().join("a.png"))
+            .unwrap();
+        touch(&temp_dir.path().join("empty.jpg"));
+
+        let options = CrawlOptions {
+            output_path: temp_dir.path().join("coco.json"),
+            ..CrawlOptions::default()
+        };
+        let report = crawl_directories(&[temp_dir.path().to_path_buf()], options.clone()).unwrap();
+        assert_eq!(report.images.len(), 1);
+        assert_eq!(report.images[0].id, 0);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].reason, CrawlFailureReason::Corrupt);
+        assert_eq!(report.failure_counts()[&CrawlFailureReason::Corrupt], 1);
+
+        let options = CrawlOptions {
+            keep_broken: true,
+            ..options
+        };
+        let report = crawl_directories(&[temp_dir.path().to_path_buf()], options).unwrap();
+        assert_eq!(report.images.len(), 2);
+        assert_eq!(report.failures.len(), 1);
+        let broken = report
+            .images
+            .iter()
+            .find(|image| image.file_name == Path::new("empty.jpg"))
+            .unwrap();
+        assert_eq!((broken.width, broken.height), (0, 0));
+    }
+
+    #[test]
+    fn test_category_from_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("dataset");
+        touch(&root.join("cat/a.jpg"));
+        touch(&root.join("dog/puppies/b.jpg"));
+        touch(&root.join("c.jpg"));
+        let roots = [root.clone()];
+
+        assert_eq!(
+            category_from_dir(&root.join("cat/a.jpg"), &roots, 1).as_deref(),
+            Some("cat")
+        );
+        assert_eq!(
+            category_from_dir(&root.join("dog/puppies/b.jpg"), &roots, 2).as_deref(),
+            Some("puppies")
+        );
+        assert_eq!(category_from_dir(&root.join("cat/a.jpg"), &roots, 2), None);
+        assert_eq!(category_from_dir(&root.join("c.jpg"), &roots, 1), None);
+    }
+
+    #[test]
+    fn test_read_coco_image() {
+        use image::{ImageBuffer, Rgb};
+
+        let temp_dir = TempDir::new().unwrap();
+        let image_path = temp_dir.path().join("nested/a.png");
+        fs::create_dir_all(image_path.parent().unwrap()).unwrap();
+        ImageBuffer::from_fn(12, 7, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+            .save(&image_path)
+            .unwrap();
+
+        for probe in [ProbeMode::Fast, ProbeMode::Full] {
+            let options = CrawlOptions {
+                output_path: temp_dir.path().join("coco.json"),
+                probe,
+                ..CrawlOptions::default()
+            };
+            let image = read_coco_image(5, &image_path, &options).unwrap();
+            assert_eq!(image.id, 5);
+            assert_eq!((image.width, image.height), (12, 7));
+            assert_eq!(image.file_name, PathBuf::from("nested/a.png"));
+            assert!(image.license.is_none());
+        }
+    }
+
+    #[test]
+    fn test_build_coco_file() {
+        use chrono::Datelike;
+        use image::{ImageBuffer, Rgb};
+
+        let temp_dir = TempDir::new().unwrap();
+        ImageBuffer::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]))
+            .save(temp_dir.path().join("a.png"))
+            .unwrap();
+        touch(&temp_dir.path().join("broken.jpg"));
+
+        let info = CocoInfo {
+            year: 2024,
+            version: "2.0.0".to_string(),
+            description: "crawl".to_string(),
+            contributor: String::new(),
+            url: String::new(),
+            date_created: Utc::now(),
+            extra: Default::default(),
+        };
+        let options = CrawlOptions {
+            output_path: temp_dir.path().join("coco.json"),
+            ..CrawlOptions::default()
+        };
+        let (coco_file, report) =
+            build_coco_file(&[temp_dir.path().to_path_buf()], options, info).unwrap();
+
+        assert_eq!(coco_file.images.len(), 1);
+        assert!(coco_file.annotations.is_empty());
+        assert_eq!(coco_file.info.as_ref().unwrap().version, "2.0.0");
+        assert!(coco_file.info.unwrap().date_created.year() >= 2024);
+        assert!(report.images.is_empty());
+        assert_eq!(report.failures.len(), 1);
+    }
+
+    #[test]
+    fn test_dimension_filter() {
+        let filter = DimensionFilter {
+            min_dim: Some(100),
+            max_dim: Some(4000),
+            min_pixels: None,
+            max_pixels: Some(8_000_000),
+        };
+        assert!(filter.contains(100, 100));
+        assert!(!filter.contains(1000, 99));
+        assert!(!filter.contains(4001, 200));
+        assert!(!filter.contains(4000, 2001));
+        assert!(DimensionFilter::default().contains(0, 0));
+        assert!(
+            !DimensionFilter {
+                min_pixels: Some(101),
+                ..DimensionFilter::default()
+            }
+            .contains(10, 10)
+        );
+    }
+}
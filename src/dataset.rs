@@ -0,0 +1,389 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::{CocoAnnotation, CocoCategory, CocoFile, CocoImage, HasID};
+
+/// Owns a [`CocoFile`] and keeps id lookups up to date while images and annotations are added
+/// and removed, unlike [`CocoFile::make_image_id_map`] which has to be rebuilt after every
+/// change.
+///
+/// Removals swap the last image or annotation into the freed slot, so the order of the file
+/// isn't preserved.
+pub struct CocoDataset {
+    coco_file: CocoFile,
+    image_index: HashMap<i64, usize>,
+    annotation_index: HashMap<i64, usize>,
+    category_index: HashMap<i32, usize>,
+    // annotation indices per image id, including annotations whose image is missing
+    image_annotations: HashMap<i64, Vec<usize>>,
+    next_image_id: i64,
+    next_annotation_id: i64,
+}
+
+impl CocoDataset {
+    /// Indexes `coco_file`, failing if image, annotation or category ids aren't unique
+    pub fn new(coco_file: CocoFile) -> Result<Self> {
+        let mut image_index = HashMap::new();
+        for (index, image) in coco_file.images.iter().enumerate() {
+            if image_index.insert(image.id, index).is_some() {
+                bail!("duplicate image id {}", image.id);
+            }
+        }
+
+        let mut annotation_index = HashMap::new();
+        let mut image_annotations: HashMap<i64, Vec<usize>> = HashMap::new();
+        for (index, annotation) in coco_file.annotations.iter().enumerate() {
+            if let Some(id) = annotation.id()
+                && annotation_index.insert(id, index).is_some()
+            {
+                bail!("duplicate annotation id {}", id);
+            }
+            image_annotations
+                .entry(annotation.image_id())
+                .or_default()
+                .push(index);
+        }
+
+        let mut category_index = HashMap::new();
+        for (index, category) in coco_file.categories.iter().flatten().enumerate() {
+            if category_index.insert(category.id(), index).is_some() {
+                bail!("duplicate category id {}", category.id());
+            }
+        }
+
+        let next_image_id = image_index.keys().max().map_or(1, |id| id + 1);
+        let next_annotation_id = annotation_index.keys().max().map_or(1, |id| id + 1);
+        Ok(CocoDataset {
+            coco_file,
+            image_index,
+            annotation_index,
+            category_index,
+            image_annotations,
+            next_image_id,
+            next_annotation_id,
+        })
+    }
+
+    pub fn coco_file(&self) -> &CocoFile {
+        &self.coco_file
+    }
+
+    pub fn into_coco_file(self) -> CocoFile {
+        self.coco_file
+    }
+
+    pub fn get_image(&self, id: i64) -> Option<&CocoImage> {
+        self.image_index
+            .get(&id)
+            .map(|&index| &self.coco_file.images[index])
+    }
+
+    /// Looks up an annotation by id, panoptic annotations have no id and can't be found here
+    pub fn get_annotation(&self, id: i64) -> Option<&CocoAnnotation> {
+        self.annotation_index
+            .get(&id)
+            .map(|&index| &self.coco_file.annotations[index])
+    }
+
+    pub fn get_category(&self, id: i32) -> Option<&CocoCategory> {
+        let categories = self.coco_file.categories.as_ref()?;
+        self.category_index
+            .get(&id)
+            .map(|&index| &categories[index])
+    }
+
+    /// Annotations of the image with `id`, in no particular order
+    pub fn annotations_for_image(&self, id: i64) -> Vec<&CocoAnnotation> {
+        self.image_annotations
+            .get(&id)
+            .map(|indices| {
+                indices
+                    .iter()
+                    .map(|&index| &self.coco_file.annotations[index])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Adds an image and returns its id. With `assign_id` the image gets the next free id,
+    /// otherwise its own id must be unused.
+    pub fn add_image(&mut self, mut image: CocoImage, assign_id: bool) -> Result<i64> {
+        if assign_id {
+            image.id = self.next_image_id;
+        } else if self.image_index.contains_key(&image.id) {
+            bail!("image id {} is already used", image.id);
+        }
+
+        let id = image.id;
+        self.next_image_id = self.next_image_id.max(id + 1);
+        self.image_index.insert(id, self.coco_file.images.len());
+        self.coco_file.images.push(image);
+        Ok(id)
+    }
+
+    /// Adds an annotation after checking that its image and categories exist and returns its
+    /// id, or `None` for panoptic annotations. With `assign_id` the annotation gets the next
+    /// free id, otherwise its own id must be unused.
+    pub fn add_annotation(
+        &mut self,
+        mut annotation: CocoAnnotation,
+        assign_id: bool,
+    ) -> Result<Option<i64>> {
+        let image_id = annotation.image_id();
+        if !self.image_index.contains_key(&image_id) {
+            bail!("annotation refers to missing image id {}", image_id);
+        }
+        if let Some(category_id) = annotation
+            .category_ids()
+            .into_iter()
+            .find(|category_id| !self.category_index.contains_key(category_id))
+        {
+            bail!("annotation refers to missing category id {}", category_id);
+        }
+
+        if assign_id {
+            annotation.set_id(self.next_annotation_id);
+        }
+        let id = annotation.id();
+        let index = self.coco_file.annotations.len();
+        if let Some(id) = id {
+            if !assign_id && self.annotation_index.contains_key(&id) {
+                bail!("annotation id {} is already used", id);
+            }
+            self.next_annotation_id = self.next_annotation_id.max(id + 1);
+            self.annotation_index.insert(id, index);
+        }
+
+        self.image_annotations
+            .entry(image_id)
+            .or_default()
+            .push(index);
+        self.coco_file.annotations.push(annotation);
+        Ok(id)
+    }
+
+    /// Removes an image together with its annotations and returns them, or `None` if there is
+    /// no image with `id`
+    pub fn remove_image(&mut self, id: i64) -> Option<(CocoImage, Vec<CocoAnnotation>)> {
+        let index = self.image_index.remove(&id)?;
+
+        let mut annotation_indices = self.image_annotations.remove(&id).unwrap_or_default();
+        // removing from the back means the annotation swapped into a freed slot is never one
+        // of this image's
+        annotation_indices.sort_unstable_by(|a, b| b.cmp(a));
+        let mut annotations: Vec<CocoAnnotation> = annotation_indices
+            .into_iter()
+            .map(|annotation_index| self.remove_annotation_at(annotation_index))
+            .collect();
+        annotations.reverse();
+
+        let image = self.coco_file.images.swap_remove(index);
+        if let Some(moved) = self.coco_file.images.get(index) {
+            self.image_index.insert(moved.id, index);
+        }
+        Some((image, annotations))
+    }
+
+    fn remove_annotation_at(&mut self, index: usize) -> CocoAnnotation {
+        let last_index = self.coco_file.annotations.len() - 1;
+        let annotation = self.coco_file.annotations.swap_remove(index);
+        if let Some(id) = annotation.id() {
+            self.annotation_index.remove(&id);
+        }
+        if let Some(indices) = self.image_annotations.get_mut(&annotation.image_id()) {
+            indices.retain(|&i| i != index);
+        }
+
+        if let Some(moved) = self.coco_file.annotations.get(index) {
+            if let Some(id) = moved.id() {
+                self.annotation_index.insert(id, index);
+            }
+            if let Some(indices) = self.image_annotations.get_mut(&moved.image_id()) {
+                indices
+                    .iter_mut()
+                    .filter(|i| **i == last_index)
+                    .for_each(|i| *i = index);
+            }
+        }
+        annotation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_dataset() -> CocoDataset {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 80, "file_name": "a.jpg"},
+                {"id": 2, "width": 100, "height": 80, "file_name": "b.jpg"},
+                {"id": 3, "width": 100, "height": 80, "file_name": "c.jpg"}
+            ],
+            "annotations": [
+                {"id": 10, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 11, "image_id": 2, "caption": "b"},
+                {"id": 12, "image_id": 1, "caption": "a"},
+                {"image_id": 3, "file_name": "c.png", "segments_info": [
+                    {"id": 1, "category_id": 1, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0}
+                ]},
+                {"id": 13, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 14, "image_id": 2, "caption": "b again"}
+            ],
+            "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+        }"#;
+        CocoDataset::new(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn caption(text: &str, image_id: i64) -> CocoAnnotation {
+        serde_json::from_value(serde_json::json!({"id": 0, "image_id": image_id, "caption": text}))
+            .unwrap()
+    }
+
+    fn image(id: i64, file_name: &str) -> CocoImage {
+        serde_json::from_value(
+            serde_json::json!({"id": id, "width": 10, "height": 10, "file_name": file_name}),
+        )
+        .unwrap()
+    }
+
+    /// Compares every index against a freshly built dataset
+    fn assert_consistent(dataset: &CocoDataset) {
+        let rebuilt = CocoDataset::new(dataset.coco_file().clone()).unwrap();
+        assert_eq!(dataset.image_index, rebuilt.image_index);
+        assert_eq!(dataset.annotation_index, rebuilt.annotation_index);
+        assert_eq!(dataset.category_index, rebuilt.category_index);
+        let sorted = |map: &HashMap<i64, Vec<usize>>| {
+            let mut entries: Vec<(i64, Vec<usize>)> = map
+                .iter()
+                .filter(|(_, indices)| !indices.is_empty())
+                .map(|(id, indices)| {
+                    let mut indices = indices.clone();
+                    indices.sort();
+                    (*id, indices)
+                })
+                .collect();
+            entries.sort();
+            entries
+        };
+        assert_eq!(
+            sorted(&dataset.image_annotations),
+            sorted(&rebuilt.image_annotations)
+        );
+    }
+
+    fn annotation_ids(dataset: &CocoDataset, image_id: i64) -> Vec<Option<i64>> {
+        let mut ids: Vec<Option<i64>> = dataset
+            .annotations_for_image(image_id)
+            .iter()
+            .map(|annotation| annotation.id())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_dataset_lookups() {
+        let dataset = create_dataset();
+        assert_eq!(
+            dataset.get_image(2).unwrap().file_name.to_str(),
+            Some("b.jpg")
+        );
+        assert!(dataset.get_image(4).is_none());
+        assert_eq!(dataset.get_annotation(12).unwrap().image_id(), 1);
+        assert_eq!(dataset.get_category(1).unwrap().id(), 1);
+        assert_eq!(
+            annotation_ids(&dataset, 1),
+            vec![Some(10), Some(12), Some(13)]
+        );
+        assert_eq!(annotation_ids(&dataset, 3), vec![None]);
+
+        let mut duplicate = dataset.into_coco_file();
+        duplicate.images.push(image(1, "again.jpg"));
+        let err = CocoDataset::new(duplicate).err().unwrap();
+        assert!(err.to_string().contains("duplicate image id 1"));
+    }
+
+    #[test]
+    fn test_dataset_add() {
+        let mut dataset = create_dataset();
+
+        assert!(dataset.add_image(image(2, "dup.jpg"), false).is_err());
+        assert_eq!(dataset.add_image(image(2, "d.jpg"), true).unwrap(), 4);
+        assert_eq!(dataset.add_image(image(10, "e.jpg"), false).unwrap(), 10);
+        assert_eq!(dataset.add_image(image(0, "f.jpg"), true).unwrap(), 11);
+
+        let err = dataset
+            .add_annotation(caption("x", 99), true)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("missing image id 99"));
+        let mut detection = dataset.get_annotation(10).unwrap().clone();
+        detection.set_image_id(4);
+        if let CocoAnnotation::ObjectDetection(ann) = &mut detection {
+            ann.category_id = 7;
+        }
+        let err = dataset.add_annotation(detection, true).err().unwrap();
+        assert!(err.to_string().contains("missing category id 7"));
+
+        let mut existing = caption("x", 4);
+        existing.set_id(12);
+        assert!(dataset.add_annotation(existing, false).is_err());
+        assert_eq!(
+            dataset.add_annotation(caption("d", 4), true).unwrap(),
+            Some(15)
+        );
+        let mut explicit = caption("e", 10);
+        explicit.set_id(20);
+        assert_eq!(dataset.add_annotation(explicit, false).unwrap(), Some(20));
+        assert_eq!(
+            dataset.add_annotation(caption("f", 4), true).unwrap(),
+            Some(21)
+        );
+
+        assert_eq!(annotation_ids(&dataset, 4), vec![Some(15), Some(21)]);
+        assert_eq!(dataset.get_annotation(20).unwrap().image_id(), 10);
+        assert_consistent(&dataset);
+
+        let coco_file = dataset.into_coco_file();
+        assert_eq!(coco_file.images.len(), 6);
+        assert_eq!(coco_file.annotations.len(), 9);
+    }
+
+    #[test]
+    fn test_dataset_remove_image_cascades() {
+        let mut dataset = create_dataset();
+
+        let (removed_image, annotations) = dataset.remove_image(1).unwrap();
+        assert_eq!(removed_image.id, 1);
+        let removed: Vec<Option<i64>> = annotations.iter().map(|ann| ann.id()).collect();
+        assert_eq!(removed, vec![Some(10), Some(12), Some(13)]);
+        assert!(dataset.remove_image(1).is_none());
+
+        assert!(dataset.get_image(1).is_none());
+        assert!(dataset.get_annotation(10).is_none());
+        assert!(dataset.annotations_for_image(1).is_empty());
+        assert_eq!(
+            dataset.get_image(3).unwrap().file_name.to_str(),
+            Some("c.jpg")
+        );
+        assert_eq!(annotation_ids(&dataset, 2), vec![Some(11), Some(14)]);
+        assert_eq!(annotation_ids(&dataset, 3), vec![None]);
+        assert_eq!(dataset.get_annotation(14).unwrap().image_id(), 2);
+        assert_consistent(&dataset);
+
+        // freed ids aren't handed out again
+        assert_eq!(dataset.add_image(image(0, "d.jpg"), true).unwrap(), 4);
+        dataset.remove_image(3).unwrap();
+        assert_eq!(annotation_ids(&dataset, 2), vec![Some(11), Some(14)]);
+        assert_consistent(&dataset);
+
+        dataset.remove_image(2).unwrap();
+        dataset.remove_image(4).unwrap();
+        let coco_file = dataset.into_coco_file();
+        assert!(coco_file.images.is_empty());
+        assert!(coco_file.annotations.is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+use image::DynamicImage;
+use image::imageops::FilterType;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::geometry::{BBox, HasBBox};
+use crate::{CocoAnnotation, CocoFile, CocoRLE, CocoSegmentation};
+
+/// How a crawl detects duplicate images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupeMode {
+    #[default]
+    None,
+    /// byte for byte identical files
+    Sha256,
+    /// perceptually similar images, see [`phash`]
+    Phash,
+}
+
+/// Default maximum Hamming distance between two perceptual hashes of near-duplicates
+pub const DEFAULT_PHASH_THRESHOLD: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageHash {
+    Sha256([u8; 32]),
+    Phash(u64),
+}
+
+/// Hashes the file at `path` for `mode`. Returns `None` if `mode` is [`DedupeMode::None`] or
+/// the file can't be read or decoded, such files are never treated as duplicates.
+pub fn hash_image(path: &Path, mode: DedupeMode) -> Option<ImageHash> {
+    match mode {
+        DedupeMode::None => None,
+        DedupeMode::Sha256 => sha256_file(path).ok().map(ImageHash::Sha256),
+        DedupeMode::Phash => image::open(path)
+            .ok()
+            .map(|image| ImageHash::Phash(phash(&image))),
+    }
+}
+
+/// SHA-256 digest of the file at `path`
+pub fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// SHA-256 digest of the file at `path` as a lowercase hex string, as stored in
+/// [`crate::CocoImage::sha256`]
+pub fn sha256_hex(path: &Path) -> io::Result<String> {
+    Ok(sha256_file(path)?
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// 64 bit DCT perceptual hash. The image is reduced to 32x32 grayscale, and each bit records
+/// whether one of the lowest 8x8 frequencies (minus the DC term) is above their median.
+pub fn phash(image: &DynamicImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW: usize = 8;
+
+    let gray = image
+        .resize_exact(SIZE as u32, SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = gray.pixels().map(|pixel| f64::from(pixel.0[0])).collect();
+
+    // only the low frequency corner of the 2D DCT-II is needed
+    let cosines: Vec<f64> = (0..LOW * SIZE)
+        .map(|i| {
+            let (u, x) = (i / SIZE, i % SIZE);
+            ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2 * SIZE) as f64).cos()
+        })
+        .collect();
+    let mut frequencies = Vec::with_capacity(LOW * LOW);
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cosines[u * SIZE + x] * cosines[v * SIZE + y];
+                }
+            }
+            frequencies.push(sum);
+        }
+    }
+
+    let mut sorted: Vec<f64> = frequencies[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    frequencies
+        .iter()
+        .enumerate()
+        .filter(|&(_, &frequency)| frequency > median)
+        .fold(0u64, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// Tracks the images kept so far and which later files duplicate them. The first path seen
+/// for a given image wins.
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+    phash_threshold: u32,
+    exact: HashMap<[u8; 32], PathBuf>,
+    perceptual: Vec<(u64, PathBuf)>,
+    /// kept path to the paths dropped as its duplicates
+    pub duplicates: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl Deduplicator {
+    pub fn new(phash_threshold: u32) -> Self {
+        Deduplicator {
+            phash_threshold,
+            ..Deduplicator::default()
+        }
+    }
+
+    /// Records `path` as kept and returns `false`, or returns `true` if it duplicates an
+    /// image kept earlier
+    pub fn is_duplicate(&mut self, hash: ImageHash, path: &Path) -> bool {
+        let kept_path = match hash {
+            ImageHash::Sha256(digest) => match self.exact.get(&digest) {
+                Some(kept_path) => kept_path.clone(),
+                None => {
+                    self.exact.insert(digest, path.to_path_buf());
+                    return false;
+                }
+            },
+            ImageHash::Phash(hash) => {
+                let threshold = self.phash_threshold;
+                match self
+                    .perceptual
+                    .iter()
+                    .find(|(kept_hash, _)| (kept_hash ^ hash).count_ones() <= threshold)
+                {
+                    Some((_, kept_path)) => kept_path.clone(),
+                    None => {
+                        self.perceptual.push((hash, path.to_path_buf()));
+                        return false;
+                    }
+                }
+            }
+        };
+
+        self.duplicates
+            .entry(kept_path)
+            .or_default()
+            .push(path.to_path_buf());
+        true
+    }
+}
+
+/// Which annotation of a group of duplicates [`CocoFile::dedupe_annotations`] keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeepPolicy {
+    #[default]
+    LargestArea,
+    LowestId,
+    /// the one earliest in the annotations array
+    First,
+}
+
+/// What annotation deduplication compares, only annotations with a bbox and category take part
+struct DedupeCandidate<'a> {
+    id: i64,
+    category_id: i32,
+    bbox: BBox,
+    /// `None` without polygons or mask
+    segmentation: Option<&'a CocoSegmentation>,
+    area: f32,
+}
+
+impl<'a> DedupeCandidate<'a> {
+    fn new(annotation: &'a CocoAnnotation) -> Option<Self> {
+        let has_shape = |segmentation: &CocoSegmentation| match segmentation {
+            CocoSegmentation::Polygon(polygons) => {
+                polygons.iter().any(|polygon| polygon.len() >= 6)
+            }
+            CocoSegmentation::RLE(_) => true,
+        };
+        let (id, category_id, bbox, segmentation, area) = match annotation {
+            CocoAnnotation::ObjectDetection(ann) => (
+                ann.id,
+                ann.category_id,
+                ann.bbox(),
+                Some(&ann.segmentation),
+                ann.area,
+            ),
+            CocoAnnotation::KeypointDetection(ann) => (
+                ann.id,
+                ann.category_id,
+                ann.bbox(),
+                Some(&ann.segmentation),
+                ann.area,
+            ),
+            // DensePose masks are relative to the bbox
+            CocoAnnotation::DensePose(ann) => {
+                (ann.id, ann.category_id, ann.bbox(), None, ann.area as f32)
+            }
+            _ => return None,
+        };
+        Some(DedupeCandidate {
+            id,
+            category_id,
+            bbox,
+            segmentation: segmentation.filter(|segmentation| has_shape(segmentation)),
+            area,
+        })
+    }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+impl CocoFile {
+    /// Index pairs of annotations of the same image and category whose IoU is above
+    /// `iou_threshold`, sorted. Masks are compared if both annotations have a segmentation
+    /// and their image is known, bboxes otherwise.
+    fn duplicate_annotation_indices(&self, iou_threshold: f32) -> Vec<(usize, usize)> {
+        let dimensions: HashMap<i64, (u32, u32)> = self
+            .images
+            .iter()
+            .map(|image| (image.id, (image.width, image.height)))
+            .collect();
+        let mut by_image: HashMap<i64, Vec<(usize, DedupeCandidate)>> = HashMap::new();
+        for (i, annotation) in self.annotations.iter().enumerate() {
+            if let Some(candidate) = DedupeCandidate::new(annotation) {
+                by_image
+                    .entry(annotation.image_id())
+                    .or_default()
+                    .push((i, candidate));
+            }
+        }
+
+        let image_pairs = |(image_id, candidates): (&i64, &Vec<(usize, DedupeCandidate)>)| {
+            let dimensions = dimensions.get(image_id).copied();
+            // each mask is rasterized at most once
+            let masks: Vec<OnceCell<Option<CocoRLE>>> =
+                candidates.iter().map(|_| OnceCell::new()).collect();
+            let mask = |k: usize| {
+                masks[k]
+                    .get_or_init(|| {
+                        let (width, height) = dimensions?;
+                        Some(candidates[k].1.segmentation?.to_rle(width, height))
+                    })
+                    .as_ref()
+            };
+
+            let mut pairs = Vec::new();
+            for a in 0..candidates.len() {
+                for b in a + 1..candidates.len() {
+                    let ((index_a, candidate_a), (index_b, candidate_b)) =
+                        (&candidates[a], &candidates[b]);
+                    if candidate_a.category_id != candidate_b.category_id {
+                        continue;
+                    }
+                    let bbox_iou = candidate_a.bbox.iou(&candidate_b.bbox);
+                    if bbox_iou <= 0.0 {
+                        continue;
+                    }
+                    let iou = match (mask(a), mask(b)) {
+                        (Some(mask_a), Some(mask_b)) => mask_a.iou(mask_b).unwrap_or(bbox_iou),
+                        _ => bbox_iou,
+                    };
+                    if iou > iou_threshold {
+                        pairs.push((*index_a, *index_b));
+                    }
+                }
+            }
+            pairs
+        };
+        #[cfg(feature = "rayon")]
+        let mut pairs: Vec<(usize, usize)> =
+            by_image.par_iter().flat_map_iter(image_pairs).collect();
+        #[cfg(not(feature = "rayon"))]
+        let mut pairs: Vec<(usize, usize)> = by_image.iter().flat_map(image_pairs).collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Id pairs of annotations of the same image and category whose IoU is above
+    /// `iou_threshold`, in annotation order. Masks are compared when both annotations have a
+    /// segmentation, bboxes otherwise. Panoptic and caption annotations are never duplicates.
+    pub fn find_duplicate_annotations(&self, iou_threshold: f32) -> Vec<(i64, i64)> {
+        let id = |i: usize| DedupeCandidate::new(&self.annotations[i]).unwrap().id;
+        self.duplicate_annotation_indices(iou_threshold)
+            .into_iter()
+            .map(|(a, b)| (id(a), id(b)))
+            .collect()
+    }
+
+    /// Removes duplicates found by [`CocoFile::find_duplicate_annotations`]. Overlapping pairs
+    /// are chained into groups, of which only the annotation chosen by `keep` stays. Returns
+    /// the ids of the removed annotations, sorted.
+    pub fn dedupe_annotations(&mut self, iou_threshold: f32, keep: KeepPolicy) -> Vec<i64> {
+        let pairs = self.duplicate_annotation_indices(iou_threshold);
+        let mut parents: Vec<usize> = (0..self.annotations.len()).collect();
+        for &(a, b) in &pairs {
+            let (root_a, root_b) = (find_root(&mut parents, a), find_root(&mut parents, b));
+            parents[root_a.max(root_b)] = root_a.min(root_b);
+        }
+
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        let members: HashSet<usize> = pairs.iter().flat_map(|&(a, b)| [a, b]).collect();
+        for i in members {
+            groups
+                .entry(find_root(&mut parents, i))
+                .or_default()
+                .push(i);
+        }
+
+        let candidate = |i: usize| DedupeCandidate::new(&self.annotations[i]).unwrap();
+        let mut removed_indices = HashSet::new();
+        let mut removed_ids = Vec::new();
+        for mut group in groups.into_values() {
+            group.sort();
+            let kept = match keep {
+                KeepPolicy::First => group[0],
+                KeepPolicy::LowestId => *group.iter().min_by_key(|&&i| candidate(i).id).unwrap(),
+                // the earliest of equally large annotations
+                KeepPolicy::LargestArea => *group
+                    .iter()
+                    .rev()
+                    .max_by(|&&a, &&b| candidate(a).area.total_cmp(&candidate(b).area))
+                    .unwrap(),
+            };
+            for i in group.into_iter().filter(|&i| i != kept) {
+                removed_ids.push(candidate(i).id);
+                removed_indices.insert(i);
+            }
+        }
+
+        let mut index = 0;
+        self.annotations.retain(|_| {
+            index += 1;
+            !removed_indices.contains(&(index - 1))
+        });
+        removed_ids.sort();
+        removed_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    /// A coarse grid of pseudo random gray levels, so the low frequencies carry real structure
+    fn blocks(width: u32, height: u32, brightness: u8) -> DynamicImage {
+        let level = |bx: u32, by: u32| ((bx * 7 + by * 13) * 37 % 200) as u8;
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            let value = level(x * 6 / width, y * 6 / height).saturating_add(brightness);
+            Rgb([value, value, value])
+        }))
+    }
+
+    #[test]
+    fn test_phash_is_stable_under_resizing() {
+        let original = blocks(200, 100, 0);
+        let resized = original.resize_exact(90, 45, FilterType::Triangle);
+        let brighter = blocks(200, 100, 20);
+        let flipped = original.fliph();
+
+        let distance = |other: &DynamicImage| (phash(&original) ^ phash(other)).count_ones();
+        assert!(
+            distance(&resized) <= DEFAULT_PHASH_THRESHOLD,
+            "{}",
+            distance(&resized)
+        );
+        assert!(
+            distance(&brighter) <= DEFAULT_PHASH_THRESHOLD,
+            "{}",
+            distance(&brighter)
+        );
+        assert!(
+            distance(&flipped) > DEFAULT_PHASH_THRESHOLD,
+            "{}",
+            distance(&flipped)
+        );
+    }
+
+    #[test]
+    fn test_deduplicator_first_path_wins() {
+        let mut deduplicator = Deduplicator::new(2);
+        let a = ImageHash::Sha256([1; 32]);
+        let b = ImageHash::Sha256([2; 32]);
+
+        assert!(!deduplicator.is_duplicate(a, Path::new("a.jpg")));
+        assert!(!deduplicator.is_duplicate(b, Path::new("b.jpg")));
+        assert!(deduplicator.is_duplicate(a, Path::new("a copy.jpg")));
+        assert!(!deduplicator.is_duplicate(ImageHash::Phash(0b1111), Path::new("c.jpg")));
+        assert!(deduplicator.is_duplicate(ImageHash::Phash(0b0011), Path::new("c2.jpg")));
+        assert!(!deduplicator.is_duplicate(ImageHash::Phash(0b110000), Path::new("d.jpg")));
+
+        assert_eq!(
+            deduplicator.duplicates,
+            BTreeMap::from([
+                (PathBuf::from("a.jpg"), vec![PathBuf::from("a copy.jpg")]),
+                (PathBuf::from("c.jpg"), vec![PathBuf::from("c2.jpg")]),
+            ])
+        );
+    }
+
+    fn duplicates_file() -> CocoFile {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+                {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"},
+                {"id": 3, "width": 100, "height": 100, "file_name": "c.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+                 "area": 400.0, "bbox": [10.0, 10.0, 20.0, 20.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+                 "area": 380.0, "bbox": [11.0, 10.0, 20.0, 20.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [],
+                 "area": 100.0, "bbox": [60.0, 60.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 5, "image_id": 2, "category_id": 1, "segmentation": [],
+                 "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 4, "image_id": 2, "category_id": 1, "segmentation": [],
+                 "area": 50.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 6, "image_id": 2, "category_id": 1, "segmentation": [],
+                 "area": 25.0, "bbox": [50.0, 50.0, 5.0, 5.0], "iscrowd": 0},
+                {"id": 7, "image_id": 2, "category_id": 2, "segmentation": [],
+                 "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 8, "image_id": 3, "category_id": 1,
+                 "segmentation": [[0.0, 0.0, 10.0, 0.0, 0.0, 10.0]],
+                 "area": 50.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 9, "image_id": 3, "category_id": 1,
+                 "segmentation": [[10.0, 0.0, 10.0, 10.0, 0.0, 10.0]],
+                 "area": 50.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 10, "image_id": 3, "caption": "two triangles"}
+            ]
+        }"#;
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_find_duplicate_annotations() {
+        let coco_file = duplicates_file();
+        // the triangles share a bbox but their masks barely overlap, and 7 has another category
+        assert_eq!(
+            coco_file.find_duplicate_annotations(0.5),
+            vec![(1, 2), (5, 4)]
+        );
+        // 380 / 420 for the shifted boxes
+        assert_eq!(coco_file.find_duplicate_annotations(0.95), vec![(5, 4)]);
+    }
+
+    #[test]
+    fn test_dedupe_annotations_keep_policies() {
+        let remaining = |coco_file: &CocoFile| -> Vec<i64> {
+            coco_file
+                .annotations
+                .iter()
+                .filter_map(|annotation| annotation.id())
+                .collect()
+        };
+
+        for (keep, removed) in [
+            (KeepPolicy::LargestArea, vec![2, 4]),
+            (KeepPolicy::LowestId, vec![2, 5]),
+            (KeepPolicy::First, vec![2, 4]),
+        ] {
+            let mut coco_file = duplicates_file();
+            assert_eq!(
+                coco_file.dedupe_annotations(0.5, keep),
+                removed,
+                "{:?}",
+                keep
+            );
+            let expected: Vec<i64> = (1..=10).filter(|id| !removed.contains(id)).collect();
+            let mut ids = remaining(&coco_file);
+            ids.sort();
+            assert_eq!(ids, expected);
+        }
+
+        // chained overlaps collapse into one annotation
+        let mut coco_file = duplicates_file();
+        if let CocoAnnotation::ObjectDetection(ann) = &mut coco_file.annotations[2] {
+            ann.bbox = [12.0, 10.0, 20.0, 20.0];
+        }
+        assert_eq!(
+            coco_file.dedupe_annotations(0.8, KeepPolicy::First),
+            vec![2, 3, 4]
+        );
+    }
+}
@@ -0,0 +1,490 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::geometry::{BBox, HasBBox};
+use crate::{CocoAnnotation, CocoCategory, CocoFile, CocoImage, HasID};
+
+/// How [`CocoFile::diff`] matches images and annotations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffOptions {
+    /// match images by id instead of file name
+    pub by_id: bool,
+    /// annotations of the same category match when their bbox IoU is above this
+    pub iou_threshold: f32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            by_id: false,
+            iou_threshold: 0.5,
+        }
+    }
+}
+
+/// What identifies an image across both files
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(untagged)]
+pub enum ImageKey {
+    FileName(PathBuf),
+    Id(i64),
+}
+
+impl fmt::Display for ImageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageKey::FileName(file_name) => write!(f, "{}", file_name.display()),
+            ImageKey::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+/// An image in both files whose dimensions or metadata differ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageChange {
+    pub image: ImageKey,
+    pub old_id: i64,
+    pub new_id: i64,
+    /// names of the differing fields, such as `width` or `coco_url`
+    pub fields: Vec<&'static str>,
+}
+
+/// Annotations of an image in both files without a counterpart in the other file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationChanges {
+    pub image: ImageKey,
+    /// ids in the new file
+    pub added: Vec<i64>,
+    /// ids in the old file
+    pub removed: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategorySummary {
+    pub id: i32,
+    pub name: String,
+}
+
+/// A category id in both files whose name or other fields differ
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryChange {
+    pub id: i32,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// Differences between two COCO files, see [`CocoFile::diff`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DiffReport {
+    /// in new file order
+    pub added_images: Vec<ImageKey>,
+    /// in old file order
+    pub removed_images: Vec<ImageKey>,
+    /// in old file order
+    pub changed_images: Vec<ImageChange>,
+    /// images in both files whose annotations differ, in old file order
+    pub annotations: Vec<AnnotationChanges>,
+    pub added_categories: Vec<CategorySummary>,
+    pub removed_categories: Vec<CategorySummary>,
+    pub changed_categories: Vec<CategoryChange>,
+}
+
+impl DiffReport {
+    /// Whether no difference was found
+    pub fn is_empty(&self) -> bool {
+        self == &DiffReport::default()
+    }
+}
+
+fn category_summary(category: &CocoCategory) -> CategorySummary {
+    CategorySummary {
+        id: category.id(),
+        name: category.name().to_string(),
+    }
+}
+
+/// Names of the fields that differ between two versions of an image, leaving out the one
+/// the images were matched by
+fn changed_image_fields(old: &CocoImage, new: &CocoImage, by_id: bool) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if by_id && old.file_name != new.file_name {
+        fields.push("file_name");
+    }
+    if !by_id && old.id != new.id {
+        fields.push("id");
+    }
+    if old.width != new.width {
+        fields.push("width");
+    }
+    if old.height != new.height {
+        fields.push("height");
+    }
+    if old.license != new.license {
+        fields.push("license");
+    }
+    if old.flickr_url != new.flickr_url {
+        fields.push("flickr_url");
+    }
+    if old.coco_url != new.coco_url {
+        fields.push("coco_url");
+    }
+    if old.date_captured != new.date_captured {
+        fields.push("date_captured");
+    }
+    fields
+}
+
+/// What annotations are matched by, panoptic annotations aren't compared
+enum DiffObject<'a> {
+    Box { category_id: i32, bbox: BBox },
+    Caption(&'a str),
+}
+
+impl<'a> DiffObject<'a> {
+    fn new(annotation: &'a CocoAnnotation) -> Option<(i64, Self)> {
+        let (id, category_id, bbox) = match annotation {
+            CocoAnnotation::ObjectDetection(ann) => (ann.id, ann.category_id, ann.bbox()),
+            CocoAnnotation::KeypointDetection(ann) => (ann.id, ann.category_id, ann.bbox()),
+            CocoAnnotation::DensePose(ann) => (ann.id, ann.category_id, ann.bbox()),
+            CocoAnnotation::ImageCaptioning(ann) => {
+                return Some((ann.id, DiffObject::Caption(&ann.caption)));
+            }
+            CocoAnnotation::PanopticSegmentation(_) => return None,
+        };
+        Some((id, DiffObject::Box { category_id, bbox }))
+    }
+
+    /// How well the objects match, `None` if they don't
+    fn similarity(&self, other: &DiffObject, iou_threshold: f32) -> Option<f32> {
+        match (self, other) {
+            (
+                DiffObject::Box { category_id, bbox },
+                DiffObject::Box {
+                    category_id: other_category_id,
+                    bbox: other_bbox,
+                },
+            ) if category_id == other_category_id => {
+                let iou = bbox.iou(other_bbox);
+                (iou > iou_threshold).then_some(iou)
+            }
+            (DiffObject::Caption(caption), DiffObject::Caption(other_caption))
+                if caption == other_caption =>
+            {
+                Some(1.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Ids of the new and old annotations left unmatched after greedily pairing the most
+/// similar ones
+fn unmatched_annotations(
+    old: &[(i64, DiffObject)],
+    new: &[(i64, DiffObject)],
+    iou_threshold: f32,
+) -> (Vec<i64>, Vec<i64>) {
+    let mut pairs: Vec<(f32, usize, usize)> = Vec::new();
+    for (o, (_, old_object)) in old.iter().enumerate() {
+        for (n, (_, new_object)) in new.iter().enumerate() {
+            if let Some(similarity) = old_object.similarity(new_object, iou_threshold) {
+                pairs.push((similarity, o, n));
+            }
+        }
+    }
+    // most similar first, ties in file order
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let mut old_matched = vec![false; old.len()];
+    let mut new_matched = vec![false; new.len()];
+    for (_, o, n) in pairs {
+        if !old_matched[o] && !new_matched[n] {
+            old_matched[o] = true;
+            new_matched[n] = true;
+        }
+    }
+    let unmatched = |objects: &[(i64, DiffObject)], matched: &[bool]| -> Vec<i64> {
+        objects
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !**matched)
+            .map(|((id, _), _)| *id)
+            .collect()
+    };
+    (unmatched(new, &new_matched), unmatched(old, &old_matched))
+}
+
+impl CocoFile {
+    fn image_key(image: &CocoImage, by_id: bool) -> ImageKey {
+        if by_id {
+            ImageKey::Id(image.id)
+        } else {
+            ImageKey::FileName(image.file_name.clone())
+        }
+    }
+
+    fn diff_objects_by_image(&self) -> HashMap<i64, Vec<(i64, DiffObject<'_>)>> {
+        let mut by_image: HashMap<i64, Vec<(i64, DiffObject)>> = HashMap::new();
+        for annotation in &self.annotations {
+            if let Some(object) = DiffObject::new(annotation) {
+                by_image
+                    .entry(annotation.image_id())
+                    .or_default()
+                    .push(object);
+            }
+        }
+        by_image
+    }
+
+    /// Compares this file, the old version, with `new`. Images are matched by file name, or
+    /// by id with [`DiffOptions::by_id`], the first image with a key counts. Annotations of
+    /// matched images are paired greedily by bbox IoU within a category and captions by
+    /// their text, panoptic annotations aren't compared. Categories are matched by id.
+    pub fn diff(&self, new: &CocoFile, options: &DiffOptions) -> DiffReport {
+        let mut new_images: HashMap<ImageKey, &CocoImage> = HashMap::new();
+        for image in &new.images {
+            new_images
+                .entry(CocoFile::image_key(image, options.by_id))
+                .or_insert(image);
+        }
+        let mut old_images: HashMap<ImageKey, &CocoImage> = HashMap::new();
+        for image in &self.images {
+            old_images
+                .entry(CocoFile::image_key(image, options.by_id))
+                .or_insert(image);
+        }
+
+        let mut report = DiffReport {
+            added_images: new
+                .images
+                .iter()
+                .map(|image| CocoFile::image_key(image, options.by_id))
+                .filter(|key| !old_images.contains_key(key))
+                .collect(),
+            ..DiffReport::default()
+        };
+
+        let mut old_objects = self.diff_objects_by_image();
+        let mut new_objects = new.diff_objects_by_image();
+        for old_image in &self.images {
+            let key = CocoFile::image_key(old_image, options.by_id);
+            if !std::ptr::eq(old_images[&key], old_image) {
+                continue;
+            }
+            let Some(new_image) = new_images.get(&key) else {
+                report.removed_images.push(key);
+                continue;
+            };
+
+            let fields = changed_image_fields(old_image, new_image, options.by_id);
+            if !fields.is_empty() {
+                report.changed_images.push(ImageChange {
+                    image: key.clone(),
+                    old_id: old_image.id,
+                    new_id: new_image.id,
+                    fields,
+                });
+            }
+
+            let old = old_objects.remove(&old_image.id).unwrap_or_default();
+            let new = new_objects.remove(&new_image.id).unwrap_or_default();
+            let (added, removed) = unmatched_annotations(&old, &new, options.iou_threshold);
+            if !added.is_empty() || !removed.is_empty() {
+                report.annotations.push(AnnotationChanges {
+                    image: key,
+                    added,
+                    removed,
+                });
+            }
+        }
+
+        let old_categories: HashMap<i32, &CocoCategory> = self
+            .categories
+            .iter()
+            .flatten()
+            .map(|category| (category.id(), category))
+            .collect();
+        let new_categories: HashMap<i32, &CocoCategory> = new
+            .categories
+            .iter()
+            .flatten()
+            .map(|category| (category.id(), category))
+            .collect();
+        for category in self.categories.iter().flatten() {
+            match new_categories.get(&category.id()) {
+                None => report.removed_categories.push(category_summary(category)),
+                Some(new_category) if *new_category != category => {
+                    report.changed_categories.push(CategoryChange {
+                        id: category.id(),
+                        old_name: category.name().to_string(),
+                        new_name: new_category.name().to_string(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        report.added_categories = new
+            .categories
+            .iter()
+            .flatten()
+            .filter(|category| !old_categories.contains_key(&category.id()))
+            .map(category_summary)
+            .collect();
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coco_file(json: serde_json::Value) -> CocoFile {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn base() -> serde_json::Value {
+        serde_json::json!({
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+                {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+                 "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 2, "segmentation": [],
+                 "area": 100.0, "bbox": [50.0, 50.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 3, "image_id": 2, "caption": "a dog"}
+            ],
+            "categories": [
+                {"id": 1, "name": "cat", "supercategory": "animal"},
+                {"id": 2, "name": "dog", "supercategory": "animal"}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let old = coco_file(base());
+        let report = old.diff(&old.clone(), &DiffOptions::default());
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_diff_images() {
+        let old = coco_file(base());
+        let mut json = base();
+        json["images"][0]["width"] = 200.into();
+        json["images"][0]["id"] = 10.into();
+        json["annotations"][0]["image_id"] = 10.into();
+        json["annotations"][1]["image_id"] = 10.into();
+        json["images"][1]["file_name"] = "c.jpg".into();
+        let new = coco_file(json);
+
+        let report = old.diff(&new, &DiffOptions::default());
+        assert_eq!(
+            report.added_images,
+            vec![ImageKey::FileName("c.jpg".into())]
+        );
+        assert_eq!(
+            report.removed_images,
+            vec![ImageKey::FileName("b.jpg".into())]
+        );
+        assert_eq!(
+            report.changed_images,
+            vec![ImageChange {
+                image: ImageKey::FileName("a.jpg".into()),
+                old_id: 1,
+                new_id: 10,
+                fields: vec!["id", "width"],
+            }]
+        );
+        // the annotations followed their image to its new id
+        assert!(report.annotations.is_empty());
+
+        let by_id = DiffOptions {
+            by_id: true,
+            ..DiffOptions::default()
+        };
+        let report = old.diff(&new, &by_id);
+        assert_eq!(report.added_images, vec![ImageKey::Id(10)]);
+        assert_eq!(report.removed_images, vec![ImageKey::Id(1)]);
+        assert_eq!(report.changed_images.len(), 1);
+        assert_eq!(report.changed_images[0].image, ImageKey::Id(2));
+        assert_eq!(report.changed_images[0].fields, vec!["file_name"]);
+    }
+
+    #[test]
+    fn test_diff_annotations() {
+        let old = coco_file(base());
+        let mut json = base();
+        // shifted a little, still the same object
+        json["annotations"][0]["bbox"] = serde_json::json!([1.0, 0.0, 10.0, 10.0]);
+        json["annotations"][0]["id"] = 7.into();
+        // same box, other category
+        json["annotations"][1]["category_id"] = 1.into();
+        json["annotations"][2]["caption"] = "a big dog".into();
+        let new = coco_file(json);
+
+        let report = old.diff(&new, &DiffOptions::default());
+        assert_eq!(
+            report.annotations,
+            vec![
+                AnnotationChanges {
+                    image: ImageKey::FileName("a.jpg".into()),
+                    added: vec![2],
+                    removed: vec![2],
+                },
+                AnnotationChanges {
+                    image: ImageKey::FileName("b.jpg".into()),
+                    added: vec![3],
+                    removed: vec![3],
+                },
+            ]
+        );
+
+        // IoU 9 / 11 is no match above 0.9
+        let strict = DiffOptions {
+            iou_threshold: 0.9,
+            ..DiffOptions::default()
+        };
+        let report = old.diff(&new, &strict);
+        assert_eq!(report.annotations[0].added, vec![7, 2]);
+        assert_eq!(report.annotations[0].removed, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_diff_categories() {
+        let old = coco_file(base());
+        let mut json = base();
+        json["categories"][0]["name"] = "kitten".into();
+        json["categories"][1]["id"] = 3.into();
+        let new = coco_file(json);
+
+        let report = old.diff(&new, &DiffOptions::default());
+        assert_eq!(
+            report.added_categories,
+            vec![CategorySummary {
+                id: 3,
+                name: "dog".to_string()
+            }]
+        );
+        assert_eq!(
+            report.removed_categories,
+            vec![CategorySummary {
+                id: 2,
+                name: "dog".to_string()
+            }]
+        );
+        assert_eq!(
+            report.changed_categories,
+            vec![CategoryChange {
+                id: 1,
+                old_name: "cat".to_string(),
+                new_name: "kitten".to_string()
+            }]
+        );
+    }
+}
@@ -0,0 +1,1086 @@
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::keypoints::COCO_PERSON_SIGMAS;
+use crate::results::{CocoResult, CocoResults};
+use crate::{CocoAnnotation, CocoFile, CocoRLE, CocoSegmentation, HasID};
+
+/// What ground truth and predictions are matched by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IouType {
+    /// box IoU
+    Bbox,
+    /// mask IoU, boxes without a mask are evaluated as rectangles
+    Segm,
+    /// object keypoint similarity
+    Keypoints,
+}
+
+/// Evaluation settings, [`EvalParams::new`] has the defaults of the COCO API
+#[derive(Debug, Clone)]
+pub struct EvalParams {
+    pub iou_type: IouType,
+    pub iou_thresholds: Vec<f64>,
+    pub recall_thresholds: Vec<f64>,
+    /// ascending, the last one limits the predictions per image and category
+    pub max_dets: Vec<usize>,
+    /// label and inclusive object area bounds, the first range should cover every area
+    pub area_ranges: Vec<(&'static str, f64, f64)>,
+    /// per keypoint OKS sigmas for [`IouType::Keypoints`]
+    pub keypoint_sigmas: Vec<f32>,
+}
+
+/// `num` evenly spaced values from `start` to `stop`, computed like numpy's linspace so
+/// thresholds compare the same as in the COCO API
+fn linspace(start: f64, stop: f64, num: usize) -> Vec<f64> {
+    let step = (stop - start) / (num - 1) as f64;
+    let mut values: Vec<f64> = (0..num).map(|i| i as f64 * step + start).collect();
+    values[num - 1] = stop;
+    values
+}
+
+impl EvalParams {
+    pub fn new(iou_type: IouType) -> Self {
+        let (max_dets, area_ranges) = match iou_type {
+            IouType::Keypoints => (
+                vec![20],
+                vec![
+                    ("all", 0.0, 1e10),
+                    ("medium", 1024.0, 9216.0),
+                    ("large", 9216.0, 1e10),
+                ],
+            ),
+            IouType::Bbox | IouType::Segm => (
+                vec![1, 10, 100],
+                vec![
+                    ("all", 0.0, 1e10),
+                    ("small", 0.0, 1024.0),
+                    ("medium", 1024.0, 9216.0),
+                    ("large", 9216.0, 1e10),
+                ],
+            ),
+        };
+        EvalParams {
+            iou_type,
+            iou_thresholds: linspace(0.5, 0.95, 10),
+            recall_thresholds: linspace(0.0, 1.0, 101),
+            max_dets,
+            area_ranges,
+            keypoint_sigmas: COCO_PERSON_SIGMAS.to_vec(),
+        }
+    }
+}
+
+/// A ground truth annotation or prediction as the evaluation sees it
+struct EvalObject {
+    score: f64,
+    area: f64,
+    bbox: [f64; 4],
+    iscrowd: bool,
+    /// ground truth that predictions may match without counting
+    ignore: bool,
+    mask: Option<CocoRLE>,
+    keypoints: Vec<f32>,
+}
+
+impl EvalObject {
+    fn new(bbox: [f32; 4], area: f64) -> Self {
+        EvalObject {
+            score: 0.0,
+            area,
+            bbox: bbox.map(f64::from),
+            iscrowd: false,
+            ignore: false,
+            mask: None,
+            keypoints: Vec::new(),
+        }
+    }
+}
+
+fn box_area(bbox: &[f32; 4]) -> f64 {
+    f64::from(bbox[2]) * f64::from(bbox[3])
+}
+
+/// Rasterizes `segmentation` at the image size, failing if it is an RLE of another size
+fn image_mask(segmentation: &CocoSegmentation, (width, height): (u32, u32)) -> Result<CocoRLE> {
+    let rle = segmentation.to_rle(width, height);
+    if rle.size != (height, width) {
+        bail!(
+            "mask of size {:?} doesn't match its {}x{} image",
+            rle.size,
+            width,
+            height
+        );
+    }
+    Ok(rle)
+}
+
+/// The ground truth of an annotation, `None` if it can't be evaluated with `params`
+fn ground_truth_object(
+    annotation: &CocoAnnotation,
+    params: &EvalParams,
+    image_size: (u32, u32),
+) -> Result<Option<EvalObject>> {
+    let (segmentation, keypoints) = match (params.iou_type, annotation) {
+        (IouType::Bbox | IouType::Segm, CocoAnnotation::ObjectDetection(ann)) => {
+            (&ann.segmentation, None)
+        }
+        (IouType::Bbox | IouType::Segm, CocoAnnotation::KeypointDetection(ann)) => {
+            (&ann.segmentation, None)
+        }
+        (IouType::Keypoints, CocoAnnotation::KeypointDetection(ann)) => {
+            (&ann.segmentation, Some(ann))
+        }
+        (IouType::Bbox, CocoAnnotation::DensePose(ann)) => {
+            let mut object = EvalObject::new(ann.bbox, f64::from(ann.area));
+            object.iscrowd = ann.iscrowd;
+            object.ignore = ann.iscrowd;
+            return Ok(Some(object));
+        }
+        _ => return Ok(None),
+    };
+    let (bbox, area, iscrowd) = match annotation {
+        CocoAnnotation::ObjectDetection(ann) => (ann.bbox, ann.area, ann.iscrowd),
+        CocoAnnotation::KeypointDetection(ann) => (ann.bbox, ann.area, ann.iscrowd),
+        _ => unreachable!(),
+    };
+
+    let mut object = EvalObject::new(bbox, f64::from(area));
+    object.iscrowd = iscrowd;
+    object.ignore = iscrowd;
+    if params.iou_type == IouType::Segm {
+        object.mask = Some(image_mask(segmentation, image_size)?);
+    }
+    if let Some(ann) = keypoints {
+        if ann.keypoints.len() != params.keypoint_sigmas.len() * 3 {
+            bail!(
+                "annotation {} has {} keypoint values, expected {} for {} sigmas",
+                ann.id,
+                ann.keypoints.len(),
+                params.keypoint_sigmas.len() * 3,
+                params.keypoint_sigmas.len()
+            );
+        }
+        object.ignore |= ann.num_keypoints == 0;
+        object.keypoints = ann.keypoints.clone();
+    }
+    Ok(Some(object))
+}
+
+/// The prediction of a result numbered `id`, with its area computed the way the COCO API
+/// loads results: from the box if there is one. `None` if it can't be evaluated with
+/// `params`.
+fn prediction_object(
+    result: &CocoResult,
+    id: i64,
+    params: &EvalParams,
+    image_size: (u32, u32),
+) -> Result<Option<EvalObject>> {
+    let (mut object, score) = match (params.iou_type, result) {
+        (IouType::Bbox, CocoResult::Detection(result)) => (
+            EvalObject::new(result.bbox, box_area(&result.bbox)),
+            result.score,
+        ),
+        (IouType::Segm, CocoResult::Detection(result)) => {
+            let mut object = EvalObject::new(result.bbox, box_area(&result.bbox));
+            let rectangle = CocoSegmentation::from_bbox(&result.bbox);
+            object.mask = Some(image_mask(&rectangle, image_size)?);
+            (object, result.score)
+        }
+        (IouType::Bbox | IouType::Segm, CocoResult::Segmentation(result)) => {
+            let mask = image_mask(&result.segmentation, image_size)?;
+            let bbox = result
+                .bbox
+                .or_else(|| mask.bounding_box())
+                .unwrap_or_default();
+            let area = match result.bbox {
+                Some(bbox) => box_area(&bbox),
+                None => mask.area() as f64,
+            };
+            let mut object = EvalObject::new(bbox, area);
+            if params.iou_type == IouType::Segm {
+                object.mask = Some(mask);
+            }
+            (object, result.score)
+        }
+        (IouType::Bbox, CocoResult::Keypoint(result)) => {
+            let bbox = result.bounding_box();
+            (EvalObject::new(bbox, box_area(&bbox)), result.score)
+        }
+        (IouType::Keypoints, CocoResult::Keypoint(result)) => {
+            if result.keypoints.len() != params.keypoint_sigmas.len() * 3 {
+                bail!(
+                    "prediction {} has {} keypoint values, expected {} for {} sigmas",
+                    id,
+                    result.keypoints.len(),
+                    params.keypoint_sigmas.len() * 3,
+                    params.keypoint_sigmas.len()
+                );
+            }
+            let bbox = result.bounding_box();
+            let mut object = EvalObject::new(bbox, box_area(&bbox));
+            object.keypoints = result.keypoints.clone();
+            (object, result.score)
+        }
+        _ => return Ok(None),
+    };
+    object.score = f64::from(score);
+    Ok(Some(object))
+}
+
+/// Box IoU of the COCO mask API, for crowd ground truth the intersection over the
+/// prediction's area
+fn box_iou(dt: &[f64; 4], gt: &[f64; 4], iscrowd: bool) -> f64 {
+    let w = (dt[0] + dt[2]).min(gt[0] + gt[2]) - dt[0].max(gt[0]);
+    let h = (dt[1] + dt[3]).min(gt[1] + gt[3]) - dt[1].max(gt[1]);
+    if w <= 0.0 || h <= 0.0 {
+        return 0.0;
+    }
+    let intersection = w * h;
+    let dt_area = dt[2] * dt[3];
+    let union = if iscrowd {
+        dt_area
+    } else {
+        dt_area + gt[2] * gt[3] - intersection
+    };
+    intersection / union
+}
+
+fn mask_iou(dt: &CocoRLE, gt: &CocoRLE, iscrowd: bool) -> Result<f64> {
+    let intersection = dt.intersection(gt)?.area() as f64;
+    let union = if iscrowd {
+        dt.area() as f64
+    } else {
+        (dt.area() + gt.area()) as f64 - intersection
+    };
+    Ok(if union > 0.0 {
+        intersection / union
+    } else {
+        0.0
+    })
+}
+
+/// Object keypoint similarity as computed by the COCO API. Without labeled ground truth
+/// keypoints, the distance of every predicted keypoint to a box twice the size of the ground
+/// truth box is used.
+fn oks(dt: &EvalObject, gt: &EvalObject, sigmas: &[f32]) -> f64 {
+    let labeled = gt.keypoints.chunks_exact(3).filter(|g| g[2] > 0.0).count();
+    let [bx, by, bw, bh] = gt.bbox;
+    let (x0, x1, y0, y1) = (bx - bw, bx + bw * 2.0, by - bh, by + bh * 2.0);
+    let errors: Vec<f64> = gt
+        .keypoints
+        .chunks_exact(3)
+        .zip(dt.keypoints.chunks_exact(3))
+        .zip(sigmas)
+        .filter(|((g, _), _)| labeled == 0 || g[2] > 0.0)
+        .map(|((g, d), &sigma)| {
+            let (xg, yg) = (f64::from(g[0]), f64::from(g[1]));
+            let (xd, yd) = (f64::from(d[0]), f64::from(d[1]));
+            let (dx, dy) = if labeled > 0 {
+                (xd - xg, yd - yg)
+            } else {
+                (
+                    (x0 - xd).max(0.0) + (xd - x1).max(0.0),
+                    (y0 - yd).max(0.0) + (yd - y1).max(0.0),
+                )
+            };
+            let variance = (f64::from(sigma) * 2.0).powi(2);
+            (dx * dx + dy * dy) / variance / (gt.area + f64::EPSILON) / 2.0
+        })
+        .collect();
+    errors.iter().map(|e| (-e).exp()).sum::<f64>() / errors.len() as f64
+}
+
+/// `[prediction][ground truth]` similarities
+fn compute_ious(
+    dts: &[&EvalObject],
+    gts: &[EvalObject],
+    params: &EvalParams,
+) -> Result<Vec<Vec<f64>>> {
+    dts.iter()
+        .map(|dt| {
+            gts.iter()
+                .map(|gt| match params.iou_type {
+                    IouType::Bbox => Ok(box_iou(&dt.bbox, &gt.bbox, gt.iscrowd)),
+                    IouType::Segm => mask_iou(
+                        dt.mask.as_ref().unwrap(),
+                        gt.mask.as_ref().unwrap(),
+                        gt.iscrowd,
+                    ),
+                    IouType::Keypoints => Ok(oks(dt, gt, &params.keypoint_sigmas)),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Matches of one image and category in one area range
+struct ImageEval {
+    /// scores of the predictions, highest first
+    dt_scores: Vec<f64>,
+    /// `[threshold][prediction]` whether the prediction matched a ground truth
+    dt_matched: Vec<Vec<bool>>,
+    /// `[threshold][prediction]` whether the prediction doesn't count
+    dt_ignore: Vec<Vec<bool>>,
+    gt_ignore: Vec<bool>,
+}
+
+/// Greedily matches the predictions, sorted by score, to the ground truth at every threshold.
+/// Predictions matching ignored ground truth and unmatched predictions outside the area range
+/// don't count.
+fn evaluate_image(
+    dts: &[&EvalObject],
+    gts: &[EvalObject],
+    ious: &[Vec<f64>],
+    (min_area, max_area): (f64, f64),
+    params: &EvalParams,
+) -> Option<ImageEval> {
+    if dts.is_empty() && gts.is_empty() {
+        return None;
+    }
+    let outside = |area: f64| area < min_area || area > max_area;
+
+    // ignored ground truth last, so it's only matched when nothing else is left
+    let mut gt_order: Vec<usize> = (0..gts.len()).collect();
+    gt_order.sort_by_key(|&g| gts[g].ignore || outside(gts[g].area));
+    let gt_ignore: Vec<bool> = gt_order
+        .iter()
+        .map(|&g| gts[g].ignore || outside(gts[g].area))
+        .collect();
+
+    let thresholds = params.iou_thresholds.len();
+    let mut dt_matched = vec![vec![false; dts.len()]; thresholds];
+    let mut dt_ignore = vec![vec![false; dts.len()]; thresholds];
+    for (t, &threshold) in params.iou_thresholds.iter().enumerate() {
+        let mut gt_matched = vec![false; gts.len()];
+        for d in 0..dts.len() {
+            let mut best_iou = threshold.min(1.0 - 1e-10);
+            let mut best: Option<usize> = None;
+            for (g, &gt_index) in gt_order.iter().enumerate() {
+                if gt_matched[g] && !gts[gt_index].iscrowd {
+                    continue;
+                }
+                // a match to counted ground truth beats any ignored one
+                if best.is_some_and(|m| !gt_ignore[m]) && gt_ignore[g] {
+                    break;
+                }
+                if ious[d][gt_index] < best_iou {
+                    continue;
+                }
+                best_iou = ious[d][gt_index];
+                best = Some(g);
+            }
+            if let Some(m) = best {
+                dt_ignore[t][d] = gt_ignore[m];
+                dt_matched[t][d] = true;
+                gt_matched[m] = true;
+            }
+        }
+        for (d, dt) in dts.iter().enumerate() {
+            if !dt_matched[t][d] && outside(dt.area) {
+                dt_ignore[t][d] = true;
+            }
+        }
+    }
+
+    Some(ImageEval {
+        dt_scores: dts.iter().map(|dt| dt.score).collect(),
+        dt_matched,
+        dt_ignore,
+        gt_ignore,
+    })
+}
+
+/// Precision and recall of the COCO evaluation
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub params: EvalParams,
+    /// evaluated categories, sorted
+    pub category_ids: Vec<i32>,
+    /// `[threshold][recall][category][area][max_dets]`, -1 where there was no ground truth
+    precision: Vec<f64>,
+    /// `[threshold][category][area][max_dets]`, -1 where there was no ground truth
+    recall: Vec<f64>,
+}
+
+/// One line of the COCO summary
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalStat {
+    /// short name such as `AP50` or `ARs`
+    pub name: &'static str,
+    pub average_precision: bool,
+    /// `None` for the mean over every threshold
+    pub iou_threshold: Option<f64>,
+    pub area: &'static str,
+    pub max_dets: usize,
+    /// -1 if there was nothing to evaluate
+    pub value: f64,
+}
+
+impl fmt::Display for EvalStat {
+    /// Formatted like the summary of the COCO API
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (title, kind) = if self.average_precision {
+            ("Average Precision", "(AP)")
+        } else {
+            ("Average Recall", "(AR)")
+        };
+        let iou = match self.iou_threshold {
+            Some(threshold) => format!("{:0.2}", threshold),
+            None => "0.50:0.95".to_string(),
+        };
+        write!(
+            f,
+            " {:<18} {} @[ IoU={:<9} | area={:>6} | maxDets={:>3} ] = {:0.3}",
+            title, kind, iou, self.area, self.max_dets, self.value
+        )
+    }
+}
+
+/// Per category results over every area, with the most predictions per image
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryEval {
+    pub category_id: i32,
+    pub ap: f64,
+    pub ap50: f64,
+    pub ap75: f64,
+    pub ar: f64,
+}
+
+/// Mean of the values that aren't -1, or -1 if there are none
+fn mean_valid(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values
+        .filter(|&value| value > -1.0)
+        .fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { -1.0 } else { sum / count as f64 }
+}
+
+impl Evaluation {
+    fn precision_index(&self, t: usize, r: usize, k: usize, a: usize, m: usize) -> usize {
+        let p = &self.params;
+        (((t * p.recall_thresholds.len() + r) * self.category_ids.len() + k) * p.area_ranges.len()
+            + a)
+            * p.max_dets.len()
+            + m
+    }
+
+    fn recall_index(&self, t: usize, k: usize, a: usize, m: usize) -> usize {
+        let p = &self.params;
+        ((t * self.category_ids.len() + k) * p.area_ranges.len() + a) * p.max_dets.len() + m
+    }
+
+    /// Interpolated precision at IoU threshold `t`, recall threshold `r`, category `k`, area
+    /// range `a` and the `m`th max detections, all indices into `params`
+    pub fn precision(&self, t: usize, r: usize, k: usize, a: usize, m: usize) -> f64 {
+        self.precision[self.precision_index(t, r, k, a, m)]
+    }
+
+    /// Recall at IoU threshold `t`, category `k`, area range `a` and the `m`th max
+    /// detections
+    pub fn recall(&self, t: usize, k: usize, a: usize, m: usize) -> f64 {
+        self.recall[self.recall_index(t, k, a, m)]
+    }
+
+    fn threshold_indices(&self, iou_threshold: Option<f64>) -> Vec<usize> {
+        (0..self.params.iou_thresholds.len())
+            .filter(|&t| {
+                iou_threshold.is_none_or(|threshold| {
+                    (self.params.iou_thresholds[t] - threshold).abs() < 1e-9
+                })
+            })
+            .collect()
+    }
+
+    /// Mean precision or recall over the categories `ks`, -1 if none of them had ground truth
+    fn mean(
+        &self,
+        average_precision: bool,
+        iou_threshold: Option<f64>,
+        ks: &[usize],
+        area: &str,
+        max_dets: usize,
+    ) -> f64 {
+        let p = &self.params;
+        let a = p.area_ranges.iter().position(|range| range.0 == area);
+        let m = p.max_dets.iter().position(|&m| m == max_dets);
+        let (Some(a), Some(m)) = (a, m) else {
+            return -1.0;
+        };
+        let ts = self.threshold_indices(iou_threshold);
+        if average_precision {
+            mean_valid(ts.iter().flat_map(|&t| {
+                (0..p.recall_thresholds.len())
+                    .flat_map(move |r| ks.iter().map(move |&k| self.precision(t, r, k, a, m)))
+            }))
+        } else {
+            mean_valid(
+                ts.iter()
+                    .flat_map(|&t| ks.iter().map(move |&k| self.recall(t, k, a, m))),
+            )
+        }
+    }
+
+    /// The summary of the COCO API, 12 values for boxes and masks and 10 for keypoints
+    pub fn stats(&self) -> Vec<EvalStat> {
+        let specs: Vec<(&'static str, bool, Option<f64>, &'static str, usize)> =
+            match self.params.iou_type {
+                IouType::Bbox | IouType::Segm => vec![
+                    ("AP", true, None, "all", 100),
+                    ("AP50", true, Some(0.5), "all", 100),
+                    ("AP75", true, Some(0.75), "all", 100),
+                    ("APs", true, None, "small", 100),
+                    ("APm", true, None, "medium", 100),
+                    ("APl", true, None, "large", 100),
+                    ("AR1", false, None, "all", 1),
+                    ("AR10", false, None, "all", 10),
+                    ("AR100", false, None, "all", 100),
+                    ("ARs", false, None, "small", 100),
+                    ("ARm", false, None, "medium", 100),
+                    ("ARl", false, None, "large", 100),
+                ],
+                IouType::Keypoints => vec![
+                    ("AP", true, None, "all", 20),
+                    ("AP50", true, Some(0.5), "all", 20),
+                    ("AP75", true, Some(0.75), "all", 20),
+                    ("APm", true, None, "medium", 20),
+                    ("APl", true, None, "large", 20),
+                    ("AR", false, None, "all", 20),
+                    ("AR50", false, Some(0.5), "all", 20),
+                    ("AR75", false, Some(0.75), "all", 20),
+                    ("ARm", false, None, "medium", 20),
+                    ("ARl", false, None, "large", 20),
+                ],
+            };
+        let all_categories: Vec<usize> = (0..self.category_ids.len()).collect();
+        specs
+            .into_iter()
+            .map(
+                |(name, average_precision, iou_threshold, area, max_dets)| EvalStat {
+                    name,
+                    average_precision,
+                    iou_threshold,
+                    area,
+                    max_dets,
+                    value: self.mean(
+                        average_precision,
+                        iou_threshold,
+                        &all_categories,
+                        area,
+                        max_dets,
+                    ),
+                },
+            )
+            .collect()
+    }
+
+    /// AP, AP50, AP75 and AR of every category
+    pub fn per_category(&self) -> Vec<CategoryEval> {
+        let (area, max_dets) = (
+            self.params.area_ranges[0].0,
+            *self.params.max_dets.last().unwrap(),
+        );
+        self.category_ids
+            .iter()
+            .enumerate()
+            .map(|(k, &category_id)| CategoryEval {
+                category_id,
+                ap: self.mean(true, None, &[k], area, max_dets),
+                ap50: self.mean(true, Some(0.5), &[k], area, max_dets),
+                ap75: self.mean(true, Some(0.75), &[k], area, max_dets),
+                ar: self.mean(false, None, &[k], area, max_dets),
+            })
+            .collect()
+    }
+}
+
+/// Evaluates `results` against `ground_truth` like the COCO API's `COCOeval`: predictions
+/// are matched greedily by score to the ground truth of their image and category, and
+/// precision is interpolated at every recall threshold. Every image of the ground truth and
+/// every category in its categories array (or its annotations, without one) is evaluated.
+/// Crowd annotations, and for keypoints annotations without labeled keypoints, can be
+/// matched but don't count. Polygons are rasterized by pixel centers, which can differ from
+/// the COCO API along the edges.
+///
+/// Fails if a prediction refers to an image that isn't in the ground truth, or if masks or
+/// keypoints don't fit their image or the sigmas.
+pub fn evaluate(
+    ground_truth: &CocoFile,
+    results: &CocoResults,
+    params: &EvalParams,
+) -> Result<Evaluation> {
+    let image_sizes: HashMap<i64, (u32, u32)> = ground_truth
+        .images
+        .iter()
+        .map(|image| (image.id, (image.width, image.height)))
+        .collect();
+    let mut image_ids: Vec<i64> = image_sizes.keys().copied().collect();
+    image_ids.sort();
+    let mut category_ids: Vec<i32> = match &ground_truth.categories {
+        Some(categories) => categories.iter().map(HasID::id).collect(),
+        None => ground_truth
+            .annotations
+            .iter()
+            .flat_map(CocoAnnotation::category_ids)
+            .collect(),
+    };
+    category_ids.sort();
+    category_ids.dedup();
+
+    let mut gts: HashMap<(i64, i32), Vec<EvalObject>> = HashMap::new();
+    for annotation in &ground_truth.annotations {
+        let (Some(&image_size), Some(category_id)) = (
+            image_sizes.get(&annotation.image_id()),
+            annotation.category_id(),
+        ) else {
+            continue;
+        };
+        if category_ids.binary_search(&category_id).is_err() {
+            continue;
+        }
+        if let Some(object) = ground_truth_object(annotation, params, image_size)? {
+            gts.entry((annotation.image_id(), category_id))
+                .or_default()
+                .push(object);
+        }
+    }
+
+    let mut dts: HashMap<(i64, i32), Vec<EvalObject>> = HashMap::new();
+    for (result, id) in results.0.iter().zip(1..) {
+        let (image_id, category_id) = result_key(result);
+        let Some(&image_size) = image_sizes.get(&image_id) else {
+            bail!(
+                "prediction {} refers to image {}, which isn't in the ground truth",
+                id,
+                image_id
+            );
+        };
+        if category_ids.binary_search(&category_id).is_err() {
+            continue;
+        }
+        if let Some(object) = prediction_object(result, id, params, image_size)? {
+            dts.entry((image_id, category_id)).or_default().push(object);
+        }
+    }
+
+    let max_det = *params.max_dets.last().unwrap();
+    // [category][area][image]
+    let mut image_evals: Vec<Vec<Vec<Option<ImageEval>>>> = Vec::new();
+    for &category_id in &category_ids {
+        let mut per_area: Vec<Vec<Option<ImageEval>>> = params
+            .area_ranges
+            .iter()
+            .map(|_| Vec::with_capacity(image_ids.len()))
+            .collect();
+        for &image_id in &image_ids {
+            let key = (image_id, category_id);
+            let image_gts = gts.get(&key).map_or(&[][..], Vec::as_slice);
+            let mut image_dts: Vec<&EvalObject> = dts.get(&key).into_iter().flatten().collect();
+            image_dts.sort_by(|a, b| b.score.total_cmp(&a.score));
+            image_dts.truncate(max_det);
+            let ious = compute_ious(&image_dts, image_gts, params)?;
+            for (a, &(_, min_area, max_area)) in params.area_ranges.iter().enumerate() {
+                per_area[a].push(evaluate_image(
+                    &image_dts,
+                    image_gts,
+                    &ious,
+                    (min_area, max_area),
+                    params,
+                ));
+            }
+        }
+        image_evals.push(per_area);
+    }
+
+    Ok(accumulate(params.clone(), category_ids, &image_evals))
+}
+
+fn result_key(result: &CocoResult) -> (i64, i32) {
+    match result {
+        CocoResult::Detection(result) => (result.image_id, result.category_id),
+        CocoResult::Segmentation(result) => (result.image_id, result.category_id),
+        CocoResult::Keypoint(result) => (result.image_id, result.category_id),
+    }
+}
+
+/// Precision at every recall threshold and the final recall from the matches of every image
+fn accumulate(
+    params: EvalParams,
+    category_ids: Vec<i32>,
+    image_evals: &[Vec<Vec<Option<ImageEval>>>],
+) -> Evaluation {
+    let (t_count, r_count) = (params.iou_thresholds.len(), params.recall_thresholds.len());
+    let (k_count, a_count, m_count) = (
+        category_ids.len(),
+        params.area_ranges.len(),
+        params.max_dets.len(),
+    );
+    let mut evaluation = Evaluation {
+        precision: vec![-1.0; t_count * r_count * k_count * a_count * m_count],
+        recall: vec![-1.0; t_count * k_count * a_count * m_count],
+        params,
+        category_ids,
+    };
+
+    for (k, per_area) in image_evals.iter().enumerate() {
+        for (a, area_evals) in per_area.iter().enumerate() {
+            let evals: Vec<&ImageEval> = area_evals.iter().flatten().collect();
+            if evals.is_empty() {
+                continue;
+            }
+            let counted_gts = evals
+                .iter()
+                .flat_map(|e| &e.gt_ignore)
+                .filter(|ignore| !**ignore)
+                .count();
+            if counted_gts == 0 {
+                continue;
+            }
+            for m in 0..m_count {
+                let max_det = evaluation.params.max_dets[m];
+                // (image eval, prediction) pairs sorted by score, ties in image order
+                let mut dts: Vec<(&ImageEval, usize)> = evals
+                    .iter()
+                    .flat_map(|e| (0..e.dt_scores.len().min(max_det)).map(move |d| (*e, d)))
+                    .collect();
+                dts.sort_by(|(a, i), (b, j)| b.dt_scores[*j].total_cmp(&a.dt_scores[*i]));
+
+                for t in 0..t_count {
+                    let (mut tp, mut fp) = (0.0, 0.0);
+                    let mut recalls = Vec::with_capacity(dts.len());
+                    let mut precisions = Vec::with_capacity(dts.len());
+                    for (e, d) in &dts {
+                        if !e.dt_ignore[t][*d] {
+                            if e.dt_matched[t][*d] {
+                                tp += 1.0;
+                            } else {
+                                fp += 1.0;
+                            }
+                        }
+                        recalls.push(tp / counted_gts as f64);
+                        precisions.push(tp / (fp + tp + f64::EPSILON));
+                    }
+                    let index = evaluation.recall_index(t, k, a, m);
+                    evaluation.recall[index] = recalls.last().copied().unwrap_or(0.0);
+
+                    // precision envelope, never increasing with recall
+                    for i in (1..precisions.len()).rev() {
+                        if precisions[i] > precisions[i - 1] {
+                            precisions[i - 1] = precisions[i];
+                        }
+                    }
+                    for (r, &recall_threshold) in
+                        evaluation.params.recall_thresholds.iter().enumerate()
+                    {
+                        let i = recalls.partition_point(|&recall| recall < recall_threshold);
+                        let index = evaluation.precision_index(t, r, k, a, m);
+                        evaluation.precision[index] = precisions.get(i).copied().unwrap_or(0.0);
+                    }
+                }
+            }
+        }
+    }
+    evaluation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{CocoDetectionResult, CocoKeypointResult, CocoSegmentationResult};
+
+    // expected values are worked out by hand following pycocotools' COCOeval
+
+    fn ground_truth(annotations: serde_json::Value) -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+            "annotations": annotations,
+            "categories": [{"id": 1, "name": "thing", "supercategory": "thing"}]
+        }))
+        .unwrap()
+    }
+
+    fn box_annotation(id: i64, bbox: [f32; 4], iscrowd: u8) -> serde_json::Value {
+        serde_json::json!({
+            "id": id, "image_id": 1, "category_id": 1, "segmentation": [],
+            "area": bbox[2] * bbox[3], "bbox": bbox, "iscrowd": iscrowd
+        })
+    }
+
+    fn detection(bbox: [f32; 4], score: f32) -> CocoResult {
+        CocoResult::Detection(CocoDetectionResult {
+            image_id: 1,
+            category_id: 1,
+            bbox,
+            score,
+        })
+    }
+
+    fn stat(evaluation: &Evaluation, name: &str) -> f64 {
+        evaluation
+            .stats()
+            .iter()
+            .find(|stat| stat.name == name)
+            .unwrap()
+            .value
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_box_iou_matches_bbox_iou() {
+        use crate::geometry::BBox;
+
+        let boxes = [
+            [0.0, 0.0, 10.0, 10.0],
+            [5.0, 0.0, 10.0, 10.0],
+            [2.5, 3.25, 4.0, 5.5],
+            [10.0, 0.0, 10.0, 10.0],
+            [30.0, 30.0, 5.0, 5.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ];
+        for a in boxes {
+            for b in boxes {
+                let bbox_iou = BBox::from(a).iou(&BBox::from(b));
+                let eval_iou = box_iou(&a.map(f64::from), &b.map(f64::from), false);
+                assert!(
+                    (f64::from(bbox_iou) - eval_iou).abs() < 1e-6,
+                    "{:?} {:?}: {} != {}",
+                    a,
+                    b,
+                    bbox_iou,
+                    eval_iou
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_bbox() {
+        let gt = ground_truth(serde_json::json!([
+            box_annotation(1, [0.0, 0.0, 10.0, 10.0], 0),
+            box_annotation(2, [50.0, 50.0, 10.0, 10.0], 0),
+            box_annotation(3, [70.0, 0.0, 20.0, 20.0], 1)
+        ]));
+        let results = CocoResults(vec![
+            detection([0.0, 0.0, 10.0, 10.0], 0.9),
+            detection([30.0, 30.0, 10.0, 10.0], 0.8),
+            detection([50.0, 50.0, 10.0, 10.0], 0.7),
+            // inside the crowd region, matched to it and ignored
+            detection([75.0, 5.0, 10.0, 10.0], 0.95),
+        ]);
+        let evaluation = evaluate(&gt, &results, &EvalParams::new(IouType::Bbox)).unwrap();
+
+        // recall 0.5 at precision 1, then recall 1 at precision 2/3
+        let ap = (51.0 + 50.0 * 2.0 / 3.0) / 101.0;
+        assert_close(stat(&evaluation, "AP"), ap);
+        assert_close(stat(&evaluation, "AP50"), ap);
+        assert_close(stat(&evaluation, "AP75"), ap);
+        assert_close(stat(&evaluation, "APs"), ap);
+        // no ground truth in these ranges
+        assert_eq!(stat(&evaluation, "APm"), -1.0);
+        assert_eq!(stat(&evaluation, "APl"), -1.0);
+        // the crowd detection has the highest score, then the first match
+        assert_close(stat(&evaluation, "AR1"), 0.0);
+        assert_close(stat(&evaluation, "AR10"), 1.0);
+        assert_close(stat(&evaluation, "AR100"), 1.0);
+
+        let per_category = evaluation.per_category();
+        assert_eq!(per_category.len(), 1);
+        assert_close(per_category[0].ap, ap);
+        assert_close(per_category[0].ar, 1.0);
+
+        assert_eq!(
+            evaluation.stats()[0].to_string(),
+            " Average Precision  (AP) @[ IoU=0.50:0.95 | area=   all | maxDets=100 ] = 0.835"
+        );
+        assert_eq!(
+            evaluation.stats()[4].to_string(),
+            " Average Precision  (AP) @[ IoU=0.50:0.95 | area=medium | maxDets=100 ] = -1.000"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_bbox_partial_overlap() {
+        let gt = ground_truth(serde_json::json!([box_annotation(
+            1,
+            [0.0, 0.0, 10.0, 10.0],
+            0
+        )]));
+        // IoU 0.72 matches at thresholds 0.5 to 0.7
+        let results = CocoResults(vec![detection([0.0, 0.0, 10.0, 7.2], 0.9)]);
+        let evaluation = evaluate(&gt, &results, &EvalParams::new(IouType::Bbox)).unwrap();
+        assert_close(stat(&evaluation, "AP"), 0.5);
+        assert_close(stat(&evaluation, "AP50"), 1.0);
+        assert_close(stat(&evaluation, "AP75"), 0.0);
+        assert_close(stat(&evaluation, "AR100"), 0.5);
+
+        let unknown_image = CocoResults(vec![CocoResult::Detection(CocoDetectionResult {
+            image_id: 2,
+            category_id: 1,
+            bbox: [0.0, 0.0, 1.0, 1.0],
+            score: 1.0,
+        })]);
+        assert!(evaluate(&gt, &unknown_image, &EvalParams::new(IouType::Bbox)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_bbox_reference_fixture() {
+        // two images and two categories covering every area range, a crowd region, false
+        // positives and a detection of the wrong category. The expected stats come from a
+        // line by line Python port of pycocotools' COCOeval (evaluate, accumulate and
+        // summarize), not from the Rust implementation.
+        let gt: CocoFile = serde_json::from_value(serde_json::json!({
+            "images": [
+                {"id": 1, "width": 200, "height": 200, "file_name": "a.jpg"},
+                {"id": 2, "width": 200, "height": 200, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 400, "bbox": [10, 10, 20, 20], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 3600, "bbox": [50, 50, 60, 60], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [], "area": 10800, "bbox": [0, 100, 120, 90], "iscrowd": 0},
+                {"id": 4, "image_id": 1, "category_id": 2, "segmentation": [], "area": 1600, "bbox": [130, 10, 40, 40], "iscrowd": 0},
+                {"id": 5, "image_id": 1, "category_id": 2, "segmentation": [], "area": 8000, "bbox": [100, 120, 100, 80], "iscrowd": 1},
+                {"id": 6, "image_id": 2, "category_id": 1, "segmentation": [], "area": 750, "bbox": [20, 20, 30, 25], "iscrowd": 0},
+                {"id": 7, "image_id": 2, "category_id": 2, "segmentation": [], "area": 10000, "bbox": [60, 60, 100, 100], "iscrowd": 0},
+                {"id": 8, "image_id": 2, "category_id": 2, "segmentation": [], "area": 225, "bbox": [5, 5, 15, 15], "iscrowd": 0}
+            ],
+            "categories": [
+                {"id": 1, "name": "cat", "supercategory": "animal"},
+                {"id": 2, "name": "dog", "supercategory": "animal"}
+            ]
+        }))
+        .unwrap();
+        let results: CocoResults = serde_json::from_value(serde_json::json!([
+            {"image_id": 1, "category_id": 1, "bbox": [11, 11, 20, 20], "score": 0.9},
+            {"image_id": 1, "category_id": 1, "bbox": [55, 52, 60, 60], "score": 0.8},
+            {"image_id": 1, "category_id": 1, "bbox": [0, 105, 110, 85], "score": 0.6},
+            {"image_id": 1, "category_id": 1, "bbox": [150, 150, 20, 20], "score": 0.7},
+            {"image_id": 1, "category_id": 2, "bbox": [128, 12, 40, 42], "score": 0.85},
+            {"image_id": 1, "category_id": 2, "bbox": [110, 130, 30, 30], "score": 0.75},
+            {"image_id": 1, "category_id": 2, "bbox": [10, 10, 20, 20], "score": 0.3},
+            {"image_id": 2, "category_id": 1, "bbox": [24, 22, 30, 28], "score": 0.5},
+            {"image_id": 2, "category_id": 1, "bbox": [100, 100, 30, 30], "score": 0.95},
+            {"image_id": 2, "category_id": 2, "bbox": [65, 55, 95, 100], "score": 0.88},
+            {"image_id": 2, "category_id": 2, "bbox": [5, 5, 30, 30], "score": 0.4}
+        ]))
+        .unwrap();
+        let evaluation = evaluate(&gt, &results, &EvalParams::new(IouType::Bbox)).unwrap();
+
+        let expected = [
+            ("AP", 0.419406),
+            ("AP50", 0.665017),
+            ("AP75", 0.574257),
+            ("APs", 0.125495),
+            ("APm", 0.600000),
+            ("APl", 0.800000),
+            ("AR1", 0.320833),
+            ("AR10", 0.533333),
+            ("AR100", 0.533333),
+            ("ARs", 0.250000),
+            ("ARm", 0.600000),
+            ("ARl", 0.800000),
+        ];
+        for (name, value) in expected {
+            let actual = stat(&evaluation, name);
+            assert!(
+                (actual - value).abs() < 1e-3,
+                "{}: {} != {}",
+                name,
+                actual,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_segm() {
+        let gt = ground_truth(serde_json::json!([{
+            "id": 1, "image_id": 1, "category_id": 1,
+            "segmentation": [[0.0, 0.0, 4.0, 0.0, 4.0, 4.0, 0.0, 4.0]],
+            "area": 16.0, "bbox": [0.0, 0.0, 4.0, 4.0], "iscrowd": 0
+        }]));
+        // 13 of the 16 pixels, IoU 0.8125 matches at thresholds 0.5 to 0.8
+        let mut mask = vec![false; 100 * 100];
+        for (x, y) in (0..4)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .chain([(0, 3)])
+        {
+            mask[y * 100 + x] = true;
+        }
+        let results = CocoResults(vec![CocoResult::Segmentation(CocoSegmentationResult {
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::RLE(CocoRLE::from_mask(&mask, 100, 100)),
+            bbox: None,
+            score: 0.9,
+        })]);
+        let evaluation = evaluate(&gt, &results, &EvalParams::new(IouType::Segm)).unwrap();
+        assert_close(stat(&evaluation, "AP"), 0.7);
+        assert_close(stat(&evaluation, "AP75"), 1.0);
+
+        // the mask's box is [0, 0, 4, 4], so it matches everywhere as a box
+        let evaluation = evaluate(&gt, &results, &EvalParams::new(IouType::Bbox)).unwrap();
+        assert_close(stat(&evaluation, "AP"), 1.0);
+
+        let wrong_size = CocoResults(vec![CocoResult::Segmentation(CocoSegmentationResult {
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::RLE(CocoRLE::from_mask(&[true], 1, 1)),
+            bbox: None,
+            score: 0.9,
+        })]);
+        assert!(evaluate(&gt, &wrong_size, &EvalParams::new(IouType::Segm)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_keypoints() {
+        let gt = ground_truth(serde_json::json!([
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 100.0,
+                "bbox": [0.0, 0.0, 20.0, 20.0], "iscrowd": 0, "keypoints": [10.0, 10.0, 2.0],
+                "num_keypoints": 1},
+            // no labeled keypoints, ignored
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 100.0,
+                "bbox": [50.0, 50.0, 20.0, 20.0], "iscrowd": 0, "keypoints": [0.0, 0.0, 0.0],
+                "num_keypoints": 0}
+        ]));
+        let mut params = EvalParams::new(IouType::Keypoints);
+        params.keypoint_sigmas = vec![0.1];
+        // OKS exp(-1.25 / 0.04 / 100 / 2) = 0.855 matches at thresholds 0.5 to 0.85
+        let results = CocoResults(vec![CocoResult::Keypoint(CocoKeypointResult {
+            image_id: 1,
+            category_id: 1,
+            keypoints: vec![11.0, 10.5, 1.0],
+            bbox: None,
+            score: 0.9,
+        })]);
+        let evaluation = evaluate(&gt, &results, &params).unwrap();
+        assert_eq!(evaluation.stats().len(), 10);
+        assert_close(stat(&evaluation, "AP"), 0.8);
+        assert_close(stat(&evaluation, "AP50"), 1.0);
+        assert_close(stat(&evaluation, "AR"), 0.8);
+        assert_eq!(stat(&evaluation, "APm"), -1.0);
+
+        // the default sigmas expect 17 keypoints
+        let params = EvalParams::new(IouType::Keypoints);
+        assert!(evaluate(&gt, &results, &params).is_err());
+    }
+
+    #[test]
+    fn test_linspace_thresholds() {
+        let params = EvalParams::new(IouType::Bbox);
+        assert_eq!(params.iou_thresholds.len(), 10);
+        assert_eq!(params.iou_thresholds[0], 0.5);
+        assert_eq!(params.iou_thresholds[5], 0.75);
+        assert_eq!(params.iou_thresholds[9], 0.95);
+        assert_eq!(params.recall_thresholds.len(), 101);
+        assert_eq!(params.recall_thresholds[100], 1.0);
+    }
+}
@@ -0,0 +1,556 @@
+use anyhow::{Context, Result, bail};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{CocoAnnotation, CocoCategory, CocoFile, CocoSegmentation, HasID, polygon_area};
+
+/// One line of a YOLO label file, coordinates normalized to [0, 1]
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloBox {
+    pub class: usize,
+    pub cx: f32,
+    pub cy: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl YoloBox {
+    /// Converts a COCO `[x, y, w, h]` pixel bbox, clipping it to the image first
+    pub fn from_coco_bbox(class: usize, bbox: &[f32; 4], width: u32, height: u32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        let x0 = bbox[0].clamp(0.0, width);
+        let y0 = bbox[1].clamp(0.0, height);
+        let x1 = (bbox[0] + bbox[2]).clamp(0.0, width);
+        let y1 = (bbox[1] + bbox[3]).clamp(0.0, height);
+
+        YoloBox {
+            class,
+            cx: (x0 + x1) / 2.0 / width,
+            cy: (y0 + y1) / 2.0 / height,
+            w: (x1 - x0) / width,
+            h: (y1 - y0) / height,
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {:.6} {:.6} {:.6} {:.6}",
+            self.class, self.cx, self.cy, self.w, self.h
+        )
+    }
+}
+
+/// One line of a YOLO segmentation label file, points normalized to [0, 1]
+#[derive(Debug, Clone, PartialEq)]
+pub struct YoloPolygon {
+    pub class: usize,
+    /// `x y` pairs
+    pub points: Vec<f32>,
+}
+
+impl YoloPolygon {
+    /// Converts a COCO polygon of `x y` pixel pairs, clipping it to the image
+    pub fn from_coco_polygon(class: usize, polygon: &[f32], width: u32, height: u32) -> Self {
+        let (width, height) = (width as f32, height as f32);
+        YoloPolygon {
+            class,
+            points: polygon
+                .chunks_exact(2)
+                .flat_map(|point| {
+                    [
+                        point[0].clamp(0.0, width) / width,
+                        point[1].clamp(0.0, height) / height,
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        let mut line = self.class.to_string();
+        for coordinate in &self.points {
+            line.push_str(&format!(" {:.6}", coordinate));
+        }
+        line
+    }
+}
+
+pub struct YoloLabelFile {
+    pub image_id: i64,
+    /// label file name, see [`yolo_label_name`]
+    pub file_name: PathBuf,
+    pub boxes: Vec<YoloBox>,
+    /// outlines instead of boxes when exporting segmentation labels
+    pub polygons: Vec<YoloPolygon>,
+}
+
+pub struct YoloLabels {
+    /// category names, indexed by YOLO class
+    pub classes: Vec<String>,
+    pub files: Vec<YoloLabelFile>,
+    /// annotations that aren't object detection annotations, have an unknown category or belong
+    /// to a skipped or missing image
+    pub skipped_annotations: usize,
+    /// ids of images skipped because their width or height is zero
+    pub skipped_images: Vec<i64>,
+}
+
+impl YoloLabels {
+    /// Writes one label file per image plus `classes.txt` into `dir`
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))?;
+
+        let mut classes = self.classes.join("\n");
+        classes.push('\n');
+        fs::write(dir.join("classes.txt"), classes).context("Could not write classes.txt")?;
+
+        self.files.iter().try_for_each(|file| {
+            let contents: String = file
+                .boxes
+                .iter()
+                .map(YoloBox::to_line)
+                .chain(file.polygons.iter().map(YoloPolygon::to_line))
+                .map(|line| line + "\n")
+                .collect();
+            let path = dir.join(&file.file_name);
+            fs::write(&path, contents).with_context(|| format!("Could not write {:?}", path))
+        })
+    }
+
+    /// Writes an Ultralytics `data.yaml` into `dir`. With `images_dir`, relative to `dir`, every
+    /// image is used for both training and validation. Without it `train` and `val` are left
+    /// out for the user to fill in, since the images aren't part of the dataset directory.
+    pub fn write_data_yaml(&self, dir: &Path, images_dir: Option<&Path>) -> Result<()> {
+        let root = fs::canonicalize(dir).with_context(|| format!("Could not resolve {:?}", dir))?;
+        // JSON strings are valid YAML and take care of quoting
+        let mut yaml = format!("path: {}\n", serde_json::to_string(&root)?);
+        if let Some(images_dir) = images_dir {
+            let images_dir = serde_json::to_string(images_dir)?;
+            yaml.push_str(&format!("train: {}\nval: {}\n", images_dir, images_dir));
+        }
+        yaml.push_str(&format!("nc: {}\nnames:\n", self.classes.len()));
+        for (class, name) in self.classes.iter().enumerate() {
+            yaml.push_str(&format!("  {}: {}\n", class, serde_json::to_string(name)?));
+        }
+        let path = dir.join("data.yaml");
+        fs::write(&path, yaml).with_context(|| format!("Could not write {:?}", path))
+    }
+}
+
+/// Name of the YOLO label file of an image, its whole file stem followed by `.txt`, so
+/// `a/img.v2.jpg` is labeled by `img.v2.txt`. `None` if the path has no file name.
+pub fn yolo_label_name(image_file_name: &Path) -> Option<PathBuf> {
+    let mut name = image_file_name.file_stem()?.to_os_string();
+    name.push(".txt");
+    Some(PathBuf::from(name))
+}
+
+/// What [`to_yolo`] exports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct YoloExportOptions {
+    /// export crowd annotations like any other
+    pub include_crowd: bool,
+    /// export segmentation polygons for YOLO-seg instead of boxes
+    pub segmentation: bool,
+}
+
+/// The largest outline of a segmentation, YOLO-seg has one polygon per object
+fn largest_polygon(segmentation: &CocoSegmentation) -> Option<Vec<f32>> {
+    let polygons = match segmentation {
+        CocoSegmentation::Polygon(polygons) => polygons.clone(),
+        CocoSegmentation::RLE(rle) => rle.to_polygons(),
+    };
+    polygons
+        .into_iter()
+        .filter(|polygon| polygon.len() >= 6)
+        .max_by(|a, b| polygon_area(a).total_cmp(&polygon_area(b)))
+}
+
+/// Converts the object detection annotations of `coco_file` to YOLO boxes, crowd annotations
+/// included. Classes are the file's categories ordered by id.
+pub fn to_yolo_labels(coco_file: &CocoFile) -> Result<YoloLabels> {
+    to_yolo(
+        coco_file,
+        &YoloExportOptions {
+            include_crowd: true,
+            segmentation: false,
+        },
+    )
+}
+
+/// Converts the object detection annotations of `coco_file` to YOLO labels. Classes are the
+/// file's categories ordered by id, so class indices are contiguous whatever the category ids.
+/// With [`YoloExportOptions::segmentation`] each annotation becomes its largest polygon, RLE
+/// masks are traced, and annotations without a polygon are skipped.
+///
+/// Label files are named after the image file stems, so this fails if two exported images share
+/// a stem, such as `a/img.jpg` and `b/img.png`, as one label file would overwrite the other.
+pub fn to_yolo(coco_file: &CocoFile, options: &YoloExportOptions) -> Result<YoloLabels> {
+    let mut categories: Vec<&CocoCategory> = coco_file.categories.iter().flatten().collect();
+    categories.sort_by_key(|category| category.id());
+    let class_indices: HashMap<i32, usize> = categories
+        .iter()
+        .enumerate()
+        .map(|(index, category)| (category.id(), index))
+        .collect();
+
+    let mut skipped_annotations = 0;
+    let mut boxes_by_image: HashMap<i64, Vec<(usize, [f32; 4])>> = HashMap::new();
+    let mut polygons_by_image: HashMap<i64, Vec<(usize, Vec<f32>)>> = HashMap::new();
+    for annotation in &coco_file.annotations {
+        let CocoAnnotation::ObjectDetection(ann) = annotation else {
+            skipped_annotations += 1;
+            continue;
+        };
+        let Some(&class) = class_indices.get(&ann.category_id) else {
+            skipped_annotations += 1;
+            continue;
+        };
+        if ann.iscrowd && !options.include_crowd {
+            skipped_annotations += 1;
+            continue;
+        }
+        if !options.segmentation {
+            boxes_by_image
+                .entry(ann.image_id)
+                .or_default()
+                .push((class, ann.bbox));
+            continue;
+        }
+        match largest_polygon(&ann.segmentation) {
+            Some(polygon) => polygons_by_image
+                .entry(ann.image_id)
+                .or_default()
+                .push((class, polygon)),
+            None => skipped_annotations += 1,
+        }
+    }
+
+    let mut skipped_images = Vec::new();
+    let mut files = Vec::new();
+    let mut label_owners: HashMap<PathBuf, i64> = HashMap::new();
+    for image in &coco_file.images {
+        let boxes = boxes_by_image.remove(&image.id).unwrap_or_default();
+        let polygons = polygons_by_image.remove(&image.id).unwrap_or_default();
+        if image.width == 0 || image.height == 0 {
+            skipped_images.push(image.id);
+            skipped_annotations += boxes.len() + polygons.len();
+            continue;
+        }
+
+        let Some(file_name) = yolo_label_name(&image.file_name) else {
+            bail!("Image {} has no file name", image.id);
+        };
+        if let Some(other) = label_owners.insert(file_name.clone(), image.id) {
+            bail!(
+                "Images {} and {} would both be labeled by {:?}, YOLO pairs images and labels by \
+                 file stem",
+                other,
+                image.id,
+                file_name
+            );
+        }
+        files.push(YoloLabelFile {
+            image_id: image.id,
+            file_name,
+            boxes: boxes
+                .iter()
+                .map(|(class, bbox)| {
+                    YoloBox::from_coco_bbox(*class, bbox, image.width, image.height)
+                })
+                .collect(),
+            polygons: polygons
+                .iter()
+                .map(|(class, polygon)| {
+                    YoloPolygon::from_coco_polygon(*class, polygon, image.width, image.height)
+                })
+                .collect(),
+        });
+    }
+
+    // annotations whose image isn't in the file
+    skipped_annotations += boxes_by_image.values().map(Vec::len).sum::<usize>();
+    skipped_annotations += polygons_by_image.values().map(Vec::len).sum::<usize>();
+
+    Ok(YoloLabels {
+        classes: categories
+            .iter()
+            .map(|category| category.name().to_string())
+            .collect(),
+        files,
+        skipped_annotations,
+        skipped_images,
+    })
+}
+
+/// Columns of the annotation CSV written by [`to_csv`] and read by [`crate::import::from_csv`]
+pub const CSV_HEADER: &str =
+    "annotation_id,image_id,file_name,category,x,y,w,h,area,iscrowd,caption";
+
+/// Quotes `field` if it contains a separator, quote or line break
+pub fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Flattens the annotations of `coco_file` into a CSV table under [`CSV_HEADER`], in file order.
+/// Object detection, keypoint and DensePose annotations fill the box columns and leave the
+/// caption empty, captions fill only the caption column. Keypoints, segmentations and panoptic
+/// annotations have no columns and are left out.
+pub fn to_csv(coco_file: &CocoFile) -> String {
+    let file_names: HashMap<i64, String> = coco_file
+        .images
+        .iter()
+        .map(|image| (image.id, image.file_name.to_string_lossy().to_string()))
+        .collect();
+    let category_names: HashMap<i32, &str> = coco_file
+        .categories
+        .iter()
+        .flatten()
+        .map(|category| (category.id(), category.name()))
+        .collect();
+
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for annotation in &coco_file.annotations {
+        let (id, category_id, bbox, area, iscrowd) = match annotation {
+            CocoAnnotation::ObjectDetection(ann) => {
+                (ann.id, ann.category_id, ann.bbox, ann.area, ann.iscrowd)
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                (ann.id, ann.category_id, ann.bbox, ann.area, ann.iscrowd)
+            }
+            CocoAnnotation::DensePose(ann) => (
+                ann.id,
+                ann.category_id,
+                ann.bbox,
+                ann.area as f32,
+                ann.iscrowd,
+            ),
+            CocoAnnotation::ImageCaptioning(ann) => {
+                csv.push_str(&format!(
+                    "{},{},{},,,,,,,,{}\n",
+                    ann.id,
+                    ann.image_id,
+                    csv_field(file_names.get(&ann.image_id).map_or("", String::as_str)),
+                    csv_field(&ann.caption)
+                ));
+                continue;
+            }
+            CocoAnnotation::PanopticSegmentation(_) => continue,
+        };
+        let image_id = annotation.image_id();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},\n",
+            id,
+            image_id,
+            csv_field(file_names.get(&image_id).map_or("", String::as_str)),
+            csv_field(
+                category_names
+                    .get(&category_id)
+                    .copied()
+                    .unwrap_or_default()
+            ),
+            bbox[0],
+            bbox[1],
+            bbox[2],
+            bbox[3],
+            area,
+            iscrowd as u8
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yolo_box_normalization() {
+        let yolo_box = YoloBox::from_coco_bbox(2, &[10.0, 20.0, 30.0, 40.0], 100, 200);
+        assert_eq!(
+            yolo_box,
+            YoloBox {
+                class: 2,
+                cx: 0.25,
+                cy: 0.2,
+                w: 0.3,
+                h: 0.2,
+            }
+        );
+        assert_eq!(yolo_box.to_line(), "2 0.250000 0.200000 0.300000 0.200000");
+    }
+
+    #[test]
+    fn test_yolo_box_clips_to_image() {
+        let yolo_box = YoloBox::from_coco_bbox(0, &[-10.0, 50.0, 20.0, 100.0], 100, 100);
+        assert_eq!(yolo_box.cx, 0.05);
+        assert_eq!(yolo_box.w, 0.1);
+        assert_eq!(yolo_box.cy, 0.75);
+        assert_eq!(yolo_box.h, 0.5);
+    }
+
+    #[test]
+    fn test_to_yolo_labels_class_order_and_skips() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a/img1.jpg"},
+                {"id": 2, "width": 0, "height": 0, "file_name": "img2.jpg"}
+            ],
+            "annotations": [
+                {
+                    "id": 1, "image_id": 1, "category_id": 7, "segmentation": [],
+                    "area": 1.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0
+                },
+                {
+                    "id": 2, "image_id": 2, "category_id": 3, "segmentation": [],
+                    "area": 1.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0
+                },
+                {"id": 3, "image_id": 1, "caption": "a caption"}
+            ],
+            "categories": [
+                {"id": 7, "name": "car", "supercategory": "vehicle"},
+                {"id": 3, "name": "person", "supercategory": "human"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let labels = to_yolo_labels(&coco_file).unwrap();
+
+        assert_eq!(labels.classes, vec!["person", "car"]);
+        assert_eq!(labels.files.len(), 1);
+        assert_eq!(labels.files[0].file_name, PathBuf::from("img1.txt"));
+        assert_eq!(labels.files[0].boxes[0].class, 1);
+        assert_eq!(labels.skipped_images, vec![2]);
+        assert_eq!(labels.skipped_annotations, 2);
+    }
+
+    #[test]
+    fn test_to_yolo_label_names() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a/img.v2.jpg"},
+                {"id": 2, "width": 100, "height": 100, "file_name": "a/img.jpg"}
+            ],
+            "annotations": []
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let labels = to_yolo_labels(&coco_file).unwrap();
+        let names: Vec<&Path> = labels
+            .files
+            .iter()
+            .map(|file| file.file_name.as_path())
+            .collect();
+        assert_eq!(names, vec![Path::new("img.v2.txt"), Path::new("img.txt")]);
+
+        // the same stem in another directory would overwrite the first label file
+        coco_file.images[0].file_name = PathBuf::from("b/img.png");
+        let error = to_yolo_labels(&coco_file).err().unwrap();
+        assert!(error.to_string().contains("Images 1 and 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_yolo_polygon_normalization() {
+        let polygon =
+            YoloPolygon::from_coco_polygon(1, &[10.0, 20.0, 110.0, 20.0, 50.0, 200.0], 100, 200);
+        assert_eq!(polygon.points, vec![0.1, 0.1, 1.0, 0.1, 0.5, 1.0]);
+        assert_eq!(
+            polygon.to_line(),
+            "1 0.100000 0.100000 1.000000 0.100000 0.500000 1.000000"
+        );
+    }
+
+    #[test]
+    fn test_to_yolo_crowd_and_segmentation() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 50, "file_name": "img.png"}],
+            "annotations": [
+                {
+                    "id": 1, "image_id": 1, "category_id": 12,
+                    "segmentation": [[0, 0, 2, 0, 2, 2], [10, 10, 50, 10, 50, 50, 10, 50]],
+                    "area": 1.0, "bbox": [10.0, 10.0, 40.0, 40.0], "iscrowd": 0
+                },
+                {
+                    "id": 2, "image_id": 1, "category_id": 4,
+                    "segmentation": {"size": [50, 100], "counts": [0, 2, 48, 2, 4948]},
+                    "area": 4.0, "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 1
+                },
+                {
+                    "id": 3, "image_id": 1, "category_id": 4, "segmentation": [],
+                    "area": 4.0, "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0
+                }
+            ],
+            "categories": [
+                {"id": 12, "name": "dog", "supercategory": "animal"},
+                {"id": 4, "name": "cat", "supercategory": "animal"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let labels = to_yolo(&coco_file, &YoloExportOptions::default()).unwrap();
+        assert_eq!(labels.classes, vec!["cat", "dog"]);
+        let classes: Vec<usize> = labels.files[0].boxes.iter().map(|b| b.class).collect();
+        assert_eq!(classes, vec![1, 0]);
+        assert_eq!(labels.skipped_annotations, 1);
+
+        let options = YoloExportOptions {
+            include_crowd: true,
+            segmentation: true,
+        };
+        let labels = to_yolo(&coco_file, &options).unwrap();
+        let file = &labels.files[0];
+        assert!(file.boxes.is_empty());
+        // the larger of the two polygons, then the traced 2x2 mask
+        assert_eq!(file.polygons.len(), 2);
+        assert_eq!(file.polygons[0].class, 1);
+        assert_eq!(
+            file.polygons[0].points,
+            vec![0.1, 0.2, 0.5, 0.2, 0.5, 1.0, 0.1, 1.0]
+        );
+        assert_eq!(file.polygons[1].class, 0);
+        let mut xs: Vec<f32> = file.polygons[1].points.iter().step_by(2).copied().collect();
+        xs.sort_by(f32::total_cmp);
+        assert_eq!((xs[0], xs[xs.len() - 1]), (0.0, 0.02));
+        // the annotation without a polygon
+        assert_eq!(labels.skipped_annotations, 1);
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 10, "height": 10, "file_name": "a,b.png"}],
+            "annotations": [
+                {
+                    "id": 1, "image_id": 1, "category_id": 4, "segmentation": [],
+                    "area": 4.5, "bbox": [0.5, 1.0, 1.5, 3.0], "iscrowd": 1
+                },
+                {"id": 2, "image_id": 1, "caption": "a cat, sitting\non a \"mat\""},
+                {"image_id": 1, "file_name": "a.png", "segments_info": []}
+            ],
+            "categories": [{"id": 4, "name": "cat", "supercategory": "animal"}]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            to_csv(&coco_file),
+            format!(
+                "{}\n1,1,\"a,b.png\",cat,0.5,1,1.5,3,4.5,1,\n\
+                 2,1,\"a,b.png\",,,,,,,,\"a cat, sitting\non a \"\"mat\"\"\"\n",
+                CSV_HEADER
+            )
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use globset::Glob;
+use std::collections::HashSet;
+
+use crate::{CocoAnnotation, CocoFile, CocoImage, DuplicateImageIdError, HasID};
+
+/// What [`CocoFile::filter_images`] and [`CocoFile::subset_by_image_ids`] keep besides the
+/// selected images and their annotations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageFilterOptions {
+    /// only keep categories referenced by a kept annotation
+    pub prune_categories: bool,
+    /// only keep licenses referenced by a kept image
+    pub prune_licenses: bool,
+}
+
+impl CocoFile {
+    /// A copy with only the images `pred` accepts, called with each image and its annotations,
+    /// and their annotations. Images and annotations keep their order, annotations of missing
+    /// images are dropped. Fails if several images share an id.
+    pub fn filter_images<F>(
+        &self,
+        pred: F,
+        options: &ImageFilterOptions,
+    ) -> Result<CocoFile, DuplicateImageIdError>
+    where
+        F: Fn(&CocoImage, &[&CocoAnnotation]) -> bool,
+    {
+        let image_id_map = self.make_image_id_map_quiet()?;
+        let kept_image_ids: HashSet<i64> = image_id_map
+            .values()
+            .filter(|entry| pred(entry.image, &entry.annotations))
+            .map(|entry| entry.id)
+            .collect();
+        Ok(self.subset_by_image_ids(&kept_image_ids, options))
+    }
+
+    /// A copy with only the images whose id is in `ids` and their annotations, keeping their
+    /// order. Annotations of ids without an image are dropped.
+    pub fn subset_by_image_ids(
+        &self,
+        ids: &HashSet<i64>,
+        options: &ImageFilterOptions,
+    ) -> CocoFile {
+        let images: Vec<CocoImage> = self
+            .images
+            .iter()
+            .filter(|image| ids.contains(&image.id))
+            .cloned()
+            .collect();
+        let kept_image_ids: HashSet<i64> = images.iter().map(|image| image.id).collect();
+        let annotations: Vec<CocoAnnotation> = self
+            .annotations
+            .iter()
+            .filter(|annotation| kept_image_ids.contains(&annotation.image_id()))
+            .cloned()
+            .collect();
+
+        let categories = self.categories.as_ref().map(|categories| {
+            let referenced: HashSet<i32> = annotations
+                .iter()
+                .flat_map(CocoAnnotation::category_ids)
+                .collect();
+            categories
+                .iter()
+                .filter(|category| !options.prune_categories || referenced.contains(&category.id()))
+                .cloned()
+                .collect()
+        });
+        let licenses = self.licenses.as_ref().map(|licenses| {
+            let referenced: HashSet<i32> =
+                images.iter().filter_map(|image| image.license).collect();
+            licenses
+                .iter()
+                .filter(|license| !options.prune_licenses || referenced.contains(&license.id))
+                .cloned()
+                .collect()
+        });
+
+        CocoFile {
+            images,
+            annotations,
+            info: self.info.clone(),
+            categories,
+            licenses,
+        }
+    }
+}
+
+/// Accepts images whose file name matches the glob `pattern`, such as `train/**/*.jpg`
+pub fn file_name_glob(pattern: &str) -> Result<impl Fn(&CocoImage, &[&CocoAnnotation]) -> bool> {
+    let matcher = Glob::new(pattern)
+        .with_context(|| format!("Invalid file name pattern {:?}", pattern))?
+        .compile_matcher();
+    Ok(move |image: &CocoImage, _: &[&CocoAnnotation]| matcher.is_match(&image.file_name))
+}
+
+/// Accepts images at least `width` wide and `height` high
+pub fn min_resolution(width: u32, height: u32) -> impl Fn(&CocoImage, &[&CocoAnnotation]) -> bool {
+    move |image, _| image.width >= width && image.height >= height
+}
+
+/// Accepts images at most `width` wide and `height` high
+pub fn max_resolution(width: u32, height: u32) -> impl Fn(&CocoImage, &[&CocoAnnotation]) -> bool {
+    move |image, _| image.width <= width && image.height <= height
+}
+
+/// Accepts images captured at or after `start` and before `end`, either bound may be left
+/// open. Images without a capture date never match.
+pub fn captured_between(
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> impl Fn(&CocoImage, &[&CocoAnnotation]) -> bool {
+    move |image, _| {
+        image.date_captured.is_some_and(|date| {
+            start.is_none_or(|start| date >= start) && end.is_none_or(|end| date < end)
+        })
+    }
+}
+
+/// Accepts images under license `license_id`
+pub fn license_id(license_id: i32) -> impl Fn(&CocoImage, &[&CocoAnnotation]) -> bool {
+    move |image, _| image.license == Some(license_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_test_file() -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [
+                {"id": 1, "width": 640, "height": 480, "file_name": "train/a.jpg", "license": 1,
+                    "date_captured": "2020-01-01T00:00:00Z"},
+                {"id": 2, "width": 1920, "height": 1080, "file_name": "train/b.png",
+                    "license": 2, "date_captured": "2021-06-01T00:00:00Z"},
+                {"id": 3, "width": 320, "height": 240, "file_name": "val/c.jpg", "license": 1}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 2, "image_id": 2, "category_id": 2, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 3, "image_id": 3, "category_id": 3, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 4, "image_id": 1, "caption": "a cat"},
+                {"id": 5, "image_id": 9, "category_id": 1, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0}
+            ],
+            "categories": [
+                {"id": 1, "name": "cat", "supercategory": "animal"},
+                {"id": 2, "name": "dog", "supercategory": "animal"},
+                {"id": 3, "name": "cow", "supercategory": "animal"}
+            ],
+            "licenses": [
+                {"id": 1, "name": "CC BY", "url": "http://example.com/1"},
+                {"id": 2, "name": "CC0", "url": "http://example.com/2"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn image_ids(coco_file: &CocoFile) -> Vec<i64> {
+        coco_file.images.iter().map(|image| image.id).collect()
+    }
+
+    fn annotation_ids(coco_file: &CocoFile) -> Vec<i64> {
+        coco_file
+            .annotations
+            .iter()
+            .filter_map(|ann| ann.id())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_images() {
+        let coco_file = filter_test_file();
+        let options = ImageFilterOptions::default();
+
+        // images are passed their own annotations only
+        let filtered = coco_file
+            .filter_images(|_, annotations| annotations.len() == 2, &options)
+            .unwrap();
+        assert_eq!(image_ids(&filtered), vec![1]);
+        assert_eq!(annotation_ids(&filtered), vec![1, 4]);
+
+        // the dangling annotation 5 goes even if every image is kept
+        let filtered = coco_file.filter_images(|_, _| true, &options).unwrap();
+        assert_eq!(image_ids(&filtered), vec![1, 2, 3]);
+        assert_eq!(annotation_ids(&filtered), vec![1, 2, 3, 4]);
+        assert_eq!(filtered.categories.unwrap().len(), 3);
+
+        let mut duplicated = filter_test_file();
+        duplicated.images[1].id = 1;
+        assert!(duplicated.filter_images(|_, _| true, &options).is_err());
+    }
+
+    #[test]
+    fn test_filter_images_prunes_categories_and_licenses() {
+        let coco_file = filter_test_file();
+        let keep_dogs = |_: &CocoImage, annotations: &[&CocoAnnotation]| {
+            annotations.iter().any(|ann| ann.category_id() == Some(2))
+        };
+
+        let filtered = coco_file
+            .filter_images(keep_dogs, &ImageFilterOptions::default())
+            .unwrap();
+        assert_eq!(filtered.categories.unwrap().len(), 3);
+        assert_eq!(filtered.licenses.unwrap().len(), 2);
+
+        let options = ImageFilterOptions {
+            prune_categories: true,
+            prune_licenses: true,
+        };
+        let filtered = coco_file.filter_images(keep_dogs, &options).unwrap();
+        let category_ids: Vec<i32> = filtered
+            .categories
+            .iter()
+            .flatten()
+            .map(HasID::id)
+            .collect();
+        assert_eq!(category_ids, vec![2]);
+        let license_ids: Vec<i32> = filtered.licenses.iter().flatten().map(|l| l.id).collect();
+        assert_eq!(license_ids, vec![2]);
+    }
+
+    #[test]
+    fn test_subset_by_image_ids() {
+        let coco_file = filter_test_file();
+        let options = ImageFilterOptions {
+            prune_categories: true,
+            prune_licenses: true,
+        };
+        let subset = coco_file.subset_by_image_ids(&HashSet::from([2]), &options);
+        assert_eq!(image_ids(&subset), vec![2]);
+        assert_eq!(annotation_ids(&subset), vec![2]);
+        let category_ids: Vec<i32> = subset.categories.iter().flatten().map(HasID::id).collect();
+        assert_eq!(category_ids, vec![2]);
+        assert_eq!(subset.licenses.unwrap().len(), 1);
+
+        // annotation 5 refers to the missing image 9
+        let subset = coco_file.subset_by_image_ids(&HashSet::from([1, 9]), &options);
+        assert_eq!(image_ids(&subset), vec![1]);
+        assert_eq!(annotation_ids(&subset), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_image_predicates() {
+        let coco_file = filter_test_file();
+        let options = ImageFilterOptions::default();
+        let filter = |pred: &dyn Fn(&CocoImage, &[&CocoAnnotation]) -> bool| {
+            image_ids(&coco_file.filter_images(pred, &options).unwrap())
+        };
+
+        assert_eq!(filter(&file_name_glob("train/*").unwrap()), vec![1, 2]);
+        assert_eq!(filter(&file_name_glob("**/*.jpg").unwrap()), vec![1, 3]);
+        assert!(file_name_glob("train/[").is_err());
+
+        assert_eq!(filter(&min_resolution(640, 480)), vec![1, 2]);
+        assert_eq!(filter(&max_resolution(640, 480)), vec![1, 3]);
+
+        let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(filter(&captured_between(None, None)), vec![1, 2]);
+        assert_eq!(
+            filter(&captured_between(Some(date("2020-01-01T00:00:00Z")), None)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            filter(&captured_between(None, Some(date("2021-06-01T00:00:00Z")))),
+            vec![1]
+        );
+
+        assert_eq!(filter(&license_id(1)), vec![1, 3]);
+        assert_eq!(filter(&license_id(3)), Vec::<i64>::new());
+    }
+}
@@ -0,0 +1,570 @@
+use crate::{
+    CocoDensePoseAnnotation, CocoKeypointDetectionAnnotation, CocoObjectDetectionAnnotation,
+    CocoPanopticSegmentInfo, CocoPolygon, CocoRLE, CocoSegmentation,
+};
+
+/// Axis aligned bounding box in pixels, stored like COCO as top left corner plus size
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl BBox {
+    /// From COCO's `[x, y, width, height]`
+    pub fn from_xywh(x: f32, y: f32, width: f32, height: f32) -> Self {
+        BBox {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// From two opposite corners, in either order
+    pub fn from_xyxy(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        BBox::from_xywh(x0.min(x1), y0.min(y1), (x1 - x0).abs(), (y1 - y0).abs())
+    }
+
+    /// From the center point and size, as used by YOLO
+    pub fn from_cxcywh(cx: f32, cy: f32, width: f32, height: f32) -> Self {
+        BBox::from_xywh(cx - width / 2.0, cy - height / 2.0, width, height)
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn x_max(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn y_max(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Alias of [`BBox::x_max`]
+    pub fn x2(&self) -> f32 {
+        self.x_max()
+    }
+
+    /// Alias of [`BBox::y_max`]
+    pub fn y2(&self) -> f32 {
+        self.y_max()
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    pub fn to_xywh(&self) -> [f32; 4] {
+        [self.x, self.y, self.width, self.height]
+    }
+
+    pub fn to_xyxy(&self) -> [f32; 4] {
+        [self.x, self.y, self.x_max(), self.y_max()]
+    }
+
+    pub fn to_cxcywh(&self) -> [f32; 4] {
+        let (cx, cy) = self.center();
+        [cx, cy, self.width, self.height]
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    /// The overlap of both boxes, `None` if they don't overlap or only touch
+    pub fn intersection(&self, other: &BBox) -> Option<BBox> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = self.x_max().min(other.x_max());
+        let y1 = self.y_max().min(other.y_max());
+        (x1 > x0 && y1 > y0).then(|| BBox::from_xyxy(x0, y0, x1, y1))
+    }
+
+    /// The smallest box containing both boxes
+    pub fn union(&self, other: &BBox) -> BBox {
+        BBox::from_xyxy(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.x_max().max(other.x_max()),
+            self.y_max().max(other.y_max()),
+        )
+    }
+
+    /// Intersection over union, 0 for disjoint boxes and 1 for identical ones
+    pub fn iou(&self, other: &BBox) -> f32 {
+        let intersection = self.intersection(other).map_or(0.0, |bbox| bbox.area());
+        let union = self.area() + other.area() - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+
+    /// The part of the box inside a `width` x `height` image, empty boxes keep the position
+    /// of the closest edge
+    pub fn clamp_to(&self, width: f32, height: f32) -> BBox {
+        let x0 = self.x.clamp(0.0, width);
+        let y0 = self.y.clamp(0.0, height);
+        let x1 = self.x_max().clamp(0.0, width);
+        let y1 = self.y_max().clamp(0.0, height);
+        BBox::from_xywh(x0, y0, x1 - x0, y1 - y0)
+    }
+
+    /// Whether the point lies inside the box or on its edge
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        (self.x..=self.x_max()).contains(&x) && (self.y..=self.y_max()).contains(&y)
+    }
+}
+
+impl From<[f32; 4]> for BBox {
+    fn from(xywh: [f32; 4]) -> Self {
+        let [x, y, width, height] = xywh;
+        BBox::from_xywh(x, y, width, height)
+    }
+}
+
+impl From<BBox> for [f32; 4] {
+    fn from(bbox: BBox) -> Self {
+        bbox.to_xywh()
+    }
+}
+
+/// A change of the pixel grid that annotations can follow when their image is resized, moved,
+/// flipped or rotated, see [`CocoAnnotation::transform`](crate::CocoAnnotation::transform)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    Scale {
+        sx: f32,
+        sy: f32,
+    },
+    Translate {
+        dx: f32,
+        dy: f32,
+    },
+    /// Mirrors an `image_width` wide image, swapping the keypoints of every `(left, right)`
+    /// index pair afterwards
+    HFlip {
+        image_width: f32,
+        keypoint_pairs: Vec<(usize, usize)>,
+    },
+    VFlip {
+        image_height: f32,
+    },
+    /// Rotates an `image_width` x `image_height` image 90 degrees clockwise
+    Rotate90 {
+        image_width: f32,
+        image_height: f32,
+    },
+}
+
+impl Transform {
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Transform::Scale { sx, sy }
+    }
+
+    pub fn translate(dx: f32, dy: f32) -> Self {
+        Transform::Translate { dx, dy }
+    }
+
+    pub fn hflip(image_width: f32) -> Self {
+        Transform::hflip_with_keypoint_pairs(image_width, Vec::new())
+    }
+
+    /// Horizontal flip that also swaps left and right keypoints, given as index pairs into the
+    /// category's keypoint list
+    pub fn hflip_with_keypoint_pairs(
+        image_width: f32,
+        keypoint_pairs: Vec<(usize, usize)>,
+    ) -> Self {
+        Transform::HFlip {
+            image_width,
+            keypoint_pairs,
+        }
+    }
+
+    pub fn vflip(image_height: f32) -> Self {
+        Transform::VFlip { image_height }
+    }
+
+    pub fn rotate90(image_width: f32, image_height: f32) -> Self {
+        Transform::Rotate90 {
+            image_width,
+            image_height,
+        }
+    }
+
+    pub fn apply_point(&self, x: f32, y: f32) -> (f32, f32) {
+        match *self {
+            Transform::Scale { sx, sy } => (x * sx, y * sy),
+            Transform::Translate { dx, dy } => (x + dx, y + dy),
+            Transform::HFlip { image_width, .. } => (image_width - x, y),
+            Transform::VFlip { image_height } => (x, image_height - y),
+            Transform::Rotate90 { image_height, .. } => (image_height - y, x),
+        }
+    }
+
+    fn invert_point(&self, x: f32, y: f32) -> (f32, f32) {
+        match *self {
+            Transform::Scale { sx, sy } => (x / sx, y / sy),
+            Transform::Translate { dx, dy } => (x - dx, y - dy),
+            Transform::HFlip { .. } | Transform::VFlip { .. } => self.apply_point(x, y),
+            Transform::Rotate90 { image_height, .. } => (y, image_height - x),
+        }
+    }
+
+    pub fn apply_bbox(&self, bbox: &BBox) -> BBox {
+        let (x0, y0) = self.apply_point(bbox.x, bbox.y);
+        let (x1, y1) = self.apply_point(bbox.x_max(), bbox.y_max());
+        BBox::from_xyxy(x0, y0, x1, y1)
+    }
+
+    /// Size of a `width` x `height` image afterwards, translating keeps the canvas size
+    pub fn apply_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match *self {
+            Transform::Scale { sx, sy } => (
+                (width as f32 * sx).round() as u32,
+                (height as f32 * sy).round() as u32,
+            ),
+            Transform::Rotate90 { .. } => (height, width),
+            _ => (width, height),
+        }
+    }
+
+    /// Factor every area is multiplied by
+    pub fn area_factor(&self) -> f32 {
+        match *self {
+            Transform::Scale { sx, sy } => sx * sy,
+            _ => 1.0,
+        }
+    }
+
+    pub fn apply_polygon(&self, polygon: &mut CocoPolygon) {
+        polygon.chunks_exact_mut(2).for_each(|point| {
+            (point[0], point[1]) = self.apply_point(point[0], point[1]);
+        });
+    }
+
+    /// Resamples the mask onto the new pixel grid by nearest neighbour, pixels moved off the
+    /// canvas are dropped
+    pub fn apply_rle(&self, rle: &CocoRLE) -> CocoRLE {
+        let (width, height) = (rle.size.1, rle.size.0);
+        let mask = rle.to_mask();
+        let (new_width, new_height) = self.apply_size(width, height);
+        let new_mask: Vec<bool> = (0..new_height)
+            .flat_map(|row| (0..new_width).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let (x, y) = self.invert_point(col as f32 + 0.5, row as f32 + 0.5);
+                let (x, y) = (x.floor(), y.floor());
+                x >= 0.0
+                    && y >= 0.0
+                    && x < width as f32
+                    && y < height as f32
+                    && mask[y as usize * width as usize + x as usize]
+            })
+            .collect();
+        CocoRLE::from_mask(&new_mask, new_width, new_height)
+    }
+
+    pub fn apply_segmentation(&self, segmentation: &mut CocoSegmentation) {
+        match segmentation {
+            CocoSegmentation::Polygon(polygons) => polygons
+                .iter_mut()
+                .for_each(|polygon| self.apply_polygon(polygon)),
+            CocoSegmentation::RLE(rle) => *rle = self.apply_rle(rle),
+        }
+    }
+
+    /// Applies to `[x, y, v]` keypoint triplets, unlabeled ones (`v == 0`) stay put
+    pub fn apply_keypoints(&self, keypoints: &mut [f32]) {
+        keypoints
+            .chunks_exact_mut(3)
+            .filter(|keypoint| keypoint[2] > 0.0)
+            .for_each(|keypoint| {
+                (keypoint[0], keypoint[1]) = self.apply_point(keypoint[0], keypoint[1]);
+            });
+        if let Transform::HFlip { keypoint_pairs, .. } = self {
+            for &(left, right) in keypoint_pairs {
+                if left.max(right) * 3 + 3 <= keypoints.len() {
+                    for i in 0..3 {
+                        keypoints.swap(left * 3 + i, right * 3 + i);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves the bbox of a DensePose annotation. Its points and masks are relative to the bbox
+    /// and only follow flips and rotations, body part indices are left as they are.
+    pub fn apply_densepose(&self, annotation: &mut CocoDensePoseAnnotation) {
+        annotation.set_bbox(self.apply_bbox(&annotation.bbox()));
+        annotation.area = (annotation.area as f32 * self.area_factor()).round() as u32;
+        if let Some(transform) = self.in_densepose_frame() {
+            for (x, y) in annotation.dp_x.iter_mut().zip(annotation.dp_y.iter_mut()) {
+                (*x, *y) = transform.apply_point(*x, *y);
+            }
+            for mask in &mut annotation.dp_masks {
+                *mask = transform.apply_rle(mask);
+            }
+        }
+    }
+
+    /// The same transform in DensePose's frame, where coordinates run from 0 to 256 across
+    /// the bbox
+    fn in_densepose_frame(&self) -> Option<Transform> {
+        match self {
+            Transform::Scale { .. } | Transform::Translate { .. } => None,
+            Transform::HFlip { .. } => Some(Transform::hflip(DENSEPOSE_FRAME_SIZE)),
+            Transform::VFlip { .. } => Some(Transform::vflip(DENSEPOSE_FRAME_SIZE)),
+            Transform::Rotate90 { .. } => Some(Transform::rotate90(
+                DENSEPOSE_FRAME_SIZE,
+                DENSEPOSE_FRAME_SIZE,
+            )),
+        }
+    }
+}
+
+const DENSEPOSE_FRAME_SIZE: f32 = 256.0;
+
+pub trait HasBBox {
+    fn bbox(&self) -> BBox;
+    fn set_bbox(&mut self, bbox: BBox);
+}
+
+impl HasBBox for CocoObjectDetectionAnnotation {
+    fn bbox(&self) -> BBox {
+        self.bbox.into()
+    }
+
+    fn set_bbox(&mut self, bbox: BBox) {
+        self.bbox = bbox.into();
+    }
+}
+
+impl HasBBox for CocoKeypointDetectionAnnotation {
+    fn bbox(&self) -> BBox {
+        self.bbox.into()
+    }
+
+    fn set_bbox(&mut self, bbox: BBox) {
+        self.bbox = bbox.into();
+    }
+}
+
+impl HasBBox for CocoDensePoseAnnotation {
+    fn bbox(&self) -> BBox {
+        self.bbox.into()
+    }
+
+    fn set_bbox(&mut self, bbox: BBox) {
+        self.bbox = bbox.into();
+    }
+}
+
+impl HasBBox for CocoPanopticSegmentInfo {
+    fn bbox(&self) -> BBox {
+        self.bbox.into()
+    }
+
+    fn set_bbox(&mut self, bbox: BBox) {
+        self.bbox = bbox.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_bbox_conversions_round_trip() {
+        let bbox = BBox::from_xywh(10.5, 20.25, 30.0, 40.75);
+        assert_close(bbox.to_xyxy(), [10.5, 20.25, 40.5, 61.0]);
+        assert_close(bbox.to_cxcywh(), [25.5, 40.625, 30.0, 40.75]);
+
+        let [x0, y0, x1, y1] = bbox.to_xyxy();
+        assert_close(BBox::from_xyxy(x0, y0, x1, y1).to_xywh(), bbox.to_xywh());
+        assert_close(BBox::from_xyxy(x1, y1, x0, y0).to_xywh(), bbox.to_xywh());
+        let [cx, cy, w, h] = bbox.to_cxcywh();
+        assert_close(BBox::from_cxcywh(cx, cy, w, h).to_xywh(), bbox.to_xywh());
+
+        let array: [f32; 4] = bbox.into();
+        assert_eq!(BBox::from(array), bbox);
+        assert_eq!(bbox.area(), 30.0 * 40.75);
+        assert_eq!((bbox.x2(), bbox.y2()), (bbox.x_max(), bbox.y_max()));
+    }
+
+    #[test]
+    fn test_bbox_iou_edge_cases() {
+        let bbox = BBox::from_xywh(0.0, 0.0, 10.0, 10.0);
+
+        // identical
+        assert_eq!(bbox.iou(&bbox), 1.0);
+        // no overlap, touching edges don't count either
+        let apart = BBox::from_xywh(20.0, 20.0, 5.0, 5.0);
+        let touching = BBox::from_xywh(10.0, 0.0, 10.0, 10.0);
+        assert_eq!(bbox.iou(&apart), 0.0);
+        assert!(bbox.intersection(&touching).is_none());
+        assert_eq!(bbox.iou(&touching), 0.0);
+        // containment
+        let inner = BBox::from_xywh(2.0, 2.0, 5.0, 5.0);
+        assert_eq!(bbox.intersection(&inner), Some(inner));
+        assert_eq!(bbox.iou(&inner), 0.25);
+        assert_eq!(inner.iou(&bbox), 0.25);
+        // partial overlap: 5x10 shared of 150 total
+        let shifted = BBox::from_xywh(5.0, 0.0, 10.0, 10.0);
+        assert!((bbox.iou(&shifted) - 50.0 / 150.0).abs() < 1e-6);
+        // degenerate boxes
+        let empty = BBox::default();
+        assert_eq!(empty.iou(&empty), 0.0);
+
+        assert_eq!(bbox.union(&apart), BBox::from_xywh(0.0, 0.0, 25.0, 25.0));
+    }
+
+    #[test]
+    fn test_bbox_iou_matches_mask_iou() {
+        // pixel aligned boxes rasterize exactly, so both IoUs have to agree
+        let boxes = [
+            [0.0, 0.0, 10.0, 10.0],
+            [5.0, 0.0, 10.0, 10.0],
+            [2.0, 3.0, 4.0, 5.0],
+            [8.0, 8.0, 12.0, 4.0],
+            [15.0, 15.0, 5.0, 5.0],
+        ];
+        for a in boxes {
+            for b in boxes {
+                let bbox_iou = BBox::from(a).iou(&BBox::from(b));
+                let mask_iou = CocoSegmentation::from_bbox(&a)
+                    .iou(&CocoSegmentation::from_bbox(&b), 20, 20)
+                    .unwrap();
+                assert!(
+                    (bbox_iou - mask_iou).abs() < 1e-6,
+                    "{:?} {:?}: {} != {}",
+                    a,
+                    b,
+                    bbox_iou,
+                    mask_iou
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bbox_clamp_and_contains() {
+        let bbox = BBox::from_xywh(-5.0, 80.0, 20.0, 40.0);
+        assert_eq!(
+            bbox.clamp_to(100.0, 100.0),
+            BBox::from_xywh(0.0, 80.0, 15.0, 20.0)
+        );
+        let outside = BBox::from_xywh(150.0, 10.0, 10.0, 10.0);
+        assert_eq!(outside.clamp_to(100.0, 100.0).area(), 0.0);
+
+        assert!(bbox.contains_point(0.0, 100.0));
+        assert!(bbox.contains_point(15.0, 120.0));
+        assert!(!bbox.contains_point(15.1, 100.0));
+        assert!(!bbox.contains_point(0.0, 79.0));
+    }
+
+    #[test]
+    fn test_has_bbox() {
+        let mut segment = CocoPanopticSegmentInfo {
+            id: 1,
+            category_id: 1,
+            area: 100,
+            bbox: [1.0, 2.0, 3.0, 4.0],
+            iscrowd: false,
+            extra: Default::default(),
+        };
+        assert_eq!(segment.bbox().to_xyxy(), [1.0, 2.0, 4.0, 6.0]);
+        segment.set_bbox(BBox::from_xyxy(0.0, 0.0, 10.0, 5.0));
+        assert_eq!(segment.bbox, [0.0, 0.0, 10.0, 5.0]);
+    }
+
+    #[test]
+    fn test_transform_points_and_bboxes() {
+        let bbox = BBox::from_xywh(10.0, 20.0, 30.0, 5.0);
+        let cases = [
+            (Transform::scale(2.0, 0.5), [20.0, 10.0, 60.0, 2.5]),
+            (Transform::translate(-10.0, 5.0), [0.0, 25.0, 30.0, 5.0]),
+            (Transform::hflip(100.0), [60.0, 20.0, 30.0, 5.0]),
+            (Transform::vflip(50.0), [10.0, 25.0, 30.0, 5.0]),
+            // the top left corner of a 100x50 image ends up top right of a 50x100 one
+            (Transform::rotate90(100.0, 50.0), [25.0, 10.0, 5.0, 30.0]),
+        ];
+        for (transform, expected) in cases {
+            assert_close(transform.apply_bbox(&bbox).to_xywh(), expected);
+            let (x, y) = transform.apply_point(3.0, 4.0);
+            assert_eq!(transform.invert_point(x, y), (3.0, 4.0), "{:?}", transform);
+        }
+        assert_eq!(
+            Transform::rotate90(100.0, 50.0).apply_size(100, 50),
+            (50, 100)
+        );
+        assert_eq!(Transform::scale(0.5, 0.5).apply_size(101, 50), (51, 25));
+    }
+
+    #[test]
+    fn test_hflip_keypoints_swaps_pairs() {
+        // left eye, right eye, nose (unlabeled)
+        let mut keypoints = vec![40.0, 10.0, 2.0, 60.0, 10.0, 1.0, 0.0, 0.0, 0.0];
+        Transform::hflip_with_keypoint_pairs(100.0, vec![(0, 1)]).apply_keypoints(&mut keypoints);
+        assert_eq!(
+            keypoints,
+            vec![40.0, 10.0, 1.0, 60.0, 10.0, 2.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_transform_rle() {
+        // 3 wide, 2 high, row major:
+        // x . .
+        // x x .
+        let mask = [true, false, false, true, true, false];
+        let rle = CocoRLE::from_mask(&mask, 3, 2);
+
+        let rotated = Transform::rotate90(3.0, 2.0).apply_rle(&rle);
+        assert_eq!(rotated.size, (3, 2));
+        assert_eq!(
+            rotated.to_mask(),
+            vec![true, true, true, false, false, false]
+        );
+
+        let flipped = Transform::hflip(3.0).apply_rle(&rle);
+        assert_eq!(
+            flipped.to_mask(),
+            vec![false, false, true, false, true, true]
+        );
+
+        let scaled = Transform::scale(2.0, 2.0).apply_rle(&rle);
+        assert_eq!(scaled.size, (4, 6));
+        assert_eq!(scaled.area(), rle.area() * 4);
+
+        let moved = Transform::translate(2.0, 0.0).apply_rle(&rle);
+        assert_eq!(
+            moved.to_mask(),
+            vec![false, false, true, false, false, true]
+        );
+    }
+}
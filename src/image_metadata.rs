@@ -0,0 +1,106 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use exif::{Field, In, Reader, Tag, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// The EXIF fields relevant to a COCO image entry
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExifMetadata {
+    /// DateTimeOriginal, shifted by OffsetTimeOriginal when present and treated as UTC otherwise
+    pub date_time_original: Option<DateTime<Utc>>,
+    /// EXIF orientation tag, 1-8
+    pub orientation: Option<u32>,
+}
+
+impl ExifMetadata {
+    /// Reads the EXIF block of the image at `path`.
+    /// Returns `None` if the image has no EXIF data or it can't be parsed.
+    pub fn read(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let exif = Reader::new()
+            .read_from_container(&mut BufReader::new(file))
+            .ok()?;
+
+        let ascii = |tag: Tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .and_then(|field: &Field| match &field.value {
+                    Value::Ascii(values) => values.first().map(Vec::as_slice),
+                    _ => None,
+                })
+        };
+
+        Some(ExifMetadata {
+            date_time_original: ascii(Tag::DateTimeOriginal).and_then(|date_time| {
+                parse_exif_date_time(date_time, ascii(Tag::OffsetTimeOriginal))
+            }),
+            orientation: exif
+                .get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0)),
+        })
+    }
+
+    /// Orientations 5-8 rotate the image by 90 degrees, so it is displayed with width and height
+    /// swapped
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self.orientation, Some(5..=8))
+    }
+}
+
+fn parse_exif_date_time(date_time: &[u8], offset: Option<&[u8]>) -> Option<DateTime<Utc>> {
+    let mut parsed = exif::DateTime::from_ascii(date_time).ok()?;
+    if let Some(offset) = offset {
+        // a malformed offset just leaves the time as UTC
+        let _ = parsed.parse_offset(offset);
+    }
+
+    let naive =
+        NaiveDate::from_ymd_opt(parsed.year as i32, parsed.month as u32, parsed.day as u32)?
+            .and_hms_opt(
+                parsed.hour as u32,
+                parsed.minute as u32,
+                parsed.second as u32,
+            )?;
+
+    match parsed.offset {
+        Some(offset_minutes) => FixedOffset::east_opt(offset_minutes as i32 * 60)?
+            .from_local_datetime(&naive)
+            .single()
+            .map(|date_time| date_time.with_timezone(&Utc)),
+        None => Some(Utc.from_utc_datetime(&naive)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exif_date_time() {
+        let parsed = parse_exif_date_time(b"2019:06:15 10:30:00", None).unwrap();
+        assert_eq!(parsed.to_rfc3339(), "2019-06-15T10:30:00+00:00");
+
+        let parsed = parse_exif_date_time(b"2019:06:15 10:30:00", Some(b"+02:00")).unwrap();
+        assert_eq!(parsed.to_rfc3339(), "2019-06-15T08:30:00+00:00");
+
+        assert!(parse_exif_date_time(b"    :  :     :  :  ", None).is_none());
+        assert!(parse_exif_date_time(b"2019:13:40 10:30:00", None).is_none());
+    }
+
+    #[test]
+    fn test_swaps_dimensions() {
+        let swaps = |orientation| {
+            ExifMetadata {
+                date_time_original: None,
+                orientation,
+            }
+            .swaps_dimensions()
+        };
+        assert!(!swaps(None));
+        assert!(!swaps(Some(1)));
+        assert!(!swaps(Some(3)));
+        assert!(swaps(Some(5)));
+        assert!(swaps(Some(6)));
+        assert!(swaps(Some(8)));
+    }
+}
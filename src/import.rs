@@ -0,0 +1,1094 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::crawl::{CrawlOptions, CrawlReport, crawl_directories};
+use crate::export::{CSV_HEADER, YoloBox, YoloPolygon, yolo_label_name};
+use crate::{
+    CocoAnnotation, CocoCategory, CocoFile, CocoImage, CocoImageCaptioningAnnotation,
+    CocoKeypointDetectionAnnotation, CocoKeypointDetectionCategory, CocoObjectDetectionAnnotation,
+    CocoObjectDetectionCategory, CocoSegmentation, HasID,
+};
+
+impl YoloBox {
+    /// Parses a `class cx cy w h` label line
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() != 5 {
+            bail!("expected 5 values in YOLO label line {:?}", line);
+        }
+
+        let value = |index: usize| -> Result<f32> {
+            tokens[index]
+                .parse()
+                .with_context(|| format!("invalid number {:?} in {:?}", tokens[index], line))
+        };
+
+        Ok(YoloBox {
+            class: tokens[0]
+                .parse()
+                .with_context(|| format!("invalid class {:?} in {:?}", tokens[0], line))?,
+            cx: value(1)?,
+            cy: value(2)?,
+            w: value(3)?,
+            h: value(4)?,
+        })
+    }
+
+    /// Converts back to a COCO `[x, y, w, h]` pixel bbox
+    pub fn to_coco_bbox(&self, width: u32, height: u32) -> [f32; 4] {
+        let (width, height) = (width as f32, height as f32);
+        [
+            (self.cx - self.w / 2.0) * width,
+            (self.cy - self.h / 2.0) * height,
+            self.w * width,
+            self.h * height,
+        ]
+    }
+}
+
+impl YoloPolygon {
+    /// Parses a `class x1 y1 x2 y2 ...` YOLO-seg label line with at least 3 points
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() < 7 || tokens.len().is_multiple_of(2) {
+            bail!(
+                "expected a class and at least 3 points in YOLO-seg label line {:?}",
+                line
+            );
+        }
+
+        Ok(YoloPolygon {
+            class: tokens[0]
+                .parse()
+                .with_context(|| format!("invalid class {:?} in {:?}", tokens[0], line))?,
+            points: tokens[1..]
+                .iter()
+                .map(|token| {
+                    token
+                        .parse()
+                        .with_context(|| format!("invalid number {:?} in {:?}", token, line))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Converts back to a COCO polygon of `x y` pixel pairs
+    pub fn to_coco_polygon(&self, width: u32, height: u32) -> Vec<f32> {
+        let (width, height) = (width as f32, height as f32);
+        self.points
+            .chunks_exact(2)
+            .flat_map(|point| [point[0] * width, point[1] * height])
+            .collect()
+    }
+}
+
+/// A line of a YOLO label file, a box or a YOLO-seg polygon
+#[derive(Debug, Clone, PartialEq)]
+pub enum YoloLabel {
+    Box(YoloBox),
+    Polygon(YoloPolygon),
+}
+
+impl YoloLabel {
+    /// Parses a box line, or a polygon line when it has more than 5 values
+    pub fn parse_line(line: &str) -> Result<Self> {
+        if line.split_whitespace().count() > 5 {
+            YoloPolygon::parse_line(line).map(YoloLabel::Polygon)
+        } else {
+            YoloBox::parse_line(line).map(YoloLabel::Box)
+        }
+    }
+
+    pub fn class(&self) -> usize {
+        match self {
+            YoloLabel::Box(yolo_box) => yolo_box.class,
+            YoloLabel::Polygon(polygon) => polygon.class,
+        }
+    }
+
+    /// The COCO segmentation in pixels, a rectangle for boxes
+    pub fn to_coco_segmentation(&self, width: u32, height: u32) -> CocoSegmentation {
+        match self {
+            YoloLabel::Box(yolo_box) => {
+                CocoSegmentation::from_bbox(&yolo_box.to_coco_bbox(width, height))
+            }
+            YoloLabel::Polygon(polygon) => {
+                CocoSegmentation::Polygon(vec![polygon.to_coco_polygon(width, height)])
+            }
+        }
+    }
+}
+
+/// Reads a `classes.txt` file with one class name per line
+pub fn read_yolo_classes(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read classes file {:?}", path))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Parses the contents of a YOLO label file, ignoring blank lines
+pub fn parse_yolo_labels(contents: &str) -> Result<Vec<YoloBox>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            YoloBox::parse_line(line).with_context(|| format!("line {}", index + 1))
+        })
+        .collect()
+}
+
+/// Parses the contents of a YOLO or YOLO-seg label file, ignoring blank lines. Each line is
+/// told apart by its number of values, so both kinds can be mixed.
+pub fn parse_yolo_label_lines(contents: &str) -> Result<Vec<YoloLabel>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            YoloLabel::parse_line(line).with_context(|| format!("line {}", index + 1))
+        })
+        .collect()
+}
+
+/// Reads the class names of an Ultralytics `data.yaml`, given as a `0: name` mapping, a block
+/// list or an inline `[a, b]` list under `names`
+pub fn read_yolo_data_yaml(path: &Path) -> Result<Vec<String>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+    let unquote = |value: &str| -> Result<String> {
+        let value = value.trim();
+        if value.starts_with('"') {
+            serde_json::from_str(value)
+                .with_context(|| format!("invalid name {} in {:?}", value, path))
+        } else {
+            Ok(value.trim_matches('\'').to_string())
+        }
+    };
+
+    let mut lines = contents
+        .lines()
+        .skip_while(|line| !line.starts_with("names:"));
+    let Some(names_line) = lines.next() else {
+        bail!("No names in {:?}", path);
+    };
+    let inline = names_line["names:".len()..].trim();
+    if let Some(list) = inline.strip_prefix('[') {
+        let list = list
+            .trim_end()
+            .strip_suffix(']')
+            .with_context(|| format!("names list in {:?} has to be on one line", path))?;
+        return list
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(unquote)
+            .collect();
+    }
+
+    let mut names = Vec::new();
+    for line in lines.take_while(|line| line.starts_with(' ') || line.starts_with('-')) {
+        let entry = line.trim();
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = entry.strip_prefix('-') {
+            names.push(unquote(name)?);
+            continue;
+        }
+        let Some((index, name)) = entry.split_once(':') else {
+            bail!("Could not parse names entry {:?} in {:?}", entry, path);
+        };
+        let index: usize = index
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid class index {:?} in {:?}", index, path))?;
+        if index != names.len() {
+            bail!("class {} in {:?} is out of order", index, path);
+        }
+        names.push(unquote(name)?);
+    }
+    Ok(names)
+}
+
+/// Class index `i` as category id `i + 1`
+fn yolo_categories(classes: &[String]) -> Vec<CocoCategory> {
+    classes
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            CocoCategory::ObjectDetection(CocoObjectDetectionCategory {
+                id: index as i32 + 1,
+                name: name.clone(),
+                supercategory: String::new(),
+                frequency: None,
+                image_count: None,
+                instance_count: None,
+                extra: Default::default(),
+            })
+        })
+        .collect()
+}
+
+/// Annotations for `images` from the label files found by `label_path`, numbered from 1
+fn annotations_from_labels(
+    images: &[CocoImage],
+    label_path: impl Fn(&CocoImage) -> Option<PathBuf>,
+    classes: &[String],
+) -> Result<Vec<CocoAnnotation>> {
+    let mut annotations = Vec::new();
+    for image in images {
+        let Some(label_path) = label_path(image) else {
+            continue;
+        };
+
+        let contents = fs::read_to_string(&label_path)
+            .with_context(|| format!("Could not read label file {:?}", label_path))?;
+        let labels = parse_yolo_label_lines(&contents)
+            .with_context(|| format!("Could not parse label file {:?}", label_path))?;
+
+        for label in labels {
+            if label.class() >= classes.len() {
+                bail!(
+                    "class {} in {:?} is out of range for {} classes",
+                    label.class(),
+                    label_path,
+                    classes.len()
+                );
+            }
+
+            let segmentation = label.to_coco_segmentation(image.width, image.height);
+            annotations.push(CocoAnnotation::ObjectDetection(
+                CocoObjectDetectionAnnotation {
+                    id: annotations.len() as i64 + 1,
+                    image_id: image.id,
+                    category_id: label.class() as i32 + 1,
+                    area: segmentation.area().unwrap_or_default(),
+                    bbox: segmentation.bounding_box().unwrap_or_default(),
+                    segmentation,
+                    iscrowd: false,
+                    score: None,
+                    attributes: None,
+                    extra: Default::default(),
+                },
+            ));
+        }
+    }
+    Ok(annotations)
+}
+
+/// Builds a COCO file from `images` and the label files in `labels_dir`, matched by file stem
+/// (`images/a.jpg` reads `labels_dir/a.txt` and `images/a.v2.jpg` reads `labels_dir/a.v2.txt`). Images without a label file are kept unannotated.
+/// Boxes get a rectangle segmentation and YOLO-seg polygons their polygon. Class index `i`
+/// becomes category id `i + 1`.
+pub fn from_yolo_labels(
+    images: Vec<CocoImage>,
+    labels_dir: &Path,
+    classes: &[String],
+) -> Result<CocoFile> {
+    let label_path = |image: &CocoImage| {
+        let name = yolo_label_name(&image.file_name)?;
+        Some(labels_dir.join(name)).filter(|path| path.is_file())
+    };
+    let annotations = annotations_from_labels(&images, label_path, classes)?;
+
+    Ok(CocoFile {
+        images,
+        annotations,
+        info: None,
+        categories: Some(yolo_categories(classes)),
+        licenses: None,
+    })
+}
+
+/// Imports an Ultralytics style YOLO dataset: the images under `dir/images` (or `dir` itself
+/// without one) are crawled with `options`, and each reads the label file at the same path
+/// under `dir/labels`, or the one with its stem directly in `dir/labels`. Class names are
+/// `names`, or else read from `dir/data.yaml` or `dir/labels/classes.txt`. Returns the file
+/// with the crawl report, whose images were moved into the file.
+pub fn from_yolo(
+    dir: &Path,
+    names: Option<Vec<String>>,
+    options: CrawlOptions,
+) -> Result<(CocoFile, CrawlReport)> {
+    let labels_dir = dir.join("labels");
+    let classes = match names {
+        Some(names) => names,
+        None if dir.join("data.yaml").is_file() => read_yolo_data_yaml(&dir.join("data.yaml"))?,
+        None if labels_dir.join("classes.txt").is_file() => {
+            read_yolo_classes(&labels_dir.join("classes.txt"))?
+        }
+        None => bail!(
+            "No class names for {:?}, expected data.yaml or labels/classes.txt",
+            dir
+        ),
+    };
+
+    let images_dir = if dir.join("images").is_dir() {
+        dir.join("images")
+    } else {
+        dir.to_path_buf()
+    };
+    let output_path = options.output_path.clone();
+    let mut report = crawl_directories(std::slice::from_ref(&images_dir), options)?;
+    let images = std::mem::take(&mut report.images);
+
+    let images_root = fs::canonicalize(&images_dir)
+        .with_context(|| format!("Could not resolve {:?}", images_dir))?;
+    let label_path = |image: &CocoImage| {
+        let mirrored = image
+            .get_absolute_path(&output_path)
+            .ok()
+            .and_then(|path| fs::canonicalize(path).ok())
+            .and_then(|path| {
+                let relative = path.strip_prefix(&images_root).ok()?;
+                let name = yolo_label_name(relative)?;
+                Some(labels_dir.join(relative.with_file_name(name)))
+            });
+        let flat = yolo_label_name(&image.file_name).map(|name| labels_dir.join(name));
+        mirrored.into_iter().chain(flat).find(|path| path.is_file())
+    };
+    let annotations = annotations_from_labels(&images, label_path, &classes)?;
+
+    let coco_file = CocoFile {
+        images,
+        annotations,
+        info: None,
+        categories: Some(yolo_categories(&classes)),
+        licenses: None,
+    };
+    Ok((coco_file, report))
+}
+
+/// Splits CSV text into records of fields, unquoting quoted fields, which may contain
+/// separators, doubled quotes and line breaks. Blank lines are skipped.
+fn parse_csv_records(contents: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("unterminated quoted field at the end of the CSV");
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Builds a COCO file from an annotation CSV written by [`crate::export::to_csv`], taking the
+/// images, info, licenses and categories from `images_from`. Rows with an empty category and box
+/// become captions, the others object detection annotations with a rectangle segmentation.
+/// Categories are looked up by name, names `images_from` doesn't have get new ids after its
+/// highest. The `file_name` column is only informational, but every image id has to exist in
+/// `images_from`.
+pub fn from_csv(contents: &str, images_from: &CocoFile) -> Result<CocoFile> {
+    let mut records = parse_csv_records(contents)?.into_iter();
+    let header = records.next().unwrap_or_default();
+    if header.join(",") != CSV_HEADER {
+        bail!("expected the CSV header {:?}", CSV_HEADER);
+    }
+
+    let image_ids: HashSet<i64> = images_from.images.iter().map(|image| image.id).collect();
+    let mut categories: Vec<CocoCategory> = images_from.categories.clone().unwrap_or_default();
+    let mut category_ids: HashMap<String, i32> = HashMap::new();
+    for category in &categories {
+        category_ids
+            .entry(category.name().to_string())
+            .or_insert(category.id());
+    }
+    let mut next_category_id = categories.iter().map(|c| c.id() + 1).max().unwrap_or(1);
+
+    let mut annotations = Vec::new();
+    // the header is row 1
+    for (row, record) in (2..).zip(records) {
+        let [
+            id,
+            image_id,
+            _file_name,
+            category,
+            x,
+            y,
+            w,
+            h,
+            area,
+            iscrowd,
+            caption,
+        ] = <[String; 11]>::try_from(record).map_err(|record| {
+            anyhow::anyhow!("row {} has {} fields instead of 11", row, record.len())
+        })?;
+        let parse_f32 = |value: &str, column: &str| -> Result<f32> {
+            value
+                .parse()
+                .with_context(|| format!("row {}: invalid {} {:?}", row, column, value))
+        };
+
+        let id: i64 = id
+            .parse()
+            .with_context(|| format!("row {}: invalid annotation_id {:?}", row, id))?;
+        let image_id: i64 = image_id
+            .parse()
+            .with_context(|| format!("row {}: invalid image_id {:?}", row, image_id))?;
+        if !image_ids.contains(&image_id) {
+            bail!(
+                "row {}: image id {} isn't in the images file",
+                row,
+                image_id
+            );
+        }
+
+        let box_columns = [&x, &y, &w, &h, &area, &iscrowd];
+        if category.is_empty() && box_columns.iter().all(|value| value.is_empty()) {
+            annotations.push(CocoAnnotation::ImageCaptioning(
+                CocoImageCaptioningAnnotation {
+                    id,
+                    image_id,
+                    caption,
+                    extra: Default::default(),
+                },
+            ));
+            continue;
+        }
+        if category.is_empty() {
+            bail!("row {} has a box but no category", row);
+        }
+
+        let category_id = *category_ids.entry(category.clone()).or_insert_with(|| {
+            categories.push(CocoCategory::ObjectDetection(CocoObjectDetectionCategory {
+                id: next_category_id,
+                name: category,
+                supercategory: String::new(),
+                frequency: None,
+                image_count: None,
+                instance_count: None,
+                extra: Default::default(),
+            }));
+            next_category_id += 1;
+            next_category_id - 1
+        });
+        let bbox = [
+            parse_f32(&x, "x")?,
+            parse_f32(&y, "y")?,
+            parse_f32(&w, "w")?,
+            parse_f32(&h, "h")?,
+        ];
+        let iscrowd = match iscrowd.as_str() {
+            "0" => false,
+            "1" => true,
+            _ => bail!(
+                "row {}: invalid iscrowd {:?}, expected 0 or 1",
+                row,
+                iscrowd
+            ),
+        };
+        annotations.push(CocoAnnotation::ObjectDetection(
+            CocoObjectDetectionAnnotation {
+                id,
+                image_id,
+                category_id,
+                segmentation: CocoSegmentation::from_bbox(&bbox),
+                area: parse_f32(&area, "area")?,
+                bbox,
+                iscrowd,
+                score: None,
+                attributes: None,
+                extra: Default::default(),
+            },
+        ));
+    }
+
+    Ok(CocoFile {
+        info: images_from.info.clone(),
+        licenses: images_from.licenses.clone(),
+        images: images_from.images.clone(),
+        annotations,
+        categories: Some(categories),
+    })
+}
+
+/// A COCO file imported from CVAT XML by [`from_cvat_xml`]
+pub struct CvatImport {
+    pub coco_file: CocoFile,
+    /// shapes without a COCO counterpart, such as polylines and masks, counted by tag
+    pub skipped_shapes: BTreeMap<String, usize>,
+}
+
+/// A `box`, `polygon` or `points` shape of a CVAT image or track
+struct CvatShape<'a> {
+    node: roxmltree::Node<'a, 'a>,
+    label: &'a str,
+    image_id: i64,
+    track_id: Option<&'a str>,
+}
+
+/// Required attribute `name` of `node`
+fn xml_attribute<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| {
+        anyhow!(
+            "<{}> at byte {} has no {} attribute",
+            node.tag_name().name(),
+            node.range().start,
+            name
+        )
+    })
+}
+
+/// Required numeric attribute `name` of `node`
+fn xml_number<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T> {
+    let value = xml_attribute(node, name)?;
+    value.trim().parse().map_err(|_| {
+        anyhow!(
+            "<{}> at byte {} has an invalid {} {:?}",
+            node.tag_name().name(),
+            node.range().start,
+            name,
+            value
+        )
+    })
+}
+
+/// Parses CVAT's `x1,y1;x2,y2;...` point lists into `x y` pairs
+fn cvat_points(node: roxmltree::Node) -> Result<Vec<f32>> {
+    let points = xml_attribute(node, "points")?;
+    let mut coordinates = Vec::new();
+    for point in points.split(';').filter(|point| !point.trim().is_empty()) {
+        let Some((x, y)) = point.split_once(',') else {
+            bail!("invalid point {:?} at byte {}", point, node.range().start);
+        };
+        for coordinate in [x, y] {
+            coordinates.push(coordinate.trim().parse().map_err(|_| {
+                anyhow!("invalid point {:?} at byte {}", point, node.range().start)
+            })?);
+        }
+    }
+    Ok(coordinates)
+}
+
+/// CVAT attribute values are text, numbers and booleans are stored as JSON numbers and booleans
+fn cvat_attribute_value(text: &str) -> serde_json::Value {
+    match text {
+        "true" => serde_json::Value::Bool(true),
+        "false" => serde_json::Value::Bool(false),
+        _ => text
+            .parse::<i64>()
+            .map(serde_json::Value::from)
+            .or_else(|_| text.parse::<f64>().map(serde_json::Value::from))
+            .unwrap_or_else(|_| serde_json::Value::String(text.to_string())),
+    }
+}
+
+/// Imports a "CVAT for images 1.1" (or "CVAT for video 1.1") XML export. Boxes and polygons
+/// become object detection annotations, points shapes keypoint annotations whose keypoints are
+/// visible (v=2), or labelled but not visible (v=1) when the shape is occluded. Shapes of tracks
+/// are imported on the image with the id of their frame, except those marked outside of the
+/// frame. Each CVAT label becomes a category with ids from 1 in the order of the task's label
+/// list, labels used by points shapes become keypoint categories with numbered keypoints.
+/// The shapes' CVAT attributes are kept in the annotations' `attributes`, together with
+/// `occluded` for occluded boxes and polygons and `track_id` for shapes of tracks.
+pub fn from_cvat_xml(contents: &str) -> Result<CvatImport> {
+    let document = roxmltree::Document::parse(contents).context("Could not parse CVAT XML")?;
+    let root = document.root_element();
+    if root.tag_name().name() != "annotations" {
+        bail!("expected an <annotations> root element in CVAT XML");
+    }
+
+    let mut labels: Vec<&str> = root
+        .descendants()
+        .filter(|node| node.has_tag_name("labels"))
+        .flat_map(|labels| labels.children().filter(|node| node.has_tag_name("label")))
+        .filter_map(|label| label.children().find(|node| node.has_tag_name("name")))
+        .filter_map(|name| name.text())
+        .collect();
+
+    let mut images = Vec::new();
+    let mut shapes = Vec::new();
+    let mut skipped_shapes: BTreeMap<String, usize> = BTreeMap::new();
+    let mut is_shape = |node: &roxmltree::Node| match node.tag_name().name() {
+        "box" | "polygon" | "points" => true,
+        "attribute" => false,
+        tag => {
+            *skipped_shapes.entry(tag.to_string()).or_default() += 1;
+            false
+        }
+    };
+    for node in root.children().filter(|node| node.is_element()) {
+        match node.tag_name().name() {
+            "image" => {
+                let image_id = xml_number(node, "id")?;
+                images.push(CocoImage {
+                    id: image_id,
+                    width: xml_number(node, "width")?,
+                    height: xml_number(node, "height")?,
+                    file_name: PathBuf::from(xml_attribute(node, "name")?),
+                    license: None,
+                    flickr_url: None,
+                    coco_url: None,
+                    date_captured: None,
+                    sha256: None,
+                    neg_category_ids: None,
+                    not_exhaustive_category_ids: None,
+                    extra: Default::default(),
+                });
+                for shape in node.children().filter(|node| node.is_element()) {
+                    if is_shape(&shape) {
+                        shapes.push(CvatShape {
+                            node: shape,
+                            label: xml_attribute(shape, "label")?,
+                            image_id,
+                            track_id: None,
+                        });
+                    }
+                }
+            }
+            "track" => {
+                let label = xml_attribute(node, "label")?;
+                let track_id = node.attribute("id");
+                for shape in node.children().filter(|node| node.is_element()) {
+                    if is_shape(&shape) && shape.attribute("outside") != Some("1") {
+                        shapes.push(CvatShape {
+                            node: shape,
+                            label,
+                            image_id: xml_number(shape, "frame")?,
+                            track_id,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let image_ids: HashSet<i64> = images.iter().map(|image| image.id).collect();
+    let mut keypoint_counts: HashMap<&str, usize> = HashMap::new();
+    for shape in &shapes {
+        if !image_ids.contains(&shape.image_id) {
+            bail!(
+                "<{}> at byte {} is on frame {}, which has no <image>",
+                shape.node.tag_name().name(),
+                shape.node.range().start,
+                shape.image_id
+            );
+        }
+        if !labels.contains(&shape.label) {
+            labels.push(shape.label);
+        }
+        if shape.node.has_tag_name("points") {
+            let count = cvat_points(shape.node)?.len() / 2;
+            let max_count = keypoint_counts.entry(shape.label).or_default();
+            *max_count = (*max_count).max(count);
+        }
+    }
+
+    let category_ids: HashMap<&str, i32> = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| (*label, index as i32 + 1))
+        .collect();
+    let categories = labels
+        .iter()
+        .map(|label| match keypoint_counts.get(label) {
+            Some(count) => CocoCategory::KeypointDetection(CocoKeypointDetectionCategory {
+                id: category_ids[label],
+                name: label.to_string(),
+                supercategory: String::new(),
+                keypoints: (1..=*count).map(|index| index.to_string()).collect(),
+                skeleton: Vec::new(),
+                extra: Default::default(),
+            }),
+            None => CocoCategory::ObjectDetection(CocoObjectDetectionCategory {
+                id: category_ids[label],
+                name: label.to_string(),
+                supercategory: String::new(),
+                frequency: None,
+                image_count: None,
+                instance_count: None,
+                extra: Default::default(),
+            }),
+        })
+        .collect();
+
+    let mut annotations = Vec::with_capacity(shapes.len());
+    for shape in shapes {
+        let node = shape.node;
+        let occluded = node.attribute("occluded") == Some("1");
+        let mut attributes: HashMap<String, serde_json::Value> = node
+            .children()
+            .filter(|child| child.has_tag_name("attribute"))
+            .filter_map(|attribute| {
+                let name = attribute.attribute("name")?;
+                let value = cvat_attribute_value(attribute.text().unwrap_or_default());
+                Some((name.to_string(), value))
+            })
+            .collect();
+        if let Some(track_id) = shape.track_id {
+            attributes.insert("track_id".to_string(), cvat_attribute_value(track_id));
+        }
+
+        let id = annotations.len() as i64 + 1;
+        let category_id = category_ids[shape.label];
+        if node.has_tag_name("points") {
+            let points = cvat_points(node)?;
+            let visibility = if occluded { 1.0 } else { 2.0 };
+            let mut keypoints: Vec<f32> = points
+                .chunks_exact(2)
+                .flat_map(|point| [point[0], point[1], visibility])
+                .collect();
+            keypoints.resize(keypoint_counts[shape.label] * 3, 0.0);
+            let bbox = CocoSegmentation::Polygon(vec![points.clone()])
+                .bounding_box()
+                .unwrap_or_default();
+            annotations.push(CocoAnnotation::KeypointDetection(
+                CocoKeypointDetectionAnnotation {
+                    id,
+                    image_id: shape.image_id,
+                    category_id,
+                    segmentation: CocoSegmentation::Polygon(Vec::new()),
+                    area: bbox[2] * bbox[3],
+                    bbox,
+                    iscrowd: false,
+                    num_keypoints: (points.len() / 2) as u32,
+                    keypoints,
+                    score: None,
+                    attributes: Some(attributes).filter(|attributes| !attributes.is_empty()),
+                    extra: Default::default(),
+                },
+            ));
+            continue;
+        }
+
+        if occluded {
+            attributes.insert("occluded".to_string(), serde_json::Value::Bool(true));
+        }
+        let segmentation = if node.has_tag_name("box") {
+            let (x0, y0): (f32, f32) = (xml_number(node, "xtl")?, xml_number(node, "ytl")?);
+            let (x1, y1): (f32, f32) = (xml_number(node, "xbr")?, xml_number(node, "ybr")?);
+            CocoSegmentation::from_bbox(&[x0, y0, x1 - x0, y1 - y0])
+        } else {
+            CocoSegmentation::Polygon(vec![cvat_points(node)?])
+        };
+        annotations.push(CocoAnnotation::ObjectDetection(
+            CocoObjectDetectionAnnotation {
+                id,
+                image_id: shape.image_id,
+                category_id,
+                area: segmentation.area().unwrap_or_default(),
+                bbox: segmentation.bounding_box().unwrap_or_default(),
+                segmentation,
+                iscrowd: false,
+                score: None,
+                attributes: Some(attributes).filter(|attributes| !attributes.is_empty()),
+                extra: Default::default(),
+            },
+        ));
+    }
+
+    Ok(CvatImport {
+        coco_file: CocoFile {
+            info: None,
+            licenses: None,
+            images,
+            annotations,
+            categories: Some(categories),
+        },
+        skipped_shapes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yolo_labels() {
+        let boxes = parse_yolo_labels("0 0.5 0.5 0.2 0.4\n\n3 0.1 0.2 0.3 0.4\n").unwrap();
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[1].class, 3);
+        let bbox = boxes[0].to_coco_bbox(100, 50);
+        for (a, b) in bbox.iter().zip([40.0, 15.0, 20.0, 20.0].iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_parse_yolo_labels_reports_bad_line() {
+        let err = parse_yolo_labels("0 0.5 0.5 0.2 0.4\n0 0.5 0.5\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2");
+        assert!(format!("{:#}", err).contains("expected 5 values"));
+    }
+
+    #[test]
+    fn test_yolo_box_round_trip() {
+        let bbox = [64.0, 48.0, 160.0, 120.0];
+        let yolo_box = YoloBox::from_coco_bbox(0, &bbox, 640, 480);
+        let round_trip = yolo_box.to_coco_bbox(640, 480);
+        for (a, b) in bbox.iter().zip(round_trip.iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_parse_yolo_label_lines_detects_polygons() {
+        let labels =
+            parse_yolo_label_lines("1 0.1 0.2 0.5 0.2 0.5 0.6\n0 0.5 0.5 0.2 0.4\n\n\n").unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].class(), 1);
+        let CocoSegmentation::Polygon(polygons) = labels[0].to_coco_segmentation(100, 50) else {
+            panic!("expected a polygon");
+        };
+        for (a, b) in polygons[0]
+            .iter()
+            .zip([10.0, 10.0, 50.0, 10.0, 50.0, 30.0].iter())
+        {
+            assert!((a - b).abs() < 1e-3);
+        }
+        assert!(matches!(labels[1], YoloLabel::Box(_)));
+        let bbox = labels[1]
+            .to_coco_segmentation(100, 50)
+            .bounding_box()
+            .unwrap();
+        for (a, b) in bbox.iter().zip([40.0, 15.0, 20.0, 20.0].iter()) {
+            assert!((a - b).abs() < 1e-3);
+        }
+
+        let err = parse_yolo_label_lines("0 0.1 0.2 0.5 0.2 0.5\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("at least 3 points"));
+    }
+
+    #[test]
+    fn test_read_yolo_data_yaml() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("data.yaml");
+        let read = |yaml: &str| {
+            fs::write(&path, yaml).unwrap();
+            read_yolo_data_yaml(&path)
+        };
+        let expected = vec!["person".to_string(), "traffic light".to_string()];
+
+        let mapping = "path: /data\ntrain: images\nnames:\n  0: person\n  1: \"traffic light\"\n";
+        assert_eq!(read(mapping).unwrap(), expected);
+        let list = "names:\n  - person\n  - 'traffic light'\nnc: 2\n";
+        assert_eq!(read(list).unwrap(), expected);
+        let inline = "nc: 2\nnames: [person, 'traffic light']\n";
+        assert_eq!(read(inline).unwrap(), expected);
+
+        assert!(read("names:\n  1: person\n").is_err());
+        assert!(read("nc: 2\n").is_err());
+    }
+
+    /// Maps each image to the category ids of its annotations
+    fn categories_by_file_name(coco_file: &CocoFile) -> HashMap<String, Vec<i32>> {
+        coco_file
+            .images
+            .iter()
+            .map(|image| {
+                let categories = coco_file
+                    .annotations
+                    .iter()
+                    .filter_map(|annotation| match annotation {
+                        CocoAnnotation::ObjectDetection(ann) if ann.image_id == image.id => {
+                            Some(ann.category_id)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let name = image.file_name.file_name().unwrap().to_string_lossy();
+                (name.into_owned(), categories)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_from_yolo_labels_keeps_dotted_stems() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(dir.path().join("img.v2.txt"), "0 0.5 0.5 0.2 0.2\n").unwrap();
+        fs::write(dir.path().join("img.txt"), "1 0.5 0.5 0.2 0.2\n").unwrap();
+        let images: Vec<CocoImage> = serde_json::from_value(serde_json::json!([
+            {"id": 1, "width": 10, "height": 10, "file_name": "images/img.v2.jpg"},
+            {"id": 2, "width": 10, "height": 10, "file_name": "images/img.jpg"}
+        ]))
+        .unwrap();
+        let classes = vec!["a".to_string(), "b".to_string()];
+
+        let coco_file = from_yolo_labels(images, dir.path(), &classes).unwrap();
+        let categories = categories_by_file_name(&coco_file);
+        assert_eq!(categories["img.v2.jpg"], vec![1]);
+        assert_eq!(categories["img.jpg"], vec![2]);
+    }
+
+    #[test]
+    fn test_from_yolo_keeps_dotted_stems() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("images/sub")).unwrap();
+        fs::create_dir_all(dir.path().join("labels/sub")).unwrap();
+        for (class, stem) in ["img.v2", "img"].iter().enumerate() {
+            let image_path = dir.path().join(format!("images/sub/{}.png", stem));
+            image::RgbImage::new(4, 4).save(&image_path).unwrap();
+            let label_path = dir.path().join(format!("labels/sub/{}.txt", stem));
+            fs::write(label_path, format!("{} 0.5 0.5 0.5 0.5\n", class)).unwrap();
+        }
+
+        let options = CrawlOptions {
+            output_path: dir.path().join("coco.json"),
+            quiet: true,
+            ..CrawlOptions::default()
+        };
+        let names = Some(vec!["a".to_string(), "b".to_string()]);
+        let (coco_file, _) = from_yolo(dir.path(), names, options).unwrap();
+        let categories = categories_by_file_name(&coco_file);
+        assert_eq!(categories["img.v2.png"], vec![1]);
+        assert_eq!(categories["img.png"], vec![2]);
+    }
+
+    #[test]
+    fn test_parse_csv_records_quoting() {
+        let records = parse_csv_records("a,\"b, \"\"c\"\"\"\r\n\n\"multi\nline\",\nlast").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b, \"c\"".to_string()],
+                vec!["multi\nline".to_string(), String::new()],
+                vec!["last".to_string()],
+            ]
+        );
+        assert!(parse_csv_records("\"open").is_err());
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.png"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "b.png"}
+            ],
+            "annotations": [
+                {
+                    "id": 5, "image_id": 2, "category_id": 4, "segmentation": [],
+                    "area": 4.5, "bbox": [0.5, 1.0, 1.5, 3.0], "iscrowd": 1
+                },
+                {"id": 6, "image_id": 1, "caption": "a cat, sitting\non a \"mat\""}
+            ],
+            "categories": [{"id": 4, "name": "cat", "supercategory": "animal"}]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let csv = crate::export::to_csv(&coco_file);
+
+        let imported = from_csv(&csv, &coco_file).unwrap();
+        assert_eq!(imported.images.len(), 2);
+        assert!(imported.categories == coco_file.categories);
+        let CocoAnnotation::ObjectDetection(ann) = &imported.annotations[0] else {
+            panic!("expected an object detection annotation");
+        };
+        assert_eq!((ann.id, ann.image_id, ann.category_id), (5, 2, 4));
+        assert_eq!(ann.bbox, [0.5, 1.0, 1.5, 3.0]);
+        assert_eq!(ann.area, 4.5);
+        assert!(ann.iscrowd);
+        let CocoAnnotation::ImageCaptioning(ann) = &imported.annotations[1] else {
+            panic!("expected a caption");
+        };
+        assert_eq!((ann.id, ann.image_id), (6, 1));
+        assert_eq!(ann.caption, "a cat, sitting\non a \"mat\"");
+
+        // new category names get ids after the existing ones
+        let csv = format!("{}\n7,1,a.png,dog,0,0,1,1,1,0,\n", CSV_HEADER);
+        let imported = from_csv(&csv, &coco_file).unwrap();
+        assert_eq!(imported.annotations[0].category_id(), Some(5));
+        assert_eq!(imported.categories.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_from_csv_validates_rows() {
+        let coco_file: CocoFile = serde_json::from_str(
+            r#"{"images": [{"id": 1, "width": 10, "height": 10, "file_name": "a.png"}],
+                "annotations": []}"#,
+        )
+        .unwrap();
+        let import = |rows: &str| {
+            let csv = format!("{}\n{}", CSV_HEADER, rows);
+            match from_csv(&csv, &coco_file) {
+                Ok(_) => panic!("{:?} was imported", rows),
+                Err(err) => format!("{:#}", err),
+            }
+        };
+
+        assert!(import("1,9,a.png,,,,,,,,hi\n").contains("image id 9 isn't in the images file"));
+        assert!(import("1,1,a.png,,0,0,1,1,1,0,\n").contains("row 2 has a box but no category"));
+        assert!(import("1,1,a.png,cat,0,0,1,1,1,2,\n").contains("invalid iscrowd"));
+        assert!(import("1,1\n").contains("row 2 has 2 fields"));
+        assert!(from_csv("id,caption\n", &coco_file).is_err());
+    }
+
+    #[test]
+    fn test_from_cvat_xml_points_and_attributes() {
+        let xml = r#"<annotations>
+            <image id="4" name="a.png" width="50" height="40">
+                <points label="hand" occluded="1" points="1,2;3,4;5,6"></points>
+                <points label="hand" occluded="0" points="7.5,8.5">
+                    <attribute name="side">left</attribute>
+                    <attribute name="fingers">5</attribute>
+                </points>
+                <tag label="blurry"></tag>
+            </image>
+        </annotations>"#;
+        let import = from_cvat_xml(xml).unwrap();
+        assert_eq!(import.skipped_shapes.get("tag"), Some(&1));
+
+        let coco_file = import.coco_file;
+        assert_eq!(coco_file.images[0].id, 4);
+        let annotations: Vec<&CocoKeypointDetectionAnnotation> =
+            coco_file.keypoint_detection_annotations().collect();
+        // occluded points are labelled but not visible
+        assert_eq!(
+            annotations[0].keypoints,
+            vec![1.0, 2.0, 1.0, 3.0, 4.0, 1.0, 5.0, 6.0, 1.0]
+        );
+        assert_eq!(annotations[0].bbox, [1.0, 2.0, 4.0, 4.0]);
+        // shorter points shapes are padded to the category's keypoints
+        assert_eq!(
+            annotations[1].keypoints,
+            vec![7.5, 8.5, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(annotations[1].num_keypoints, 1);
+        let attributes = annotations[1].attributes.as_ref().unwrap();
+        assert_eq!(attributes["side"], "left");
+        assert_eq!(attributes["fingers"], 5);
+
+        assert!(from_cvat_xml("<annotations><image id=\"0\"/></annotations>").is_err());
+        assert!(from_cvat_xml("<dataset/>").is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use anyhow::{Context, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Builds a rayon pool of `jobs` threads, or one thread per CPU if `None`. Work run through
+/// [`ThreadPool::install`] uses it instead of the global pool.
+pub fn thread_pool(jobs: Option<usize>) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .context("Could not build thread pool")
+}
@@ -0,0 +1,309 @@
+use anyhow::{Result, bail};
+
+use crate::{CocoKeypointDetectionAnnotation, CocoKeypointDetectionCategory};
+
+/// COCO keypoint visibility flag, stored as the third value of every triplet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// v = 0, x and y are usually 0 as well
+    NotLabeled,
+    /// v = 1, labeled but not visible
+    Occluded,
+    /// v = 2
+    Visible,
+}
+
+impl Visibility {
+    pub fn is_labeled(&self) -> bool {
+        *self != Visibility::NotLabeled
+    }
+}
+
+impl TryFrom<f32> for Visibility {
+    type Error = anyhow::Error;
+
+    fn try_from(value: f32) -> Result<Self> {
+        match value {
+            0.0 => Ok(Visibility::NotLabeled),
+            1.0 => Ok(Visibility::Occluded),
+            2.0 => Ok(Visibility::Visible),
+            _ => bail!("invalid keypoint visibility {}", value),
+        }
+    }
+}
+
+impl From<Visibility> for f32 {
+    fn from(visibility: Visibility) -> Self {
+        match visibility {
+            Visibility::NotLabeled => 0.0,
+            Visibility::Occluded => 1.0,
+            Visibility::Visible => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keypoint {
+    pub x: f32,
+    pub y: f32,
+    pub visibility: Visibility,
+}
+
+impl CocoKeypointDetectionAnnotation {
+    /// The `[x, y, v]` triplets of `keypoints`. A trailing incomplete triplet or an unknown
+    /// visibility flag yields an error item.
+    pub fn keypoints_iter(&self) -> impl Iterator<Item = Result<Keypoint>> + '_ {
+        self.keypoints
+            .chunks(3)
+            .enumerate()
+            .map(|(index, triplet)| {
+                let &[x, y, v] = triplet else {
+                    bail!(
+                        "annotation {} has {} keypoint values, not a multiple of 3",
+                        self.id,
+                        self.keypoints.len()
+                    );
+                };
+                let visibility = Visibility::try_from(v).map_err(|err| {
+                    err.context(format!("keypoint {} of annotation {}", index, self.id))
+                })?;
+                Ok(Keypoint { x, y, visibility })
+            })
+    }
+
+    /// All keypoints, or the first error of [`Self::keypoints_iter`]
+    pub fn keypoints_vec(&self) -> Result<Vec<Keypoint>> {
+        self.keypoints_iter().collect()
+    }
+
+    /// Overwrites the triplet of keypoint `index`, which has to exist already
+    pub fn set_keypoint(&mut self, index: usize, keypoint: Keypoint) -> Result<()> {
+        if !self.keypoints.len().is_multiple_of(3) {
+            bail!(
+                "annotation {} has {} keypoint values, not a multiple of 3",
+                self.id,
+                self.keypoints.len()
+            );
+        }
+        let Some(triplet) = self.keypoints.chunks_exact_mut(3).nth(index) else {
+            bail!(
+                "annotation {} has no keypoint {}, only {}",
+                self.id,
+                index,
+                self.keypoints.len() / 3
+            );
+        };
+        triplet.copy_from_slice(&[keypoint.x, keypoint.y, keypoint.visibility.into()]);
+        Ok(())
+    }
+
+    /// Sets `num_keypoints` to the number of labeled keypoints (v > 0) and returns it
+    pub fn recount_num_keypoints(&mut self) -> u32 {
+        self.num_keypoints = self
+            .keypoints
+            .chunks_exact(3)
+            .filter(|triplet| triplet[2] > 0.0)
+            .count() as u32;
+        self.num_keypoints
+    }
+}
+
+impl CocoKeypointDetectionCategory {
+    /// Checks that every skeleton edge connects two existing keypoints. The official COCO
+    /// annotations number keypoints from 1, set `one_based` for them; other tools often
+    /// number from 0.
+    pub fn validate_skeleton(&self, one_based: bool) -> Result<()> {
+        let first = usize::from(one_based);
+        for (i, edge) in self.skeleton.iter().enumerate() {
+            for &index in edge {
+                let position = (index as usize).checked_sub(first);
+                if position.is_none_or(|position| position >= self.keypoints.len()) {
+                    bail!(
+                        "skeleton edge {} {:?} of category {} references keypoint {}, but there are {} keypoints numbered from {}",
+                        i,
+                        edge,
+                        self.id,
+                        index,
+                        self.keypoints.len(),
+                        first
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Per keypoint OKS sigmas of the 17 COCO person keypoints, in their standard order
+pub const COCO_PERSON_SIGMAS: [f32; 17] = [
+    0.026, 0.025, 0.025, 0.035, 0.035, 0.079, 0.079, 0.072, 0.072, 0.062, 0.062, 0.107, 0.107,
+    0.087, 0.087, 0.089, 0.089,
+];
+
+/// Object keypoint similarity as in the COCO evaluation, the mean of
+/// `exp(-d² / (2 * area * (2 * sigma)²))` over keypoints labeled in `gt`. Keypoints without a
+/// sigma are ignored, and the similarity is 0 if `gt` has no labeled keypoints.
+pub fn keypoint_oks(
+    gt: &CocoKeypointDetectionAnnotation,
+    pred: &CocoKeypointDetectionAnnotation,
+    sigmas: &[f32],
+) -> f32 {
+    let area = gt.area + f32::EPSILON;
+    let (similarity, labeled) = gt
+        .keypoints
+        .chunks_exact(3)
+        .zip(pred.keypoints.chunks_exact(3))
+        .zip(sigmas)
+        .filter(|((gt, _), _)| gt[2] > 0.0)
+        .fold((0.0, 0), |(similarity, labeled), ((gt, pred), sigma)| {
+            let distance_squared = (gt[0] - pred[0]).powi(2) + (gt[1] - pred[1]).powi(2);
+            let variance = (2.0 * sigma).powi(2);
+            let similarity = similarity + (-distance_squared / (2.0 * area * variance)).exp();
+            (similarity, labeled + 1)
+        });
+    if labeled == 0 {
+        0.0
+    } else {
+        similarity / labeled as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CocoSegmentation;
+
+    fn annotation(keypoints: Vec<f32>) -> CocoKeypointDetectionAnnotation {
+        CocoKeypointDetectionAnnotation {
+            id: 7,
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::Polygon(vec![]),
+            area: 0.0,
+            bbox: [0.0, 0.0, 0.0, 0.0],
+            iscrowd: false,
+            keypoints,
+            num_keypoints: 0,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
+        }
+    }
+
+    fn category(skeleton: Vec<[u32; 2]>) -> CocoKeypointDetectionCategory {
+        CocoKeypointDetectionCategory {
+            id: 1,
+            name: "person".to_string(),
+            supercategory: "person".to_string(),
+            keypoints: vec![
+                "nose".to_string(),
+                "left_eye".to_string(),
+                "right_eye".to_string(),
+            ],
+            skeleton,
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_keypoints_vec_and_set_keypoint() {
+        let mut ann = annotation(vec![1.0, 2.0, 2.0, 0.0, 0.0, 0.0, 5.0, 6.0, 1.0]);
+        let keypoints = ann.keypoints_vec().unwrap();
+        assert_eq!(keypoints.len(), 3);
+        assert_eq!(
+            keypoints[0],
+            Keypoint {
+                x: 1.0,
+                y: 2.0,
+                visibility: Visibility::Visible
+            }
+        );
+        assert!(!keypoints[1].visibility.is_labeled());
+        assert_eq!(keypoints[2].visibility, Visibility::Occluded);
+        assert_eq!(ann.recount_num_keypoints(), 2);
+
+        let nose = Keypoint {
+            x: 3.0,
+            y: 4.0,
+            visibility: Visibility::Occluded,
+        };
+        ann.set_keypoint(1, nose).unwrap();
+        assert_eq!(ann.keypoints[3..6], [3.0, 4.0, 1.0]);
+        assert_eq!(ann.recount_num_keypoints(), 3);
+        assert_eq!(ann.num_keypoints, 3);
+        assert!(ann.set_keypoint(3, nose).is_err());
+    }
+
+    #[test]
+    fn test_malformed_keypoints_are_errors() {
+        let mut ann = annotation(vec![1.0, 2.0, 2.0, 3.0]);
+        let results: Vec<_> = ann.keypoints_iter().collect();
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(ann.keypoints_vec().is_err());
+        assert!(
+            ann.set_keypoint(
+                0,
+                Keypoint {
+                    x: 0.0,
+                    y: 0.0,
+                    visibility: Visibility::Visible
+                }
+            )
+            .is_err()
+        );
+
+        let err = annotation(vec![1.0, 2.0, 3.0]).keypoints_vec().unwrap_err();
+        assert!(format!("{:#}", err).contains("invalid keypoint visibility 3"));
+    }
+
+    #[test]
+    fn test_validate_skeleton() {
+        assert!(
+            category(vec![[0, 1], [0, 2]])
+                .validate_skeleton(false)
+                .is_ok()
+        );
+        assert!(
+            category(vec![[0, 1], [0, 3]])
+                .validate_skeleton(false)
+                .is_err()
+        );
+        assert!(
+            category(vec![[1, 2], [1, 3]])
+                .validate_skeleton(true)
+                .is_ok()
+        );
+        assert!(category(vec![[0, 1]]).validate_skeleton(true).is_err());
+
+        let mut empty = category(vec![[0, 0]]);
+        empty.keypoints.clear();
+        assert!(empty.validate_skeleton(false).is_err());
+        empty.skeleton.clear();
+        assert!(empty.validate_skeleton(false).is_ok());
+    }
+
+    #[test]
+    fn test_keypoint_oks() {
+        let mut gt = annotation(vec![10.0, 10.0, 2.0, 20.0, 20.0, 1.0, 30.0, 30.0, 0.0]);
+        gt.area = 100.0;
+        let sigmas = [0.1, 0.1, 0.1];
+
+        assert!((keypoint_oks(&gt, &gt, &sigmas) - 1.0).abs() < 1e-6);
+
+        // second keypoint 1px off: exp(-1 / (2 * 100 * 0.2²)) = exp(-0.125), the unlabeled
+        // third keypoint doesn't count however far off it is
+        let pred = annotation(vec![10.0, 10.0, 2.0, 21.0, 20.0, 2.0, 90.0, 90.0, 2.0]);
+        let expected = (1.0 + (-0.125f32).exp()) / 2.0;
+        assert!((keypoint_oks(&gt, &pred, &sigmas) - expected).abs() < 1e-6);
+
+        // 3px and 4px off on the first keypoint only: exp(-25 / 8) for it, 1 for the second
+        let pred = annotation(vec![13.0, 14.0, 2.0, 20.0, 20.0, 2.0, 30.0, 30.0, 2.0]);
+        let expected = ((-25.0f32 / 8.0).exp() + 1.0) / 2.0;
+        assert!((keypoint_oks(&gt, &pred, &sigmas) - expected).abs() < 1e-6);
+
+        let unlabeled = annotation(vec![0.0; 9]);
+        assert_eq!(keypoint_oks(&unlabeled, &pred, &sigmas), 0.0);
+        assert_eq!(COCO_PERSON_SIGMAS.len(), 17);
+    }
+}
@@ -1,11 +1,50 @@
-use chrono::{DateTime, Utc};
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDateTime, Utc};
+#[cfg(feature = "rayon")]
 use indicatif::ParallelProgressIterator;
+#[cfg(feature = "rayon")]
+use progress::progress_bar;
+#[cfg(feature = "rayon")]
 use rayon::prelude::*;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{collections::HashMap, path::{Path, PathBuf}};
-
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+pub mod archive;
+pub mod categories;
+pub mod compact;
+pub mod crawl;
+pub mod dataset;
+pub mod dedupe;
+pub mod diff;
+pub mod eval;
+pub mod export;
+pub mod filter;
+pub mod geometry;
+pub mod image_metadata;
+pub mod import;
+#[cfg(feature = "rayon")]
+pub mod jobs;
+pub mod keypoints;
+pub mod mask;
+pub mod ndjson;
+pub mod panoptic;
 pub mod path_utils;
+pub mod probe;
+#[cfg(feature = "rayon")]
+pub mod progress;
+pub mod prune;
+pub mod results;
+pub mod sample;
+pub mod transform;
+pub mod validation;
+
+pub use crawl::{CrawlOptions, build_coco_file, crawl_directories, crawl_new_images};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CocoFile {
@@ -30,6 +69,11 @@ pub struct CocoInfo {
     pub contributor: String,
     pub url: String,
     pub date_created: DateTime<Utc>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -37,6 +81,9 @@ pub struct CocoImage {
     pub id: i64,
     pub width: u32,
     pub height: u32,
+
+    /// LVIS leaves this out, its images are found through `coco_url`
+    #[serde(default)]
     pub file_name: PathBuf,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -48,19 +95,53 @@ pub struct CocoImage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub coco_url: Option<String>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// RFC 3339, or the `YYYY-MM-DD HH:MM:SS` of the original COCO and LVIS files read as UTC
+    #[serde(
+        default,
+        deserialize_with = "date_from_coco",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub date_captured: Option<DateTime<Utc>>,
+
+    /// lowercase hex SHA-256 digest of the image file, to detect silent corruption
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// LVIS categories verified to be absent from the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neg_category_ids: Option<Vec<i32>>,
+
+    /// LVIS categories present in the image but not annotated on every instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_exhaustive_category_ids: Option<Vec<i32>>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl CocoImage {
+    /// Resolves `file_name` against the dataset JSON at `dataset_file_path`: relative file names
+    /// are relative to the JSON's directory, absolute ones are taken as they are. The result is
+    /// canonicalized, resolving symlinks and `..`, when the file exists. A missing file still
+    /// gets an absolute path, made without touching the filesystem, so callers can report it.
     pub fn get_absolute_path(&self, dataset_file_path: &Path) -> Result<PathBuf> {
-        if self.file_name.is_absolute() {
-            Ok(self.file_name.clone())
+        let path = if self.file_name.is_absolute() {
+            self.file_name.clone()
         } else {
-            Ok(dataset_file_path
-                .parent()
-                .expect(format!("unable to get parent dir for {}", dataset_file_path.to_string_lossy()).as_str())
-                .join(&self.file_name))
+            let Some(parent) = dataset_file_path.parent() else {
+                bail!(
+                    "unable to get parent dir for {}",
+                    dataset_file_path.to_string_lossy()
+                );
+            };
+            parent.join(&self.file_name)
+        };
+        match path.canonicalize() {
+            Ok(canonical) => Ok(canonical),
+            Err(_) => std::path::absolute(&path)
+                .with_context(|| format!("Could not make {:?} absolute", path)),
         }
     }
 }
@@ -75,11 +156,15 @@ impl HasID<i64> for CocoImage {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CocoLicense {
     pub id: i32,
     pub name: String,
     pub url: String,
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl PartialEq for CocoLicense {
@@ -90,6 +175,13 @@ impl PartialEq for CocoLicense {
 
 impl Eq for CocoLicense {}
 
+impl Hash for CocoLicense {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.url.hash(state);
+    }
+}
+
 impl HasID<i32> for CocoLicense {
     fn id(&self) -> i32 {
         self.id
@@ -123,6 +215,28 @@ impl CocoAnnotation {
         }
     }
 
+    /// Annotation id, or `None` for panoptic annotations which only carry ids on their segments
+    pub fn id(&self) -> Option<i64> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => Some(ann.id),
+            CocoAnnotation::KeypointDetection(ann) => Some(ann.id),
+            CocoAnnotation::PanopticSegmentation(_) => None,
+            CocoAnnotation::ImageCaptioning(ann) => Some(ann.id),
+            CocoAnnotation::DensePose(ann) => Some(ann.id),
+        }
+    }
+
+    /// Sets the annotation id, panoptic annotations have none so they're left unchanged
+    pub fn set_id(&mut self, new_id: i64) {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => ann.id = new_id,
+            CocoAnnotation::KeypointDetection(ann) => ann.id = new_id,
+            CocoAnnotation::PanopticSegmentation(_) => {}
+            CocoAnnotation::ImageCaptioning(ann) => ann.id = new_id,
+            CocoAnnotation::DensePose(ann) => ann.id = new_id,
+        }
+    }
+
     pub fn set_image_id(&mut self, new_image_id: i64) {
         match self {
             CocoAnnotation::ObjectDetection(ann) => ann.image_id = new_image_id,
@@ -132,6 +246,130 @@ impl CocoAnnotation {
             CocoAnnotation::DensePose(ann) => ann.image_id = new_image_id,
         }
     }
+
+    /// Category id, or `None` for captions and for panoptic annotations which carry categories
+    /// on their segments
+    pub fn category_id(&self) -> Option<i32> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => Some(ann.category_id),
+            CocoAnnotation::KeypointDetection(ann) => Some(ann.category_id),
+            CocoAnnotation::PanopticSegmentation(_) => None,
+            CocoAnnotation::ImageCaptioning(_) => None,
+            CocoAnnotation::DensePose(ann) => Some(ann.category_id),
+        }
+    }
+
+    /// Every category the annotation refers to, including those of panoptic segments
+    pub fn category_ids(&self) -> Vec<i32> {
+        match self {
+            CocoAnnotation::PanopticSegmentation(ann) => ann
+                .segments_info
+                .iter()
+                .map(|segment| segment.category_id)
+                .collect(),
+            _ => self.category_id().into_iter().collect(),
+        }
+    }
+
+    /// Prediction score, `None` for ground truth and for captions and panoptic annotations
+    /// which can't carry one
+    pub fn score(&self) -> Option<f32> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => ann.score(),
+            CocoAnnotation::KeypointDetection(ann) => ann.score(),
+            CocoAnnotation::DensePose(ann) => ann.score(),
+            CocoAnnotation::PanopticSegmentation(_) | CocoAnnotation::ImageCaptioning(_) => None,
+        }
+    }
+
+    /// The object detection annotation, if this is one
+    ///
+    /// ```
+    /// use cococrawl::CocoAnnotation;
+    ///
+    /// let annotation: CocoAnnotation =
+    ///     serde_json::from_str(r#"{"id": 1, "image_id": 1, "caption": "a cat"}"#).unwrap();
+    /// assert!(annotation.as_object_detection().is_none());
+    /// assert_eq!(annotation.as_captioning().unwrap().caption, "a cat");
+    /// ```
+    pub fn as_object_detection(&self) -> Option<&CocoObjectDetectionAnnotation> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`CocoAnnotation::as_object_detection`]
+    pub fn as_object_detection_mut(&mut self) -> Option<&mut CocoObjectDetectionAnnotation> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// The keypoint detection annotation, if this is one
+    pub fn as_keypoint_detection(&self) -> Option<&CocoKeypointDetectionAnnotation> {
+        match self {
+            CocoAnnotation::KeypointDetection(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`CocoAnnotation::as_keypoint_detection`]
+    pub fn as_keypoint_detection_mut(&mut self) -> Option<&mut CocoKeypointDetectionAnnotation> {
+        match self {
+            CocoAnnotation::KeypointDetection(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// The panoptic segmentation annotation, if this is one
+    pub fn as_panoptic(&self) -> Option<&CocoPanopticSegmentationAnnotation> {
+        match self {
+            CocoAnnotation::PanopticSegmentation(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`CocoAnnotation::as_panoptic`]
+    pub fn as_panoptic_mut(&mut self) -> Option<&mut CocoPanopticSegmentationAnnotation> {
+        match self {
+            CocoAnnotation::PanopticSegmentation(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// The image captioning annotation, if this is one
+    pub fn as_captioning(&self) -> Option<&CocoImageCaptioningAnnotation> {
+        match self {
+            CocoAnnotation::ImageCaptioning(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`CocoAnnotation::as_captioning`]
+    pub fn as_captioning_mut(&mut self) -> Option<&mut CocoImageCaptioningAnnotation> {
+        match self {
+            CocoAnnotation::ImageCaptioning(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// The DensePose annotation, if this is one
+    pub fn as_densepose(&self) -> Option<&CocoDensePoseAnnotation> {
+        match self {
+            CocoAnnotation::DensePose(ann) => Some(ann),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`CocoAnnotation::as_densepose`]
+    pub fn as_densepose_mut(&mut self) -> Option<&mut CocoDensePoseAnnotation> {
+        match self {
+            CocoAnnotation::DensePose(ann) => Some(ann),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -143,8 +381,26 @@ pub struct CocoObjectDetectionAnnotation {
     pub area: f32,
     pub bbox: [f32; 4],
 
-    #[serde(deserialize_with = "bool_from_int", serialize_with = "bool_to_int")]
+    /// false when missing, as in LVIS which has no crowd annotations
+    #[serde(
+        default,
+        deserialize_with = "bool_from_int",
+        serialize_with = "bool_to_int"
+    )]
     pub iscrowd: bool,
+
+    /// confidence of a prediction in the COCO results format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+
+    /// free-form attributes from other annotation tools, such as CVAT's per shape attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, serde_json::Value>>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl HasID<i64> for CocoObjectDetectionAnnotation {
@@ -167,6 +423,16 @@ impl HasCategoryID for CocoObjectDetectionAnnotation {
     }
 }
 
+impl HasScore for CocoObjectDetectionAnnotation {
+    fn score(&self) -> Option<f32> {
+        self.score
+    }
+
+    fn set_score(&mut self, new_score: Option<f32>) {
+        self.score = new_score;
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CocoKeypointDetectionAnnotation {
     pub id: i64,
@@ -176,10 +442,28 @@ pub struct CocoKeypointDetectionAnnotation {
     pub area: f32,
     pub bbox: [f32; 4],
 
-    #[serde(deserialize_with = "bool_from_int", serialize_with = "bool_to_int")]
+    /// false when missing, as in LVIS which has no crowd annotations
+    #[serde(
+        default,
+        deserialize_with = "bool_from_int",
+        serialize_with = "bool_to_int"
+    )]
     pub iscrowd: bool,
     pub keypoints: Vec<f32>, // [x1, y1, v1, x2, y2, v2, ..., xn, yn, vn]
     pub num_keypoints: u32,
+
+    /// confidence of a prediction in the COCO results format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+
+    /// free-form attributes from other annotation tools, such as CVAT's per shape attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, serde_json::Value>>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl HasID<i64> for CocoKeypointDetectionAnnotation {
@@ -202,11 +486,26 @@ impl HasCategoryID for CocoKeypointDetectionAnnotation {
     }
 }
 
+impl HasScore for CocoKeypointDetectionAnnotation {
+    fn score(&self) -> Option<f32> {
+        self.score
+    }
+
+    fn set_score(&mut self, new_score: Option<f32>) {
+        self.score = new_score;
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CocoPanopticSegmentationAnnotation {
     pub image_id: i64,
     pub file_name: PathBuf,
     pub segments_info: Vec<CocoPanopticSegmentInfo>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -214,6 +513,11 @@ pub struct CocoImageCaptioningAnnotation {
     pub id: i64,
     pub image_id: i64,
     pub caption: String,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl HasID<i64> for CocoImageCaptioningAnnotation {
@@ -253,6 +557,15 @@ pub struct CocoDensePoseAnnotation {
     pub dp_y: Vec<f32>,
 
     pub dp_masks: Vec<CocoRLE>,
+
+    /// confidence of a prediction in the COCO results format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl HasID<i64> for CocoDensePoseAnnotation {
@@ -275,6 +588,16 @@ impl HasCategoryID for CocoDensePoseAnnotation {
     }
 }
 
+impl HasScore for CocoDensePoseAnnotation {
+    fn score(&self) -> Option<f32> {
+        self.score
+    }
+
+    fn set_score(&mut self, new_score: Option<f32>) {
+        self.score = new_score;
+    }
+}
+
 // category types ///////////////////////////////////
 
 #[derive(Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
@@ -303,12 +626,50 @@ impl HasID<i32> for CocoCategory {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash)]
+impl CocoCategory {
+    pub fn name(&self) -> &str {
+        match self {
+            CocoCategory::ObjectDetection(cat) => &cat.name,
+            CocoCategory::KeypointDetection(cat) => &cat.name,
+            CocoCategory::PanopticSegmentation(cat) => &cat.name,
+        }
+    }
+
+    pub fn supercategory(&self) -> &str {
+        match self {
+            CocoCategory::ObjectDetection(cat) => &cat.supercategory,
+            CocoCategory::KeypointDetection(cat) => &cat.supercategory,
+            CocoCategory::PanopticSegmentation(cat) => &cat.supercategory,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CocoObjectDetectionCategory {
     // also used for dense pose
     pub id: i32,
     pub name: String,
+
+    /// empty when missing, as in LVIS
+    #[serde(default)]
     pub supercategory: String,
+
+    /// LVIS frequency group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<LvisFrequency>,
+
+    /// LVIS number of images with an annotation of the category
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_count: Option<u64>,
+
+    /// LVIS number of annotations of the category
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_count: Option<u64>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl PartialEq for CocoObjectDetectionCategory {
@@ -319,6 +680,13 @@ impl PartialEq for CocoObjectDetectionCategory {
 
 impl Eq for CocoObjectDetectionCategory {}
 
+impl Hash for CocoObjectDetectionCategory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.supercategory.hash(state);
+        self.name.hash(state);
+    }
+}
+
 impl HasID<i32> for CocoObjectDetectionCategory {
     fn id(&self) -> i32 {
         self.id
@@ -329,13 +697,18 @@ impl HasID<i32> for CocoObjectDetectionCategory {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CocoKeypointDetectionCategory {
     pub id: i32,
     pub name: String,
     pub supercategory: String,
     pub keypoints: Vec<String>,
     pub skeleton: Vec<[u32; 2]>,
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl PartialEq for CocoKeypointDetectionCategory {
@@ -349,6 +722,15 @@ impl PartialEq for CocoKeypointDetectionCategory {
 
 impl Eq for CocoKeypointDetectionCategory {}
 
+impl Hash for CocoKeypointDetectionCategory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.supercategory.hash(state);
+        self.name.hash(state);
+        self.keypoints.hash(state);
+        self.skeleton.hash(state);
+    }
+}
+
 impl HasID<i32> for CocoKeypointDetectionCategory {
     fn id(&self) -> i32 {
         self.id
@@ -359,7 +741,7 @@ impl HasID<i32> for CocoKeypointDetectionCategory {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Hash)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CocoPanopticSegmentationCategory {
     pub id: i32,
     pub name: String,
@@ -367,6 +749,11 @@ pub struct CocoPanopticSegmentationCategory {
     #[serde(deserialize_with = "bool_from_int", serialize_with = "bool_to_int")]
     pub isthing: bool,
     pub color: [u8; 3],
+
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl PartialEq for CocoPanopticSegmentationCategory {
@@ -380,6 +767,15 @@ impl PartialEq for CocoPanopticSegmentationCategory {
 
 impl Eq for CocoPanopticSegmentationCategory {}
 
+impl Hash for CocoPanopticSegmentationCategory {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.supercategory.hash(state);
+        self.name.hash(state);
+        self.isthing.hash(state);
+        self.color.hash(state);
+    }
+}
+
 impl HasID<i32> for CocoPanopticSegmentationCategory {
     fn id(&self) -> i32 {
         self.id
@@ -392,6 +788,20 @@ impl HasID<i32> for CocoPanopticSegmentationCategory {
 
 // special types ///////////////////////////////////
 
+/// LVIS frequency group of a category, by the number of training images it appears in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LvisFrequency {
+    /// 1 to 10 images
+    #[serde(rename = "r")]
+    Rare,
+    /// 11 to 100 images
+    #[serde(rename = "c")]
+    Common,
+    /// more than 100 images
+    #[serde(rename = "f")]
+    Frequent,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CocoPanopticSegmentInfo {
     pub id: i64,
@@ -400,6 +810,10 @@ pub struct CocoPanopticSegmentInfo {
     pub bbox: [f32; 4],
     #[serde(deserialize_with = "bool_from_int", serialize_with = "bool_to_int")]
     pub iscrowd: bool,
+    /// fields this struct doesn't know, such as tool specific keys, kept as they are so
+    /// they survive a round trip
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl HasID<i64> for CocoPanopticSegmentInfo {
@@ -412,23 +826,217 @@ impl HasID<i64> for CocoPanopticSegmentInfo {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(untagged)]
 pub enum CocoSegmentation {
     RLE(CocoRLE),
     Polygon(Vec<CocoPolygon>),
 }
 
+impl CocoSegmentation {
+    /// A single rectangle polygon covering `[x, y, w, h]`
+    pub fn from_bbox(bbox: &[f32; 4]) -> Self {
+        let [x, y, w, h] = *bbox;
+        CocoSegmentation::Polygon(vec![vec![x, y, x + w, y, x + w, y + h, x, y + h]])
+    }
+
+    /// `[x, y, w, h]` around every polygon point or every foreground pixel of the mask, `None`
+    /// if there are no points or the mask is empty
+    pub fn bounding_box(&self) -> Option<[f32; 4]> {
+        match self {
+            CocoSegmentation::Polygon(polygons) => {
+                let mut points = polygons
+                    .iter()
+                    .flat_map(|polygon| polygon.chunks_exact(2))
+                    .peekable();
+                points.peek()?;
+                let (x0, y0, x1, y1) = points.fold(
+                    (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                    |(x0, y0, x1, y1), point| {
+                        (
+                            x0.min(point[0]),
+                            y0.min(point[1]),
+                            x1.max(point[0]),
+                            y1.max(point[1]),
+                        )
+                    },
+                );
+                Some([x0, y0, x1 - x0, y1 - y0])
+            }
+            CocoSegmentation::RLE(rle) => rle.bounding_box(),
+        }
+    }
+
+    /// Polygon area by the shoelace formula or the number of foreground pixels of the mask,
+    /// `None` if there is no polygon with at least 3 points
+    pub fn area(&self) -> Option<f32> {
+        match self {
+            CocoSegmentation::Polygon(polygons) => {
+                let polygons: Vec<&CocoPolygon> = polygons
+                    .iter()
+                    .filter(|polygon| polygon.len() >= 6)
+                    .collect();
+                if polygons.is_empty() {
+                    return None;
+                }
+                Some(polygons.iter().map(|polygon| polygon_area(polygon)).sum())
+            }
+            CocoSegmentation::RLE(rle) => Some(rle.area() as f32),
+        }
+    }
+}
+
+fn polygon_area(polygon: &[f32]) -> f32 {
+    let points: Vec<&[f32]> = polygon.chunks_exact(2).collect();
+    let twice_area: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum();
+    twice_area.abs() / 2.0
+}
+
 // Each polygon is a vector of [x1, y1, x2, y2, ..., xn, yn]
-type CocoPolygon = Vec<f32>;
+pub type CocoPolygon = Vec<f32>;
 
 // Run-length encoding for masks
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct CocoRLE {
+    /// read from either an array or the compressed string form, always written as an array
+    #[serde(deserialize_with = "rle_counts")]
     pub counts: Vec<u32>,
     pub size: (u32, u32),
 }
 
+impl CocoRLE {
+    /// Number of foreground pixels, the odd runs since counts start with background
+    pub fn area(&self) -> u64 {
+        self.counts
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|&count| u64::from(count))
+            .sum()
+    }
+
+    /// `[x, y, w, h]` around the foreground pixels, `None` for an empty mask. Masks are
+    /// stored column by column, so a run can wrap into the next column.
+    pub fn bounding_box(&self) -> Option<[f32; 4]> {
+        let height = u64::from(self.size.0);
+        if height == 0 {
+            return None;
+        }
+        let mut extent: Option<(u64, u64, u64, u64)> = None;
+        let mut start = 0u64;
+        for (i, &count) in self.counts.iter().enumerate() {
+            let count = u64::from(count);
+            if i % 2 == 1 && count > 0 {
+                let end = start + count - 1;
+                let (x0, x1) = (start / height, end / height);
+                let (y0, y1) = if x0 == x1 {
+                    (start % height, end % height)
+                } else {
+                    (0, height - 1)
+                };
+                extent = Some(match extent {
+                    Some((ex0, ey0, ex1, ey1)) => {
+                        (ex0.min(x0), ey0.min(y0), ex1.max(x1), ey1.max(y1))
+                    }
+                    None => (x0, y0, x1, y1),
+                });
+            }
+            start += count;
+        }
+        let (x0, y0, x1, y1) = extent?;
+        Some([
+            x0 as f32,
+            y0 as f32,
+            (x1 + 1 - x0) as f32,
+            (y1 + 1 - y0) as f32,
+        ])
+    }
+
+    /// Encodes `counts` in the compressed string form of the COCO mask API: every count past
+    /// the second is stored as the difference to the count two before it, in little endian
+    /// groups of 5 bits offset into printable ASCII.
+    pub fn to_compressed_string(&self) -> String {
+        let mut encoded = String::new();
+        for (i, &count) in self.counts.iter().enumerate() {
+            let mut x = i64::from(count);
+            if i > 2 {
+                x -= i64::from(self.counts[i - 2]);
+            }
+            loop {
+                let mut c = (x & 0x1f) as u8;
+                x >>= 5;
+                let more = if c & 0x10 != 0 { x != -1 } else { x != 0 };
+                if more {
+                    c |= 0x20;
+                }
+                encoded.push(char::from(c + 48));
+                if !more {
+                    break;
+                }
+            }
+        }
+        encoded
+    }
+
+    /// Decodes the compressed string form, see [`CocoRLE::to_compressed_string`]
+    pub fn decode_compressed_counts(encoded: &str) -> Result<Vec<u32>> {
+        let mut counts: Vec<u32> = Vec::new();
+        let mut bytes = encoded.bytes();
+        while bytes.len() > 0 {
+            let mut x: i64 = 0;
+            let mut shift = 0;
+            loop {
+                let Some(byte) = bytes.next() else {
+                    bail!("Compressed RLE counts {:?} end mid value", encoded);
+                };
+                if !(48..48 + 64).contains(&byte) || shift > 35 {
+                    bail!("Invalid compressed RLE counts {:?}", encoded);
+                }
+                let c = i64::from(byte - 48);
+                x |= (c & 0x1f) << shift;
+                shift += 5;
+                if c & 0x20 == 0 {
+                    if c & 0x10 != 0 {
+                        x |= -1 << shift;
+                    }
+                    break;
+                }
+            }
+            if counts.len() > 2 {
+                x += i64::from(counts[counts.len() - 2]);
+            }
+            counts.push(
+                u32::try_from(x)
+                    .with_context(|| format!("Invalid compressed RLE counts {:?}", encoded))?,
+            );
+        }
+        Ok(counts)
+    }
+}
+
+fn rle_counts<'de, D>(deserializer: D) -> Result<Vec<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Counts {
+        Uncompressed(Vec<u32>),
+        Compressed(String),
+    }
+
+    match Counts::deserialize(deserializer)? {
+        Counts::Uncompressed(counts) => Ok(counts),
+        Counts::Compressed(encoded) => {
+            CocoRLE::decode_compressed_counts(&encoded).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
 // Methods for CocoFile ///////////////////////////////////
 
 pub struct IDMapEntry<'a> {
@@ -438,38 +1046,308 @@ pub struct IDMapEntry<'a> {
     pub annotations: Vec<&'a CocoAnnotation>,
 }
 
+pub struct CategoryIDMapEntry<'a> {
+    /// category id
+    pub id: i32,
+    pub category: &'a CocoCategory,
+    pub annotations: Vec<&'a CocoAnnotation>,
+}
+
+/// Returned when building an id map from a file where several images share an id
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateImageIdError {
+    /// each duplicated id once, in ascending order
+    pub image_ids: Vec<i64>,
+}
+
+impl std::fmt::Display for DuplicateImageIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids: Vec<String> = self.image_ids.iter().map(|id| id.to_string()).collect();
+        write!(f, "duplicate image id(s) in COCO file: {}", ids.join(", "))
+    }
+}
+
+impl std::error::Error for DuplicateImageIdError {}
+
+impl std::fmt::Display for CocoFile {
+    /// A one line summary: image, annotation and category counts with annotations broken down
+    /// by type, and the range of capture dates
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut counts = [0usize; 5];
+        for annotation in &self.annotations {
+            let index = match annotation {
+                CocoAnnotation::ObjectDetection(_) => 0,
+                CocoAnnotation::KeypointDetection(_) => 1,
+                CocoAnnotation::PanopticSegmentation(_) => 2,
+                CocoAnnotation::ImageCaptioning(_) => 3,
+                CocoAnnotation::DensePose(_) => 4,
+            };
+            counts[index] += 1;
+        }
+        let types = [
+            "object detection",
+            "keypoint detection",
+            "panoptic segmentation",
+            "image captioning",
+            "densepose",
+        ];
+        let breakdown: Vec<String> = counts
+            .iter()
+            .zip(types)
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, name)| format!("{} {}", count, name))
+            .collect();
+
+        write!(
+            f,
+            "{} images, {} annotations",
+            self.images.len(),
+            self.annotations.len()
+        )?;
+        if !breakdown.is_empty() {
+            write!(f, " ({})", breakdown.join(", "))?;
+        }
+        write!(
+            f,
+            ", {} categories",
+            self.categories.iter().flatten().count()
+        )?;
+
+        let dates = self.images.iter().filter_map(|image| image.date_captured);
+        match (dates.clone().min(), dates.max()) {
+            (Some(first), Some(last)) => write!(
+                f,
+                ", captured {} to {}",
+                first.format("%Y-%m-%d"),
+                last.format("%Y-%m-%d")
+            ),
+            _ => write!(f, ", no capture dates"),
+        }
+    }
+}
+
 impl CocoFile {
-    pub fn make_image_id_map(&self) -> std::collections::HashMap<i64, IDMapEntry<'_>> {
+    /// Writes the file as JSON, indented if `pretty` is set. Compact output is considerably
+    /// smaller and faster to write for large datasets.
+    pub fn to_writer<W: Write>(&self, mut writer: W, pretty: bool) -> Result<()> {
+        if pretty {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+        } else {
+            serde_json::to_writer(&mut writer, self)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the file as JSON to `path`, see [`CocoFile::to_writer`]
+    pub fn to_path(&self, path: &Path, pretty: bool) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("Could not create {:?}", path))?;
+        self.to_writer(BufWriter::new(file), pretty)
+            .with_context(|| format!("Could not write COCO JSON to {:?}", path))
+    }
+
+    /// Iterates over the object detection annotations, skipping every other type
+    ///
+    /// ```
+    /// use cococrawl::CocoFile;
+    ///
+    /// let coco_file: CocoFile = serde_json::from_str(r#"{
+    ///     "images": [{"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"}],
+    ///     "annotations": [
+    ///         {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]],
+    ///          "area": 4.0, "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0},
+    ///         {"id": 2, "image_id": 1, "caption": "a cat"}
+    ///     ]
+    /// }"#).unwrap();
+    /// let areas: Vec<f32> = coco_file.object_detection_annotations().map(|ann| ann.area).collect();
+    /// assert_eq!(areas, vec![4.0]);
+    /// ```
+    pub fn object_detection_annotations(
+        &self,
+    ) -> impl Iterator<Item = &CocoObjectDetectionAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(CocoAnnotation::as_object_detection)
+    }
+
+    /// Mutable version of [`CocoFile::object_detection_annotations`]
+    pub fn object_detection_annotations_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut CocoObjectDetectionAnnotation> {
+        self.annotations
+            .iter_mut()
+            .filter_map(CocoAnnotation::as_object_detection_mut)
+    }
+
+    /// Iterates over the keypoint detection annotations, see [`CocoFile::object_detection_annotations`]
+    pub fn keypoint_detection_annotations(
+        &self,
+    ) -> impl Iterator<Item = &CocoKeypointDetectionAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(CocoAnnotation::as_keypoint_detection)
+    }
+
+    /// Mutable version of [`CocoFile::keypoint_detection_annotations`]
+    pub fn keypoint_detection_annotations_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut CocoKeypointDetectionAnnotation> {
+        self.annotations
+            .iter_mut()
+            .filter_map(CocoAnnotation::as_keypoint_detection_mut)
+    }
+
+    /// Iterates over the panoptic segmentation annotations, see [`CocoFile::object_detection_annotations`]
+    pub fn panoptic_annotations(
+        &self,
+    ) -> impl Iterator<Item = &CocoPanopticSegmentationAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(CocoAnnotation::as_panoptic)
+    }
+
+    /// Mutable version of [`CocoFile::panoptic_annotations`]
+    pub fn panoptic_annotations_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut CocoPanopticSegmentationAnnotation> {
+        self.annotations
+            .iter_mut()
+            .filter_map(CocoAnnotation::as_panoptic_mut)
+    }
+
+    /// Iterates over the image captioning annotations, see [`CocoFile::object_detection_annotations`]
+    pub fn captioning_annotations(&self) -> impl Iterator<Item = &CocoImageCaptioningAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(CocoAnnotation::as_captioning)
+    }
+
+    /// Mutable version of [`CocoFile::captioning_annotations`]
+    pub fn captioning_annotations_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut CocoImageCaptioningAnnotation> {
+        self.annotations
+            .iter_mut()
+            .filter_map(CocoAnnotation::as_captioning_mut)
+    }
+
+    /// Iterates over the DensePose annotations, see [`CocoFile::object_detection_annotations`]
+    pub fn densepose_annotations(&self) -> impl Iterator<Item = &CocoDensePoseAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(CocoAnnotation::as_densepose)
+    }
+
+    /// Mutable version of [`CocoFile::densepose_annotations`]
+    pub fn densepose_annotations_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut CocoDensePoseAnnotation> {
+        self.annotations
+            .iter_mut()
+            .filter_map(CocoAnnotation::as_densepose_mut)
+    }
+
+    /// Maps image ids to their image and annotations. Fails if several images share an id,
+    /// since their annotations couldn't be told apart. Annotations of missing images aren't in
+    /// the map, see [`CocoFile::dangling_annotations`]. Built in parallel with progress bars
+    /// with the `rayon` feature, sequentially without.
+    pub fn make_image_id_map(&self) -> Result<HashMap<i64, IDMapEntry<'_>>, DuplicateImageIdError> {
+        self.build_image_id_map(false)
+    }
+
+    /// Owned counterpart of [`CocoFile::make_image_id_map`], consuming the file. Annotations
+    /// keep their order within each image, annotations of missing images are dropped.
+    pub fn group_by_image(
+        self,
+    ) -> Result<HashMap<i64, (CocoImage, Vec<CocoAnnotation>)>, DuplicateImageIdError> {
+        let mut groups: HashMap<i64, (CocoImage, Vec<CocoAnnotation>)> =
+            HashMap::with_capacity(self.images.len());
+        let mut duplicate_ids = Vec::new();
+        for image in self.images {
+            let id = image.id;
+            if groups.insert(id, (image, Vec::new())).is_some() {
+                duplicate_ids.push(id);
+            }
+        }
+        if !duplicate_ids.is_empty() {
+            duplicate_ids.sort();
+            duplicate_ids.dedup();
+            return Err(DuplicateImageIdError {
+                image_ids: duplicate_ids,
+            });
+        }
+
+        for annotation in self.annotations {
+            if let Some((_, annotations)) = groups.get_mut(&annotation.image_id()) {
+                annotations.push(annotation);
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Same as [`CocoFile::make_image_id_map`] without drawing progress bars
+    pub fn make_image_id_map_quiet(
+        &self,
+    ) -> Result<HashMap<i64, IDMapEntry<'_>>, DuplicateImageIdError> {
+        self.build_image_id_map(true)
+    }
+
+    fn build_image_id_map(
+        &self,
+        quiet: bool,
+    ) -> Result<HashMap<i64, IDMapEntry<'_>>, DuplicateImageIdError> {
+        let mut seen_ids = std::collections::HashSet::new();
+        let mut duplicate_ids: Vec<i64> = self
+            .images
+            .iter()
+            .filter(|im| !seen_ids.insert(im.id))
+            .map(|im| im.id)
+            .collect();
+        if !duplicate_ids.is_empty() {
+            duplicate_ids.sort();
+            duplicate_ids.dedup();
+            return Err(DuplicateImageIdError {
+                image_ids: duplicate_ids,
+            });
+        }
+
+        #[cfg(feature = "rayon")]
+        let image_id_map = self.parallel_image_id_map(quiet);
+        #[cfg(not(feature = "rayon"))]
+        let image_id_map = {
+            // there are no progress bars to hide without rayon
+            let _ = quiet;
+            self.sequential_image_id_map()
+        };
+        Ok(image_id_map)
+    }
+
+    #[cfg(feature = "rayon")]
+    fn parallel_image_id_map(&self, quiet: bool) -> HashMap<i64, IDMapEntry<'_>> {
         let image_map: HashMap<i64, &CocoImage> = self
             .images
             .par_iter()
-            .progress()
+            .progress_with(progress_bar(self.images.len() as u64, quiet))
             .map(|im| (im.id, im))
             .collect();
         let annotation_map: HashMap<i64, Vec<&CocoAnnotation>> = self
             .annotations
             .par_iter()
-            .progress()
-            .fold(
-                || HashMap::new(),
-                |mut acc, ann| {
-                    acc.entry(ann.image_id()).or_insert_with(Vec::new).push(ann);
-                    acc
-                },
-            )
-            .reduce(
-                || HashMap::new(),
-                |mut acc, map| {
-                    map.into_iter().for_each(|(k, v)| {
-                        acc.entry(k).or_insert_with(Vec::new).extend(v);
-                    });
-                    acc
-                },
-            );
+            .progress_with(progress_bar(self.annotations.len() as u64, quiet))
+            .fold(HashMap::new, |mut acc, ann| {
+                acc.entry(ann.image_id()).or_insert_with(Vec::new).push(ann);
+                acc
+            })
+            .reduce(HashMap::new, |mut acc, map| {
+                map.into_iter().for_each(|(k, v)| {
+                    acc.entry(k).or_insert_with(Vec::new).extend(v);
+                });
+                acc
+            });
 
         image_map
             .par_iter()
-            .progress_count(image_map.len() as u64)
+            .progress_with(progress_bar(image_map.len() as u64, quiet))
             .map(|(&id, &image)| {
                 (
                     id,
@@ -482,23 +1360,277 @@ impl CocoFile {
             })
             .collect()
     }
+
+    /// [`CocoFile::parallel_image_id_map`] without rayon, which draws no progress bars. Compiled
+    /// either way so the tests can hold it against the parallel version.
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn sequential_image_id_map(&self) -> HashMap<i64, IDMapEntry<'_>> {
+        let mut annotation_map: HashMap<i64, Vec<&CocoAnnotation>> = HashMap::new();
+        for annotation in &self.annotations {
+            annotation_map
+                .entry(annotation.image_id())
+                .or_default()
+                .push(annotation);
+        }
+
+        self.images
+            .iter()
+            .map(|image| {
+                let entry = IDMapEntry {
+                    id: image.id,
+                    image,
+                    annotations: annotation_map.remove(&image.id).unwrap_or_default(),
+                };
+                (image.id, entry)
+            })
+            .collect()
+    }
+
+    /// Maps category ids to their category and the annotations referring to it. Panoptic
+    /// annotations are listed once under each category of their segments. Annotations of
+    /// missing categories aren't in the map.
+    pub fn make_category_id_map<'a>(&'a self) -> HashMap<i32, CategoryIDMapEntry<'a>> {
+        let add = |mut acc: HashMap<i32, Vec<&'a CocoAnnotation>>, ann: &'a CocoAnnotation| {
+            let mut category_ids = ann.category_ids();
+            category_ids.sort();
+            category_ids.dedup();
+            for category_id in category_ids {
+                acc.entry(category_id).or_default().push(ann);
+            }
+            acc
+        };
+        #[cfg(feature = "rayon")]
+        let annotation_map: HashMap<i32, Vec<&CocoAnnotation>> = self
+            .annotations
+            .par_iter()
+            .fold(HashMap::new, add)
+            .reduce(HashMap::new, |mut acc, map| {
+                map.into_iter().for_each(|(k, v)| {
+                    acc.entry(k).or_default().extend(v);
+                });
+                acc
+            });
+        #[cfg(not(feature = "rayon"))]
+        let annotation_map = self.annotations.iter().fold(HashMap::new(), add);
+
+        self.categories
+            .iter()
+            .flatten()
+            .map(|category| {
+                let id = category.id();
+                let entry = CategoryIDMapEntry {
+                    id,
+                    category,
+                    annotations: annotation_map.get(&id).cloned().unwrap_or_default(),
+                };
+                (id, entry)
+            })
+            .collect()
+    }
+
+    /// Maps annotation ids to their annotations. Panoptic annotations have no id of their own
+    /// and are left out.
+    pub fn make_annotation_id_map(&self) -> HashMap<i64, &CocoAnnotation> {
+        #[cfg(feature = "rayon")]
+        let annotations = self.annotations.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let annotations = self.annotations.iter();
+        annotations
+            .filter_map(|ann| ann.id().map(|id| (id, ann)))
+            .collect()
+    }
+
+    /// Annotations whose image id isn't in `image_id_map`, i.e. the ones
+    /// [`CocoFile::make_image_id_map`] leaves out
+    pub fn dangling_annotations(
+        &self,
+        image_id_map: &HashMap<i64, IDMapEntry<'_>>,
+    ) -> Vec<&CocoAnnotation> {
+        self.annotations
+            .iter()
+            .filter(|annotation| !image_id_map.contains_key(&annotation.image_id()))
+            .collect()
+    }
+
+    /// Removes images whose file doesn't exist, resolving relative file names against
+    /// `base_dir`, together with their annotations. Returns the number of images removed.
+    pub fn remove_missing_images(&mut self, base_dir: &Path) -> usize {
+        #[cfg(feature = "rayon")]
+        let images = self.images.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let images = self.images.iter();
+        let missing_ids: std::collections::HashSet<i64> = images
+            .filter(|image| !base_dir.join(&image.file_name).exists())
+            .map(|image| image.id)
+            .collect();
+        if missing_ids.is_empty() {
+            return 0;
+        }
+
+        let image_count = self.images.len();
+        self.images.retain(|image| !missing_ids.contains(&image.id));
+        self.annotations
+            .retain(|annotation| !missing_ids.contains(&annotation.image_id()));
+        image_count - self.images.len()
+    }
+
+    /// Sets the `area` of object detection, keypoint and DensePose annotations from their
+    /// segmentation, or from the bbox when there is no segmentation shape. DensePose
+    /// annotations have no segmentation and always use the rounded bbox area.
+    pub fn recompute_areas(&mut self) {
+        fn segmentation_area(segmentation: &CocoSegmentation, bbox: &[f32; 4]) -> f32 {
+            segmentation.area().unwrap_or(bbox[2] * bbox[3])
+        }
+
+        #[cfg(feature = "rayon")]
+        let annotations = self.annotations.par_iter_mut();
+        #[cfg(not(feature = "rayon"))]
+        let annotations = self.annotations.iter_mut();
+        annotations.for_each(|annotation| match annotation {
+            CocoAnnotation::ObjectDetection(ann) => {
+                ann.area = segmentation_area(&ann.segmentation, &ann.bbox);
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                ann.area = segmentation_area(&ann.segmentation, &ann.bbox);
+            }
+            CocoAnnotation::DensePose(ann) => {
+                ann.area = (ann.bbox[2] * ann.bbox[3]).round().max(0.0) as u32;
+            }
+            CocoAnnotation::PanopticSegmentation(_) | CocoAnnotation::ImageCaptioning(_) => {}
+        });
+    }
+
+    /// Removes annotations whose score is below `min`, keeping those without a score such as
+    /// ground truth mixed into a pseudo-labeled dataset. Returns the number of annotations
+    /// removed.
+    pub fn filter_by_score(&mut self, min: f32) -> usize {
+        let annotation_count = self.annotations.len();
+        self.annotations
+            .retain(|annotation| annotation.score().is_none_or(|score| score >= min));
+        annotation_count - self.annotations.len()
+    }
+
+    /// Recounts `image_count` and `instance_count` of the categories that have them, as in LVIS,
+    /// from the annotations, e.g. after the images have been split up
+    pub fn recount_lvis_categories(&mut self) {
+        let mut counts: HashMap<i32, (std::collections::HashSet<i64>, u64)> = HashMap::new();
+        for annotation in &self.annotations {
+            for category_id in annotation.category_ids() {
+                let (images, instances) = counts.entry(category_id).or_default();
+                images.insert(annotation.image_id());
+                *instances += 1;
+            }
+        }
+
+        for category in self.categories.iter_mut().flatten() {
+            let CocoCategory::ObjectDetection(category) = category else {
+                continue;
+            };
+            let (images, instances) = counts
+                .get(&category.id)
+                .map_or((0, 0), |(images, instances)| {
+                    (images.len() as u64, *instances)
+                });
+            if category.image_count.is_some() {
+                category.image_count = Some(images);
+            }
+            if category.instance_count.is_some() {
+                category.instance_count = Some(instances);
+            }
+        }
+    }
+
+    /// Drops the score of every annotation, turning predictions into plain ground truth
+    pub fn strip_scores(&mut self) {
+        self.annotations
+            .iter_mut()
+            .for_each(|annotation| match annotation {
+                CocoAnnotation::ObjectDetection(ann) => ann.set_score(None),
+                CocoAnnotation::KeypointDetection(ann) => ann.set_score(None),
+                CocoAnnotation::DensePose(ann) => ann.set_score(None),
+                CocoAnnotation::PanopticSegmentation(_) | CocoAnnotation::ImageCaptioning(_) => {}
+            });
+    }
+
+    /// Maps category ids to their categories, empty if the file has no categories
+    pub fn make_category_map(&self) -> HashMap<i32, &CocoCategory> {
+        self.categories
+            .iter()
+            .flatten()
+            .map(|category| (category.id(), category))
+            .collect()
+    }
+
+    /// Resolves category names, or ids when `by_id` is set, to category ids. Fails on selectors
+    /// that don't match any category.
+    pub fn find_category_ids<S: AsRef<str>>(
+        &self,
+        selectors: &[S],
+        by_id: bool,
+    ) -> Result<std::collections::HashSet<i32>> {
+        let category_map = self.make_category_map();
+        selectors
+            .iter()
+            .map(|selector| {
+                let selector = selector.as_ref();
+                if by_id {
+                    let id: i32 = selector
+                        .parse()
+                        .with_context(|| format!("Invalid category id {:?}", selector))?;
+                    if !category_map.contains_key(&id) {
+                        bail!("No category with id {}", id);
+                    }
+                    Ok(id)
+                } else {
+                    category_map
+                        .values()
+                        .find(|category| category.name() == selector)
+                        .map(|category| category.id())
+                        .with_context(|| format!("No category named {:?}", selector))
+                }
+            })
+            .collect()
+    }
+}
+
+/// COCO stores flags as 0 or 1, some exporters write JSON booleans instead
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntOrBool {
+    Int(i64),
+    Bool(bool),
 }
 
 fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let v = i64::deserialize(deserializer)?;
-    match v {
-        0 => Ok(false),
-        1 => Ok(true),
-        _ => Err(serde::de::Error::custom(format!(
+    match IntOrBool::deserialize(deserializer)? {
+        IntOrBool::Bool(b) => Ok(b),
+        IntOrBool::Int(0) => Ok(false),
+        IntOrBool::Int(1) => Ok(true),
+        IntOrBool::Int(v) => Err(serde::de::Error::custom(format!(
             "invalid bool value: {}",
             v
         ))),
     }
 }
 
+fn date_from_coco<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(date) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    if let Ok(date) = DateTime::parse_from_rfc3339(&date) {
+        return Ok(Some(date.with_timezone(&Utc)));
+    }
+    NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M:%S")
+        .map(|date| Some(date.and_utc()))
+        .map_err(|_| serde::de::Error::custom(format!("invalid date: {:?}", date)))
+}
+
 fn bool_to_int<S>(b: &bool, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -518,6 +1650,12 @@ pub trait HasCategoryID {
     fn set_category_id(&mut self, new_category_id: i32);
 }
 
+/// Annotations that can carry the confidence of a prediction
+pub trait HasScore {
+    fn score(&self) -> Option<f32>;
+    fn set_score(&mut self, new_score: Option<f32>);
+}
+
 // Tests ///////////////////////////////////
 
 #[cfg(test)]
@@ -525,6 +1663,43 @@ mod tests {
     use super::*;
     use chrono::Utc;
 
+    #[test]
+    fn test_coco_file_display() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.jpg",
+                 "date_captured": "2021-06-01T12:00:00Z"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "b.jpg",
+                 "date_captured": "2020-01-01T00:00:00Z"},
+                {"id": 3, "width": 10, "height": 10, "file_name": "c.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 4.0, "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 4.0, "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0},
+                {"id": 3, "image_id": 2, "caption": "a cat"}
+            ],
+            "categories": [{"id": 1, "name": "cat", "supercategory": "animal"}]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            coco_file.to_string(),
+            "3 images, 3 annotations (2 object detection, 1 image captioning), 1 categories, \
+             captured 2020-01-01 to 2021-06-01"
+        );
+
+        coco_file
+            .images
+            .iter_mut()
+            .for_each(|image| image.date_captured = None);
+        coco_file.annotations.clear();
+        assert_eq!(
+            coco_file.to_string(),
+            "3 images, 0 annotations, 1 categories, no capture dates"
+        );
+    }
+
     #[test]
     fn test_coco_info_serde() {
         let json = r#"{
@@ -638,13 +1813,82 @@ mod tests {
             CocoCategory::PanopticSegmentation(cat) => {
                 assert_eq!(cat.id, 1);
                 assert_eq!(cat.name, "person");
-                assert_eq!(cat.isthing, true);
+                assert!(cat.isthing);
                 assert_eq!(cat.color, [255, 128, 0]);
             }
             _ => panic!("Expected PanopticSegmentation category"),
         }
     }
 
+    #[test]
+    fn test_get_absolute_path_relative_file_name() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("images")).unwrap();
+        std::fs::create_dir(root.join("data")).unwrap();
+        std::fs::write(root.join("images/a.jpg"), b"").unwrap();
+        let image = |file_name: &str| CocoImage {
+            id: 1,
+            width: 1,
+            height: 1,
+            file_name: PathBuf::from(file_name),
+            license: None,
+            flickr_url: None,
+            coco_url: None,
+            date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
+        };
+        let dataset = root.join("data/../coco.json");
+
+        assert_eq!(
+            image("images/./a.jpg").get_absolute_path(&dataset).unwrap(),
+            root.join("images/a.jpg")
+        );
+        // missing files are resolved against the dataset directory without canonicalizing
+        let missing = image("images/missing.jpg")
+            .get_absolute_path(&dataset)
+            .unwrap();
+        assert!(missing.is_absolute());
+        assert_eq!(missing, root.join("data/../images/missing.jpg"));
+    }
+
+    #[test]
+    fn test_get_absolute_path_absolute_file_name() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.jpg"), b"").unwrap();
+        let mut image = CocoImage {
+            id: 1,
+            width: 1,
+            height: 1,
+            file_name: root.join("a.jpg"),
+            license: None,
+            flickr_url: None,
+            coco_url: None,
+            date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
+        };
+
+        // the dataset location doesn't matter for absolute file names
+        let dataset = Path::new("elsewhere/coco.json");
+        assert_eq!(
+            image.get_absolute_path(dataset).unwrap(),
+            root.join("a.jpg")
+        );
+        image.file_name = root.join("sub/../a.jpg");
+        assert_eq!(
+            image.get_absolute_path(dataset).unwrap(),
+            root.join("a.jpg")
+        );
+    }
+
     #[test]
     fn test_object_detection_annotation() {
         let json = r#"{
@@ -665,7 +1909,7 @@ mod tests {
                 assert_eq!(ann.category_id, 1);
                 assert_eq!(ann.area, 100.0);
                 assert_eq!(ann.bbox, [10.0, 10.0, 10.0, 10.0]);
-                assert_eq!(ann.iscrowd, false);
+                assert!(!ann.iscrowd);
             }
             _ => panic!("Expected ObjectDetection annotation"),
         }
@@ -791,6 +2035,104 @@ mod tests {
         assert_eq!(rle.size, (640, 480));
     }
 
+    #[test]
+    fn test_coco_rle_compressed_counts() {
+        // pycocotools: mask.encode of a 4x4 mask with the middle 2x2 set
+        let json = r#"{"counts": "52203", "size": [4, 4]}"#;
+        let rle: CocoRLE = serde_json::from_str(json).unwrap();
+        assert_eq!(rle.counts, vec![5, 2, 2, 2, 5]);
+        assert_eq!(rle.to_compressed_string(), "52203");
+
+        // multi byte values and negative differences
+        let rle = CocoRLE {
+            counts: vec![100, 1, 1, 10, 1, 70000],
+            size: (300, 300),
+        };
+        let encoded = rle.to_compressed_string();
+        assert_eq!(&encoded[..2], "T3");
+        assert_eq!(
+            CocoRLE::decode_compressed_counts(&encoded).unwrap(),
+            rle.counts
+        );
+
+        let serialized = serde_json::to_value(&rle).unwrap();
+        assert_eq!(
+            serialized["counts"],
+            serde_json::json!([100, 1, 1, 10, 1, 70000])
+        );
+
+        assert!(CocoRLE::decode_compressed_counts("T").is_err());
+        assert!(CocoRLE::decode_compressed_counts("0 1").is_err());
+        assert!(serde_json::from_str::<CocoRLE>(r#"{"counts": "G", "size": [1, 1]}"#).is_err());
+    }
+
+    #[test]
+    fn test_segmentation_bounding_box_and_area() {
+        let square =
+            CocoSegmentation::Polygon(vec![vec![10.0, 20.0, 20.0, 20.0, 20.0, 30.0, 10.0, 30.0]]);
+        assert_eq!(square.bounding_box(), Some([10.0, 20.0, 10.0, 10.0]));
+        assert_eq!(square.area(), Some(100.0));
+
+        let empty = CocoSegmentation::Polygon(vec![vec![]]);
+        assert_eq!(empty.bounding_box(), None);
+        assert_eq!(empty.area(), None);
+
+        // 4x4 mask with the middle 2x2 set
+        let rle = CocoSegmentation::RLE(CocoRLE {
+            counts: vec![5, 2, 2, 2, 5],
+            size: (4, 4),
+        });
+        assert_eq!(rle.bounding_box(), Some([1.0, 1.0, 2.0, 2.0]));
+        assert_eq!(rle.area(), Some(4.0));
+
+        // a run from the bottom of column 0 into the top of column 1
+        let wrapping = CocoRLE {
+            counts: vec![3, 2, 11],
+            size: (4, 4),
+        };
+        assert_eq!(wrapping.bounding_box(), Some([0.0, 0.0, 2.0, 4.0]));
+        assert_eq!(wrapping.area(), 2);
+
+        let blank = CocoRLE {
+            counts: vec![16],
+            size: (4, 4),
+        };
+        assert_eq!(blank.bounding_box(), None);
+        assert_eq!(blank.area(), 0);
+    }
+
+    #[test]
+    fn test_coco_rle_equality_and_default() {
+        let rle = CocoRLE {
+            counts: vec![5, 2, 2, 2, 5],
+            size: (4, 4),
+        };
+        let compressed: CocoRLE =
+            serde_json::from_str(r#"{"counts": "52203", "size": [4, 4]}"#).unwrap();
+        assert_eq!(rle, compressed);
+        assert_ne!(
+            rle,
+            CocoRLE {
+                counts: vec![5, 2, 2, 2, 5],
+                size: (2, 8),
+            }
+        );
+        assert_eq!(
+            CocoSegmentation::RLE(rle.clone()),
+            CocoSegmentation::RLE(compressed)
+        );
+        assert_ne!(
+            CocoSegmentation::RLE(rle),
+            CocoSegmentation::Polygon(vec![])
+        );
+
+        let default = CocoRLE::default();
+        assert!(default.counts.is_empty());
+        assert_eq!(default.size, (0, 0));
+        assert_eq!(default.area(), 0);
+        assert_eq!(default.bounding_box(), None);
+    }
+
     #[test]
     fn test_panoptic_segment_info() {
         let json = r#"{
@@ -805,7 +2147,7 @@ mod tests {
         assert_eq!(segment.id, 1);
         assert_eq!(segment.category_id, 5);
         assert_eq!(segment.area, 1500);
-        assert_eq!(segment.iscrowd, true);
+        assert!(segment.iscrowd);
     }
 
     #[test]
@@ -818,7 +2160,7 @@ mod tests {
             iscrowd: bool,
         }
         let test: Test = serde_json::from_str(json).unwrap();
-        assert_eq!(test.iscrowd, false);
+        assert!(!test.iscrowd);
     }
 
     #[test]
@@ -831,7 +2173,30 @@ mod tests {
             iscrowd: bool,
         }
         let test: Test = serde_json::from_str(json).unwrap();
-        assert_eq!(test.iscrowd, true);
+        assert!(test.iscrowd);
+    }
+
+    #[test]
+    fn test_bool_from_int_booleans() {
+        #[derive(Deserialize)]
+        struct Test {
+            #[serde(deserialize_with = "bool_from_int")]
+            #[allow(dead_code)]
+            iscrowd: bool,
+        }
+        let test: Test = serde_json::from_str(r#"{"iscrowd": false}"#).unwrap();
+        assert!(!test.iscrowd);
+        let test: Test = serde_json::from_str(r#"{"iscrowd": true}"#).unwrap();
+        assert!(test.iscrowd);
+        assert!(serde_json::from_str::<Test>(r#"{"iscrowd": "true"}"#).is_err());
+
+        // and written back as an integer
+        let json = r#"{"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+            "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": true}"#;
+        let annotation: CocoAnnotation = serde_json::from_str(json).unwrap();
+        assert!(annotation.as_object_detection().unwrap().iscrowd);
+        let serialized = serde_json::to_string(&annotation).unwrap();
+        assert!(serialized.contains("\"iscrowd\":1"));
     }
 
     #[test]
@@ -923,6 +2288,9 @@ mod tests {
             area: 1.0,
             bbox: [0.0, 0.0, 1.0, 1.0],
             iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         });
         assert_eq!(obj_det.image_id(), 42);
 
@@ -930,6 +2298,7 @@ mod tests {
             id: 2,
             image_id: 99,
             caption: "test".to_string(),
+            extra: Default::default(),
         });
         assert_eq!(captioning.image_id(), 99);
     }
@@ -944,6 +2313,7 @@ mod tests {
                 contributor: "Test".to_string(),
                 url: "http://test.com".to_string(),
                 date_created: Utc::now(),
+                extra: Default::default(),
             }),
             licenses: Some(vec![]),
             images: vec![
@@ -956,6 +2326,10 @@ mod tests {
                     flickr_url: Some("".to_string()),
                     coco_url: Some("".to_string()),
                     date_captured: Some(Utc::now()),
+                    sha256: None,
+                    neg_category_ids: None,
+                    not_exhaustive_category_ids: None,
+                    extra: Default::default(),
                 },
                 CocoImage {
                     id: 2,
@@ -966,6 +2340,10 @@ mod tests {
                     flickr_url: Some("".to_string()),
                     coco_url: Some("".to_string()),
                     date_captured: Some(Utc::now()),
+                    sha256: None,
+                    neg_category_ids: None,
+                    not_exhaustive_category_ids: None,
+                    extra: Default::default(),
                 },
             ],
             annotations: vec![
@@ -973,22 +2351,25 @@ mod tests {
                     id: 1,
                     image_id: 1,
                     caption: "First image".to_string(),
+                    extra: Default::default(),
                 }),
                 CocoAnnotation::ImageCaptioning(CocoImageCaptioningAnnotation {
                     id: 2,
                     image_id: 1,
                     caption: "First image alt".to_string(),
+                    extra: Default::default(),
                 }),
                 CocoAnnotation::ImageCaptioning(CocoImageCaptioningAnnotation {
                     id: 3,
                     image_id: 2,
                     caption: "Second image".to_string(),
+                    extra: Default::default(),
                 }),
             ],
             categories: None,
         };
 
-        let id_map = coco_file.make_image_id_map();
+        let id_map = coco_file.make_image_id_map().unwrap();
 
         assert_eq!(id_map.len(), 2);
 
@@ -1053,7 +2434,7 @@ mod tests {
                 assert_eq!(ann.id, 1);
                 assert_eq!(ann.image_id, 42);
                 assert_eq!(ann.category_id, 1);
-                assert_eq!(ann.iscrowd, false);
+                assert!(!ann.iscrowd);
                 assert_eq!(ann.area, 1500);
                 assert_eq!(ann.bbox, [100.0, 100.0, 50.0, 30.0]);
                 assert_eq!(ann.dp_i, vec![1.0, 2.0, 3.0]);
@@ -1086,6 +2467,8 @@ mod tests {
                 counts: vec![10, 20, 30],
                 size: (100, 200),
             }],
+            score: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1094,7 +2477,7 @@ mod tests {
         assert_eq!(deserialized.id, 123);
         assert_eq!(deserialized.image_id, 456);
         assert_eq!(deserialized.category_id, 7);
-        assert_eq!(deserialized.iscrowd, true);
+        assert!(deserialized.iscrowd);
         assert_eq!(deserialized.dp_i, vec![1.0, 2.0]);
         assert_eq!(deserialized.dp_masks[0].counts, vec![10, 20, 30]);
     }
@@ -1114,6 +2497,8 @@ mod tests {
             dp_x: vec![],
             dp_y: vec![],
             dp_masks: vec![],
+            score: None,
+            extra: Default::default(),
         };
 
         assert_eq!(ann.id(), 100);
@@ -1136,6 +2521,8 @@ mod tests {
             dp_x: vec![],
             dp_y: vec![],
             dp_masks: vec![],
+            score: None,
+            extra: Default::default(),
         };
 
         assert_eq!(ann.category_id(), 5);
@@ -1158,6 +2545,8 @@ mod tests {
             dp_x: vec![],
             dp_y: vec![],
             dp_masks: vec![],
+            score: None,
+            extra: Default::default(),
         });
 
         assert_eq!(ann.image_id(), 50);
@@ -1178,6 +2567,10 @@ mod tests {
             flickr_url: None,
             coco_url: None,
             date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
         };
 
         assert_eq!(image.id(), 10);
@@ -1192,6 +2585,7 @@ mod tests {
             id: 1,
             name: "MIT".to_string(),
             url: "http://mit.edu".to_string(),
+            extra: Default::default(),
         };
 
         assert_eq!(license.id(), 1);
@@ -1210,6 +2604,9 @@ mod tests {
             area: 100.0,
             bbox: [0.0, 0.0, 10.0, 10.0],
             iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         assert_eq!(ann.id(), 1);
@@ -1233,6 +2630,9 @@ mod tests {
             iscrowd: false,
             keypoints: vec![1.0, 2.0, 3.0],
             num_keypoints: 1,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         assert_eq!(ann.id(), 2);
@@ -1250,6 +2650,7 @@ mod tests {
             id: 5,
             image_id: 50,
             caption: "Test caption".to_string(),
+            extra: Default::default(),
         };
 
         assert_eq!(ann.id(), 5);
@@ -1265,6 +2666,7 @@ mod tests {
             area: 100,
             bbox: [0.0, 0.0, 10.0, 10.0],
             iscrowd: false,
+            extra: Default::default(),
         };
 
         assert_eq!(segment.id(), 10);
@@ -1278,6 +2680,10 @@ mod tests {
             id: 1,
             name: "person".to_string(),
             supercategory: "human".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         });
 
         assert_eq!(cat.id(), 1);
@@ -1291,6 +2697,10 @@ mod tests {
             id: 1,
             name: "car".to_string(),
             supercategory: "vehicle".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         };
 
         assert_eq!(cat.id(), 1);
@@ -1306,6 +2716,7 @@ mod tests {
             supercategory: "human".to_string(),
             keypoints: vec!["nose".to_string()],
             skeleton: vec![[0, 1]],
+            extra: Default::default(),
         };
 
         assert_eq!(cat.id(), 2);
@@ -1321,6 +2732,7 @@ mod tests {
             supercategory: "background".to_string(),
             isthing: false,
             color: [135, 206, 235],
+            extra: Default::default(),
         };
 
         assert_eq!(cat.id(), 3);
@@ -1340,6 +2752,9 @@ mod tests {
             area: 1.0,
             bbox: [0.0, 0.0, 1.0, 1.0],
             iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         });
         obj_det.set_image_id(100);
         assert_eq!(obj_det.image_id(), 100);
@@ -1354,17 +2769,20 @@ mod tests {
             iscrowd: false,
             keypoints: vec![],
             num_keypoints: 0,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         });
         kp_det.set_image_id(200);
         assert_eq!(kp_det.image_id(), 200);
 
-        let mut panoptic = CocoAnnotation::PanopticSegmentation(
-            CocoPanopticSegmentationAnnotation {
+        let mut panoptic =
+            CocoAnnotation::PanopticSegmentation(CocoPanopticSegmentationAnnotation {
                 image_id: 30,
                 file_name: PathBuf::from("test.png"),
                 segments_info: vec![],
-            },
-        );
+                extra: Default::default(),
+            });
         panoptic.set_image_id(300);
         assert_eq!(panoptic.image_id(), 300);
 
@@ -1372,6 +2790,7 @@ mod tests {
             id: 4,
             image_id: 40,
             caption: "test".to_string(),
+            extra: Default::default(),
         });
         caption.set_image_id(400);
         assert_eq!(caption.image_id(), 400);
@@ -1389,6 +2808,8 @@ mod tests {
             dp_x: vec![],
             dp_y: vec![],
             dp_masks: vec![],
+            score: None,
+            extra: Default::default(),
         });
         densepose.set_image_id(500);
         assert_eq!(densepose.image_id(), 500);
@@ -1402,16 +2823,19 @@ mod tests {
             id: 1,
             name: "MIT".to_string(),
             url: "http://mit.edu".to_string(),
+            extra: Default::default(),
         };
         let license2 = CocoLicense {
             id: 999, // Different ID
             name: "MIT".to_string(),
             url: "http://mit.edu".to_string(),
+            extra: Default::default(),
         };
         let license3 = CocoLicense {
             id: 1,
             name: "Apache".to_string(),
             url: "http://apache.org".to_string(),
+            extra: Default::default(),
         };
 
         // Same name and URL, different ID -> should be equal
@@ -1426,16 +2850,28 @@ mod tests {
             id: 1,
             name: "person".to_string(),
             supercategory: "human".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         };
         let cat2 = CocoObjectDetectionCategory {
             id: 999, // Different ID
             name: "person".to_string(),
             supercategory: "human".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         };
         let cat3 = CocoObjectDetectionCategory {
             id: 1,
             name: "car".to_string(),
             supercategory: "vehicle".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         };
 
         assert!(cat1 == cat2);
@@ -1450,6 +2886,7 @@ mod tests {
             supercategory: "human".to_string(),
             keypoints: vec!["nose".to_string(), "eye".to_string()],
             skeleton: vec![[0, 1], [1, 2]],
+            extra: Default::default(),
         };
         let cat2 = CocoKeypointDetectionCategory {
             id: 999,
@@ -1457,6 +2894,7 @@ mod tests {
             supercategory: "human".to_string(),
             keypoints: vec!["nose".to_string(), "eye".to_string()],
             skeleton: vec![[0, 1], [1, 2]],
+            extra: Default::default(),
         };
         let cat3 = CocoKeypointDetectionCategory {
             id: 1,
@@ -1464,6 +2902,7 @@ mod tests {
             supercategory: "human".to_string(),
             keypoints: vec!["nose".to_string()], // Different keypoints
             skeleton: vec![[0, 1], [1, 2]],
+            extra: Default::default(),
         };
 
         assert!(cat1 == cat2);
@@ -1478,6 +2917,7 @@ mod tests {
             supercategory: "background".to_string(),
             isthing: false,
             color: [135, 206, 235],
+            extra: Default::default(),
         };
         let cat2 = CocoPanopticSegmentationCategory {
             id: 999,
@@ -1485,6 +2925,7 @@ mod tests {
             supercategory: "background".to_string(),
             isthing: false,
             color: [135, 206, 235],
+            extra: Default::default(),
         };
         let cat3 = CocoPanopticSegmentationCategory {
             id: 1,
@@ -1492,6 +2933,7 @@ mod tests {
             supercategory: "background".to_string(),
             isthing: true, // Different isthing
             color: [135, 206, 235],
+            extra: Default::default(),
         };
 
         assert!(cat1 == cat2);
@@ -1527,6 +2969,10 @@ mod tests {
             flickr_url: None,
             coco_url: None,
             date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&image).unwrap();
@@ -1547,6 +2993,10 @@ mod tests {
             flickr_url: Some("http://flickr.com".to_string()),
             coco_url: Some("http://coco.com".to_string()),
             date_captured: Some(Utc::now()),
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&image).unwrap();
@@ -1568,11 +3018,13 @@ mod tests {
                 contributor: "Tester".to_string(),
                 url: "http://test.com".to_string(),
                 date_created: Utc::now(),
+                extra: Default::default(),
             }),
             licenses: Some(vec![CocoLicense {
                 id: 1,
                 name: "MIT".to_string(),
                 url: "http://mit.edu".to_string(),
+                extra: Default::default(),
             }]),
             images: vec![CocoImage {
                 id: 1,
@@ -1583,6 +3035,10 @@ mod tests {
                 flickr_url: None,
                 coco_url: None,
                 date_captured: None,
+                sha256: None,
+                neg_category_ids: None,
+                not_exhaustive_category_ids: None,
+                extra: Default::default(),
             }],
             annotations: vec![],
             categories: Some(vec![CocoCategory::ObjectDetection(
@@ -1590,6 +3046,10 @@ mod tests {
                     id: 1,
                     name: "person".to_string(),
                     supercategory: "human".to_string(),
+                    frequency: None,
+                    image_count: None,
+                    instance_count: None,
+                    extra: Default::default(),
                 },
             )]),
         };
@@ -1613,6 +3073,9 @@ mod tests {
             area: 50.0,
             bbox: [0.0, 0.0, 10.0, 10.0],
             iscrowd: true,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&original).unwrap();
@@ -1622,7 +3085,7 @@ mod tests {
         assert_eq!(deserialized.id, 1);
         assert_eq!(deserialized.image_id, 10);
         assert_eq!(deserialized.category_id, 5);
-        assert_eq!(deserialized.iscrowd, true);
+        assert!(deserialized.iscrowd);
     }
 
     #[test]
@@ -1637,6 +3100,9 @@ mod tests {
             iscrowd: false,
             keypoints: vec![10.0, 10.0, 2.0, 15.0, 15.0, 2.0],
             num_keypoints: 2,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&original).unwrap();
@@ -1647,6 +3113,329 @@ mod tests {
         assert_eq!(deserialized.keypoints.len(), 6);
     }
 
+    #[test]
+    fn test_prediction_score_roundtrip() {
+        let json = r#"[
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "score": 0.875},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0,
+             "keypoints": [1.0, 1.0, 2.0], "num_keypoints": 1, "score": 0.5},
+            {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0}
+        ]"#;
+        let annotations: Vec<CocoAnnotation> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            annotations[0].as_object_detection().unwrap().score,
+            Some(0.875)
+        );
+        assert_eq!(
+            annotations[1].as_keypoint_detection().unwrap().score,
+            Some(0.5)
+        );
+        assert_eq!(annotations[2].as_object_detection().unwrap().score, None);
+
+        let reserialized: Vec<serde_json::Value> =
+            serde_json::from_str(&serde_json::to_string(&annotations).unwrap()).unwrap();
+        assert_eq!(reserialized[0]["score"], 0.875);
+        assert_eq!(reserialized[1]["score"], 0.5);
+        // ground truth annotations don't gain a score
+        assert!(reserialized[2].get("score").is_none());
+    }
+
+    #[test]
+    fn test_annotation_attributes_roundtrip() {
+        let json = r#"[
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0,
+             "attributes": {"color": "red", "truncated": true}},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0}
+        ]"#;
+        let annotations: Vec<CocoAnnotation> = serde_json::from_str(json).unwrap();
+        let attributes = annotations[0]
+            .as_object_detection()
+            .unwrap()
+            .attributes
+            .as_ref();
+        assert_eq!(attributes.unwrap()["truncated"], true);
+
+        let reserialized: Vec<serde_json::Value> =
+            serde_json::from_str(&serde_json::to_string(&annotations).unwrap()).unwrap();
+        assert_eq!(reserialized[0]["attributes"]["color"], "red");
+        // plain COCO annotations stay plain
+        assert!(reserialized[1].get("attributes").is_none());
+    }
+
+    #[test]
+    fn test_filter_by_score_and_strip_scores() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 2, "height": 2, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+                 "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "score": 0.9},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+                 "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "score": 0.2},
+                {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+                 "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "keypoints": [1.0, 1.0, 2.0],
+                 "num_keypoints": 1, "score": 0.4},
+                {"id": 4, "image_id": 1, "category_id": 1, "iscrowd": 0, "area": 4,
+                 "bbox": [0.0, 0.0, 2.0, 2.0], "dp_I": [], "dp_U": [], "dp_V": [], "dp_x": [],
+                 "dp_y": [], "dp_masks": [], "score": 0.6},
+                {"id": 5, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+                 "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0},
+                {"id": 6, "image_id": 1, "caption": "a cat"}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        // the score doesn't change which variant an annotation resolves to
+        assert!(coco_file.annotations[2].as_keypoint_detection().is_some());
+        assert_eq!(
+            coco_file.annotations[3].as_densepose().unwrap().score,
+            Some(0.6)
+        );
+
+        assert_eq!(coco_file.filter_by_score(0.5), 2);
+        let ids: Vec<i64> = coco_file
+            .annotations
+            .iter()
+            .filter_map(|ann| ann.id())
+            .collect();
+        assert_eq!(ids, vec![1, 4, 5, 6]);
+
+        coco_file.strip_scores();
+        assert!(
+            coco_file
+                .annotations
+                .iter()
+                .all(|ann| ann.score().is_none())
+        );
+        let reserialized = serde_json::to_string(&coco_file).unwrap();
+        assert!(!reserialized.contains("score"));
+    }
+
+    /// The shape of lvis_v1_train.json, cut down to a few entries
+    const LVIS_SNIPPET: &str = r#"{
+        "info": {"year": 2020, "version": "1.0", "description": "LVIS", "contributor": "LVIS",
+                 "url": "https://www.lvisdataset.org", "date_created": "2020-01-01T00:00:00Z"},
+        "licenses": [{"id": 1, "name": "CC BY 4.0", "url": "http://creativecommons.org/licenses/by/4.0/"}],
+        "images": [
+            {"id": 391895, "width": 640, "height": 360, "license": 1,
+             "date_captured": "2013-11-14 11:18:45",
+             "flickr_url": "http://farm9.staticflickr.com/8186/8119368305_4e622c8349_z.jpg",
+             "coco_url": "http://images.cocodataset.org/train2017/000000391895.jpg",
+             "neg_category_ids": [3, 7], "not_exhaustive_category_ids": [1]},
+            {"id": 522418, "width": 640, "height": 480, "license": 1,
+             "date_captured": "2013-11-14 11:38:44",
+             "flickr_url": "http://farm1.staticflickr.com/1/127244861_ab0c0381e7_z.jpg",
+             "coco_url": "http://images.cocodataset.org/train2017/000000522418.jpg",
+             "neg_category_ids": [], "not_exhaustive_category_ids": []}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 391895, "category_id": 1, "area": 24.0,
+             "segmentation": [[10.0, 10.0, 16.0, 10.0, 16.0, 14.0, 10.0, 14.0]],
+             "bbox": [10.0, 10.0, 6.0, 4.0]},
+            {"id": 2, "image_id": 522418, "category_id": 2, "area": 8.5,
+             "segmentation": [[0.0, 0.0, 4.25, 0.0, 4.25, 2.0, 0.0, 2.0]],
+             "bbox": [0.0, 0.0, 4.25, 2.0]}
+        ],
+        "categories": [
+            {"id": 1, "name": "aerosol_can", "synset": "aerosol.n.02",
+             "synonyms": ["aerosol_can", "spray_can"],
+             "def": "a dispenser that holds a substance under pressure",
+             "frequency": "c", "image_count": 64, "instance_count": 109},
+            {"id": 2, "name": "air_conditioner", "synset": "air_conditioner.n.01",
+             "synonyms": ["air_conditioner"], "def": "a machine that keeps air cool and dry",
+             "frequency": "f", "image_count": 364, "instance_count": 1081}
+        ]
+    }"#;
+
+    #[test]
+    fn test_lvis_roundtrip() {
+        let coco_file: CocoFile = serde_json::from_str(LVIS_SNIPPET).unwrap();
+
+        let image = &coco_file.images[0];
+        assert_eq!(image.neg_category_ids, Some(vec![3, 7]));
+        assert_eq!(image.not_exhaustive_category_ids, Some(vec![1]));
+        assert_eq!(
+            image.date_captured.unwrap().to_rfc3339(),
+            "2013-11-14T11:18:45+00:00"
+        );
+        assert!(
+            coco_file
+                .annotations
+                .iter()
+                .all(|ann| ann.as_object_detection().is_some_and(|ann| !ann.iscrowd))
+        );
+        match &coco_file.categories.as_ref().unwrap()[0] {
+            CocoCategory::ObjectDetection(category) => {
+                assert_eq!(category.frequency, Some(LvisFrequency::Common));
+                assert_eq!(category.image_count, Some(64));
+                assert_eq!(category.instance_count, Some(109));
+                assert_eq!(category.extra["synset"], "aerosol.n.02");
+            }
+            _ => panic!("LVIS categories should be object detection categories"),
+        }
+
+        // every LVIS key survives with its value, COCO's own required keys are added with
+        // their defaults
+        let original: serde_json::Value = serde_json::from_str(LVIS_SNIPPET).unwrap();
+        let reserialized: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&coco_file).unwrap()).unwrap();
+        for section in ["images", "annotations", "categories"] {
+            let reserialized = reserialized[section].as_array().unwrap();
+            for (original, reserialized) in original[section]
+                .as_array()
+                .unwrap()
+                .iter()
+                .zip(reserialized)
+            {
+                for (key, value) in original.as_object().unwrap() {
+                    if key != "date_captured" {
+                        assert_eq!(&reserialized[key], value, "{} {} differs", section, key);
+                    }
+                }
+                let added: Vec<&String> = reserialized
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .filter(|key| !original.as_object().unwrap().contains_key(*key))
+                    .collect();
+                assert!(
+                    added.iter().all(
+                        |key| ["file_name", "iscrowd", "supercategory"].contains(&key.as_str())
+                    ),
+                    "{} gained {:?}",
+                    section,
+                    added
+                );
+            }
+        }
+        let reparsed: CocoFile = serde_json::from_value(reserialized).unwrap();
+        assert_eq!(reparsed.images[0].date_captured, image.date_captured);
+    }
+
+    #[test]
+    fn test_recount_lvis_categories() {
+        let mut coco_file: CocoFile = serde_json::from_str(LVIS_SNIPPET).unwrap();
+        coco_file.annotations.truncate(1);
+        coco_file.recount_lvis_categories();
+
+        let counts: Vec<(Option<u64>, Option<u64>)> = coco_file
+            .categories
+            .iter()
+            .flatten()
+            .map(|category| match category {
+                CocoCategory::ObjectDetection(category) => {
+                    (category.image_count, category.instance_count)
+                }
+                _ => (None, None),
+            })
+            .collect();
+        assert_eq!(counts, vec![(Some(1), Some(1)), (Some(0), Some(0))]);
+    }
+
+    #[test]
+    fn test_annotation_extra_fields_roundtrip() {
+        let json = r#"[
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "keypoints": [1.0, 1.0, 2.0],
+             "num_keypoints": 1, "track_id": 3},
+            {"image_id": 1, "file_name": "1.png", "source": "sim", "segments_info": [
+                {"id": 5, "category_id": 1, "area": 4, "bbox": [0.0, 0.0, 2.0, 2.0],
+                 "iscrowd": 0, "confidence": 0.5}]},
+            {"id": 2, "image_id": 1, "caption": "a cat", "language": "en"},
+            {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [], "area": 4.0,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "iscrowd": 0, "track_id": 4},
+            {"id": 4, "image_id": 1, "category_id": 1, "iscrowd": 0, "area": 4,
+             "bbox": [0.0, 0.0, 2.0, 2.0], "dp_I": [], "dp_U": [], "dp_V": [], "dp_x": [],
+             "dp_y": [], "dp_masks": [], "reviewed": true}
+        ]"#;
+        let annotations: Vec<CocoAnnotation> = serde_json::from_str(json).unwrap();
+
+        let keypoint = annotations[0].as_keypoint_detection().unwrap();
+        assert_eq!(keypoint.extra["track_id"], 3);
+        assert_eq!(keypoint.extra.len(), 1);
+        let panoptic = annotations[1].as_panoptic().unwrap();
+        assert_eq!(panoptic.extra["source"], "sim");
+        assert_eq!(panoptic.segments_info[0].extra["confidence"], 0.5);
+        assert_eq!(panoptic.segments_info[0].extra.len(), 1);
+        let caption = annotations[2].as_captioning().unwrap();
+        assert_eq!(caption.extra["language"], "en");
+        let detection = annotations[3].as_object_detection().unwrap();
+        assert_eq!(detection.extra["track_id"], 4);
+        assert_eq!(detection.extra.len(), 1);
+        let densepose = annotations[4].as_densepose().unwrap();
+        assert_eq!(densepose.extra["reviewed"], true);
+        assert_eq!(densepose.extra.len(), 1);
+
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        let reserialized: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&annotations).unwrap()).unwrap();
+        for (original, reserialized) in original
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(reserialized.as_array().unwrap())
+        {
+            for key in ["track_id", "source", "language", "reviewed"] {
+                assert_eq!(original.get(key), reserialized.get(key), "{} differs", key);
+            }
+        }
+        assert_eq!(
+            original[1]["segments_info"][0]["confidence"],
+            reserialized[1]["segments_info"][0]["confidence"]
+        );
+    }
+
+    #[test]
+    fn test_file_extra_fields_roundtrip() {
+        let json = r##"{
+            "info": {"year": 2024, "version": "1", "description": "", "contributor": "",
+                     "url": "", "date_created": "2024-01-01T00:00:00Z", "split": "train"},
+            "images": [{"id": 1, "width": 2, "height": 2, "file_name": "a.jpg",
+                        "sensor": {"name": "cam0", "exposure": 0.01}}],
+            "annotations": [],
+            "licenses": [{"id": 1, "name": "CC BY 4.0", "url": "", "attribution": "J. Doe"}],
+            "categories": [
+                {"id": 1, "name": "cat", "supercategory": "animal", "color": "#ff0000"},
+                {"id": 2, "name": "person", "supercategory": "person", "keypoints": ["nose"],
+                 "skeleton": [], "frequency": "c"},
+                {"id": 3, "name": "sky", "supercategory": "background", "isthing": 0,
+                 "color": [0, 0, 255], "synonyms": ["heaven"]}
+            ]
+        }"##;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        assert_eq!(coco_file.info.as_ref().unwrap().extra["split"], "train");
+        assert_eq!(coco_file.images[0].extra["sensor"]["name"], "cam0");
+        assert_eq!(
+            coco_file.licenses.as_ref().unwrap()[0].extra["attribution"],
+            "J. Doe"
+        );
+
+        let categories = coco_file.categories.as_ref().unwrap();
+        match (&categories[0], &categories[1], &categories[2]) {
+            (
+                CocoCategory::ObjectDetection(detection),
+                CocoCategory::KeypointDetection(keypoint),
+                CocoCategory::PanopticSegmentation(panoptic),
+            ) => {
+                assert_eq!(detection.extra["color"], "#ff0000");
+                assert_eq!(keypoint.extra["frequency"], "c");
+                assert_eq!(panoptic.extra["synonyms"][0], "heaven");
+            }
+            _ => panic!("categories resolved to the wrong variants"),
+        }
+
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        let reserialized: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&coco_file).unwrap()).unwrap();
+        assert_eq!(original["info"]["split"], reserialized["info"]["split"]);
+        assert_eq!(original["images"], reserialized["images"]);
+        assert_eq!(original["licenses"], reserialized["licenses"]);
+        assert_eq!(original["categories"], reserialized["categories"]);
+    }
+
     // ========== MAKE_ID_MAP EDGE CASE TESTS ==========
 
     #[test]
@@ -1664,6 +3453,10 @@ mod tests {
                     flickr_url: None,
                     coco_url: None,
                     date_captured: None,
+                    sha256: None,
+                    neg_category_ids: None,
+                    not_exhaustive_category_ids: None,
+                    extra: Default::default(),
                 },
                 CocoImage {
                     id: 2,
@@ -1674,19 +3467,67 @@ mod tests {
                     flickr_url: None,
                     coco_url: None,
                     date_captured: None,
+                    sha256: None,
+                    neg_category_ids: None,
+                    not_exhaustive_category_ids: None,
+                    extra: Default::default(),
                 },
             ],
             annotations: vec![],
             categories: None,
         };
 
-        let id_map = coco_file.make_image_id_map();
+        let id_map = coco_file.make_image_id_map().unwrap();
 
         assert_eq!(id_map.len(), 2);
         assert_eq!(id_map.get(&1).unwrap().annotations.len(), 0);
         assert_eq!(id_map.get(&2).unwrap().annotations.len(), 0);
     }
 
+    #[test]
+    fn test_sequential_image_id_map() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.png"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "b.png"},
+                {"id": 3, "width": 10, "height": 10, "file_name": "c.png"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 2, "caption": "first"},
+                {"id": 2, "image_id": 1, "caption": "second"},
+                {"id": 3, "image_id": 2, "caption": "third"},
+                {"id": 4, "image_id": 9, "caption": "dangling"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let summarize = |map: HashMap<i64, IDMapEntry<'_>>| {
+            let mut entries: Vec<(i64, i64, Vec<Option<i64>>)> = map
+                .into_iter()
+                .map(|(id, entry)| {
+                    let annotation_ids = entry.annotations.iter().map(|a| a.id()).collect();
+                    (id, entry.image.id, annotation_ids)
+                })
+                .collect();
+            entries.sort_by_key(|entry| entry.0);
+            entries
+        };
+
+        let sequential = summarize(coco_file.sequential_image_id_map());
+        assert_eq!(
+            sequential,
+            vec![
+                (1, 1, vec![Some(2)]),
+                (2, 2, vec![Some(1), Some(3)]),
+                (3, 3, vec![]),
+            ]
+        );
+        // the public map is parallel with the rayon feature and must agree
+        assert_eq!(
+            summarize(coco_file.make_image_id_map_quiet().unwrap()),
+            sequential
+        );
+    }
+
     #[test]
     fn test_make_id_map_with_mixed_annotation_types() {
         let coco_file = CocoFile {
@@ -1701,6 +3542,10 @@ mod tests {
                 flickr_url: None,
                 coco_url: None,
                 date_captured: None,
+                sha256: None,
+                neg_category_ids: None,
+                not_exhaustive_category_ids: None,
+                extra: Default::default(),
             }],
             annotations: vec![
                 CocoAnnotation::ObjectDetection(CocoObjectDetectionAnnotation {
@@ -1711,11 +3556,15 @@ mod tests {
                     area: 1.0,
                     bbox: [0.0, 0.0, 1.0, 1.0],
                     iscrowd: false,
+                    score: None,
+                    attributes: None,
+                    extra: Default::default(),
                 }),
                 CocoAnnotation::ImageCaptioning(CocoImageCaptioningAnnotation {
                     id: 2,
                     image_id: 1,
                     caption: "test".to_string(),
+                    extra: Default::default(),
                 }),
                 CocoAnnotation::DensePose(CocoDensePoseAnnotation {
                     id: 3,
@@ -1730,12 +3579,14 @@ mod tests {
                     dp_x: vec![],
                     dp_y: vec![],
                     dp_masks: vec![],
+                    score: None,
+                    extra: Default::default(),
                 }),
             ],
             categories: None,
         };
 
-        let id_map = coco_file.make_image_id_map();
+        let id_map = coco_file.make_image_id_map().unwrap();
         let entry = id_map.get(&1).unwrap();
 
         assert_eq!(entry.annotations.len(), 3);
@@ -1751,10 +3602,306 @@ mod tests {
             categories: None,
         };
 
-        let id_map = coco_file.make_image_id_map();
+        let id_map = coco_file.make_image_id_map().unwrap();
         assert_eq!(id_map.len(), 0);
     }
 
+    #[test]
+    fn test_make_id_map_duplicate_image_ids() {
+        let image = |id: i64, name: &str| CocoImage {
+            id,
+            width: 100,
+            height: 100,
+            file_name: PathBuf::from(name),
+            license: None,
+            flickr_url: None,
+            coco_url: None,
+            date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
+        };
+        let coco_file = CocoFile {
+            info: None,
+            licenses: None,
+            images: vec![
+                image(1, "img1.jpg"),
+                image(2, "img2.jpg"),
+                image(1, "img3.jpg"),
+                image(1, "img4.jpg"),
+            ],
+            annotations: vec![],
+            categories: None,
+        };
+
+        let err = coco_file.make_image_id_map().err().unwrap();
+        assert_eq!(err.image_ids, vec![1]);
+        assert_eq!(err.to_string(), "duplicate image id(s) in COCO file: 1");
+        assert!(coco_file.make_image_id_map_quiet().is_err());
+    }
+
+    #[test]
+    fn test_make_category_and_annotation_id_maps() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 80, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 2, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0,
+                 "keypoints": [1.0, 1.0, 2.0], "num_keypoints": 1},
+                {"id": 3, "image_id": 1, "caption": "no category"},
+                {"image_id": 1, "file_name": "a.png", "segments_info": [
+                    {"id": 1, "category_id": 3, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+                    {"id": 2, "category_id": 3, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+                    {"id": 3, "category_id": 1, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0}
+                ]},
+                {"id": 5, "image_id": 1, "category_id": 9, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0}
+            ],
+            "categories": [
+                {"id": 1, "name": "person", "supercategory": "human"},
+                {"id": 2, "name": "pose", "supercategory": "human", "keypoints": ["nose"], "skeleton": []},
+                {"id": 3, "name": "road", "supercategory": "stuff", "isthing": 0, "color": [0, 0, 0]},
+                {"id": 4, "name": "unused", "supercategory": "none"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let category_map = coco_file.make_category_id_map();
+        assert_eq!(category_map.len(), 4);
+        let annotation_count = |id: i32| category_map[&id].annotations.len();
+        assert_eq!(annotation_count(1), 2);
+        assert_eq!(annotation_count(2), 1);
+        assert_eq!(annotation_count(3), 1);
+        assert_eq!(annotation_count(4), 0);
+        assert!(matches!(
+            category_map[&3].category,
+            CocoCategory::PanopticSegmentation(_)
+        ));
+
+        let annotation_map = coco_file.make_annotation_id_map();
+        let mut ids: Vec<i64> = annotation_map.keys().copied().collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3, 5]);
+        assert!(matches!(
+            annotation_map[&3],
+            CocoAnnotation::ImageCaptioning(_)
+        ));
+    }
+
+    #[test]
+    fn test_typed_annotation_iterators() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 80, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 2, "segmentation": [[]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0,
+                 "keypoints": [1.0, 1.0, 2.0], "num_keypoints": 1},
+                {"id": 4, "image_id": 1, "caption": "a caption"},
+                {"id": 5, "image_id": 1, "caption": "another caption"},
+                {"id": 6, "image_id": 1, "caption": "a third caption"},
+                {"image_id": 1, "file_name": "a.png", "segments_info": []},
+                {"id": 7, "image_id": 1, "category_id": 1, "iscrowd": 0, "area": 10,
+                 "bbox": [0.0, 0.0, 5.0, 2.0], "dp_I": [], "dp_U": [], "dp_V": [],
+                 "dp_x": [], "dp_y": [], "dp_masks": []}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(coco_file.object_detection_annotations().count(), 2);
+        assert_eq!(coco_file.keypoint_detection_annotations().count(), 1);
+        assert_eq!(coco_file.panoptic_annotations().count(), 1);
+        assert_eq!(coco_file.captioning_annotations().count(), 3);
+        assert_eq!(coco_file.densepose_annotations().count(), 1);
+
+        coco_file
+            .captioning_annotations_mut()
+            .for_each(|ann| ann.caption = ann.caption.to_uppercase());
+        assert_eq!(
+            coco_file.annotations[3].as_captioning().unwrap().caption,
+            "A CAPTION"
+        );
+        assert!(coco_file.annotations[3].as_object_detection().is_none());
+
+        coco_file.annotations[7]
+            .as_densepose_mut()
+            .unwrap()
+            .category_id = 4;
+        assert_eq!(coco_file.annotations[7].category_id(), Some(4));
+        assert!(coco_file.annotations[6].as_panoptic().is_some());
+        assert_eq!(
+            coco_file.annotations[2]
+                .as_keypoint_detection()
+                .unwrap()
+                .num_keypoints,
+            1
+        );
+    }
+
+    #[test]
+    fn test_remove_missing_images() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("present.jpg"), b"").unwrap();
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "present.jpg"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "moved/missing.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "caption": "kept"},
+                {"id": 2, "image_id": 2, "caption": "dropped"}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(coco_file.remove_missing_images(temp_dir.path()), 1);
+        assert_eq!(coco_file.images.len(), 1);
+        assert_eq!(coco_file.images[0].id, 1);
+        assert_eq!(coco_file.annotations.len(), 1);
+        assert_eq!(coco_file.annotations[0].id(), Some(1));
+
+        // absolute file names ignore the base directory
+        coco_file.images[0].file_name = temp_dir.path().join("present.jpg");
+        assert_eq!(
+            coco_file.remove_missing_images(Path::new("/nonexistent")),
+            0
+        );
+    }
+
+    #[test]
+    fn test_recompute_areas() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+                    "area": 0.0, "bbox": [5.0, 5.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1,
+                    "segmentation": [[0.0, 0.0, 4.0, 0.0, 4.0, 4.0]],
+                    "area": 16.0, "bbox": [0.0, 0.0, 4.0, 4.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1,
+                    "segmentation": {"size": [4, 4], "counts": [2, 3, 11]},
+                    "area": 0.0, "bbox": [0.0, 0.0, 4.0, 4.0], "iscrowd": 1},
+                {"id": 4, "image_id": 1, "category_id": 1, "iscrowd": 0, "area": 0,
+                    "bbox": [0.0, 0.0, 2.5, 3.0], "dp_I": [], "dp_U": [], "dp_V": [],
+                    "dp_x": [], "dp_y": [], "dp_masks": []},
+                {"id": 5, "image_id": 1, "caption": "untouched"}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        coco_file.recompute_areas();
+
+        let areas: Vec<f32> = coco_file
+            .object_detection_annotations()
+            .map(|ann| ann.area)
+            .collect();
+        assert_eq!(areas, vec![100.0, 8.0, 3.0]);
+        assert_eq!(coco_file.densepose_annotations().next().unwrap().area, 8);
+    }
+
+    #[test]
+    fn test_group_by_image_matches_image_id_map() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "b.jpg"},
+                {"id": 3, "width": 10, "height": 10, "file_name": "c.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 2, "caption": "first"},
+                {"id": 2, "image_id": 1, "caption": "second"},
+                {"id": 3, "image_id": 2, "caption": "third"},
+                {"id": 4, "image_id": 9, "caption": "dangling"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let borrowed: HashMap<i64, (PathBuf, Vec<Option<i64>>)> = coco_file
+            .make_image_id_map_quiet()
+            .unwrap()
+            .into_iter()
+            .map(|(id, entry)| {
+                let annotation_ids = entry.annotations.iter().map(|ann| ann.id()).collect();
+                (id, (entry.image.file_name.clone(), annotation_ids))
+            })
+            .collect();
+
+        let owned: HashMap<i64, (PathBuf, Vec<Option<i64>>)> = coco_file
+            .group_by_image()
+            .unwrap()
+            .into_iter()
+            .map(|(id, (image, annotations))| {
+                let annotation_ids = annotations.iter().map(|ann| ann.id()).collect();
+                (id, (image.file_name, annotation_ids))
+            })
+            .collect();
+
+        assert_eq!(owned, borrowed);
+        assert_eq!(owned[&2].1, vec![Some(1), Some(3)]);
+        assert!(owned[&3].1.is_empty());
+
+        let duplicates: CocoFile = serde_json::from_str(
+            r#"{"images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"},
+                {"id": 1, "width": 10, "height": 10, "file_name": "b.jpg"}
+            ], "annotations": []}"#,
+        )
+        .unwrap();
+        match duplicates.group_by_image() {
+            Err(err) => assert_eq!(err.image_ids, vec![1]),
+            Ok(_) => panic!("Expected duplicate image ids to be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_dangling_annotations() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 80, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "caption": "kept"},
+                {"id": 2, "image_id": 7, "caption": "dangling"},
+                {"id": 3, "image_id": 1, "caption": "kept"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let id_map = coco_file.make_image_id_map_quiet().unwrap();
+
+        assert_eq!(id_map[&1].annotations.len(), 2);
+        let dangling = coco_file.dangling_annotations(&id_map);
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].image_id(), 7);
+    }
+
+    #[test]
+    fn test_to_writer_compact_round_trip() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 80, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[1.0, 2.0, 3.0, 4.0]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0}
+            ],
+            "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let mut compact = Vec::new();
+        coco_file.to_writer(&mut compact, false).unwrap();
+        let mut pretty = Vec::new();
+        coco_file.to_writer(&mut pretty, true).unwrap();
+
+        assert!(!compact.contains(&b'\n'));
+        assert!(compact.len() < pretty.len());
+        let round_trip: CocoFile = serde_json::from_slice(&compact).unwrap();
+        assert_eq!(
+            serde_json::to_value(&round_trip).unwrap(),
+            serde_json::from_slice::<serde_json::Value>(&pretty).unwrap()
+        );
+    }
+
     // ========== EDGE CASE TESTS ==========
 
     #[test]
@@ -1810,6 +3957,10 @@ mod tests {
             flickr_url: None,
             coco_url: None,
             date_captured: None,
+            sha256: None,
+            neg_category_ids: None,
+            not_exhaustive_category_ids: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&image).unwrap();
@@ -1829,6 +3980,9 @@ mod tests {
             area: 0.0,
             bbox: [0.0, 0.0, 0.0, 0.0],
             iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1849,6 +4003,9 @@ mod tests {
             area: 100.0,
             bbox: [-10.0, -20.0, 30.0, 40.0],
             iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1871,6 +4028,9 @@ mod tests {
             iscrowd: false,
             keypoints: vec![],
             num_keypoints: 0,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1887,11 +4047,14 @@ mod tests {
             id: 1,
             name: "".to_string(),
             supercategory: "".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&cat).unwrap();
-        let deserialized: CocoObjectDetectionCategory =
-            serde_json::from_str(&serialized).unwrap();
+        let deserialized: CocoObjectDetectionCategory = serde_json::from_str(&serialized).unwrap();
 
         assert_eq!(deserialized.name, "");
         assert_eq!(deserialized.supercategory, "");
@@ -1903,6 +4066,7 @@ mod tests {
             id: 1,
             image_id: 1,
             caption: "".to_string(),
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1918,6 +4082,7 @@ mod tests {
             image_id: 1,
             file_name: PathBuf::from("seg_1.png"),
             segments_info: vec![],
+            extra: Default::default(),
         };
 
         let serialized = serde_json::to_string(&ann).unwrap();
@@ -1933,6 +4098,10 @@ mod tests {
             id: 1,
             name: "test".to_string(),
             supercategory: "test".to_string(),
+            frequency: None,
+            image_count: None,
+            instance_count: None,
+            extra: Default::default(),
         });
         assert_eq!(obj_det.id(), 1);
 
@@ -1942,6 +4111,7 @@ mod tests {
             supercategory: "test".to_string(),
             keypoints: vec![],
             skeleton: vec![],
+            extra: Default::default(),
         });
         assert_eq!(kp_det.id(), 2);
 
@@ -1951,7 +4121,62 @@ mod tests {
             supercategory: "test".to_string(),
             isthing: true,
             color: [0, 0, 0],
+            extra: Default::default(),
         });
         assert_eq!(panoptic.id(), 3);
     }
+
+    #[test]
+    fn test_category_enum_names() {
+        let json = r#"[
+            {"id": 1, "name": "car", "supercategory": "vehicle"},
+            {"id": 2, "name": "person", "supercategory": "human",
+                "keypoints": ["nose"], "skeleton": []},
+            {"id": 3, "name": "road", "supercategory": "stuff", "isthing": 0,
+                "color": [128, 64, 128]},
+            {"id": 4, "name": "aerosol_can"}
+        ]"#;
+
+        let categories: Vec<CocoCategory> = serde_json::from_str(json).unwrap();
+        assert!(matches!(categories[1], CocoCategory::KeypointDetection(_)));
+        assert!(matches!(
+            categories[2],
+            CocoCategory::PanopticSegmentation(_)
+        ));
+        let names: Vec<(&str, &str)> = categories
+            .iter()
+            .map(|category| (category.name(), category.supercategory()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("car", "vehicle"),
+                ("person", "human"),
+                ("road", "stuff"),
+                ("aerosol_can", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_make_category_map() {
+        let json = r#"{
+            "images": [],
+            "annotations": [],
+            "categories": [
+                {"id": 1, "name": "person", "supercategory": "human"},
+                {"id": 3, "name": "car", "supercategory": "vehicle"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let category_map = coco_file.make_category_map();
+
+        assert_eq!(category_map.len(), 2);
+        assert_eq!(category_map[&3].id(), 3);
+        assert!(!category_map.contains_key(&2));
+
+        let no_categories: CocoFile =
+            serde_json::from_str(r#"{"images": [], "annotations": []}"#).unwrap();
+        assert!(no_categories.make_category_map().is_empty());
+    }
 }
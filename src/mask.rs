@@ -0,0 +1,487 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::{CocoPolygon, CocoRLE, CocoSegmentation, polygon_area};
+
+impl CocoRLE {
+    /// Encodes a row major `width` x `height` mask. Counts run column by column and start
+    /// with background, like the COCO mask API.
+    pub fn from_mask(mask: &[bool], width: u32, height: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let mut counts = Vec::new();
+        let mut current = false;
+        let mut run = 0u32;
+        for x in 0..width {
+            for y in 0..height {
+                if mask[y * width + x] != current {
+                    counts.push(run);
+                    current = !current;
+                    run = 0;
+                }
+                run += 1;
+            }
+        }
+        counts.push(run);
+        CocoRLE {
+            counts,
+            size: (height as u32, width as u32),
+        }
+    }
+
+    /// Decodes into a row major mask of `size`, counts past the end of the mask are ignored
+    pub fn to_mask(&self) -> Vec<bool> {
+        let (height, width) = (self.size.0 as usize, self.size.1 as usize);
+        let mut mask = vec![false; width * height];
+        let mut position = 0;
+        for (i, &count) in self.counts.iter().enumerate() {
+            let end = (position + count as usize).min(mask.len());
+            if i % 2 == 1 {
+                // column major position -> row major index
+                (position..end).for_each(|p| mask[(p % height) * width + p / height] = true);
+            }
+            position = end;
+        }
+        mask
+    }
+
+    /// Traces the outline of every 4-connected region of the mask along pixel edges, so
+    /// rasterizing the polygons gives back the mask. Holes are filled in since COCO polygons
+    /// can't express them.
+    pub fn to_polygons(&self) -> Vec<CocoPolygon> {
+        let (height, width) = (self.size.0 as i64, self.size.1 as i64);
+        let mask = self.to_mask();
+        let filled = |x: i64, y: i64| {
+            (0..width).contains(&x) && (0..height).contains(&y) && mask[(y * width + x) as usize]
+        };
+
+        // boundary edges between foreground and background pixels with the foreground on the
+        // right, which makes outer boundaries clockwise (y down) and holes counterclockwise
+        let mut edges: Vec<((i64, i64), (i64, i64))> = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                if !filled(x, y) {
+                    continue;
+                }
+                if !filled(x, y - 1) {
+                    edges.push(((x, y), (x + 1, y)));
+                }
+                if !filled(x + 1, y) {
+                    edges.push(((x + 1, y), (x + 1, y + 1)));
+                }
+                if !filled(x, y + 1) {
+                    edges.push(((x + 1, y + 1), (x, y + 1)));
+                }
+                if !filled(x - 1, y) {
+                    edges.push(((x, y + 1), (x, y)));
+                }
+            }
+        }
+
+        let mut outgoing: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        edges
+            .iter()
+            .enumerate()
+            .for_each(|(i, (start, _))| outgoing.entry(*start).or_default().push(i));
+        let mut used = vec![false; edges.len()];
+        let direction = |edge: usize| {
+            let (start, end) = edges[edge];
+            (end.0 - start.0, end.1 - start.1)
+        };
+
+        let mut polygons = Vec::new();
+        for first in 0..edges.len() {
+            if used[first] {
+                continue;
+            }
+            let mut ring = Vec::new();
+            let mut edge = first;
+            loop {
+                used[edge] = true;
+                let (start, end) = edges[edge];
+                ring.push(start);
+                // where pixels touch diagonally two edges leave the corner, turning right
+                // stays with the current pixel so regions touching at corners stay separate
+                let (dx, dy) = direction(edge);
+                let next = outgoing[&end]
+                    .iter()
+                    .copied()
+                    .filter(|&next| !used[next])
+                    .min_by_key(|&next| direction(next) != (-dy, dx));
+                match next {
+                    Some(next) => edge = next,
+                    None => break,
+                }
+            }
+            if signed_area(&ring) > 0 {
+                polygons.push(flatten_ring(&ring));
+            }
+        }
+        polygons
+    }
+
+    /// The outline of the largest region of the mask, for callers that want a single
+    /// polygon. Smaller regions are dropped, see [`CocoRLE::to_polygons`] to keep them.
+    pub fn to_polygon(&self) -> Option<CocoPolygon> {
+        self.to_polygons()
+            .into_iter()
+            .max_by(|a, b| polygon_area(a).total_cmp(&polygon_area(b)))
+    }
+}
+
+/// Runs of `(value, length)` covering `total` pixels, padding short counts with background
+fn runs(counts: &[u32], total: u64) -> impl Iterator<Item = (bool, u64)> + '_ {
+    let mut remaining = total;
+    counts
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| (i % 2 == 1, u64::from(count)))
+        .chain(std::iter::once((false, u64::MAX)))
+        .map(move |(value, count)| {
+            let count = count.min(remaining);
+            remaining -= count;
+            (value, count)
+        })
+        .filter(|&(_, count)| count > 0)
+}
+
+/// Walks two masks run by run, calling `visit` with both values and the length they
+/// share, without decoding either
+fn zip_runs(a: &CocoRLE, b: &CocoRLE, mut visit: impl FnMut(bool, bool, u64)) -> Result<()> {
+    if a.size != b.size {
+        bail!("mask sizes {:?} and {:?} differ", a.size, b.size);
+    }
+    let total = u64::from(a.size.0) * u64::from(a.size.1);
+    let mut a_runs = runs(&a.counts, total);
+    let mut b_runs = runs(&b.counts, total);
+    let (mut a_run, mut b_run) = (a_runs.next(), b_runs.next());
+    while let (Some((a_value, a_count)), Some((b_value, b_count))) = (a_run, b_run) {
+        let shared = a_count.min(b_count);
+        visit(a_value, b_value, shared);
+        a_run = match a_count - shared {
+            0 => a_runs.next(),
+            left => Some((a_value, left)),
+        };
+        b_run = match b_count - shared {
+            0 => b_runs.next(),
+            left => Some((b_value, left)),
+        };
+    }
+    Ok(())
+}
+
+impl CocoRLE {
+    fn combine(&self, other: &CocoRLE, op: impl Fn(bool, bool) -> bool) -> Result<CocoRLE> {
+        let mut counts: Vec<u32> = vec![0];
+        let mut current = false;
+        zip_runs(self, other, |a, b, count| {
+            let value = op(a, b);
+            if value != current {
+                counts.push(0);
+                current = value;
+            }
+            *counts.last_mut().unwrap() += count as u32;
+        })?;
+        Ok(CocoRLE {
+            counts,
+            size: self.size,
+        })
+    }
+
+    /// Pixels set in either mask, fails if the mask sizes differ
+    pub fn union(&self, other: &CocoRLE) -> Result<CocoRLE> {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// Pixels set in both masks, fails if the mask sizes differ
+    pub fn intersection(&self, other: &CocoRLE) -> Result<CocoRLE> {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// Pixels set in this mask but not in `other`, fails if the mask sizes differ
+    pub fn difference(&self, other: &CocoRLE) -> Result<CocoRLE> {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    /// Intersection over union of the masks, 0 if both are empty. Fails if the mask sizes
+    /// differ.
+    pub fn iou(&self, other: &CocoRLE) -> Result<f32> {
+        let (mut intersection, mut union) = (0u64, 0u64);
+        zip_runs(self, other, |a, b, count| {
+            if a && b {
+                intersection += count;
+            }
+            if a || b {
+                union += count;
+            }
+        })?;
+        Ok(if union > 0 {
+            intersection as f32 / union as f32
+        } else {
+            0.0
+        })
+    }
+}
+
+/// Twice the signed area, positive for clockwise rings in image coordinates
+fn signed_area(ring: &[(i64, i64)]) -> i64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum()
+}
+
+/// Drops the points in the middle of straight runs and flattens to `[x1, y1, x2, y2, ...]`
+fn flatten_ring(ring: &[(i64, i64)]) -> CocoPolygon {
+    let n = ring.len();
+    ring.iter()
+        .enumerate()
+        .filter(|&(i, point)| {
+            let previous = ring[(i + n - 1) % n];
+            let next = ring[(i + 1) % n];
+            (point.0 - previous.0) * (next.1 - point.1)
+                != (point.1 - previous.1) * (next.0 - point.0)
+        })
+        .flat_map(|(_, &(x, y))| [x as f32, y as f32])
+        .collect()
+}
+
+impl CocoSegmentation {
+    /// Rasterizes the polygons into a `width` x `height` mask, setting the pixels whose
+    /// center lies inside. Like the COCO mask API every polygon is filled on its own
+    /// (even-odd for self intersecting ones) and the results are unioned. RLE segmentations
+    /// are returned as they are.
+    pub fn to_rle(&self, width: u32, height: u32) -> CocoRLE {
+        let polygons = match self {
+            CocoSegmentation::RLE(rle) => return rle.clone(),
+            CocoSegmentation::Polygon(polygons) => polygons,
+        };
+
+        let mut mask = vec![false; width as usize * height as usize];
+        let mut crossings: Vec<f32> = Vec::new();
+        for polygon in polygons.iter().filter(|polygon| polygon.len() >= 6) {
+            let points: Vec<&[f32]> = polygon.chunks_exact(2).collect();
+            let edges: Vec<(&[f32], &[f32])> = (0..points.len())
+                .map(|i| (points[i], points[(i + 1) % points.len()]))
+                .collect();
+            for y in 0..height as usize {
+                let center = y as f32 + 0.5;
+                crossings.clear();
+                crossings.extend(
+                    edges
+                        .iter()
+                        .filter(|(a, b)| (a[1] <= center) != (b[1] <= center))
+                        .map(|(a, b)| a[0] + (center - a[1]) * (b[0] - a[0]) / (b[1] - a[1])),
+                );
+                crossings.sort_by(f32::total_cmp);
+                for span in crossings.chunks_exact(2) {
+                    // pixels whose center lies in [span[0], span[1])
+                    let start = (span[0] - 0.5).ceil().clamp(0.0, width as f32) as usize;
+                    let end = (span[1] - 0.5).ceil().clamp(0.0, width as f32) as usize;
+                    mask[y * width as usize..][start..end].fill(true);
+                }
+            }
+        }
+        CocoRLE::from_mask(&mask, width, height)
+    }
+
+    /// Mask intersection over union, rasterizing polygons into a `width` x `height` image.
+    /// Fails if an RLE segmentation has a different size.
+    pub fn iou(&self, other: &CocoSegmentation, width: u32, height: u32) -> Result<f32> {
+        self.to_rle(width, height).iou(&other.to_rle(width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_mask(rng: &mut StdRng, width: u32, height: u32) -> Vec<bool> {
+        // long runs as well as noise
+        let density = rng.random_range(0.0..1.0);
+        (0..width * height)
+            .map(|_| rng.random_bool(density))
+            .collect()
+    }
+
+    #[test]
+    fn test_mask_set_operations_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..500 {
+            let (width, height) = (rng.random_range(1..8), rng.random_range(1..8));
+            let a = random_mask(&mut rng, width, height);
+            let b = random_mask(&mut rng, width, height);
+            let (a_rle, b_rle) = (
+                CocoRLE::from_mask(&a, width, height),
+                CocoRLE::from_mask(&b, width, height),
+            );
+            let brute_force = |op: fn(bool, bool) -> bool| {
+                let mask: Vec<bool> = a.iter().zip(&b).map(|(&a, &b)| op(a, b)).collect();
+                CocoRLE::from_mask(&mask, width, height)
+            };
+
+            assert_eq!(a_rle.union(&b_rle).unwrap(), brute_force(|a, b| a || b));
+            assert_eq!(
+                a_rle.intersection(&b_rle).unwrap(),
+                brute_force(|a, b| a && b)
+            );
+            assert_eq!(
+                a_rle.difference(&b_rle).unwrap(),
+                brute_force(|a, b| a && !b)
+            );
+
+            let intersection = brute_force(|a, b| a && b).area();
+            let union = brute_force(|a, b| a || b).area();
+            let expected = if union > 0 {
+                intersection as f32 / union as f32
+            } else {
+                0.0
+            };
+            assert_eq!(a_rle.iou(&b_rle).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_mask_iou() {
+        let full = CocoRLE::from_mask(&[true; 4], 2, 2);
+        let empty = CocoRLE::default().union(&CocoRLE::default()).unwrap();
+        assert_eq!(empty.counts, vec![0]);
+        assert_eq!(full.iou(&full).unwrap(), 1.0);
+        assert!(full.iou(&CocoRLE::from_mask(&[true; 6], 3, 2)).is_err());
+
+        // counts shorter than the mask are padded with background
+        let short = CocoRLE {
+            counts: vec![1, 1],
+            size: (2, 2),
+        };
+        assert_eq!(short.iou(&full).unwrap(), 0.25);
+
+        let square = CocoSegmentation::Polygon(vec![vec![0.0, 0.0, 4.0, 0.0, 4.0, 4.0, 0.0, 4.0]]);
+        let shifted = CocoSegmentation::Polygon(vec![vec![2.0, 0.0, 6.0, 0.0, 6.0, 4.0, 2.0, 4.0]]);
+        assert_eq!(square.iou(&shifted, 8, 4).unwrap(), 8.0 / 24.0);
+        let rle = CocoSegmentation::RLE(square.to_rle(8, 4));
+        assert_eq!(rle.iou(&square, 8, 4).unwrap(), 1.0);
+        assert!(rle.iou(&square, 8, 5).is_err());
+    }
+
+    #[test]
+    fn test_square_polygon_to_rle() {
+        let square = CocoSegmentation::Polygon(vec![vec![2.0, 1.0, 7.0, 1.0, 7.0, 5.0, 2.0, 5.0]]);
+        let rle = square.to_rle(10, 8);
+        assert_eq!(rle.size, (8, 10));
+        assert!((rle.area() as f32 - square.area().unwrap()).abs() <= 1.0);
+        assert_eq!(rle.bounding_box(), square.bounding_box());
+
+        // fractional corners only fill pixels whose center is covered
+        let shifted = CocoSegmentation::Polygon(vec![vec![2.3, 1.2, 7.6, 1.2, 7.6, 5.4, 2.3, 5.4]]);
+        // centers x 2.5..=7.5 and y 1.5..=4.5
+        assert_eq!(shifted.to_rle(10, 8).area(), 24);
+
+        // parts outside the image are cut off
+        let outside =
+            CocoSegmentation::Polygon(vec![vec![-5.0, -5.0, 2.0, -5.0, 2.0, 2.0, -5.0, 2.0]]);
+        assert_eq!(outside.to_rle(4, 4).area(), 4);
+    }
+
+    #[test]
+    fn test_two_square_multi_polygon() {
+        let squares = CocoSegmentation::Polygon(vec![
+            vec![1.0, 1.0, 3.0, 1.0, 3.0, 3.0, 1.0, 3.0],
+            vec![5.0, 2.0, 8.0, 2.0, 8.0, 5.0, 5.0, 5.0],
+        ]);
+        let rle = squares.to_rle(10, 6);
+        assert_eq!(rle.area(), 4 + 9);
+
+        let polygons = rle.to_polygons();
+        let mut sorted = polygons.clone();
+        sorted.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        assert_eq!(
+            sorted,
+            vec![
+                vec![1.0, 1.0, 3.0, 1.0, 3.0, 3.0, 1.0, 3.0],
+                vec![5.0, 2.0, 8.0, 2.0, 8.0, 5.0, 5.0, 5.0],
+            ]
+        );
+        assert_eq!(CocoSegmentation::Polygon(polygons).to_rle(10, 6), rle);
+
+        // overlapping polygons are unioned, not cut out of each other
+        let overlapping = CocoSegmentation::Polygon(vec![
+            vec![1.0, 1.0, 5.0, 1.0, 5.0, 5.0, 1.0, 5.0],
+            vec![3.0, 3.0, 7.0, 3.0, 7.0, 6.0, 3.0, 6.0],
+        ]);
+        let rle = overlapping.to_rle(10, 6);
+        assert_eq!(rle.area(), 16 + 12 - 4);
+        assert_eq!(rle.to_polygons().len(), 1);
+    }
+
+    #[test]
+    fn test_rle_to_polygons_fills_holes() {
+        let mask: Vec<bool> = [
+            "#####", //
+            "#...#", //
+            "#####", //
+        ]
+        .iter()
+        .flat_map(|row| row.chars().map(|c| c == '#'))
+        .collect();
+        let rle = CocoRLE::from_mask(&mask, 5, 3);
+
+        let polygons = rle.to_polygons();
+        assert_eq!(polygons, vec![vec![0.0, 0.0, 5.0, 0.0, 5.0, 3.0, 0.0, 3.0]]);
+        assert_eq!(CocoSegmentation::Polygon(polygons).to_rle(5, 3).area(), 15);
+    }
+
+    #[test]
+    fn test_rle_to_polygon_keeps_largest_region() {
+        // ring with a hole next to a single pixel
+        let mask: Vec<bool> = [
+            "###.#", //
+            "#.#..", //
+            "###..", //
+        ]
+        .iter()
+        .flat_map(|row| row.chars().map(|c| c == '#'))
+        .collect();
+        let rle = CocoRLE::from_mask(&mask, 5, 3);
+        assert_eq!(rle.to_polygons().len(), 2);
+
+        let polygon = rle.to_polygon().unwrap();
+        assert_eq!(polygon, vec![0.0, 0.0, 3.0, 0.0, 3.0, 3.0, 0.0, 3.0]);
+        // the hole is filled in
+        assert_eq!(
+            CocoSegmentation::Polygon(vec![polygon]).to_rle(5, 3).area(),
+            9
+        );
+
+        assert_eq!(CocoRLE::from_mask(&[false; 4], 2, 2).to_polygon(), None);
+    }
+
+    #[test]
+    fn test_rle_to_polygons() {
+        // L shape and a separate pixel touching it diagonally
+        let mask: Vec<bool> = [
+            "##...", //
+            "#....", //
+            "##...", //
+            "..#..", //
+        ]
+        .iter()
+        .flat_map(|row| row.chars().map(|c| c == '#'))
+        .collect();
+        let rle = CocoRLE::from_mask(&mask, 5, 4);
+        assert_eq!(rle.to_mask(), mask);
+        assert_eq!(rle.area(), 6);
+
+        let polygons = rle.to_polygons();
+        assert_eq!(polygons.len(), 2);
+        let rasterized = CocoSegmentation::Polygon(polygons).to_rle(5, 4);
+        assert_eq!(rasterized, rle);
+
+        assert!(
+            CocoRLE::from_mask(&[false; 4], 2, 2)
+                .to_polygons()
+                .is_empty()
+        );
+    }
+}
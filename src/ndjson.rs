@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{CocoFile, CocoInfo, CocoLicense};
+
+pub const IMAGES_FILE: &str = "images.ndjson";
+pub const ANNOTATIONS_FILE: &str = "annotations.ndjson";
+pub const CATEGORIES_FILE: &str = "categories.ndjson";
+pub const META_FILE: &str = "meta.json";
+
+/// The parts of a COCO file that aren't streamed
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    info: Option<CocoInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    licenses: Option<Vec<CocoLicense>>,
+}
+
+/// Writes one JSON value per line
+fn write_lines<T: Serialize>(path: &Path, items: &[T]) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Could not create {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    for item in items {
+        serde_json::to_writer(&mut writer, item)?;
+        writer.write_all(b"\n")?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Could not write {:?}", path))
+}
+
+/// Reads one JSON value per line, skipping blank lines
+fn read_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let file = File::open(path).with_context(|| format!("Could not open {:?}", path))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(i, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("Could not parse line {} of {:?}", i + 1, path))
+        })
+        .collect()
+}
+
+impl CocoFile {
+    /// Writes the file into `dir` as `images.ndjson`, `annotations.ndjson` and
+    /// `categories.ndjson` with one entry per line, plus `meta.json` holding info and licenses.
+    /// `categories.ndjson` is only written if the file has categories. Tools can append entries
+    /// to the NDJSON files without rewriting them.
+    pub fn write_ndjson(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))?;
+        write_lines(&dir.join(IMAGES_FILE), &self.images)?;
+        write_lines(&dir.join(ANNOTATIONS_FILE), &self.annotations)?;
+        if let Some(categories) = &self.categories {
+            write_lines(&dir.join(CATEGORIES_FILE), categories)?;
+        }
+
+        let meta = Meta {
+            info: self.info.clone(),
+            licenses: self.licenses.clone(),
+        };
+        let meta_path = dir.join(META_FILE);
+        let file = File::create(&meta_path)
+            .with_context(|| format!("Could not create {:?}", meta_path))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &meta)
+            .with_context(|| format!("Could not write {:?}", meta_path))
+    }
+
+    /// Loads a file written by [`CocoFile::write_ndjson`]. `meta.json` and
+    /// `categories.ndjson` are optional.
+    pub fn from_ndjson(dir: &Path) -> Result<CocoFile> {
+        let meta_path = dir.join(META_FILE);
+        let meta = if meta_path.exists() {
+            let meta_json = fs::read_to_string(&meta_path)
+                .with_context(|| format!("Could not read {:?}", meta_path))?;
+            serde_json::from_str(&meta_json)
+                .with_context(|| format!("Could not parse {:?}", meta_path))?
+        } else {
+            Meta {
+                info: None,
+                licenses: None,
+            }
+        };
+
+        let categories_path = dir.join(CATEGORIES_FILE);
+        Ok(CocoFile {
+            images: read_lines(&dir.join(IMAGES_FILE))?,
+            annotations: read_lines(&dir.join(ANNOTATIONS_FILE))?,
+            info: meta.info,
+            categories: if categories_path.exists() {
+                Some(read_lines(&categories_path)?)
+            } else {
+                None
+            },
+            licenses: meta.licenses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ndjson_round_trip() {
+        let json = r#"{
+            "info": {
+                "year": 2024,
+                "version": "1.0",
+                "description": "ndjson",
+                "contributor": "",
+                "url": "",
+                "date_created": "2024-01-01T00:00:00Z"
+            },
+            "licenses": [{"id": 1, "name": "CC BY 4.0", "url": ""}],
+            "images": [
+                {"id": 1, "width": 100, "height": 80, "file_name": "a.jpg", "license": 1},
+                {"id": 2, "width": 50, "height": 50, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[1.0, 2.0, 3.0, 4.0]],
+                 "area": 5.0, "bbox": [1.0, 2.0, 3.0, 4.0], "iscrowd": 0},
+                {"id": 2, "image_id": 2, "caption": "a line\nbreak"}
+            ],
+            "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("ndjson");
+
+        coco_file.write_ndjson(&dir).unwrap();
+        let annotations = fs::read_to_string(dir.join(ANNOTATIONS_FILE)).unwrap();
+        assert_eq!(annotations.lines().count(), 2);
+
+        let reloaded = CocoFile::from_ndjson(&dir).unwrap();
+        assert_eq!(
+            serde_json::to_value(&reloaded).unwrap(),
+            serde_json::to_value(&coco_file).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ndjson_appended_lines_and_optional_parts() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::write(
+            dir.join(IMAGES_FILE),
+            "{\"id\": 1, \"width\": 10, \"height\": 10, \"file_name\": \"a.jpg\"}\n\n",
+        )
+        .unwrap();
+        fs::write(dir.join(ANNOTATIONS_FILE), "").unwrap();
+
+        let coco_file = CocoFile::from_ndjson(dir).unwrap();
+        assert_eq!(coco_file.images.len(), 1);
+        assert!(coco_file.annotations.is_empty());
+        assert!(coco_file.categories.is_none());
+        assert!(coco_file.info.is_none());
+
+        fs::write(dir.join(ANNOTATIONS_FILE), "{\"id\": 1, \"image_id\": 1}\n").unwrap();
+        let err = CocoFile::from_ndjson(dir).err().unwrap();
+        assert!(format!("{:#}", err).contains("line 1"));
+    }
+}
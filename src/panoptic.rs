@@ -0,0 +1,180 @@
+use anyhow::{Context, Result, bail};
+use image::{ImageFormat, RgbImage};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::CocoPanopticSegmentationAnnotation;
+
+/// Segment id of a panoptic PNG pixel, `R + G * 256 + B * 256²`
+pub fn rgb_to_id([r, g, b]: [u8; 3]) -> u32 {
+    u32::from(r) | u32::from(g) << 8 | u32::from(b) << 16
+}
+
+/// Pixel color of a segment id, the inverse of [`rgb_to_id`] for ids below 256³
+pub fn id_to_rgb(id: u32) -> [u8; 3] {
+    [id as u8, (id >> 8) as u8, (id >> 16) as u8]
+}
+
+/// Reads the segment ids of a panoptic PNG as a row major vector, together with the width and
+/// height of the image
+pub fn decode_png(path: &Path) -> Result<(Vec<u32>, u32, u32)> {
+    let image = image::open(path)
+        .with_context(|| format!("Could not read panoptic PNG {:?}", path))?
+        .to_rgb8();
+    let ids = image.pixels().map(|pixel| rgb_to_id(pixel.0)).collect();
+    Ok((ids, image.width(), image.height()))
+}
+
+/// Writes row major segment ids as a panoptic PNG. Fails if `ids` isn't `width` x `height` or
+/// an id doesn't fit in 24 bits.
+pub fn encode_png(ids: &[u32], width: u32, height: u32, path: &Path) -> Result<()> {
+    if ids.len() != width as usize * height as usize {
+        bail!(
+            "{} segment ids don't make up a {}x{} image",
+            ids.len(),
+            width,
+            height
+        );
+    }
+    if let Some(id) = ids.iter().find(|&&id| id >= 1 << 24) {
+        bail!("segment id {} doesn't fit in an RGB pixel", id);
+    }
+
+    let pixels = ids.iter().flat_map(|&id| id_to_rgb(id)).collect();
+    RgbImage::from_raw(width, height, pixels)
+        .context("Could not build panoptic image")?
+        .save_with_format(path, ImageFormat::Png)
+        .with_context(|| format!("Could not write panoptic PNG {:?}", path))
+}
+
+impl CocoPanopticSegmentationAnnotation {
+    /// Reads the annotation's PNG from `base_dir` (the panoptic PNG directory, e.g.
+    /// `panoptic_val2017`) and returns a row major mask the size of the PNG for every segment,
+    /// keyed by segment id. Fails unless the ids of `segments_info` and the non zero ids of
+    /// the PNG are the same set, 0 being unlabeled.
+    pub fn load_segments(&self, base_dir: &Path) -> Result<HashMap<i64, Vec<bool>>> {
+        let (ids, _, _) = decode_png(&base_dir.join(&self.file_name))?;
+
+        let png_ids: BTreeSet<i64> = ids
+            .iter()
+            .filter(|&&id| id != 0)
+            .map(|&id| i64::from(id))
+            .collect();
+        let info_ids: BTreeSet<i64> = self
+            .segments_info
+            .iter()
+            .map(|segment| segment.id)
+            .collect();
+        let missing: Vec<&i64> = info_ids.difference(&png_ids).collect();
+        if !missing.is_empty() {
+            bail!(
+                "segments {:?} of image {} don't appear in {:?}",
+                missing,
+                self.image_id,
+                self.file_name
+            );
+        }
+        let unlisted: Vec<&i64> = png_ids.difference(&info_ids).collect();
+        if !unlisted.is_empty() {
+            bail!(
+                "{:?} of image {} has segments {:?} that aren't in segments_info",
+                self.file_name,
+                self.image_id,
+                unlisted
+            );
+        }
+
+        let mut masks: HashMap<i64, Vec<bool>> = info_ids
+            .iter()
+            .map(|&id| (id, vec![false; ids.len()]))
+            .collect();
+        for (index, &id) in ids.iter().enumerate() {
+            if let Some(mask) = masks.get_mut(&i64::from(id)) {
+                mask[index] = true;
+            }
+        }
+        Ok(masks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CocoPanopticSegmentInfo;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// A 6x4 image split into four 3x2 quadrants, with ids that need all three channels
+    fn quadrant_ids() -> Vec<u32> {
+        let quadrant_ids = [1, 300, 70_000, 0xABCDEF];
+        (0..4)
+            .flat_map(|y| (0..6).map(move |x| quadrant_ids[(y / 2) * 2 + x / 3]))
+            .collect()
+    }
+
+    fn annotation(segment_ids: &[i64]) -> CocoPanopticSegmentationAnnotation {
+        CocoPanopticSegmentationAnnotation {
+            image_id: 1,
+            file_name: PathBuf::from("1.png"),
+            segments_info: segment_ids
+                .iter()
+                .map(|&id| CocoPanopticSegmentInfo {
+                    id,
+                    category_id: 1,
+                    area: 6,
+                    bbox: [0.0, 0.0, 3.0, 2.0],
+                    iscrowd: false,
+                    extra: Default::default(),
+                })
+                .collect(),
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_id_rgb_conversion() {
+        assert_eq!(rgb_to_id([1, 2, 3]), 1 + 2 * 256 + 3 * 256 * 256);
+        assert_eq!(id_to_rgb(rgb_to_id([1, 2, 3])), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_encode_decode_png_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("1.png");
+        let ids = quadrant_ids();
+
+        encode_png(&ids, 6, 4, &path).unwrap();
+        assert_eq!(decode_png(&path).unwrap(), (ids, 6, 4));
+
+        assert!(encode_png(&[1, 2, 3], 2, 2, &path).is_err());
+        assert!(encode_png(&[1 << 24], 1, 1, &path).is_err());
+    }
+
+    #[test]
+    fn test_load_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        encode_png(&quadrant_ids(), 6, 4, &temp_dir.path().join("1.png")).unwrap();
+
+        let masks = annotation(&[1, 300, 70_000, 0xABCDEF])
+            .load_segments(temp_dir.path())
+            .unwrap();
+        assert_eq!(masks.len(), 4);
+        assert!(
+            masks
+                .values()
+                .all(|mask| mask.iter().filter(|&&set| set).count() == 6)
+        );
+        let top_right: Vec<bool> = (0..24).map(|i| i % 6 >= 3 && i / 6 < 2).collect();
+        assert_eq!(masks[&300], top_right);
+
+        // a listed segment missing from the PNG and a PNG segment missing from the list
+        let error = annotation(&[1, 300, 70_000, 0xABCDEF, 5])
+            .load_segments(temp_dir.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("[5]"));
+        let error = annotation(&[1, 300, 70_000])
+            .load_segments(temp_dir.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("[11259375]"));
+    }
+}
@@ -1,5 +1,5 @@
-use std::path::{Path, PathBuf};
 use anyhow::Result;
+use std::path::{Path, PathBuf};
 
 pub fn is_in_directory_tree(file_path: &Path, directory: &Path) -> Result<bool> {
     let file_path = file_path.canonicalize()?;
@@ -14,19 +14,21 @@ pub fn create_coco_image_path(
     force_absolute: bool,
 ) -> Result<PathBuf> {
     if force_absolute {
-        return Ok(image_file_path.canonicalize()?)
+        return Ok(image_file_path.canonicalize()?);
     }
 
-    let absolute_dataset_file_path = dataset_file_path.canonicalize()?;
-    let dataset_file_parent = absolute_dataset_file_path.parent().expect(format!("unable to get parent dir for {}", dataset_file_path.to_string_lossy()).as_str());
+    // canonicalize the parent so the dataset file doesn't have to exist yet
+    let dataset_file_parent = match dataset_file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => Path::new(".").canonicalize()?,
+    };
+    let dataset_file_parent = dataset_file_parent.as_path();
 
     if is_in_directory_tree(image_file_path, dataset_file_parent)? {
-        Ok(
-            image_file_path
+        Ok(image_file_path
             .canonicalize()?
             .strip_prefix(dataset_file_parent)?
-            .to_path_buf()
-        )
+            .to_path_buf())
     } else {
         Ok(image_file_path.canonicalize()?)
     }
@@ -0,0 +1,306 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How a crawl reads image dimensions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProbeMode {
+    /// parse just the file header for JPEG, PNG, GIF, BMP, WebP and TIFF, falling back to `Full`
+    /// for anything else
+    #[default]
+    Fast,
+    /// always go through the image crate's decoder
+    Full,
+}
+
+/// Reads `(width, height)` from the header of a JPEG, PNG, GIF, BMP, WebP or TIFF file without
+/// decoding it. Returns `None` for other formats or headers it can't make sense of, in which
+/// case the caller should fall back to a full decoder.
+pub fn probe_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let file = File::open(path).ok()?;
+    probe_reader(&mut BufReader::new(file)).ok().flatten()
+}
+
+fn probe_reader<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<(u32, u32)>> {
+    let mut header = Vec::with_capacity(32);
+    reader.by_ref().take(32).read_to_end(&mut header)?;
+
+    let dimensions = if header.starts_with(&[0xFF, 0xD8]) {
+        reader.seek(SeekFrom::Start(2))?;
+        probe_jpeg(reader)?
+    } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        probe_png(&header)
+    } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        probe_gif(&header)
+    } else if header.starts_with(b"BM") {
+        probe_bmp(&header)
+    } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP") {
+        probe_webp(&header)
+    } else if header.starts_with(b"II*\x00") {
+        probe_tiff(reader, &header, false)?
+    } else if header.starts_with(b"MM\x00*") {
+        probe_tiff(reader, &header, true)?
+    } else {
+        None
+    };
+
+    // a zero sized header is as good as no answer, let the decoder have a look
+    Ok(dimensions.filter(|&(width, height)| width > 0 && height > 0))
+}
+
+fn u16_le(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u24_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 3)?;
+    Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+}
+
+fn u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Walks the marker segments after SOI until the first start of frame
+fn probe_jpeg<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<(u32, u32)>> {
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] != 0xFF {
+            return Ok(None);
+        }
+        // markers may be preceded by any number of fill bytes
+        let mut marker = 0xFF;
+        while marker == 0xFF {
+            reader.read_exact(&mut byte)?;
+            marker = byte[0];
+        }
+
+        match marker {
+            // standalone markers without a length
+            0x01 | 0xD0..=0xD7 => continue,
+            // end of image or start of scan before any frame header
+            0xD9 | 0xDA => return Ok(None),
+            _ => {}
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let length = u16::from_be_bytes(length);
+        if length < 2 {
+            return Ok(None);
+        }
+
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let mut frame = [0u8; 5];
+            reader.read_exact(&mut frame)?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]);
+            let width = u16::from_be_bytes([frame[3], frame[4]]);
+            return Ok(Some((u32::from(width), u32::from(height))));
+        }
+
+        reader.seek(SeekFrom::Current(i64::from(length) - 2))?;
+    }
+}
+
+fn probe_png(header: &[u8]) -> Option<(u32, u32)> {
+    if header.get(12..16) != Some(b"IHDR") {
+        return None;
+    }
+    Some((u32_be(header, 16)?, u32_be(header, 20)?))
+}
+
+fn probe_gif(header: &[u8]) -> Option<(u32, u32)> {
+    Some((u32::from(u16_le(header, 6)?), u32::from(u16_le(header, 8)?)))
+}
+
+fn probe_bmp(header: &[u8]) -> Option<(u32, u32)> {
+    match u32_le(header, 14)? {
+        // BITMAPCOREHEADER stores unsigned 16 bit dimensions
+        12 => Some((
+            u32::from(u16_le(header, 18)?),
+            u32::from(u16_le(header, 20)?),
+        )),
+        // everything newer stores signed 32 bit dimensions, negative height means top-down
+        _ => {
+            let width = u32_le(header, 18)? as i32;
+            let height = u32_le(header, 22)? as i32;
+            Some((width.unsigned_abs(), height.unsigned_abs()))
+        }
+    }
+}
+
+fn probe_webp(header: &[u8]) -> Option<(u32, u32)> {
+    match header.get(12..16)? {
+        // lossy, dimensions follow the frame start code
+        b"VP8 " => {
+            if header.get(23..26) != Some(&[0x9D, 0x01, 0x2A]) {
+                return None;
+            }
+            Some((
+                u32::from(u16_le(header, 26)? & 0x3FFF),
+                u32::from(u16_le(header, 28)? & 0x3FFF),
+            ))
+        }
+        // lossless, 14 bit width - 1 and height - 1 packed after the signature byte
+        b"VP8L" => {
+            if header.get(20) != Some(&0x2F) {
+                return None;
+            }
+            let bits = u32_le(header, 21)?;
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // extended, 24 bit canvas width - 1 and height - 1
+        b"VP8X" => Some((u24_le(header, 24)? + 1, u24_le(header, 27)? + 1)),
+        _ => None,
+    }
+}
+
+/// Reads the ImageWidth and ImageLength tags of the first image file directory, which may be
+/// anywhere in the file, often after the pixel data. BigTIFF isn't handled.
+fn probe_tiff<R: Read + Seek>(
+    reader: &mut R,
+    header: &[u8],
+    big_endian: bool,
+) -> std::io::Result<Option<(u32, u32)>> {
+    let u16_at = |bytes: &[u8], offset: usize| -> Option<u16> {
+        let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |bytes: &[u8], offset: usize| -> Option<u32> {
+        if big_endian {
+            u32_be(bytes, offset)
+        } else {
+            u32_le(bytes, offset)
+        }
+    };
+
+    let Some(ifd_offset) = u32_at(header, 4) else {
+        return Ok(None);
+    };
+    reader.seek(SeekFrom::Start(u64::from(ifd_offset)))?;
+    let mut count = [0u8; 2];
+    reader.read_exact(&mut count)?;
+    let Some(count) = u16_at(&count, 0) else {
+        return Ok(None);
+    };
+
+    let (mut width, mut height) = (None, None);
+    let mut entry = [0u8; 12];
+    for _ in 0..count {
+        reader.read_exact(&mut entry)?;
+        // SHORT values sit in the first two bytes of the value field, LONG values fill it
+        let value = match u16_at(&entry, 2) {
+            Some(3) => u16_at(&entry, 8).map(u32::from),
+            Some(4) => u32_at(&entry, 8),
+            _ => None,
+        };
+        match u16_at(&entry, 0) {
+            Some(256) => width = value,
+            Some(257) => height = value,
+            _ => {}
+        }
+        if let (Some(width), Some(height)) = (width, height) {
+            return Ok(Some((width, height)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageBuffer, ImageEncoder, ImageReader, Rgb};
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    fn full_dimensions(path: &Path) -> (u32, u32) {
+        ImageReader::open(path)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .into_dimensions()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_probe_matches_full_decoder() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = ImageBuffer::from_fn(37, 21, |x, y| Rgb([x as u8, y as u8, 0u8]));
+
+        for extension in ["jpg", "png", "gif", "bmp", "webp", "tiff"] {
+            let path = temp_dir.path().join(format!("image.{}", extension));
+            image.save(&path).unwrap();
+
+            assert_eq!(
+                probe_dimensions(&path),
+                Some(full_dimensions(&path)),
+                "{} dimensions differ",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn test_probe_jpeg_skips_segments_before_frame() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("exif.jpg");
+
+        // a large APP1 segment pushes the frame header well past the first read
+        let image = ImageBuffer::from_fn(300, 17, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+        let mut encoder = JpegEncoder::new(File::create(&path).unwrap());
+        encoder.set_exif_metadata(vec![0u8; 4000]).unwrap();
+        encoder
+            .write_image(image.as_raw(), 300, 17, image::ExtendedColorType::Rgb8)
+            .unwrap();
+
+        assert_eq!(probe_dimensions(&path), Some((300, 17)));
+    }
+
+    #[test]
+    fn test_probe_big_endian_tiff() {
+        // an IFD after 4 bytes of "pixel data" with a SHORT width and a LONG height
+        let mut tiff = b"MM\x00*\x00\x00\x00\x0c\x00\x00\x00\x00".to_vec();
+        tiff.extend([0x00, 0x03]);
+        tiff.extend([0x01, 0x03, 0x00, 0x03, 0, 0, 0, 1, 0x00, 0x10, 0, 0]);
+        tiff.extend([0x01, 0x00, 0x00, 0x03, 0, 0, 0, 1, 0x01, 0x2C, 0, 0]);
+        tiff.extend([0x01, 0x01, 0x00, 0x04, 0, 0, 0, 1, 0x00, 0x01, 0x00, 0x00]);
+
+        let dimensions = probe_reader(&mut Cursor::new(tiff)).unwrap();
+        assert_eq!(dimensions, Some((300, 65536)));
+    }
+
+    #[test]
+    fn test_probe_falls_back_on_unknown_or_truncated_headers() {
+        let probe = |bytes: &[u8]| {
+            probe_reader(&mut Cursor::new(bytes.to_vec()))
+                .ok()
+                .flatten()
+        };
+
+        assert_eq!(probe(b""), None);
+        assert_eq!(probe(b"8BPS not handled"), None);
+        assert_eq!(probe(b"II*\x00\x08\x00\x00\x00\x01\x00"), None);
+        assert_eq!(probe(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), None);
+        assert_eq!(
+            probe(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00"),
+            None
+        );
+    }
+}
@@ -0,0 +1,12 @@
+use indicatif::ProgressBar;
+use std::io::IsTerminal;
+
+/// Creates a progress bar of `len` steps, hidden when `quiet` is set or stderr is not a terminal
+/// so piped and redirected output stays clean
+pub fn progress_bar(len: u64, quiet: bool) -> ProgressBar {
+    if quiet || !std::io::stderr().is_terminal() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(len)
+    }
+}
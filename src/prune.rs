@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{CocoAnnotation, CocoFile, HasID};
+
+/// What a prune removed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// ids of the removed categories, sorted
+    pub categories: Vec<i32>,
+    /// number of removed annotations, counting panoptic segments individually
+    pub annotations: usize,
+    /// ids of the removed images, in file order
+    pub images: Vec<i64>,
+}
+
+impl CocoFile {
+    /// Removes the categories with fewer than `min_annotations` annotations together with their
+    /// annotations. Panoptic segments count and are removed individually, a panoptic
+    /// annotation only goes once all its segments are gone.
+    pub fn prune_categories(&mut self, min_annotations: usize) -> PruneReport {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for annotation in &self.annotations {
+            for category_id in annotation.category_ids() {
+                *counts.entry(category_id).or_default() += 1;
+            }
+        }
+
+        let mut categories: Vec<i32> = self
+            .categories
+            .iter()
+            .flatten()
+            .map(HasID::id)
+            .filter(|id| counts.get(id).copied().unwrap_or(0) < min_annotations)
+            .collect();
+        categories.sort();
+        categories.dedup();
+
+        let annotations = self
+            .drop_categories(&categories)
+            .expect("pruned categories exist");
+        PruneReport {
+            categories,
+            annotations,
+            images: Vec::new(),
+        }
+    }
+
+    /// Removes the images without annotations. Captions don't count, an image with only
+    /// captions is removed together with them.
+    pub fn prune_unannotated_images(&mut self) -> PruneReport {
+        let annotated_image_ids: HashSet<i64> = self
+            .annotations
+            .iter()
+            .filter(|annotation| !matches!(annotation, CocoAnnotation::ImageCaptioning(_)))
+            .map(CocoAnnotation::image_id)
+            .collect();
+
+        let images: Vec<i64> = self
+            .images
+            .iter()
+            .map(|image| image.id)
+            .filter(|id| !annotated_image_ids.contains(id))
+            .collect();
+        self.images
+            .retain(|image| annotated_image_ids.contains(&image.id));
+
+        let annotation_count = self.annotations.len();
+        self.annotations
+            .retain(|annotation| annotated_image_ids.contains(&annotation.image_id()));
+        PruneReport {
+            categories: Vec::new(),
+            annotations: annotation_count - self.annotations.len(),
+            images,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_tail_file() -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+                {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"},
+                {"id": 3, "width": 100, "height": 100, "file_name": "c.jpg"},
+                {"id": 4, "width": 100, "height": 100, "file_name": "d.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"id": 3, "image_id": 2, "category_id": 2, "segmentation": [], "area": 1.0,
+                    "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                {"image_id": 3, "file_name": "c.png", "segments_info": [
+                    {"id": 1, "category_id": 1, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 2, "category_id": 3, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 3, "category_id": 3, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0}
+                ]},
+                {"image_id": 4, "file_name": "d.png", "segments_info": [
+                    {"id": 1, "category_id": 2, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0}
+                ]},
+                {"id": 4, "image_id": 2, "caption": "a rare thing"}
+            ],
+            "categories": [
+                {"id": 1, "name": "common", "supercategory": "thing"},
+                {"id": 2, "name": "rare", "supercategory": "thing"},
+                {"id": 3, "name": "stuff", "supercategory": "stuff", "isthing": 0,
+                    "color": [0, 0, 0]},
+                {"id": 4, "name": "unused", "supercategory": "thing"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_prune_categories() {
+        let mut coco_file = long_tail_file();
+        let report = coco_file.prune_categories(3);
+        // common has 2 boxes and 1 segment, stuff 2 segments, rare 1 box and 1 segment
+        assert_eq!(report.categories, vec![2, 3, 4]);
+        assert_eq!(report.annotations, 4);
+        assert!(report.images.is_empty());
+
+        let remaining: Vec<i32> = coco_file
+            .annotations
+            .iter()
+            .flat_map(CocoAnnotation::category_ids)
+            .collect();
+        assert_eq!(remaining, vec![1, 1, 1]);
+        // image 4's panoptic annotation lost its only segment, image 3's kept one
+        assert_eq!(coco_file.annotations.len(), 4);
+        assert_eq!(coco_file.annotations[2].category_ids(), vec![1]);
+        assert_eq!(coco_file.prune_categories(3), PruneReport::default());
+    }
+
+    #[test]
+    fn test_prune_unannotated_images() {
+        let mut coco_file = long_tail_file();
+        assert_eq!(coco_file.prune_unannotated_images(), PruneReport::default());
+
+        coco_file.prune_categories(3);
+        let report = coco_file.prune_unannotated_images();
+        assert_eq!(report.images, vec![2, 4]);
+        assert_eq!(report.annotations, 1, "the caption of image 2 goes with it");
+        let image_ids: Vec<i64> = coco_file.images.iter().map(|image| image.id).collect();
+        assert_eq!(image_ids, vec![1, 3]);
+        assert_eq!(coco_file.annotations.len(), 3);
+    }
+}
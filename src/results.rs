@@ -0,0 +1,372 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::{
+    CocoAnnotation, CocoFile, CocoKeypointDetectionAnnotation, CocoObjectDetectionAnnotation,
+    CocoSegmentation,
+};
+
+/// A box prediction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CocoDetectionResult {
+    pub image_id: i64,
+    pub category_id: i32,
+    pub bbox: [f32; 4],
+    pub score: f32,
+}
+
+/// A mask prediction, usually with the box around it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CocoSegmentationResult {
+    pub image_id: i64,
+    pub category_id: i32,
+    pub segmentation: CocoSegmentation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f32; 4]>,
+    pub score: f32,
+}
+
+/// A keypoint prediction, usually with the box of the instance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CocoKeypointResult {
+    pub image_id: i64,
+    pub category_id: i32,
+    pub keypoints: Vec<f32>, // [x1, y1, v1, x2, y2, v2, ..., xn, yn, vn]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f32; 4]>,
+    pub score: f32,
+}
+
+/// One prediction of a results file. Keypoint results come first since they may also carry a
+/// box, then masks, then plain boxes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum CocoResult {
+    Keypoint(CocoKeypointResult),
+    Segmentation(CocoSegmentationResult),
+    Detection(CocoDetectionResult),
+}
+
+impl CocoKeypointResult {
+    /// The box of the prediction, or the box around its keypoints if it has none
+    pub fn bounding_box(&self) -> [f32; 4] {
+        self.bbox
+            .unwrap_or_else(|| keypoints_bounding_box(&self.keypoints))
+    }
+}
+
+impl CocoResult {
+    pub fn image_id(&self) -> i64 {
+        match self {
+            CocoResult::Keypoint(result) => result.image_id,
+            CocoResult::Segmentation(result) => result.image_id,
+            CocoResult::Detection(result) => result.image_id,
+        }
+    }
+}
+
+/// A COCO results file, the bare array of predictions written by evaluation pipelines
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct CocoResults(pub Vec<CocoResult>);
+
+impl CocoResults {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Could not open {:?}", path))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Could not parse COCO results {:?}", path))
+    }
+
+    /// The images, categories, licenses and info of `ground_truth` with the predictions as
+    /// annotations numbered from 1, the way the COCO API loads results. Boxes missing from
+    /// masks and keypoints are computed from them, and areas from masks or boxes. Fails if a
+    /// prediction refers to an image `ground_truth` doesn't have.
+    pub fn into_coco_file(self, ground_truth: &CocoFile) -> Result<CocoFile> {
+        let image_ids: HashSet<i64> = ground_truth.images.iter().map(|image| image.id).collect();
+        let annotations = self
+            .0
+            .into_iter()
+            .zip(1..)
+            .map(|(result, id)| {
+                if !image_ids.contains(&result.image_id()) {
+                    bail!(
+                        "prediction {} refers to image {}, which isn't in the ground truth",
+                        id,
+                        result.image_id()
+                    );
+                }
+                Ok(result_annotation(result, id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CocoFile {
+            images: ground_truth.images.clone(),
+            annotations,
+            info: ground_truth.info.clone(),
+            categories: ground_truth.categories.clone(),
+            licenses: ground_truth.licenses.clone(),
+        })
+    }
+}
+
+fn result_annotation(result: CocoResult, id: i64) -> CocoAnnotation {
+    match result {
+        CocoResult::Detection(result) => {
+            CocoAnnotation::ObjectDetection(CocoObjectDetectionAnnotation {
+                id,
+                image_id: result.image_id,
+                category_id: result.category_id,
+                segmentation: CocoSegmentation::Polygon(vec![]),
+                area: result.bbox[2] * result.bbox[3],
+                bbox: result.bbox,
+                iscrowd: false,
+                score: Some(result.score),
+                attributes: None,
+                extra: Default::default(),
+            })
+        }
+        CocoResult::Segmentation(result) => {
+            let bbox = result
+                .bbox
+                .or_else(|| result.segmentation.bounding_box())
+                .unwrap_or_default();
+            CocoAnnotation::ObjectDetection(CocoObjectDetectionAnnotation {
+                id,
+                image_id: result.image_id,
+                category_id: result.category_id,
+                area: result.segmentation.area().unwrap_or(bbox[2] * bbox[3]),
+                segmentation: result.segmentation,
+                bbox,
+                iscrowd: false,
+                score: Some(result.score),
+                attributes: None,
+                extra: Default::default(),
+            })
+        }
+        CocoResult::Keypoint(result) => {
+            let bbox = result.bounding_box();
+            let mut annotation = CocoKeypointDetectionAnnotation {
+                id,
+                image_id: result.image_id,
+                category_id: result.category_id,
+                segmentation: CocoSegmentation::Polygon(vec![]),
+                area: bbox[2] * bbox[3],
+                bbox,
+                iscrowd: false,
+                keypoints: result.keypoints,
+                num_keypoints: 0,
+                score: Some(result.score),
+                attributes: None,
+                extra: Default::default(),
+            };
+            annotation.recount_num_keypoints();
+            CocoAnnotation::KeypointDetection(annotation)
+        }
+    }
+}
+
+/// `[x, y, w, h]` around every keypoint whatever its visibility, as the COCO API computes it
+fn keypoints_bounding_box(keypoints: &[f32]) -> [f32; 4] {
+    let mut triplets = keypoints.chunks_exact(3).peekable();
+    if triplets.peek().is_none() {
+        return [0.0; 4];
+    }
+    let (x0, y0, x1, y1) = triplets.fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(x0, y0, x1, y1), triplet| {
+            (
+                x0.min(triplet[0]),
+                y0.min(triplet[1]),
+                x1.max(triplet[0]),
+                y1.max(triplet[1]),
+            )
+        },
+    );
+    [x0, y0, x1 - x0, y1 - y0]
+}
+
+fn has_segmentation(segmentation: &CocoSegmentation) -> bool {
+    match segmentation {
+        CocoSegmentation::Polygon(polygons) => polygons.iter().any(|polygon| !polygon.is_empty()),
+        CocoSegmentation::RLE(_) => true,
+    }
+}
+
+impl CocoFile {
+    /// The object detection and keypoint annotations as predictions, scored with their own
+    /// score or `score` if they have none, such as ground truth. Annotations with a mask
+    /// become mask predictions, the others box predictions. Captions, panoptic and DensePose
+    /// annotations are left out.
+    pub fn to_results(&self, score: f32) -> CocoResults {
+        CocoResults(
+            self.annotations
+                .iter()
+                .filter_map(|annotation| match annotation {
+                    CocoAnnotation::ObjectDetection(ann) if has_segmentation(&ann.segmentation) => {
+                        Some(CocoResult::Segmentation(CocoSegmentationResult {
+                            image_id: ann.image_id,
+                            category_id: ann.category_id,
+                            segmentation: ann.segmentation.clone(),
+                            bbox: Some(ann.bbox),
+                            score: ann.score.unwrap_or(score),
+                        }))
+                    }
+                    CocoAnnotation::ObjectDetection(ann) => {
+                        Some(CocoResult::Detection(CocoDetectionResult {
+                            image_id: ann.image_id,
+                            category_id: ann.category_id,
+                            bbox: ann.bbox,
+                            score: ann.score.unwrap_or(score),
+                        }))
+                    }
+                    CocoAnnotation::KeypointDetection(ann) => {
+                        Some(CocoResult::Keypoint(CocoKeypointResult {
+                            image_id: ann.image_id,
+                            category_id: ann.category_id,
+                            keypoints: ann.keypoints.clone(),
+                            bbox: Some(ann.bbox),
+                            score: ann.score.unwrap_or(score),
+                        }))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CocoRLE;
+
+    /// Abridged from a Detectron2 `coco_instances_results.json`, a box, a mask and a keypoint
+    /// prediction
+    const DETECTRON2_RESULTS: &str = r#"[
+        {"image_id": 139, "category_id": 62, "bbox": [5.4537, 166.6412, 150.3107, 96.7398],
+         "score": 0.9845},
+        {"image_id": 139, "category_id": 1, "bbox": [0.0, 0.0, 3.0, 2.0], "score": 0.9327,
+         "segmentation": {"size": [4, 4], "counts": "52203"}},
+        {"image_id": 785, "category_id": 1, "bbox": [283.2, 41.9, 196.7, 345.5],
+         "score": 0.9988, "keypoints": [371.5, 67.1, 0.8817, 376.6, 61.9, 0.9124,
+                                        366.3, 62.4, 0.6251]}
+    ]"#;
+
+    fn ground_truth() -> CocoFile {
+        serde_json::from_value(serde_json::json!({
+            "images": [
+                {"id": 139, "width": 640, "height": 426, "file_name": "000000000139.jpg"},
+                {"id": 785, "width": 640, "height": 425, "file_name": "000000000785.jpg"}
+            ],
+            "annotations": [],
+            "categories": [
+                {"id": 1, "name": "person", "supercategory": "person"},
+                {"id": 62, "name": "chair", "supercategory": "furniture"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_results_roundtrip() {
+        let results: CocoResults = serde_json::from_str(DETECTRON2_RESULTS).unwrap();
+        assert!(matches!(results.0[0], CocoResult::Detection(_)));
+        let CocoResult::Segmentation(segmentation) = &results.0[1] else {
+            panic!("expected a mask prediction");
+        };
+        assert_eq!(
+            segmentation.segmentation,
+            CocoSegmentation::RLE(CocoRLE {
+                counts: CocoRLE::decode_compressed_counts("52203").unwrap(),
+                size: (4, 4),
+            })
+        );
+        let CocoResult::Keypoint(keypoint) = &results.0[2] else {
+            panic!("expected a keypoint prediction");
+        };
+        assert_eq!(keypoint.keypoints.len(), 9);
+        assert_eq!(keypoint.bbox, Some([283.2, 41.9, 196.7, 345.5]));
+
+        let reserialized = serde_json::to_string(&results).unwrap();
+        assert!(reserialized.starts_with('['));
+        let reparsed: CocoResults = serde_json::from_str(&reserialized).unwrap();
+        assert_eq!(reparsed, results);
+    }
+
+    #[test]
+    fn test_results_into_coco_file() {
+        let results: CocoResults = serde_json::from_str(DETECTRON2_RESULTS).unwrap();
+        let coco_file = results.clone().into_coco_file(&ground_truth()).unwrap();
+        assert_eq!(coco_file.images.len(), 2);
+        assert_eq!(coco_file.categories.as_ref().unwrap().len(), 2);
+
+        let ids: Vec<Option<i64>> = coco_file.annotations.iter().map(|ann| ann.id()).collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+        let boxes = coco_file.annotations[0].as_object_detection().unwrap();
+        assert_eq!(boxes.score, Some(0.9845));
+        assert_eq!(boxes.area, 150.3107 * 96.7398);
+        let mask = coco_file.annotations[1].as_object_detection().unwrap();
+        assert_eq!(Some(mask.area), mask.segmentation.area());
+        let keypoints = coco_file.annotations[2].as_keypoint_detection().unwrap();
+        assert_eq!(keypoints.num_keypoints, 3);
+
+        // and back to the same predictions
+        assert_eq!(coco_file.to_results(1.0), results);
+
+        let mut unknown_image = results;
+        let CocoResult::Detection(detection) = &mut unknown_image.0[0] else {
+            unreachable!();
+        };
+        detection.image_id = 1;
+        assert!(unknown_image.into_coco_file(&ground_truth()).is_err());
+    }
+
+    #[test]
+    fn test_to_results_defaults_score() {
+        let coco_file: CocoFile = serde_json::from_value(serde_json::json!({
+            "images": [{"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 4.0,
+                    "bbox": [0, 0, 2, 2], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "caption": "a cat"},
+                {"id": 3, "image_id": 1, "category_id": 1, "keypoints": [1, 1, 2, 9, 5, 0],
+                    "num_keypoints": 1, "segmentation": [], "area": 0.0, "bbox": [0, 0, 0, 0],
+                    "iscrowd": 0}
+            ]
+        }))
+        .unwrap();
+        let results = coco_file.to_results(1.0);
+        assert_eq!(
+            results.0,
+            vec![
+                CocoResult::Detection(CocoDetectionResult {
+                    image_id: 1,
+                    category_id: 1,
+                    bbox: [0.0, 0.0, 2.0, 2.0],
+                    score: 1.0,
+                }),
+                CocoResult::Keypoint(CocoKeypointResult {
+                    image_id: 1,
+                    category_id: 1,
+                    keypoints: vec![1.0, 1.0, 2.0, 9.0, 5.0, 0.0],
+                    bbox: Some([0.0, 0.0, 0.0, 0.0]),
+                    score: 1.0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keypoints_bounding_box() {
+        // invisible keypoints count too
+        assert_eq!(
+            keypoints_bounding_box(&[1.0, 1.0, 2.0, 9.0, 5.0, 0.0]),
+            [1.0, 1.0, 8.0, 4.0]
+        );
+        assert_eq!(keypoints_bounding_box(&[]), [0.0; 4]);
+    }
+}
@@ -0,0 +1,197 @@
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::filter::ImageFilterOptions;
+use crate::{CocoFile, DuplicateImageIdError};
+
+impl CocoFile {
+    /// A copy with `count` images drawn at random, reproducibly for the same `seed`, and their
+    /// annotations. Keeps every image if there are fewer.
+    pub fn sample_images(
+        &self,
+        count: usize,
+        seed: u64,
+    ) -> Result<CocoFile, DuplicateImageIdError> {
+        let mut image_ids: Vec<i64> = self.images.iter().map(|image| image.id).collect();
+        image_ids.sort();
+        image_ids.shuffle(&mut StdRng::seed_from_u64(seed));
+        let selected: HashSet<i64> = image_ids.into_iter().take(count).collect();
+        self.filter_images(
+            |image, _| selected.contains(&image.id),
+            &ImageFilterOptions::default(),
+        )
+    }
+
+    /// A copy where every category is represented by up to `max_images_per_category` images,
+    /// and their annotations. Images are picked greedily for the rarest categories first, in a
+    /// random order that is reproducible for the same `seed`. An image counts toward every
+    /// category it contains, so common categories can end up with more images than the
+    /// maximum. Images without categories are left out.
+    pub fn subsample_per_category(
+        &self,
+        max_images_per_category: usize,
+        seed: u64,
+    ) -> Result<CocoFile, DuplicateImageIdError> {
+        let image_id_map = self.make_image_id_map_quiet()?;
+        let mut image_categories: Vec<(i64, Vec<i32>)> = image_id_map
+            .values()
+            .map(|entry| {
+                let mut category_ids: Vec<i32> = entry
+                    .annotations
+                    .iter()
+                    .flat_map(|annotation| annotation.category_ids())
+                    .collect();
+                category_ids.sort();
+                category_ids.dedup();
+                (entry.id, category_ids)
+            })
+            .filter(|(_, category_ids)| !category_ids.is_empty())
+            .collect();
+        image_categories.sort();
+        image_categories.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        let mut images_by_category: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for (index, (_, category_ids)) in image_categories.iter().enumerate() {
+            for &category_id in category_ids {
+                images_by_category
+                    .entry(category_id)
+                    .or_default()
+                    .push(index);
+            }
+        }
+        let mut rarest_first: Vec<(&i32, &Vec<usize>)> = images_by_category.iter().collect();
+        rarest_first.sort_by_key(|(_, images)| images.len());
+
+        let mut selected = vec![false; image_categories.len()];
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for (category_id, images) in rarest_first {
+            for &index in images {
+                if counts.get(category_id).copied().unwrap_or(0) >= max_images_per_category {
+                    break;
+                }
+                if !selected[index] {
+                    selected[index] = true;
+                    for &image_category_id in &image_categories[index].1 {
+                        *counts.entry(image_category_id).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let selected_ids: HashSet<i64> = image_categories
+            .iter()
+            .zip(selected)
+            .filter(|(_, selected)| *selected)
+            .map(|((id, _), _)| *id)
+            .collect();
+        self.filter_images(
+            |image, _| selected_ids.contains(&image.id),
+            &ImageFilterOptions::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CocoAnnotation;
+
+    /// Image `i` has a common category 1 annotation, every fifth image a rare category 2 one
+    /// as well, and image 50 only a caption
+    fn long_tail_file() -> CocoFile {
+        let mut annotations = Vec::new();
+        for image_id in 1..50 {
+            annotations.push(serde_json::json!({
+                "id": image_id * 10, "image_id": image_id, "category_id": 1, "segmentation": [],
+                "area": 1.0, "bbox": [0, 0, 1, 1], "iscrowd": 0
+            }));
+            if image_id % 5 == 0 {
+                annotations.push(serde_json::json!({
+                    "id": image_id * 10 + 1, "image_id": image_id, "category_id": 2,
+                    "segmentation": [], "area": 1.0, "bbox": [0, 0, 1, 1], "iscrowd": 0
+                }));
+            }
+        }
+        annotations.push(serde_json::json!({"id": 1000, "image_id": 50, "caption": "empty"}));
+        let images: Vec<serde_json::Value> = (1..=50)
+            .map(
+                |id| serde_json::json!({"id": id, "width": 10, "height": 10, "file_name": "a.jpg"}),
+            )
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "images": images,
+            "annotations": annotations,
+            "categories": [
+                {"id": 1, "name": "common", "supercategory": "thing"},
+                {"id": 2, "name": "rare", "supercategory": "thing"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn image_ids(coco_file: &CocoFile) -> Vec<i64> {
+        coco_file.images.iter().map(|image| image.id).collect()
+    }
+
+    fn images_with_category(coco_file: &CocoFile, category_id: i32) -> usize {
+        let image_ids: HashSet<i64> = coco_file
+            .annotations
+            .iter()
+            .filter(|annotation| annotation.category_id() == Some(category_id))
+            .map(CocoAnnotation::image_id)
+            .collect();
+        image_ids.len()
+    }
+
+    #[test]
+    fn test_sample_images() {
+        let coco_file = long_tail_file();
+        let sample = coco_file.sample_images(10, 42).unwrap();
+        assert_eq!(sample.images.len(), 10);
+        assert_eq!(
+            image_ids(&sample),
+            image_ids(&coco_file.sample_images(10, 42).unwrap())
+        );
+        assert_ne!(
+            image_ids(&sample),
+            image_ids(&coco_file.sample_images(10, 7).unwrap())
+        );
+
+        let sampled: HashSet<i64> = image_ids(&sample).into_iter().collect();
+        assert!(
+            sample
+                .annotations
+                .iter()
+                .all(|annotation| sampled.contains(&annotation.image_id()))
+        );
+        assert_eq!(coco_file.sample_images(100, 42).unwrap().images.len(), 50);
+    }
+
+    #[test]
+    fn test_subsample_per_category() {
+        let coco_file = long_tail_file();
+        let sample = coco_file.subsample_per_category(3, 42).unwrap();
+        // the 3 rare images are picked first and already cover the common category
+        assert_eq!(sample.images.len(), 3);
+        assert_eq!(images_with_category(&sample, 1), 3);
+        assert_eq!(images_with_category(&sample, 2), 3);
+        assert_eq!(
+            image_ids(&sample),
+            image_ids(&coco_file.subsample_per_category(3, 42).unwrap())
+        );
+
+        let sample = coco_file.subsample_per_category(20, 42).unwrap();
+        // all 9 rare images, then 11 more of the common category
+        assert_eq!(images_with_category(&sample, 2), 9);
+        assert_eq!(images_with_category(&sample, 1), 20);
+        assert!(!image_ids(&sample).contains(&50));
+
+        assert!(
+            coco_file
+                .subsample_per_category(0, 42)
+                .unwrap()
+                .images
+                .is_empty()
+        );
+    }
+}
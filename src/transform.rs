@@ -0,0 +1,952 @@
+use anyhow::{Result, bail};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::geometry::{BBox, HasBBox, Transform};
+use crate::{
+    CocoAnnotation, CocoFile, CocoImage, CocoKeypointDetectionAnnotation,
+    CocoObjectDetectionAnnotation, CocoPolygon, CocoRLE, CocoSegmentation,
+};
+
+/// Clamps `bbox` into a `width` x `height` image, returns whether it changed
+fn clamp_bbox(bbox: &mut [f32; 4], width: f32, height: f32) -> bool {
+    let clamped = BBox::from(*bbox).clamp_to(width, height).to_xywh();
+    let changed = clamped != *bbox;
+    *bbox = clamped;
+    changed
+}
+
+/// Clamps polygon points into a `width` x `height` image, returns whether any moved
+fn clamp_segmentation(segmentation: &mut CocoSegmentation, width: f32, height: f32) -> bool {
+    let CocoSegmentation::Polygon(polygons) = segmentation else {
+        // RLE masks are sized to the image already
+        return false;
+    };
+    let mut changed = false;
+    polygons.iter_mut().for_each(|polygon| {
+        polygon.chunks_exact_mut(2).for_each(|point| {
+            let clamped = [point[0].clamp(0.0, width), point[1].clamp(0.0, height)];
+            changed |= clamped[..] != point[..];
+            point.copy_from_slice(&clamped);
+        })
+    });
+    changed
+}
+
+/// Area of the polygons, or of the bbox if there are no polygons. `None` for RLE
+/// segmentations.
+fn polygon_or_bbox_area(segmentation: &CocoSegmentation, bbox: &[f32; 4]) -> Option<f32> {
+    match segmentation {
+        CocoSegmentation::Polygon(_) => Some(segmentation.area().unwrap_or(bbox[2] * bbox[3])),
+        CocoSegmentation::RLE(_) => None,
+    }
+}
+
+/// Bbox and area of a segmentation, `None` if it has no polygon or is an empty mask
+fn segmentation_geometry(segmentation: &CocoSegmentation) -> Option<([f32; 4], f32)> {
+    Some((segmentation.bounding_box()?, segmentation.area()?))
+}
+
+/// Whether any bbox value or the area differ by more than `tolerance`
+fn geometry_differs(a: ([f32; 4], f32), b: ([f32; 4], f32), tolerance: f32) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .chain(std::iter::once((&a.1, &b.1)))
+        .any(|(a, b)| (a - b).abs() > tolerance)
+}
+
+/// Sutherland-Hodgman clip of a polygon to the `width` x `height` rectangle at the origin. A
+/// polygon the rectangle cuts into several pieces stays one polygon whose pieces are joined by
+/// zero width bridges along the rectangle's edge.
+fn clip_polygon(polygon: &[f32], width: f32, height: f32) -> CocoPolygon {
+    // (axis, bound, whether the inside is above the bound)
+    let edges = [
+        (0, 0.0, true),
+        (0, width, false),
+        (1, 0.0, true),
+        (1, height, false),
+    ];
+    let mut points: Vec<[f32; 2]> = polygon
+        .chunks_exact(2)
+        .map(|point| [point[0], point[1]])
+        .collect();
+    for (axis, bound, above) in edges {
+        let inside = |point: &[f32; 2]| {
+            if above {
+                point[axis] >= bound
+            } else {
+                point[axis] <= bound
+            }
+        };
+        let crossing = |a: &[f32; 2], b: &[f32; 2]| {
+            let t = (bound - a[axis]) / (b[axis] - a[axis]);
+            let mut point = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+            point[axis] = bound;
+            point
+        };
+        let mut clipped = Vec::with_capacity(points.len() + 4);
+        for (i, current) in points.iter().enumerate() {
+            let previous = &points[(i + points.len() - 1) % points.len()];
+            match (inside(previous), inside(current)) {
+                (true, true) => clipped.push(*current),
+                (true, false) => clipped.push(crossing(previous, current)),
+                (false, true) => {
+                    clipped.push(crossing(previous, current));
+                    clipped.push(*current);
+                }
+                (false, false) => {}
+            }
+        }
+        points = clipped;
+    }
+    points.into_iter().flatten().collect()
+}
+
+/// Distance from `point` to the segment between `a` and `b`
+fn segment_distance(point: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((point[0] - a[0]) * dx + (point[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (point[0] - a[0] - t * dx).hypot(point[1] - a[1] - t * dy)
+}
+
+/// Douglas-Peucker simplification of an open polyline, keeping both endpoints
+fn simplify_polyline(polyline: &[f32], epsilon: f32) -> Vec<f32> {
+    let points: Vec<[f32; 2]> = polyline
+        .chunks_exact(2)
+        .map(|point| [point[0], point[1]])
+        .collect();
+    if points.len() < 3 {
+        return polyline[..points.len() * 2].to_vec();
+    }
+
+    let last = points.len() - 1;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[last] = true;
+    // a stack instead of recursion, dense polygons can have many thousands of points
+    let mut ranges = vec![(0, last)];
+    while let Some((start, end)) = ranges.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+        let (index, distance) = (start + 1..end)
+            .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("range has inner points");
+        if distance > epsilon {
+            keep[index] = true;
+            ranges.push((start, index));
+            ranges.push((index, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .flat_map(|(point, _)| *point)
+        .collect()
+}
+
+/// Douglas-Peucker simplification of a closed polygon, split into two polylines at its first
+/// point and the point farthest from it. `None` if fewer than 3 points are left, as the polygon
+/// then has no area.
+fn simplify_ring(polygon: &[f32], epsilon: f32) -> Option<CocoPolygon> {
+    let point_count = polygon.len() / 2;
+    if point_count < 3 {
+        return None;
+    }
+    let distance = |i: usize| (polygon[2 * i] - polygon[0]).hypot(polygon[2 * i + 1] - polygon[1]);
+    let farthest = (1..point_count)
+        .max_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+        .expect("polygon has more than one point");
+
+    let mut simplified = simplify_polyline(&polygon[..2 * farthest + 2], epsilon);
+    // the second half runs back to the first point, which the first half already has
+    let mut second_half = polygon[2 * farthest..2 * point_count].to_vec();
+    second_half.extend_from_slice(&polygon[..2]);
+    let second_half = simplify_polyline(&second_half, epsilon);
+    simplified.extend_from_slice(&second_half[2..second_half.len() - 2]);
+    (simplified.len() >= 6).then_some(simplified)
+}
+
+/// The part of the mask under `crop`, sampled at pixel centers
+fn crop_rle(rle: &CocoRLE, crop: &BBox) -> CocoRLE {
+    let (width, height) = (rle.size.1 as usize, rle.size.0 as usize);
+    let (new_width, new_height) = (crop.width().round() as u32, crop.height().round() as u32);
+    let mask = rle.to_mask();
+    let new_mask: Vec<bool> = (0..new_height)
+        .flat_map(|row| (0..new_width).map(move |col| (col, row)))
+        .map(|(col, row)| {
+            let x = (crop.x() + col as f32 + 0.5).floor();
+            let y = (crop.y() + row as f32 + 0.5).floor();
+            x >= 0.0
+                && y >= 0.0
+                && (x as usize) < width
+                && (y as usize) < height
+                && mask[y as usize * width + x as usize]
+        })
+        .collect();
+    CocoRLE::from_mask(&new_mask, new_width, new_height)
+}
+
+/// Moves the segmentation and bbox into the frame of `crop` and clips them to it. Returns the
+/// area before and after, measured on the polygons or mask, or on the bbox without either.
+fn crop_geometry(
+    segmentation: &mut CocoSegmentation,
+    bbox: &mut [f32; 4],
+    crop: &BBox,
+) -> (f32, f32) {
+    let to_crop = Transform::translate(-crop.x(), -crop.y());
+    let (width, height) = (crop.width(), crop.height());
+    let before = segmentation.area();
+    match segmentation {
+        CocoSegmentation::RLE(rle) => *rle = crop_rle(rle, crop),
+        CocoSegmentation::Polygon(polygons) => {
+            *polygons = polygons
+                .iter()
+                .map(|polygon| {
+                    let mut polygon = polygon.clone();
+                    to_crop.apply_polygon(&mut polygon);
+                    clip_polygon(&polygon, width, height)
+                })
+                .filter(|polygon| polygon.len() >= 6)
+                .collect();
+        }
+    }
+
+    let new_bbox = to_crop
+        .apply_bbox(&BBox::from(*bbox))
+        .clamp_to(width, height);
+    let areas = match before {
+        Some(before) => (before, segmentation.area().unwrap_or(0.0)),
+        None => (bbox[2] * bbox[3], new_bbox.area()),
+    };
+    *bbox = new_bbox.to_xywh();
+    areas
+}
+
+/// Options for [`CocoFile::crop_image`]
+#[derive(Debug, Clone, Default)]
+pub struct CropOptions {
+    /// fraction of an annotation's area that has to stay inside the crop for it to be kept,
+    /// annotations with nothing left inside are always dropped
+    pub min_visibility: f32,
+}
+
+/// Result of [`CocoFile::clamp_to_image_bounds`]
+#[derive(Debug, Default)]
+pub struct ClampReport {
+    /// number of annotations whose coordinates changed
+    pub clamped: usize,
+    /// ids of missing images that annotations refer to, those annotations are left untouched
+    pub missing_images: BTreeSet<i64>,
+}
+
+impl CocoSegmentation {
+    /// Simplifies every polygon with Douglas-Peucker, dropping points that are at most
+    /// `epsilon` pixels off the simplified outline. Polygons left with fewer than 3 points are
+    /// removed. RLE masks are left untouched.
+    pub fn simplify(&mut self, epsilon: f32) {
+        if let CocoSegmentation::Polygon(polygons) = self {
+            *polygons = polygons
+                .iter()
+                .filter_map(|polygon| simplify_ring(polygon, epsilon))
+                .collect();
+        }
+    }
+}
+
+impl CocoObjectDetectionAnnotation {
+    /// Overwrites bbox and area with those of the segmentation. Annotations without a
+    /// polygon or with an empty mask are left alone.
+    pub fn recompute_from_segmentation(&mut self) {
+        if let Some((bbox, area)) = segmentation_geometry(&self.segmentation) {
+            self.bbox = bbox;
+            self.area = area;
+        }
+    }
+}
+
+impl CocoKeypointDetectionAnnotation {
+    /// See [`CocoObjectDetectionAnnotation::recompute_from_segmentation`]
+    pub fn recompute_from_segmentation(&mut self) {
+        if let Some((bbox, area)) = segmentation_geometry(&self.segmentation) {
+            self.bbox = bbox;
+            self.area = area;
+        }
+    }
+}
+
+impl CocoFile {
+    /// Recomputes bbox and area of every object detection and keypoint annotation from its
+    /// segmentation. Returns how many annotations had a bbox value or area more than
+    /// `tolerance` off.
+    pub fn repair_geometry(&mut self, tolerance: f32) -> usize {
+        let mut changed = 0;
+        for annotation in &mut self.annotations {
+            let (before, after) = match annotation {
+                CocoAnnotation::ObjectDetection(ann) => {
+                    let before = (ann.bbox, ann.area);
+                    ann.recompute_from_segmentation();
+                    (before, (ann.bbox, ann.area))
+                }
+                CocoAnnotation::KeypointDetection(ann) => {
+                    let before = (ann.bbox, ann.area);
+                    ann.recompute_from_segmentation();
+                    (before, (ann.bbox, ann.area))
+                }
+                _ => continue,
+            };
+            if geometry_differs(before, after, tolerance) {
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Clamps the bboxes and polygons of object detection and keypoint annotations into their
+    /// image, recomputing the area of annotations that changed from their polygons (or bbox
+    /// without polygons). DensePose coordinates are relative to the bbox and panoptic masks
+    /// live in separate PNGs, so neither is touched.
+    pub fn clamp_to_image_bounds(&mut self) -> ClampReport {
+        let dimensions: HashMap<i64, (u32, u32)> = self
+            .images
+            .iter()
+            .map(|image| (image.id, (image.width, image.height)))
+            .collect();
+
+        let mut report = ClampReport::default();
+        for annotation in &mut self.annotations {
+            let image_id = annotation.image_id();
+            match dimensions.get(&image_id) {
+                Some(&(width, height)) => {
+                    if annotation.clamp_to_bounds(width, height) {
+                        report.clamped += 1;
+                    }
+                }
+                None => {
+                    report.missing_images.insert(image_id);
+                }
+            }
+        }
+        report
+    }
+
+    /// Cuts the `[x, y, w, h]` rectangle `crop` out of an image, see [`CocoAnnotation::crop`].
+    /// Returns the image resized to the crop and its annotations in the crop's frame, the file
+    /// itself is left unchanged. The crop is clamped to the image first.
+    pub fn crop_image(
+        &self,
+        image_id: i64,
+        crop: [f32; 4],
+        options: &CropOptions,
+    ) -> Result<(CocoImage, Vec<CocoAnnotation>)> {
+        let Some(image) = self.images.iter().find(|image| image.id == image_id) else {
+            bail!("image id {} does not exist", image_id);
+        };
+        let crop = BBox::from(crop).clamp_to(image.width as f32, image.height as f32);
+        if crop.area() <= 0.0 {
+            bail!(
+                "crop {:?} does not overlap image id {}",
+                crop.to_xywh(),
+                image_id
+            );
+        }
+
+        let mut cropped_image = image.clone();
+        cropped_image.width = crop.width().round() as u32;
+        cropped_image.height = crop.height().round() as u32;
+
+        let annotations = self
+            .annotations
+            .iter()
+            .filter(|annotation| annotation.image_id() == image_id)
+            .filter_map(|annotation| annotation.crop(&crop, options).transpose())
+            .collect::<Result<_>>()?;
+        Ok((cropped_image, annotations))
+    }
+
+    /// Applies `transform` to every annotation of the image and updates its width and height.
+    /// Returns the number of annotations transformed. Nothing changes if the image doesn't
+    /// exist or one of its annotations can't be transformed.
+    pub fn transform_image(&mut self, image_id: i64, transform: &Transform) -> Result<usize> {
+        let Some(image) = self.images.iter_mut().find(|image| image.id == image_id) else {
+            bail!("image id {} does not exist", image_id);
+        };
+
+        let mut transformed = Vec::new();
+        for (i, annotation) in self.annotations.iter().enumerate() {
+            if annotation.image_id() == image_id {
+                let mut annotation = annotation.clone();
+                annotation.transform(transform)?;
+                transformed.push((i, annotation));
+            }
+        }
+
+        (image.width, image.height) = transform.apply_size(image.width, image.height);
+        let count = transformed.len();
+        for (i, annotation) in transformed {
+            self.annotations[i] = annotation;
+        }
+        Ok(count)
+    }
+
+    /// Simplifies the polygons of object detection and keypoint annotations, see
+    /// [`CocoSegmentation::simplify`]. Bboxes and areas are left as they were. Returns the
+    /// number of polygon points removed.
+    pub fn simplify_polygons(&mut self, epsilon: f32) -> usize {
+        fn point_count(segmentation: &CocoSegmentation) -> usize {
+            match segmentation {
+                CocoSegmentation::Polygon(polygons) => {
+                    polygons.iter().map(|polygon| polygon.len() / 2).sum()
+                }
+                CocoSegmentation::RLE(_) => 0,
+            }
+        }
+
+        let mut removed = 0;
+        for annotation in &mut self.annotations {
+            let segmentation = match annotation {
+                CocoAnnotation::ObjectDetection(ann) => &mut ann.segmentation,
+                CocoAnnotation::KeypointDetection(ann) => &mut ann.segmentation,
+                _ => continue,
+            };
+            let before = point_count(segmentation);
+            segmentation.simplify(epsilon);
+            removed += before - point_count(segmentation);
+        }
+        removed
+    }
+}
+
+impl CocoAnnotation {
+    /// Clamps the bbox and polygon points of object detection and keypoint annotations into a
+    /// `width` x `height` image and recomputes their area if anything moved. Returns whether
+    /// the annotation changed, other annotation types are left alone.
+    pub fn clamp_to_bounds(&mut self, width: u32, height: u32) -> bool {
+        let (width, height) = (width as f32, height as f32);
+        let (segmentation, bbox, area) = match self {
+            CocoAnnotation::ObjectDetection(ann) => {
+                (&mut ann.segmentation, &mut ann.bbox, &mut ann.area)
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                (&mut ann.segmentation, &mut ann.bbox, &mut ann.area)
+            }
+            _ => return false,
+        };
+
+        let bbox_changed = clamp_bbox(bbox, width, height);
+        let segmentation_changed = clamp_segmentation(segmentation, width, height);
+        if !(bbox_changed || segmentation_changed) {
+            return false;
+        }
+        match polygon_or_bbox_area(segmentation, bbox) {
+            Some(new_area) => *area = new_area,
+            // an RLE mask can't extend past the image, but the bbox it came with did
+            None => *area = area.min(bbox[2] * bbox[3]),
+        }
+        true
+    }
+
+    /// Scales all pixel coordinates of the annotation by `sx` horizontally and `sy`
+    /// vertically, and the area by `sx * sy`. Shorthand for [`CocoAnnotation::transform`] with
+    /// [`Transform::scale`].
+    pub fn scale(&mut self, sx: f32, sy: f32) -> Result<()> {
+        self.transform(&Transform::scale(sx, sy))
+    }
+
+    /// Moves the annotation into the frame of `crop`, a rectangle in image pixels, clipping its
+    /// bbox and segmentation and recomputing its area. Keypoints outside the crop become
+    /// unlabeled. Returns `None` if less than `options.min_visibility` of the annotation
+    /// remains. DensePose and panoptic annotations can't be cropped and return an error.
+    pub fn crop(&self, crop: &BBox, options: &CropOptions) -> Result<Option<CocoAnnotation>> {
+        let mut cropped = self.clone();
+        let (segmentation, bbox, area) = match &mut cropped {
+            CocoAnnotation::ObjectDetection(ann) => {
+                (&mut ann.segmentation, &mut ann.bbox, &mut ann.area)
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                (&mut ann.segmentation, &mut ann.bbox, &mut ann.area)
+            }
+            CocoAnnotation::ImageCaptioning(_) => return Ok(Some(cropped)),
+            CocoAnnotation::DensePose(_) | CocoAnnotation::PanopticSegmentation(_) => {
+                bail!(
+                    "cropping DensePose and panoptic annotations is not supported (image id {})",
+                    self.image_id()
+                )
+            }
+        };
+
+        let (before, after) = crop_geometry(segmentation, bbox, crop);
+        if after <= 0.0 || after < options.min_visibility * before {
+            return Ok(None);
+        }
+        *area = after;
+
+        if let CocoAnnotation::KeypointDetection(ann) = &mut cropped {
+            let (width, height) = (crop.width(), crop.height());
+            for keypoint in ann.keypoints.chunks_exact_mut(3) {
+                if keypoint[2] <= 0.0 {
+                    continue;
+                }
+                let (x, y) = (keypoint[0] - crop.x(), keypoint[1] - crop.y());
+                if (0.0..=width).contains(&x) && (0.0..=height).contains(&y) {
+                    keypoint.copy_from_slice(&[x, y, keypoint[2]]);
+                } else {
+                    keypoint.fill(0.0);
+                }
+            }
+            ann.num_keypoints = ann
+                .keypoints
+                .chunks_exact(3)
+                .filter(|keypoint| keypoint[2] > 0.0)
+                .count() as u32;
+        }
+        Ok(Some(cropped))
+    }
+
+    /// Moves all pixel coordinates of the annotation onto the grid `transform` produces, RLE
+    /// masks are decoded and resampled. Panoptic annotations return an error since their masks
+    /// live in a separate PNG.
+    pub fn transform(&mut self, transform: &Transform) -> Result<()> {
+        match self {
+            CocoAnnotation::ObjectDetection(ann) => {
+                transform.apply_segmentation(&mut ann.segmentation);
+                ann.set_bbox(transform.apply_bbox(&ann.bbox()));
+                ann.area *= transform.area_factor();
+            }
+            CocoAnnotation::KeypointDetection(ann) => {
+                transform.apply_segmentation(&mut ann.segmentation);
+                ann.set_bbox(transform.apply_bbox(&ann.bbox()));
+                ann.area *= transform.area_factor();
+                transform.apply_keypoints(&mut ann.keypoints);
+            }
+            CocoAnnotation::DensePose(ann) => transform.apply_densepose(ann),
+            CocoAnnotation::PanopticSegmentation(ann) => {
+                bail!(
+                    "transforming panoptic annotations is not supported (image id {})",
+                    ann.image_id
+                )
+            }
+            CocoAnnotation::ImageCaptioning(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CocoKeypointDetectionAnnotation, CocoObjectDetectionAnnotation, CocoRLE};
+
+    #[test]
+    fn test_scale_object_detection() {
+        let mut ann = CocoAnnotation::ObjectDetection(CocoObjectDetectionAnnotation {
+            id: 1,
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::Polygon(vec![vec![10.0, 20.0, 30.0, 20.0, 30.0, 40.0]]),
+            area: 400.0,
+            bbox: [10.0, 20.0, 20.0, 20.0],
+            iscrowd: false,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
+        });
+
+        ann.scale(0.5, 0.25).unwrap();
+
+        match ann {
+            CocoAnnotation::ObjectDetection(ann) => {
+                assert_eq!(ann.bbox, [5.0, 5.0, 10.0, 5.0]);
+                assert_eq!(ann.area, 50.0);
+                match ann.segmentation {
+                    CocoSegmentation::Polygon(polygons) => {
+                        assert_eq!(polygons[0], vec![5.0, 5.0, 15.0, 5.0, 15.0, 10.0]);
+                    }
+                    _ => panic!("Expected Polygon segmentation"),
+                }
+            }
+            _ => panic!("Expected ObjectDetection annotation"),
+        }
+    }
+
+    #[test]
+    fn test_scale_keypoints_keeps_visibility() {
+        let mut ann = CocoAnnotation::KeypointDetection(CocoKeypointDetectionAnnotation {
+            id: 1,
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::Polygon(vec![]),
+            area: 100.0,
+            bbox: [0.0, 0.0, 10.0, 10.0],
+            iscrowd: false,
+            keypoints: vec![4.0, 8.0, 2.0, 6.0, 2.0, 1.0],
+            num_keypoints: 2,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
+        });
+
+        ann.scale(2.0, 2.0).unwrap();
+
+        match ann {
+            CocoAnnotation::KeypointDetection(ann) => {
+                assert_eq!(ann.keypoints, vec![8.0, 16.0, 2.0, 12.0, 4.0, 1.0]);
+                assert_eq!(ann.area, 400.0);
+            }
+            _ => panic!("Expected KeypointDetection annotation"),
+        }
+    }
+
+    #[test]
+    fn test_clamp_to_image_bounds() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 50, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1,
+                 "segmentation": [[80.0, 10.0, 120.0, 10.0, 120.0, 30.0, 80.0, 30.0]],
+                 "area": 800.0, "bbox": [80.0, 10.0, 40.0, 20.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 100.0, "bbox": [-5.0, 40.0, 10.0, 20.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 42.0, "bbox": [10.0, 10.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 4, "image_id": 9, "category_id": 1, "segmentation": [[]],
+                 "area": 800.0, "bbox": [80.0, 10.0, 40.0, 20.0], "iscrowd": 0}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let report = coco_file.clamp_to_image_bounds();
+        assert_eq!(report.clamped, 2);
+        assert_eq!(report.missing_images, BTreeSet::from([9]));
+
+        let detection = |i: usize| match &coco_file.annotations[i] {
+            CocoAnnotation::ObjectDetection(ann) => ann.clone(),
+            _ => panic!("Expected ObjectDetection annotation"),
+        };
+        // 20px past the right edge
+        let poking_out = detection(0);
+        assert_eq!(poking_out.bbox, [80.0, 10.0, 20.0, 20.0]);
+        assert_eq!(poking_out.area, 400.0);
+        match poking_out.segmentation {
+            CocoSegmentation::Polygon(polygons) => assert_eq!(
+                polygons[0],
+                vec![80.0, 10.0, 100.0, 10.0, 100.0, 30.0, 80.0, 30.0]
+            ),
+            _ => panic!("Expected Polygon segmentation"),
+        }
+
+        let corner = detection(1);
+        assert_eq!(corner.bbox, [0.0, 40.0, 5.0, 10.0]);
+        assert_eq!(corner.area, 50.0);
+        // in bounds annotations keep their area
+        assert_eq!(detection(2).area, 42.0);
+        assert_eq!(detection(3).bbox, [80.0, 10.0, 40.0, 20.0]);
+    }
+
+    #[test]
+    fn test_repair_geometry() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1,
+                 "segmentation": [[10.0, 20.0, 20.0, 20.0, 20.0, 30.0, 10.0, 30.0]],
+                 "area": 90.0, "bbox": [0.0, 0.0, 5.0, 5.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1,
+                 "segmentation": [[10.0, 20.0, 20.0, 20.0, 20.0, 30.0, 10.0, 30.0]],
+                 "area": 100.001, "bbox": [10.0, 20.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1,
+                 "segmentation": {"counts": [5, 2, 2, 2, 5], "size": [4, 4]},
+                 "area": 16.0, "bbox": [0.0, 0.0, 4.0, 4.0], "iscrowd": 1},
+                {"id": 4, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 42.0, "bbox": [1.0, 1.0, 6.0, 7.0], "iscrowd": 0}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(coco_file.repair_geometry(0.01), 2);
+
+        let geometry = |i: usize| match &coco_file.annotations[i] {
+            CocoAnnotation::ObjectDetection(ann) => (ann.bbox, ann.area),
+            _ => panic!("Expected ObjectDetection annotation"),
+        };
+        assert_eq!(geometry(0), ([10.0, 20.0, 10.0, 10.0], 100.0));
+        // within tolerance still gets the exact values
+        assert_eq!(geometry(1), ([10.0, 20.0, 10.0, 10.0], 100.0));
+        assert_eq!(geometry(2), ([1.0, 1.0, 2.0, 2.0], 4.0));
+        // nothing to recompute from
+        assert_eq!(geometry(3), ([1.0, 1.0, 6.0, 7.0], 42.0));
+    }
+
+    #[test]
+    fn test_scale_resamples_rle() {
+        let mut ann = CocoAnnotation::ObjectDetection(CocoObjectDetectionAnnotation {
+            id: 1,
+            image_id: 1,
+            category_id: 1,
+            segmentation: CocoSegmentation::RLE(CocoRLE {
+                counts: vec![10, 5, 10],
+                size: (5, 5),
+            }),
+            area: 5.0,
+            bbox: [0.0, 0.0, 5.0, 5.0],
+            iscrowd: true,
+            score: None,
+            attributes: None,
+            extra: Default::default(),
+        });
+
+        ann.scale(2.0, 2.0).unwrap();
+
+        match ann {
+            CocoAnnotation::ObjectDetection(ann) => {
+                assert_eq!(ann.bbox, [0.0, 0.0, 10.0, 10.0]);
+                assert_eq!(ann.area, 20.0);
+                match ann.segmentation {
+                    // the third column of the mask becomes the fifth and sixth
+                    CocoSegmentation::RLE(rle) => {
+                        assert_eq!(rle.size, (10, 10));
+                        assert_eq!(rle.counts, vec![40, 20, 40]);
+                    }
+                    _ => panic!("Expected RLE segmentation"),
+                }
+            }
+            _ => panic!("Expected ObjectDetection annotation"),
+        }
+    }
+
+    #[test]
+    fn test_transform_image() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 50, "file_name": "a.jpg"},
+                {"id": 2, "width": 100, "height": 50, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1,
+                 "segmentation": [[10.0, 20.0, 40.0, 20.0, 40.0, 25.0]],
+                 "area": 75.0, "bbox": [10.0, 20.0, 30.0, 5.0], "iscrowd": 0,
+                 "keypoints": [10.0, 20.0, 2.0, 0.0, 0.0, 0.0], "num_keypoints": 1},
+                {"id": 2, "image_id": 2, "category_id": 1, "segmentation": [[]],
+                 "area": 150.0, "bbox": [10.0, 20.0, 30.0, 5.0], "iscrowd": 0}
+            ]
+        }"#;
+        let mut coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let transformed = coco_file
+            .transform_image(1, &Transform::rotate90(100.0, 50.0))
+            .unwrap();
+        assert_eq!(transformed, 1);
+        assert_eq!(
+            (coco_file.images[0].width, coco_file.images[0].height),
+            (50, 100)
+        );
+        let ann = coco_file.annotations[0].as_keypoint_detection().unwrap();
+        assert_eq!(ann.bbox, [25.0, 10.0, 5.0, 30.0]);
+        assert_eq!(ann.area, 75.0);
+        assert_eq!(ann.keypoints, vec![30.0, 10.0, 2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(
+            ann.segmentation,
+            CocoSegmentation::Polygon(vec![vec![30.0, 10.0, 30.0, 40.0, 25.0, 40.0]])
+        );
+        // other images are left alone
+        assert_eq!(
+            coco_file.annotations[1].as_object_detection().unwrap().bbox,
+            [10.0, 20.0, 30.0, 5.0]
+        );
+        assert_eq!(coco_file.images[1].width, 100);
+
+        assert!(
+            coco_file
+                .transform_image(9, &Transform::hflip(1.0))
+                .is_err()
+        );
+    }
+
+    fn crop_test_file() -> CocoFile {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+                {"id": 2, "width": 4, "height": 4, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "category_id": 1,
+                 "segmentation": [[10.0, 10.0, 40.0, 10.0, 40.0, 40.0, 10.0, 40.0]],
+                 "area": 900.0, "bbox": [10.0, 10.0, 30.0, 30.0], "iscrowd": 0},
+                {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 100.0, "bbox": [80.0, 80.0, 10.0, 10.0], "iscrowd": 0},
+                {"id": 3, "image_id": 1, "category_id": 1,
+                 "segmentation": [[30.0, 0.0, 60.0, 0.0, 60.0, 30.0, 50.0, 30.0,
+                                   50.0, 10.0, 40.0, 10.0, 40.0, 30.0, 30.0, 30.0]],
+                 "area": 700.0, "bbox": [30.0, 0.0, 30.0, 30.0], "iscrowd": 0},
+                {"id": 4, "image_id": 1, "category_id": 1, "segmentation": [[]],
+                 "area": 100.0, "bbox": [20.0, 20.0, 10.0, 10.0], "iscrowd": 0,
+                 "keypoints": [25.0, 25.0, 2.0, 10.0, 10.0, 1.0, 0.0, 0.0, 0.0],
+                 "num_keypoints": 2},
+                {"id": 5, "image_id": 2, "category_id": 1,
+                 "segmentation": {"counts": [5, 2, 2, 2, 5], "size": [4, 4]},
+                 "area": 4.0, "bbox": [1.0, 1.0, 2.0, 2.0], "iscrowd": 1}
+            ]
+        }"#;
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn polygons(annotation: &CocoAnnotation) -> Vec<CocoPolygon> {
+        let segmentation = match annotation {
+            CocoAnnotation::ObjectDetection(ann) => &ann.segmentation,
+            CocoAnnotation::KeypointDetection(ann) => &ann.segmentation,
+            _ => panic!("Expected an annotation with a segmentation"),
+        };
+        match segmentation {
+            CocoSegmentation::Polygon(polygons) => polygons.clone(),
+            _ => panic!("Expected Polygon segmentation"),
+        }
+    }
+
+    #[test]
+    fn test_crop_image() {
+        let coco_file = crop_test_file();
+        let (image, annotations) = coco_file
+            .crop_image(1, [20.0, 20.0, 50.0, 50.0], &CropOptions::default())
+            .unwrap();
+        assert_eq!((image.id, image.width, image.height), (1, 50, 50));
+        // annotation 2 is fully outside the crop
+        let ids: Vec<_> = annotations.iter().filter_map(|ann| ann.id()).collect();
+        assert_eq!(ids, vec![1, 3, 4]);
+
+        // the square's bottom right quarter
+        let square = annotations[0].as_object_detection().unwrap();
+        assert_eq!(square.bbox, [0.0, 0.0, 20.0, 20.0]);
+        assert_eq!(square.area, 400.0);
+        assert_eq!(
+            polygons(&annotations[0]),
+            vec![vec![0.0, 0.0, 20.0, 0.0, 20.0, 20.0, 0.0, 20.0]]
+        );
+
+        // an arch whose two legs reach into the crop, cut into two 10x10 pieces
+        let arch = annotations[1].as_object_detection().unwrap();
+        assert_eq!(arch.bbox, [10.0, 0.0, 30.0, 10.0]);
+        assert_eq!(arch.area, 200.0);
+        let arch_polygons = polygons(&annotations[1]);
+        assert_eq!(arch_polygons.len(), 1);
+        for point in arch_polygons[0].chunks_exact(2) {
+            assert!((10.0..=40.0).contains(&point[0]) && (0.0..=10.0).contains(&point[1]));
+        }
+
+        // labeled keypoints outside the crop become unlabeled
+        let person = annotations[2].as_keypoint_detection().unwrap();
+        assert_eq!(person.bbox, [0.0, 0.0, 10.0, 10.0]);
+        assert_eq!(person.area, 100.0);
+        assert_eq!(
+            person.keypoints,
+            vec![5.0, 5.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(person.num_keypoints, 1);
+    }
+
+    #[test]
+    fn test_crop_image_min_visibility() {
+        let coco_file = crop_test_file();
+        let options = CropOptions {
+            min_visibility: 0.5,
+        };
+        let (_, annotations) = coco_file
+            .crop_image(1, [20.0, 20.0, 50.0, 50.0], &options)
+            .unwrap();
+        // 400 of 900 and 200 of 700 visible are not enough
+        let ids: Vec<_> = annotations.iter().filter_map(|ann| ann.id()).collect();
+        assert_eq!(ids, vec![4]);
+    }
+
+    #[test]
+    fn test_crop_image_rle() {
+        let coco_file = crop_test_file();
+        // the right half of a 4x4 image with a 2x2 square in the middle
+        let (image, annotations) = coco_file
+            .crop_image(2, [2.0, 0.0, 2.0, 4.0], &CropOptions::default())
+            .unwrap();
+        assert_eq!((image.width, image.height), (2, 4));
+        let crowd = annotations[0].as_object_detection().unwrap();
+        assert_eq!(
+            crowd.segmentation,
+            CocoSegmentation::RLE(CocoRLE {
+                counts: vec![1, 2, 5],
+                size: (4, 2),
+            })
+        );
+        assert_eq!(crowd.bbox, [0.0, 1.0, 1.0, 2.0]);
+        assert_eq!(crowd.area, 2.0);
+    }
+
+    #[test]
+    fn test_crop_image_errors() {
+        let coco_file = crop_test_file();
+        let options = CropOptions::default();
+        assert!(
+            coco_file
+                .crop_image(9, [0.0, 0.0, 1.0, 1.0], &options)
+                .is_err()
+        );
+        assert!(
+            coco_file
+                .crop_image(1, [200.0, 0.0, 10.0, 10.0], &options)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_clip_polygon() {
+        // a triangle poking out of the right edge keeps its inside part
+        let clipped = clip_polygon(&[5.0, 0.0, 15.0, 5.0, 5.0, 10.0], 10.0, 10.0);
+        assert_eq!(
+            CocoSegmentation::Polygon(vec![clipped]).area(),
+            Some(50.0 - 12.5)
+        );
+        // fully outside
+        assert!(clip_polygon(&[20.0, 0.0, 30.0, 0.0, 30.0, 10.0], 10.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_simplify_straight_edge() {
+        // a densely sampled, slightly noisy straight edge
+        let edge: Vec<f32> = (0..=100)
+            .flat_map(|i| [i as f32, if i % 2 == 0 { 0.0 } else { 0.4 }])
+            .collect();
+        assert_eq!(simplify_polyline(&edge, 1.0), vec![0.0, 0.0, 100.0, 0.0]);
+        // points further off than epsilon are kept
+        assert_eq!(simplify_polyline(&edge, 0.1).len(), edge.len());
+    }
+
+    #[test]
+    fn test_simplify_segmentation() {
+        // a 10x20 rectangle sampled every pixel collapses to its corners
+        let mut ring: Vec<f32> = Vec::new();
+        ring.extend((0..10).flat_map(|x| [x as f32, 0.0]));
+        ring.extend((0..20).flat_map(|y| [10.0, y as f32]));
+        ring.extend((0..10).flat_map(|x| [10.0 - x as f32, 20.0]));
+        ring.extend((0..20).flat_map(|y| [0.0, 20.0 - y as f32]));
+        // a sliver whose points are all within epsilon of a line
+        let sliver = vec![0.0, 0.0, 50.0, 0.5, 100.0, 0.0, 50.0, 0.2];
+        let mut segmentation = CocoSegmentation::Polygon(vec![ring, sliver]);
+
+        segmentation.simplify(1.0);
+        assert_eq!(
+            segmentation,
+            CocoSegmentation::Polygon(vec![vec![0.0, 0.0, 10.0, 0.0, 10.0, 20.0, 0.0, 20.0]])
+        );
+
+        let mut rle = CocoSegmentation::RLE(CocoRLE {
+            size: (2, 2),
+            counts: vec![1, 2, 1],
+        });
+        let before = rle.clone();
+        rle.simplify(1.0);
+        assert_eq!(rle, before);
+    }
+}
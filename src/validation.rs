@@ -0,0 +1,478 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{CocoAnnotation, CocoCategory, CocoFile, HasID};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single integrity problem found in a COCO file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationIssue {
+    /// more than one image uses this id
+    DuplicateImageId { image_id: i64 },
+
+    /// more than one annotation (or panoptic segment) uses this id
+    DuplicateAnnotationId { annotation_id: i64 },
+
+    /// annotation references an image id that is not in the file
+    DanglingImageId {
+        annotation_id: Option<i64>,
+        image_id: i64,
+    },
+
+    /// annotation (or panoptic segment) references a category id that is not in the file
+    DanglingCategoryId {
+        annotation_id: Option<i64>,
+        category_id: i32,
+    },
+
+    /// panoptic segment references a category that exists but isn't a panoptic category
+    NonPanopticCategory { segment_id: i64, category_id: i32 },
+
+    /// bbox extends past the edges of its image
+    BboxOutOfBounds {
+        annotation_id: Option<i64>,
+        image_id: i64,
+        bbox: [f32; 4],
+        width: u32,
+        height: u32,
+    },
+
+    /// keypoints array isn't 3 values per keypoint declared on the category, or holds fewer
+    /// keypoints than its `num_keypoints` says are labeled
+    KeypointLengthMismatch {
+        annotation_id: i64,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl ValidationIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationIssue::BboxOutOfBounds { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::DuplicateImageId { image_id } => {
+                write!(f, "duplicate image id {}", image_id)
+            }
+            ValidationIssue::DuplicateAnnotationId { annotation_id } => {
+                write!(f, "duplicate annotation id {}", annotation_id)
+            }
+            ValidationIssue::DanglingImageId {
+                annotation_id: Some(annotation_id),
+                image_id,
+            } => write!(
+                f,
+                "annotation {} references missing image id {}",
+                annotation_id, image_id
+            ),
+            ValidationIssue::DanglingImageId {
+                annotation_id: None,
+                image_id,
+            } => write!(
+                f,
+                "panoptic annotation references missing image id {}",
+                image_id
+            ),
+            ValidationIssue::DanglingCategoryId {
+                annotation_id,
+                category_id,
+            } => write!(
+                f,
+                "annotation {} references missing category id {}",
+                display_optional_id(annotation_id),
+                category_id
+            ),
+            ValidationIssue::NonPanopticCategory {
+                segment_id,
+                category_id,
+            } => write!(
+                f,
+                "panoptic segment {} references category id {}, which isn't a panoptic category",
+                segment_id, category_id
+            ),
+            ValidationIssue::BboxOutOfBounds {
+                annotation_id,
+                image_id,
+                bbox,
+                width,
+                height,
+            } => write!(
+                f,
+                "annotation {} bbox {:?} is outside the {}x{} bounds of image {}",
+                display_optional_id(annotation_id),
+                bbox,
+                width,
+                height,
+                image_id
+            ),
+            ValidationIssue::KeypointLengthMismatch {
+                annotation_id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "annotation {} has {} keypoint values, expected {}",
+                annotation_id, actual, expected
+            ),
+        }
+    }
+}
+
+fn display_optional_id(id: &Option<i64>) -> String {
+    id.map(|id| id.to_string())
+        .unwrap_or_else(|| "<no id>".to_string())
+}
+
+fn bbox_in_bounds(bbox: &[f32; 4], width: u32, height: u32) -> bool {
+    let [x, y, w, h] = *bbox;
+    x >= 0.0 && y >= 0.0 && x + w <= width as f32 && y + h <= height as f32
+}
+
+impl CocoFile {
+    /// Checks the file for duplicate ids, annotations pointing at images or categories that don't
+    /// exist, panoptic segments of non panoptic categories, bboxes outside their image and
+    /// malformed keypoint arrays.
+    /// Returns an empty vec for a consistent file.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        let mut image_sizes: HashMap<i64, (u32, u32)> = HashMap::new();
+        let mut reported_image_ids: HashSet<i64> = HashSet::new();
+        for image in &self.images {
+            if image_sizes
+                .insert(image.id, (image.width, image.height))
+                .is_some()
+                && reported_image_ids.insert(image.id)
+            {
+                issues.push(ValidationIssue::DuplicateImageId { image_id: image.id });
+            }
+        }
+
+        // category id -> number of keypoints (0 for non keypoint categories)
+        let category_keypoints: Option<HashMap<i32, usize>> =
+            self.categories.as_ref().map(|categories| {
+                categories
+                    .iter()
+                    .map(|category| match category {
+                        CocoCategory::KeypointDetection(cat) => (cat.id, cat.keypoints.len()),
+                        _ => (category.id(), 0),
+                    })
+                    .collect()
+            });
+        let panoptic_category_ids: HashSet<i32> = self
+            .categories
+            .iter()
+            .flatten()
+            .filter(|category| matches!(category, CocoCategory::PanopticSegmentation(_)))
+            .map(HasID::id)
+            .collect();
+        let check_category = |annotation_id: Option<i64>, category_id: i32| {
+            if let Some(category_keypoints) = &category_keypoints
+                && !category_keypoints.contains_key(&category_id)
+            {
+                return Some(ValidationIssue::DanglingCategoryId {
+                    annotation_id,
+                    category_id,
+                });
+            }
+            None
+        };
+
+        let mut annotation_ids: HashSet<i64> = HashSet::new();
+        let mut reported_annotation_ids: HashSet<i64> = HashSet::new();
+        for annotation in &self.annotations {
+            // panoptic segment ids share the annotation id space, same as in cocomerge
+            let ids: Vec<i64> = match annotation {
+                CocoAnnotation::PanopticSegmentation(ann) => {
+                    ann.segments_info.iter().map(|segment| segment.id).collect()
+                }
+                _ => annotation.id().into_iter().collect(),
+            };
+            for id in ids {
+                if !annotation_ids.insert(id) && reported_annotation_ids.insert(id) {
+                    issues.push(ValidationIssue::DuplicateAnnotationId { annotation_id: id });
+                }
+            }
+
+            let image_size = image_sizes.get(&annotation.image_id()).copied();
+            if image_size.is_none() {
+                issues.push(ValidationIssue::DanglingImageId {
+                    annotation_id: annotation.id(),
+                    image_id: annotation.image_id(),
+                });
+            }
+
+            // (annotation id, category id, bbox) triples to check against categories and bounds
+            let boxed: Vec<(Option<i64>, i32, [f32; 4])> = match annotation {
+                CocoAnnotation::ObjectDetection(ann) => {
+                    vec![(Some(ann.id), ann.category_id, ann.bbox)]
+                }
+                CocoAnnotation::KeypointDetection(ann) => {
+                    vec![(Some(ann.id), ann.category_id, ann.bbox)]
+                }
+                CocoAnnotation::DensePose(ann) => vec![(Some(ann.id), ann.category_id, ann.bbox)],
+                CocoAnnotation::PanopticSegmentation(ann) => ann
+                    .segments_info
+                    .iter()
+                    .map(|segment| (Some(segment.id), segment.category_id, segment.bbox))
+                    .collect(),
+                CocoAnnotation::ImageCaptioning(_) => Vec::new(),
+            };
+            for (annotation_id, category_id, bbox) in boxed {
+                if let Some(issue) = check_category(annotation_id, category_id) {
+                    issues.push(issue);
+                }
+                if let Some((width, height)) = image_size
+                    && !bbox_in_bounds(&bbox, width, height)
+                {
+                    issues.push(ValidationIssue::BboxOutOfBounds {
+                        annotation_id,
+                        image_id: annotation.image_id(),
+                        bbox,
+                        width,
+                        height,
+                    });
+                }
+            }
+
+            if let CocoAnnotation::PanopticSegmentation(ann) = annotation {
+                for segment in &ann.segments_info {
+                    let known = category_keypoints
+                        .as_ref()
+                        .is_some_and(|categories| categories.contains_key(&segment.category_id));
+                    // missing categories are already reported as dangling
+                    if known && !panoptic_category_ids.contains(&segment.category_id) {
+                        issues.push(ValidationIssue::NonPanopticCategory {
+                            segment_id: segment.id,
+                            category_id: segment.category_id,
+                        });
+                    }
+                }
+            }
+
+            if let CocoAnnotation::KeypointDetection(ann) = annotation {
+                let expected = category_keypoints
+                    .as_ref()
+                    .and_then(|category_keypoints| category_keypoints.get(&ann.category_id))
+                    .filter(|&&count| count > 0)
+                    .map(|count| count * 3);
+                let malformed = match expected {
+                    Some(expected) => ann.keypoints.len() != expected,
+                    None => ann.keypoints.len() % 3 != 0,
+                };
+                // num_keypoints only counts labeled keypoints, so it can't exceed the array
+                let labeled = ann.num_keypoints as usize * 3;
+                if malformed {
+                    issues.push(ValidationIssue::KeypointLengthMismatch {
+                        annotation_id: ann.id,
+                        expected: expected.unwrap_or(ann.keypoints.len() / 3 * 3),
+                        actual: ann.keypoints.len(),
+                    });
+                } else if labeled > ann.keypoints.len() {
+                    issues.push(ValidationIssue::KeypointLengthMismatch {
+                        annotation_id: ann.id,
+                        expected: labeled,
+                        actual: ann.keypoints.len(),
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_clean_file() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 1, "image_id": 1, "caption": "a"},
+                {"id": 2, "image_id": 2, "caption": "b"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        assert!(coco_file.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_duplicates_and_dangling() {
+        let json = r#"{
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "a.jpg"},
+                {"id": 1, "width": 10, "height": 10, "file_name": "b.jpg"}
+            ],
+            "annotations": [
+                {"id": 7, "image_id": 1, "caption": "a"},
+                {"id": 7, "image_id": 1, "caption": "b"},
+                {"id": 8, "image_id": 99, "caption": "c"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let issues = coco_file.validate();
+
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&ValidationIssue::DuplicateImageId { image_id: 1 }));
+        assert!(issues.contains(&ValidationIssue::DuplicateAnnotationId { annotation_id: 7 }));
+        assert!(issues.contains(&ValidationIssue::DanglingImageId {
+            annotation_id: Some(8),
+            image_id: 99,
+        }));
+    }
+
+    #[test]
+    fn test_validate_categories_bounds_and_keypoints() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 50, "file_name": "a.jpg"}],
+            "annotations": [
+                {
+                    "id": 1, "image_id": 1, "category_id": 3, "segmentation": [],
+                    "area": 1.0, "bbox": [90.0, 10.0, 20.0, 10.0], "iscrowd": 0
+                },
+                {
+                    "id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+                    "area": 1.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0,
+                    "keypoints": [1.0, 1.0, 2.0], "num_keypoints": 1
+                }
+            ],
+            "categories": [
+                {
+                    "id": 1, "name": "person", "supercategory": "human",
+                    "keypoints": ["nose", "left_eye"], "skeleton": [[0, 1]]
+                }
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+        let issues = coco_file.validate();
+
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&ValidationIssue::DanglingCategoryId {
+            annotation_id: Some(1),
+            category_id: 3,
+        }));
+        assert!(issues.contains(&ValidationIssue::BboxOutOfBounds {
+            annotation_id: Some(1),
+            image_id: 1,
+            bbox: [90.0, 10.0, 20.0, 10.0],
+            width: 100,
+            height: 50,
+        }));
+        assert!(issues.contains(&ValidationIssue::KeypointLengthMismatch {
+            annotation_id: 2,
+            expected: 6,
+            actual: 3,
+        }));
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|issue| issue.severity() == Severity::Warning)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_validate_panoptic_segment_categories() {
+        let json = r#"{
+            "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+            "annotations": [
+                {"image_id": 1, "file_name": "a.png", "segments_info": [
+                    {"id": 1, "category_id": 1, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 2, "category_id": 2, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0},
+                    {"id": 3, "category_id": 9, "area": 1, "bbox": [0, 0, 1, 1], "iscrowd": 0}
+                ]}
+            ],
+            "categories": [
+                {"id": 1, "name": "road", "supercategory": "stuff", "isthing": 0,
+                 "color": [0, 0, 0]},
+                {"id": 2, "name": "car", "supercategory": "vehicle"}
+            ]
+        }"#;
+        let coco_file: CocoFile = serde_json::from_str(json).unwrap();
+
+        let issues = coco_file.validate();
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::DanglingCategoryId {
+                    annotation_id: Some(3),
+                    category_id: 9,
+                },
+                ValidationIssue::NonPanopticCategory {
+                    segment_id: 2,
+                    category_id: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "panoptic segment 2 references category id 2, which isn't a panoptic category"
+        );
+    }
+
+    #[test]
+    fn test_validate_keypoints_shorter_than_num_keypoints() {
+        let keypoints = |count: usize| serde_json::json!(vec![1.0; count]);
+        let json = serde_json::json!({
+            "images": [{"id": 1, "width": 100, "height": 50, "file_name": "a.jpg"}],
+            "annotations": [
+                {
+                    "id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+                    "area": 1.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0,
+                    "keypoints": keypoints(30), "num_keypoints": 17
+                },
+                {
+                    "id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+                    "area": 1.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0,
+                    "keypoints": keypoints(51), "num_keypoints": 12
+                }
+            ]
+        });
+        let coco_file: CocoFile = serde_json::from_value(json).unwrap();
+
+        let issues = coco_file.validate();
+        assert_eq!(
+            issues,
+            vec![ValidationIssue::KeypointLengthMismatch {
+                annotation_id: 1,
+                expected: 51,
+                actual: 30,
+            }]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "annotation 1 has 30 keypoint values, expected 51"
+        );
+    }
+}
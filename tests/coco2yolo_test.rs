@@ -0,0 +1,125 @@
+// Integration tests for coco2yolo binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// Categories with sparse ids, a crowd annotation and an image file on disk
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 200, "height": 100, "file_name": "imgs/img1.png"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 90, "segmentation": [[20, 10, 60, 10, 60, 50]],
+             "area": 800.0, "bbox": [20.0, 10.0, 40.0, 40.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 5, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 1}
+        ],
+        "categories": [
+            {"id": 90, "name": "toothbrush", "supercategory": "indoor"},
+            {"id": 5, "name": "airplane", "supercategory": "vehicle"}
+        ]
+    }"#;
+    fs::create_dir(temp_dir.path().join("imgs")).unwrap();
+    fs::write(temp_dir.path().join("imgs/img1.png"), b"not really a png").unwrap();
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_coco2yolo_boxes_and_data_yaml() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("yolo");
+
+    let output = Command::new(get_binary_path("coco2yolo"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--copy-images")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // the crowd annotation is skipped, the toothbrush is class 1 after the airplane
+    let labels = fs::read_to_string(output_dir.join("labels/img1.txt")).unwrap();
+    assert_eq!(labels, "1 0.200000 0.300000 0.200000 0.400000\n");
+
+    let data_yaml = fs::read_to_string(output_dir.join("data.yaml")).unwrap();
+    assert!(data_yaml.contains("train: \"images\"\nval: \"images\"\n"));
+    assert!(data_yaml.contains("nc: 2\n"));
+    assert!(data_yaml.ends_with("names:\n  0: \"airplane\"\n  1: \"toothbrush\"\n"));
+
+    assert_eq!(
+        fs::read(output_dir.join("images/img1.png")).unwrap(),
+        b"not really a png"
+    );
+}
+
+#[test]
+fn test_coco2yolo_segmentation_with_crowd() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("yolo");
+
+    let output = Command::new(get_binary_path("coco2yolo"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--segmentation")
+        .arg("--include-crowd")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // the crowd annotation has no polygon to export
+    let labels = fs::read_to_string(output_dir.join("labels/img1.txt")).unwrap();
+    assert_eq!(
+        labels,
+        "1 0.100000 0.100000 0.300000 0.100000 0.300000 0.500000\n"
+    );
+    assert!(!output_dir.join("images").exists());
+    // without the images there is nothing to train on yet
+    let data_yaml = fs::read_to_string(output_dir.join("data.yaml")).unwrap();
+    assert!(!data_yaml.contains("train:"));
+    assert!(!data_yaml.contains("val:"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Skipped 0 image(s) and 1 annotation(s)"));
+}
+
+#[test]
+fn test_coco2yolo_rejects_shared_stems_without_copying_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "a/img.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "b/img.png"}
+        ],
+        "annotations": [],
+        "categories": [{"id": 1, "name": "thing", "supercategory": "thing"}]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_dir = temp_dir.path().join("yolo");
+
+    let output = Command::new(get_binary_path("coco2yolo"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Images 1 and 2"), "{}", stderr);
+    assert!(!output_dir.join("labels").exists());
+}
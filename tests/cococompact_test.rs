@@ -0,0 +1,128 @@
+// Integration tests for cococompact binary
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// Sparse ids across detection, keypoint, caption and panoptic annotations
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 9000000000, "width": 100, "height": 100, "file_name": "img1.png"},
+            {"id": 77, "width": 100, "height": 100, "file_name": "img2.png"}
+        ],
+        "annotations": [
+            {"id": 1000, "image_id": 77, "category_id": 40, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 5, "image_id": 9000000000, "category_id": 12, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0,
+             "keypoints": [5, 5, 2], "num_keypoints": 1},
+            {"id": 300, "image_id": 77, "caption": "a thing"},
+            {"image_id": 9000000000, "file_name": "img1.png", "segments_info": [
+                {"id": 6579300, "category_id": 40, "area": 10, "bbox": [0, 0, 5, 5], "iscrowd": 0}
+            ]}
+        ],
+        "categories": [
+            {"id": 40, "name": "thing", "supercategory": "thing"},
+            {"id": 12, "name": "person", "supercategory": "person", "keypoints": ["nose"],
+             "skeleton": []}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn run_cococompact(temp_dir: &TempDir, extra_args: &[&str]) -> Output {
+    Command::new(get_binary_path("cococompact"))
+        .arg(write_coco(temp_dir))
+        .arg("-o")
+        .arg(temp_dir.path().join("compacted.json"))
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+fn read_json(path: PathBuf) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn ids(values: &serde_json::Value, key: &str) -> Vec<i64> {
+    values
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|value| value[key].as_i64())
+        .collect()
+}
+
+#[test]
+fn test_cococompact() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cococompact(&temp_dir, &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("segment PNGs"));
+
+    let compacted = read_json(temp_dir.path().join("compacted.json"));
+    assert_eq!(ids(&compacted["images"], "id"), vec![1, 2]);
+    assert_eq!(ids(&compacted["categories"], "id"), vec![2, 1]);
+    let annotations = &compacted["annotations"];
+    assert_eq!(ids(annotations, "id"), vec![1, 2, 3]);
+    assert_eq!(annotations[3]["segments_info"][0]["id"], 4);
+
+    // references follow their images and categories
+    assert_eq!(ids(annotations, "image_id"), vec![2, 1, 2, 1]);
+    assert_eq!(annotations[0]["category_id"], 2);
+    assert_eq!(annotations[1]["category_id"], 1);
+    assert_eq!(annotations[3]["segments_info"][0]["category_id"], 2);
+
+    let image_ids: HashSet<i64> = ids(&compacted["images"], "id").into_iter().collect();
+    assert!(
+        ids(annotations, "image_id")
+            .iter()
+            .all(|id| image_ids.contains(id))
+    );
+}
+
+#[test]
+fn test_cococompact_start_ids_and_remap_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let remap_path = temp_dir.path().join("remap.json");
+    let output = run_cococompact(
+        &temp_dir,
+        &[
+            "--start-image-id",
+            "0",
+            "--start-annotation-id",
+            "100",
+            "--start-category-id",
+            "10",
+            "--remap-output",
+            remap_path.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let compacted = read_json(temp_dir.path().join("compacted.json"));
+    assert_eq!(ids(&compacted["images"], "id"), vec![0, 1]);
+    assert_eq!(ids(&compacted["annotations"], "id"), vec![100, 101, 102]);
+
+    let remap = read_json(remap_path);
+    assert_eq!(remap["images"]["9000000000"], 0);
+    assert_eq!(remap["images"]["77"], 1);
+    assert_eq!(remap["categories"]["12"], 10);
+    assert_eq!(remap["categories"]["40"], 11);
+    assert_eq!(
+        remap["annotations"],
+        serde_json::json!([[1000, 100], [5, 101], [300, 102], [6579300, 103]])
+    );
+}
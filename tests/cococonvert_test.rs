@@ -0,0 +1,229 @@
+// Integration tests for cococonvert binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+fn create_test_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 640, "height": 480, "file_name": "images/img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "images/img2.jpg"},
+            {"id": 3, "width": 0, "height": 0, "file_name": "images/broken.jpg"}
+        ],
+        "annotations": [
+            {
+                "id": 1,
+                "image_id": 1,
+                "category_id": 2,
+                "segmentation": [],
+                "area": 4800.0,
+                "bbox": [64.0, 48.0, 160.0, 120.0],
+                "iscrowd": 0
+            },
+            {
+                "id": 2,
+                "image_id": 1,
+                "category_id": 1,
+                "segmentation": [],
+                "area": 100.0,
+                "bbox": [0.0, 0.0, 10.0, 10.0],
+                "iscrowd": 0,
+                "keypoints": [5.0, 5.0, 2.0],
+                "num_keypoints": 1
+            },
+            {
+                "id": 3,
+                "image_id": 3,
+                "category_id": 1,
+                "segmentation": [],
+                "area": 100.0,
+                "bbox": [0.0, 0.0, 10.0, 10.0],
+                "iscrowd": 0
+            }
+        ],
+        "categories": [
+            {"id": 1, "name": "person", "supercategory": "human"},
+            {"id": 2, "name": "car", "supercategory": "vehicle"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cococonvert_yolo_export() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco(&temp_dir);
+    let labels_dir = temp_dir.path().join("labels");
+
+    let output = Command::new(get_binary_path("cococonvert"))
+        .arg(&coco_path)
+        .arg("--to")
+        .arg("yolo")
+        .arg("-o")
+        .arg(&labels_dir)
+        .output()
+        .expect("Failed to execute cococonvert");
+
+    assert!(output.status.success(), "cococonvert failed: {:?}", output);
+
+    let classes = fs::read_to_string(labels_dir.join("classes.txt")).unwrap();
+    assert_eq!(classes, "person\ncar\n");
+
+    // bbox [64, 48, 160, 120] on a 640x480 image
+    let label = fs::read_to_string(labels_dir.join("img1.txt")).unwrap();
+    assert_eq!(label, "1 0.225000 0.225000 0.250000 0.250000\n");
+
+    // images without object detection annotations get an empty label file
+    assert_eq!(fs::read_to_string(labels_dir.join("img2.txt")).unwrap(), "");
+    assert!(!labels_dir.join("broken.txt").exists());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Wrote 2 label file(s) with 2 class(es)"));
+    assert!(stdout.contains("Skipped 1 image(s) and 2 annotation(s)"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("skipping image id 3 with zero width or height"));
+}
+
+fn create_dummy_image(path: &PathBuf, width: u32, height: u32) {
+    use image::{ImageBuffer, Rgb};
+    let img = ImageBuffer::from_fn(width, height, |_x, _y| Rgb([255u8, 0u8, 0u8]));
+    img.save(path).unwrap();
+}
+
+fn bboxes_by_file(coco: &serde_json::Value) -> Vec<(String, i64, Vec<f64>)> {
+    let mut bboxes: Vec<(String, i64, Vec<f64>)> = coco["annotations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|ann| {
+            let image = coco["images"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|image| image["id"] == ann["image_id"])
+                .unwrap();
+            let file_name = PathBuf::from(image["file_name"].as_str().unwrap());
+            (
+                file_name.file_name().unwrap().to_string_lossy().to_string(),
+                ann["category_id"].as_i64().unwrap(),
+                ann["bbox"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|v| v.as_f64().unwrap())
+                    .collect(),
+            )
+        })
+        .collect();
+    bboxes.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+    bboxes
+}
+
+#[test]
+fn test_cococonvert_yolo_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 640, 480);
+    create_dummy_image(&images_dir.join("b.png"), 123, 77);
+    create_dummy_image(&images_dir.join("empty.png"), 10, 10);
+
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 640, "height": 480, "file_name": "images/a.png"},
+            {"id": 2, "width": 123, "height": 77, "file_name": "images/b.png"},
+            {"id": 3, "width": 10, "height": 10, "file_name": "images/empty.png"}
+        ],
+        "annotations": [
+            {
+                "id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+                "area": 1.0, "bbox": [64.0, 48.0, 160.0, 120.0], "iscrowd": 0
+            },
+            {
+                "id": 2, "image_id": 1, "category_id": 2, "segmentation": [],
+                "area": 1.0, "bbox": [300.5, 10.25, 33.0, 401.0], "iscrowd": 0
+            },
+            {
+                "id": 3, "image_id": 2, "category_id": 2, "segmentation": [],
+                "area": 1.0, "bbox": [1.0, 2.0, 100.0, 70.0], "iscrowd": 0
+            }
+        ],
+        "categories": [
+            {"id": 1, "name": "person", "supercategory": "human"},
+            {"id": 2, "name": "car", "supercategory": "vehicle"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let labels_dir = temp_dir.path().join("labels");
+
+    let output = Command::new(get_binary_path("cococonvert"))
+        .arg(&coco_path)
+        .arg("--to")
+        .arg("yolo")
+        .arg("-o")
+        .arg(&labels_dir)
+        .output()
+        .expect("Failed to execute cococonvert");
+    assert!(output.status.success(), "export failed: {:?}", output);
+
+    // an image without a label file is imported unannotated
+    fs::remove_file(labels_dir.join("empty.txt")).unwrap();
+
+    let imported_path = temp_dir.path().join("imported.json");
+    let output = Command::new(get_binary_path("cococonvert"))
+        .arg("--from")
+        .arg("yolo")
+        .arg("--images-dir")
+        .arg(&images_dir)
+        .arg("--labels-dir")
+        .arg(&labels_dir)
+        .arg("--classes")
+        .arg(labels_dir.join("classes.txt"))
+        .arg("-o")
+        .arg(&imported_path)
+        .output()
+        .expect("Failed to execute cococonvert");
+    assert!(output.status.success(), "import failed: {:?}", output);
+
+    let original: serde_json::Value = serde_json::from_str(coco_json).unwrap();
+    let imported: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&imported_path).unwrap()).unwrap();
+
+    assert_eq!(imported["images"].as_array().unwrap().len(), 3);
+    assert_eq!(imported["categories"][0]["name"], "person");
+    assert_eq!(imported["categories"][1]["name"], "car");
+
+    let expected = bboxes_by_file(&original);
+    let actual = bboxes_by_file(&imported);
+    assert_eq!(expected.len(), actual.len());
+    for ((file_a, category_a, bbox_a), (file_b, category_b, bbox_b)) in
+        expected.iter().zip(actual.iter())
+    {
+        assert_eq!(file_a, file_b);
+        assert_eq!(category_a, category_b);
+        for (a, b) in bbox_a.iter().zip(bbox_b.iter()) {
+            assert!((a - b).abs() < 0.01, "{:?} != {:?}", bbox_a, bbox_b);
+        }
+    }
+}
+
+#[test]
+fn test_cococonvert_requires_direction() {
+    let output = Command::new(get_binary_path("cococonvert"))
+        .arg("dataset.json")
+        .output()
+        .expect("Failed to execute cococonvert");
+    assert!(!output.status.success());
+}
@@ -1,6 +1,8 @@
 // Integration tests for cococp binary
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -371,3 +373,637 @@ fn test_cococp_preserves_extension() {
     assert!(images_output.join("img2.png").exists());
     assert!(images_output.join("img3.bmp").exists());
 }
+
+fn create_resize_test_coco(temp_dir: &TempDir, segmentation: &str) -> PathBuf {
+    let images_dir = temp_dir.path().join("source");
+    fs::create_dir(&images_dir).unwrap();
+
+    create_dummy_image(&images_dir.join("big.png"), 400, 200);
+    create_dummy_image(&images_dir.join("small.png"), 50, 50);
+
+    let coco_json = format!(
+        r#"{{
+        "images": [
+            {{"id": 0, "width": 400, "height": 200, "file_name": "source/big.png"}},
+            {{"id": 1, "width": 50, "height": 50, "file_name": "source/small.png"}}
+        ],
+        "annotations": [
+            {{
+                "id": 1,
+                "image_id": 0,
+                "category_id": 1,
+                "segmentation": {},
+                "area": 800.0,
+                "bbox": [100.0, 40.0, 40.0, 20.0],
+                "iscrowd": 0,
+                "keypoints": [120.0, 50.0, 2.0],
+                "num_keypoints": 1
+            }},
+            {{
+                "id": 2,
+                "image_id": 1,
+                "category_id": 1,
+                "segmentation": [[10.0, 10.0, 20.0, 10.0, 20.0, 20.0]],
+                "area": 100.0,
+                "bbox": [10.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            }}
+        ]
+    }}"#,
+        segmentation
+    );
+
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cococp_max_side_scales_annotations() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_resize_test_coco(&temp_dir, "[[100.0, 40.0, 140.0, 40.0, 140.0, 60.0]]");
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--max-side")
+        .arg("100")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+
+    let resized = image::open(output_dir.join("images").join("big.png")).unwrap();
+    assert_eq!((resized.width(), resized.height()), (100, 50));
+
+    let coco_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+
+    assert_eq!(coco["images"][0]["width"].as_u64().unwrap(), 100);
+    assert_eq!(coco["images"][0]["height"].as_u64().unwrap(), 50);
+
+    let ann = &coco["annotations"][0];
+    let bbox: Vec<f64> = ann["bbox"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(bbox, vec![25.0, 10.0, 10.0, 5.0]);
+    assert_eq!(ann["area"].as_f64().unwrap(), 50.0);
+    let polygon: Vec<f64> = ann["segmentation"][0]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(polygon, vec![25.0, 10.0, 35.0, 10.0, 35.0, 15.0]);
+    let keypoints: Vec<f64> = ann["keypoints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(keypoints, vec![30.0, 12.5, 2.0]);
+
+    // the small image is untouched, as are its annotations
+    assert_eq!(
+        fs::read(output_dir.join("images").join("small.png")).unwrap(),
+        fs::read(temp_dir.path().join("source").join("small.png")).unwrap()
+    );
+    assert_eq!(coco["images"][1]["width"].as_u64().unwrap(), 50);
+    assert_eq!(coco["annotations"][1]["area"].as_f64().unwrap(), 100.0);
+}
+
+#[test]
+fn test_cococp_format_reencodes() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--format")
+        .arg("png")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+
+    let images_dir = output_dir.join("images");
+    assert!(images_dir.join("img1.png").exists());
+    assert!(images_dir.join("img2.png").exists());
+    assert!(!images_dir.join("img1.jpg").exists());
+
+    let decoded = image::open(images_dir.join("img1.png")).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (100, 100));
+
+    let coco_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+    assert_eq!(
+        coco["images"][0]["file_name"].as_str().unwrap(),
+        "images/img1.png"
+    );
+}
+
+#[test]
+fn test_cococp_max_side_resamples_crowd_rle() {
+    let temp_dir = TempDir::new().unwrap();
+    // crowd mask covering the annotation's bbox [100, 40, 40, 20] of the 400x200 image
+    let mask: Vec<bool> = (0..200)
+        .flat_map(|y| (0..400).map(move |x| (100..140).contains(&x) && (40..60).contains(&y)))
+        .collect();
+    let rle = cococrawl::CocoRLE::from_mask(&mask, 400, 200);
+    let coco_path = create_resize_test_coco(&temp_dir, &serde_json::to_string(&rle).unwrap());
+    let mut coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&coco_path).unwrap()).unwrap();
+    coco["annotations"][0]["iscrowd"] = 1.into();
+    fs::write(&coco_path, coco.to_string()).unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--max-side")
+        .arg("100")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+
+    let coco_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+    let ann = &coco["annotations"][0];
+    assert_eq!(ann["iscrowd"], 1);
+    let rle: cococrawl::CocoRLE = serde_json::from_value(ann["segmentation"].clone()).unwrap();
+    assert_eq!(rle.size, (50, 100));
+    assert_eq!(rle.area(), 10 * 5);
+    let bbox: Vec<f64> = ann["bbox"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(bbox, vec![25.0, 10.0, 10.0, 5.0]);
+}
+
+fn run_cococp_directory(coco_path: &PathBuf, output_dir: &PathBuf) {
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(coco_path)
+        .arg("-o")
+        .arg(output_dir)
+        .output()
+        .expect("Failed to execute cococp");
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+}
+
+fn run_cococp_archive(coco_path: &PathBuf, archive_path: &PathBuf) {
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(coco_path)
+        .arg("--archive")
+        .arg(archive_path)
+        .output()
+        .expect("Failed to execute cococp");
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+}
+
+fn assert_matches_directory(entries: &HashMap<String, Vec<u8>>, output_dir: &Path) {
+    let expected = ["test.json", "images/img1.jpg", "images/img2.png"];
+    assert_eq!(entries.len(), expected.len());
+    for name in expected {
+        assert_eq!(
+            entries[name],
+            fs::read(output_dir.join(name)).unwrap(),
+            "archive entry {} differs",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_cococp_tar_archive_matches_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+    let archive_path = temp_dir.path().join("dataset.tar");
+
+    run_cococp_directory(&coco_path, &output_dir);
+    run_cococp_archive(&coco_path, &archive_path);
+
+    let mut archive = tar::Archive::new(fs::File::open(&archive_path).unwrap());
+    let entries: HashMap<String, Vec<u8>> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            (name, data)
+        })
+        .collect();
+
+    assert_matches_directory(&entries, &output_dir);
+    assert!(!temp_dir.path().join("coco-dataset").exists());
+}
+
+#[test]
+fn test_cococp_tar_gz_archive() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let archive_path = temp_dir.path().join("dataset.tar.gz");
+
+    run_cococp_archive(&coco_path, &archive_path);
+
+    let decoder = flate2::read::GzDecoder::new(fs::File::open(&archive_path).unwrap());
+    let mut archive = tar::Archive::new(decoder);
+    let names: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+        .collect();
+
+    assert_eq!(names.len(), 3);
+    assert!(names.contains(&"test.json".to_string()));
+    assert!(names.contains(&"images/img1.jpg".to_string()));
+}
+
+#[test]
+fn test_cococp_zip_archive_matches_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+    let archive_path = temp_dir.path().join("dataset.zip");
+
+    run_cococp_directory(&coco_path, &output_dir);
+    run_cococp_archive(&coco_path, &archive_path);
+
+    let mut archive = zip::ZipArchive::new(fs::File::open(&archive_path).unwrap()).unwrap();
+    let entries: HashMap<String, Vec<u8>> = (0..archive.len())
+        .map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            (file.name().to_string(), data)
+        })
+        .collect();
+
+    assert_matches_directory(&entries, &output_dir);
+}
+
+#[test]
+fn test_cococp_archive_unsupported_extension() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("--archive")
+        .arg(temp_dir.path().join("dataset.rar"))
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unsupported archive extension"));
+}
+
+fn create_stale_dims_coco(temp_dir: &TempDir) -> PathBuf {
+    let images_dir = temp_dir.path().join("source");
+    fs::create_dir(&images_dir).unwrap();
+
+    create_dummy_image(&images_dir.join("stale.png"), 120, 80);
+    create_dummy_image(&images_dir.join("correct.png"), 50, 50);
+
+    let coco_json = r#"{
+        "images": [
+            {"id": 0, "width": 100, "height": 100, "file_name": "source/stale.png"},
+            {"id": 1, "width": 50, "height": 50, "file_name": "source/correct.png"}
+        ],
+        "annotations": []
+    }"#;
+
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cococp_verify_dims_reports_mismatch() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_stale_dims_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--verify-dims")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(
+        "Dimension mismatch: image id 0 (source/stale.png) recorded 100x100, actual 120x80"
+    ));
+    assert!(stderr.contains("1 image(s) with mismatched dimensions"));
+    assert!(!stderr.contains("image id 1"));
+
+    // without --fix-dims the recorded dimensions are kept
+    let coco_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+    assert_eq!(coco["images"][0]["width"].as_u64().unwrap(), 100);
+}
+
+#[test]
+fn test_cococp_fix_dims() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_stale_dims_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--verify-dims")
+        .arg("--fix-dims")
+        .arg("--strict")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+
+    let coco_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+    assert_eq!(coco["images"][0]["width"].as_u64().unwrap(), 120);
+    assert_eq!(coco["images"][0]["height"].as_u64().unwrap(), 80);
+    assert_eq!(coco["images"][1]["width"].as_u64().unwrap(), 50);
+}
+
+#[test]
+fn test_cococp_verify_dims_strict() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_stale_dims_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--verify-dims")
+        .arg("--strict")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 image(s) have mismatched dimensions"));
+}
+
+#[test]
+fn test_cococp_verify_copies() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--verify-copies")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Copied 2, failed 0, skipped 0"));
+
+    // the copy of an image with stale dimensions doesn't match them
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_stale_dims_coco(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--verify-copies")
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is 120x80 instead of 100x100"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Copied 1, failed 1, skipped 0"));
+}
+
+#[test]
+fn test_cococp_skip_existing() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_with_images(&temp_dir);
+    let output_dir = temp_dir.path().join("output");
+
+    let run = |extra_args: &[&str]| {
+        let output = Command::new(get_binary_path("cococp"))
+            .arg(&coco_path)
+            .arg("-o")
+            .arg(&output_dir)
+            .arg("--skip-existing")
+            .args(extra_args)
+            .output()
+            .expect("Failed to execute cococp");
+        assert!(output.status.success(), "cococp failed: {:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert!(run(&[]).contains("Skipped 0 existing image(s), copied 2"));
+    let first_json = fs::read_to_string(output_dir.join("test.json")).unwrap();
+
+    assert!(run(&[]).contains("Skipped 2 existing image(s), copied 0"));
+    assert_eq!(
+        fs::read_to_string(output_dir.join("test.json")).unwrap(),
+        first_json
+    );
+
+    // same size but different content is only caught by --verify
+    let copied = output_dir.join("images/img1.jpg");
+    let mut data = fs::read(&copied).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xFF;
+    fs::write(&copied, data).unwrap();
+    assert!(run(&[]).contains("Skipped 2 existing image(s), copied 0"));
+    assert!(run(&["--verify"]).contains("Skipped 1 existing image(s), copied 1"));
+    assert_eq!(
+        fs::read(&copied).unwrap(),
+        fs::read(temp_dir.path().join("source_images/img1.jpg")).unwrap()
+    );
+}
+
+#[test]
+fn test_cococp_strip_metadata() {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageBuffer, ImageEncoder, Rgb};
+
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("source");
+    fs::create_dir(&images_dir).unwrap();
+
+    let has_exif = |data: &[u8]| data.windows(6).any(|window| window == b"Exif\0\0");
+    let src_path = images_dir.join("gps.jpg");
+    let image = ImageBuffer::from_fn(40, 30, |x, y| Rgb([x as u8, y as u8, 0u8]));
+    let mut encoder = JpegEncoder::new(fs::File::create(&src_path).unwrap());
+    encoder
+        .set_exif_metadata(b"MM\0*\0\0\0\x08GPS position".to_vec())
+        .unwrap();
+    encoder
+        .write_image(image.as_raw(), 40, 30, image::ExtendedColorType::Rgb8)
+        .unwrap();
+    assert!(has_exif(&fs::read(&src_path).unwrap()));
+
+    // stale dimensions are corrected from the decoded image
+    let coco_json = r#"{
+        "images": [
+            {"id": 0, "width": 10, "height": 10, "file_name": "source/gps.jpg"}
+        ],
+        "annotations": []
+    }"#;
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .arg("--strip-metadata")
+        .arg("--quality")
+        .arg("60")
+        .output()
+        .expect("Failed to execute cococp");
+    assert!(output.status.success(), "cococp failed: {:?}", output);
+
+    let copied = fs::read(output_dir.join("images/gps.jpg")).unwrap();
+    assert!(!has_exif(&copied));
+    assert_eq!(image::load_from_memory(&copied).unwrap().width(), 40);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_dir.join("test.json")).unwrap()).unwrap();
+    assert_eq!(coco["images"][0]["width"], 40);
+    assert_eq!(coco["images"][0]["height"], 30);
+}
+
+#[test]
+fn test_cococp_continues_after_failed_image() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("source_images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("img1.jpg"), 100, 100);
+    create_dummy_image(&images_dir.join("img3.png"), 100, 100);
+    // not an image, so it can't be decoded for re-encoding
+    fs::write(images_dir.join("img2.jpg"), b"not a jpeg").unwrap();
+
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "source_images/img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "source_images/img2.jpg"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "source_images/img3.png"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 2, "category_id": 1, "segmentation": [],
+             "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"id": 2, "image_id": 3, "category_id": 1, "segmentation": [],
+             "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_dir = temp_dir.path().join("output");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .args(["--format", "png", "--quiet"])
+        .output()
+        .expect("Failed to execute cococp");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Could not copy image id 2"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Copied 2, failed 1, skipped 0"),
+        "{}",
+        stdout
+    );
+
+    assert!(output_dir.join("images/img1.png").exists());
+    assert!(output_dir.join("images/img3.png").exists());
+    assert!(!output_dir.join("images/img2.png").exists());
+
+    // the failed image and its annotations are left out
+    let output_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_dir.join("test.json")).unwrap()).unwrap();
+    let image_ids: Vec<_> = output_json["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(image_ids, vec![1, 3]);
+    assert_eq!(output_json["annotations"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_cococp_manifest() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("source_images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("img1.jpg"), 100, 100);
+    create_dummy_image(&images_dir.join("img,2.png"), 100, 100);
+    fs::write(images_dir.join("img3.jpg"), b"not a jpeg").unwrap();
+
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "source_images/img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "source_images/img,2.png"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "source_images/img3.jpg"},
+            {"id": 4, "width": 100, "height": 100, "file_name": "source_images/missing.jpg"}
+        ],
+        "annotations": []
+    }"#;
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_dir = temp_dir.path().join("output");
+    let manifest_path = temp_dir.path().join("manifest.csv");
+
+    let output = Command::new(get_binary_path("cococp"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_dir)
+        .args(["--format", "png", "--quiet", "--manifest"])
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute cococp");
+    // img3.jpg can't be decoded for re-encoding
+    assert!(!output.status.success());
+
+    let source = |name: &str| images_dir.join(name).to_string_lossy().into_owned();
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    let rows: Vec<&str> = manifest.lines().collect();
+    assert_eq!(
+        rows,
+        vec![
+            "image_id,source_path,dest_path,status".to_string(),
+            format!("1,{},images/img1.png,copied", source("img1.jpg")),
+            format!("2,\"{}\",\"images/img,2.png\",copied", source("img,2.png")),
+            format!("3,{},,failed", source("img3.jpg")),
+            format!("4,{},,missing", source("missing.jpg")),
+        ]
+    );
+}
@@ -44,7 +44,7 @@ fn test_cococrawl_single_directory() {
     let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
 
     assert_eq!(coco["images"].as_array().unwrap().len(), 2);
-    
+
     // Check that we have one 100x100 and one 200x200 image (order may vary)
     let widths: Vec<u64> = coco["images"]
         .as_array()
@@ -58,7 +58,7 @@ fn test_cococrawl_single_directory() {
         .iter()
         .map(|img| img["height"].as_u64().unwrap())
         .collect();
-    
+
     assert!(widths.contains(&100));
     assert!(widths.contains(&200));
     assert!(heights.contains(&100));
@@ -203,6 +203,104 @@ fn test_cococrawl_multiple_formats() {
     assert_eq!(coco["images"].as_array().unwrap().len(), 4);
 }
 
+#[test]
+fn test_cococrawl_header_dimensions() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+
+    let sizes = [
+        ("a.jpg", 31, 17),
+        ("b.png", 40, 9),
+        ("c.bmp", 7, 23),
+        ("d.tiff", 64, 3),
+    ];
+    for (name, width, height) in sizes {
+        create_dummy_image(&images_dir.join(name), width, height);
+    }
+
+    let output_path = temp_dir.path().join("coco.json");
+    for probe in ["fast", "full"] {
+        let output = Command::new(get_binary_path("cococrawl"))
+            .arg(&images_dir)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--probe")
+            .arg(probe)
+            .arg("--extensions")
+            .arg("jpg,png,bmp,tiff")
+            .output()
+            .expect("Failed to execute cococrawl");
+        assert!(output.status.success());
+
+        let coco: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        for (name, width, height) in sizes {
+            let image = coco["images"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|image| image["file_name"] == format!("images/{}", name))
+                .unwrap();
+            assert_eq!(
+                (image["width"].as_u64(), image["height"].as_u64()),
+                (Some(width as u64), Some(height as u64)),
+                "{} with --probe {}",
+                name,
+                probe
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cococrawl_checksums() {
+    use sha2::{Digest, Sha256};
+
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 20, 10);
+
+    let output_path = temp_dir.path().join("coco.json");
+    let crawl = |extra_args: &[&str]| -> serde_json::Value {
+        let output = Command::new(get_binary_path("cococrawl"))
+            .arg(&images_dir)
+            .arg("-o")
+            .arg(&output_path)
+            .args(extra_args)
+            .output()
+            .expect("Failed to execute cococrawl");
+        assert!(output.status.success());
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap()
+    };
+
+    let coco = crawl(&["--checksums"]);
+    let images = coco["images"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+    for image in images {
+        let digest = image["sha256"].as_str().unwrap();
+        assert_eq!(digest.len(), 64);
+        assert!(
+            digest
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        );
+
+        let file = temp_dir.path().join(image["file_name"].as_str().unwrap());
+        let expected: String = Sha256::digest(fs::read(file).unwrap())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(digest, expected);
+    }
+
+    // without the flag nothing is hashed
+    let coco = crawl(&[]);
+    assert!(coco["images"][0].get("sha256").is_none());
+}
+
 #[test]
 fn test_cococrawl_ignores_non_images() {
     let temp_dir = TempDir::new().unwrap();
@@ -254,6 +352,24 @@ fn test_cococrawl_empty_directory() {
     assert_eq!(coco["images"].as_array().unwrap().len(), 0);
 }
 
+#[test]
+fn test_cococrawl_missing_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("coco.json");
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(temp_dir.path().join("missing"))
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .expect("Failed to execute cococrawl");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing"), "unexpected stderr: {}", stderr);
+    assert!(!output_path.exists());
+}
+
 #[test]
 fn test_cococrawl_version_string() {
     let temp_dir = TempDir::new().unwrap();
@@ -332,3 +448,855 @@ fn test_cococrawl_image_metadata() {
     assert_eq!(image["height"].as_u64().unwrap(), 240);
     assert!(image["file_name"].is_string());
 }
+
+/// Builds a little endian TIFF block with an optional Orientation tag in IFD0 and an optional
+/// DateTimeOriginal in the Exif sub-IFD
+fn exif_block(orientation: Option<u16>, date_time_original: Option<&str>) -> Vec<u8> {
+    let ifd0_entries = orientation.is_some() as u16 + date_time_original.is_some() as u16;
+    let exif_ifd_offset = 8 + 2 + 12 * ifd0_entries as u32 + 4;
+    let date_offset = exif_ifd_offset + 2 + 12 + 4;
+
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"II");
+    tiff.extend_from_slice(&42u16.to_le_bytes());
+    tiff.extend_from_slice(&8u32.to_le_bytes());
+
+    // IFD0, entries are tag, type, count, value
+    tiff.extend_from_slice(&ifd0_entries.to_le_bytes());
+    if let Some(orientation) = orientation {
+        tiff.extend_from_slice(&0x0112u16.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+    }
+    if date_time_original.is_some() {
+        tiff.extend_from_slice(&0x8769u16.to_le_bytes());
+        tiff.extend_from_slice(&4u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&exif_ifd_offset.to_le_bytes());
+    }
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+
+    if let Some(date_time_original) = date_time_original {
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x9003u16.to_le_bytes());
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        tiff.extend_from_slice(&20u32.to_le_bytes());
+        tiff.extend_from_slice(&date_offset.to_le_bytes());
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(date_time_original.as_bytes());
+        tiff.push(0);
+    }
+
+    tiff
+}
+
+fn create_exif_jpeg(path: &PathBuf, width: u32, height: u32, exif: Vec<u8>) {
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageBuffer, ImageEncoder, Rgb};
+    let img = ImageBuffer::from_fn(width, height, |_x, _y| Rgb([255u8, 0u8, 0u8]));
+    let mut encoder = JpegEncoder::new(fs::File::create(path).unwrap());
+    encoder.set_exif_metadata(exif).unwrap();
+    encoder
+        .write_image(img.as_raw(), width, height, image::ExtendedColorType::Rgb8)
+        .unwrap();
+}
+
+fn crawl_single_image(temp_dir: &TempDir, extra_args: &[&str]) -> serde_json::Value {
+    let output_path = temp_dir.path().join("coco.json");
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(temp_dir.path().join("images"))
+        .arg("-o")
+        .arg(&output_path)
+        .args(extra_args)
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(output.status.success(), "cococrawl failed: {:?}", output);
+
+    let coco_json = fs::read_to_string(&output_path).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+    coco["images"][0].clone()
+}
+
+#[test]
+fn test_cococrawl_exif_date_captured() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+
+    create_exif_jpeg(
+        &images_dir.join("photo.jpg"),
+        40,
+        20,
+        exif_block(None, Some("2019:06:15 10:30:00")),
+    );
+
+    let image = crawl_single_image(&temp_dir, &[]);
+    assert_eq!(image["date_captured"], "2019-06-15T10:30:00Z");
+}
+
+#[test]
+fn test_cococrawl_respect_exif_orientation() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+
+    // orientation 6 is rotated 90 degrees clockwise for display
+    create_exif_jpeg(
+        &images_dir.join("photo.jpg"),
+        40,
+        20,
+        exif_block(Some(6), None),
+    );
+
+    let image = crawl_single_image(&temp_dir, &[]);
+    assert_eq!(image["width"].as_u64().unwrap(), 40);
+    assert_eq!(image["height"].as_u64().unwrap(), 20);
+
+    let image = crawl_single_image(&temp_dir, &["--respect-exif-orientation"]);
+    assert_eq!(image["width"].as_u64().unwrap(), 20);
+    assert_eq!(image["height"].as_u64().unwrap(), 40);
+}
+
+#[test]
+fn test_cococrawl_exif_orientation_without_rotation() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+
+    create_exif_jpeg(
+        &images_dir.join("photo.jpg"),
+        40,
+        20,
+        exif_block(Some(3), None),
+    );
+
+    let image = crawl_single_image(&temp_dir, &["--respect-exif-orientation"]);
+    assert_eq!(image["width"].as_u64().unwrap(), 40);
+    assert_eq!(image["height"].as_u64().unwrap(), 20);
+}
+
+#[test]
+fn test_cococrawl_extensions_and_exclude() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    let thumbnails_dir = images_dir.join("thumbnails");
+    fs::create_dir_all(&thumbnails_dir).unwrap();
+
+    create_dummy_image(&images_dir.join("photo.JPG"), 10, 10);
+    create_dummy_image(&images_dir.join("diagram.png"), 10, 10);
+    create_dummy_image(&thumbnails_dir.join("photo.jpg"), 5, 5);
+
+    let output_path = temp_dir.path().join("coco.json");
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(&images_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--extensions")
+        .arg("jpg")
+        .arg("--exclude")
+        .arg("**/thumbnails/**")
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(output.status.success(), "cococrawl failed: {:?}", output);
+
+    let coco_json = fs::read_to_string(&output_path).unwrap();
+    let coco: serde_json::Value = serde_json::from_str(&coco_json).unwrap();
+
+    let images = coco["images"].as_array().unwrap();
+    assert_eq!(images.len(), 1);
+    assert!(
+        images[0]["file_name"]
+            .as_str()
+            .unwrap()
+            .ends_with("photo.JPG")
+    );
+}
+
+fn run_cococrawl(args: &[&std::ffi::OsStr]) -> std::process::Output {
+    let output = Command::new(get_binary_path("cococrawl"))
+        .args(args)
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(output.status.success(), "cococrawl failed: {:?}", output);
+    output
+}
+
+fn image_ids_by_name(coco_path: &PathBuf) -> std::collections::HashMap<String, i64> {
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(coco_path).unwrap()).unwrap();
+    coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|img| {
+            (
+                img["file_name"].as_str().unwrap().to_string(),
+                img["id"].as_i64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_cococrawl_append_keeps_existing_ids() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 10, 10);
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+    ]);
+    let before = image_ids_by_name(&output_path);
+    assert_eq!(before.len(), 2);
+
+    // annotations of the existing dataset must survive the append
+    let mut coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    coco["annotations"] = serde_json::json!([{
+        "id": 1,
+        "image_id": before["images/a.png"],
+        "category_id": 1,
+        "segmentation": [],
+        "area": 1.0,
+        "bbox": [0.0, 0.0, 1.0, 1.0],
+        "iscrowd": 0
+    }]);
+    fs::write(&output_path, serde_json::to_string(&coco).unwrap()).unwrap();
+
+    create_dummy_image(&images_dir.join("c.png"), 10, 10);
+    let output = run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--append".as_ref(),
+        output_path.as_os_str(),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Kept 2 image(s), added 1, pruned 0"));
+
+    let after = image_ids_by_name(&output_path);
+    assert_eq!(after.len(), 3);
+    assert_eq!(after["images/a.png"], before["images/a.png"]);
+    assert_eq!(after["images/b.png"], before["images/b.png"]);
+    assert_eq!(after["images/c.png"], 2);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(coco["annotations"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn test_cococrawl_merge_into_sidecar() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 10, 10);
+
+    // a hand written sidecar that registers a.png under a non-normalized name
+    let sidecar_path = temp_dir.path().join("coco.json");
+    let sidecar = serde_json::json!({
+        "images": [{"id": 7, "width": 10, "height": 10, "file_name": "./images/../images/a.png"}],
+        "annotations": [{"id": 1, "image_id": 7, "caption": "a red square"}],
+        "categories": []
+    });
+    fs::write(&sidecar_path, sidecar.to_string()).unwrap();
+
+    let output = run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        sidecar_path.as_os_str(),
+        "--merge-into".as_ref(),
+        sidecar_path.as_os_str(),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Kept 1 image(s), added 1, pruned 0"));
+
+    let after = image_ids_by_name(&sidecar_path);
+    assert_eq!(after.len(), 2);
+    assert_eq!(after["images/a.png"], 7);
+    assert_eq!(after["images/b.png"], 8);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&sidecar_path).unwrap()).unwrap();
+    assert_eq!(coco["annotations"][0]["image_id"], 7);
+}
+
+#[test]
+fn test_cococrawl_append_prune_missing() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 10, 10);
+
+    let existing_path = temp_dir.path().join("existing.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        existing_path.as_os_str(),
+    ]);
+    let before = image_ids_by_name(&existing_path);
+
+    fs::remove_file(images_dir.join("a.png")).unwrap();
+    let output_path = temp_dir.path().join("updated.json");
+
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--append".as_ref(),
+        existing_path.as_os_str(),
+    ]);
+    assert_eq!(image_ids_by_name(&output_path).len(), 2);
+
+    let output = run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--append".as_ref(),
+        existing_path.as_os_str(),
+        "--prune-missing".as_ref(),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Kept 1 image(s), added 0, pruned 1"));
+
+    let after = image_ids_by_name(&output_path);
+    assert_eq!(after.len(), 1);
+    assert_eq!(after["images/b.png"], before["images/b.png"]);
+}
+
+#[test]
+fn test_cococrawl_append_failure_keeps_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+    ]);
+    let before = fs::read_to_string(&output_path).unwrap();
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(&images_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--append")
+        .arg(&output_path)
+        .arg("--exclude")
+        .arg("[bad")
+        .output()
+        .expect("Failed to execute cococrawl");
+
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&output_path).unwrap(), before);
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_cococrawl_skips_broken_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("good.png"), 10, 10);
+    fs::write(images_dir.join("empty.jpg"), b"").unwrap();
+
+    let output_path = temp_dir.path().join("coco.json");
+    let output = run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped 1 image(s): 1 corrupt"));
+
+    let ids = image_ids_by_name(&output_path);
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids["images/good.png"], 0);
+
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--keep-broken".as_ref(),
+    ]);
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    let images = coco["images"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+    let broken = images
+        .iter()
+        .find(|img| img["file_name"] == "images/empty.jpg")
+        .unwrap();
+    assert_eq!(broken["width"], 0);
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(&images_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--fail-on-error")
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(!output.status.success());
+    assert_eq!(image_ids_by_name(&output_path).len(), 1);
+}
+
+#[test]
+fn test_cococrawl_dedupe_sha256() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir_all(images_dir.join("copies")).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 20, 10);
+    fs::copy(
+        images_dir.join("a.png"),
+        images_dir.join("copies/a-copy.png"),
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("coco.json");
+    let report_path = temp_dir.path().join("dupes.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--dedupe".as_ref(),
+        "sha256".as_ref(),
+        "--dedupe-report".as_ref(),
+        report_path.as_os_str(),
+    ]);
+
+    let ids = image_ids_by_name(&output_path);
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains_key("images/b.png"));
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+    let report = report.as_object().unwrap();
+    assert_eq!(report.len(), 1);
+    let (kept, dropped) = report.iter().next().unwrap();
+    let dropped = dropped.as_array().unwrap();
+    assert_eq!(dropped.len(), 1);
+
+    // crawl order decides which copy is kept
+    let mut names: Vec<&str> = vec![kept.as_str(), dropped[0].as_str().unwrap()];
+    names.sort();
+    assert!(names[0].ends_with("images/a.png"));
+    assert!(names[1].ends_with("images/copies/a-copy.png"));
+}
+
+#[test]
+fn test_cococrawl_dedupe_phash() {
+    use image::{ImageBuffer, Rgb};
+
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+
+    // coarse blocks of different gray levels give the perceptual hash something to latch onto
+    let photo = ImageBuffer::from_fn(120, 80, |x, y| {
+        let value = ((x / 20 * 7 + y / 20 * 13) * 37 % 200) as u8;
+        Rgb([value, value, value])
+    });
+    photo.save(images_dir.join("photo.png")).unwrap();
+    image::imageops::resize(&photo, 60, 40, image::imageops::FilterType::Triangle)
+        .save(images_dir.join("photo-small.jpg"))
+        .unwrap();
+    image::imageops::flip_vertical(&photo)
+        .save(images_dir.join("flipped.png"))
+        .unwrap();
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--dedupe".as_ref(),
+        "phash".as_ref(),
+    ]);
+
+    let ids = image_ids_by_name(&output_path);
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains_key("images/flipped.png"));
+}
+
+#[test]
+fn test_cococrawl_category_from_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_dir = temp_dir.path().join("dataset");
+    fs::create_dir_all(dataset_dir.join("dog")).unwrap();
+    fs::create_dir_all(dataset_dir.join("cat")).unwrap();
+    create_dummy_image(&dataset_dir.join("dog/a.png"), 30, 20);
+    create_dummy_image(&dataset_dir.join("dog/b.png"), 30, 20);
+    create_dummy_image(&dataset_dir.join("cat/c.png"), 10, 40);
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        dataset_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--category-from-dir".as_ref(),
+        "1".as_ref(),
+    ]);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+
+    // ids are assigned by sorted name
+    let categories = coco["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 2);
+    assert_eq!(categories[0]["id"], 1);
+    assert_eq!(categories[0]["name"], "cat");
+    assert_eq!(categories[1]["id"], 2);
+    assert_eq!(categories[1]["name"], "dog");
+
+    let ids = image_ids_by_name(&output_path);
+    let annotations = coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 3);
+    let cat_annotation = annotations
+        .iter()
+        .find(|ann| ann["image_id"] == ids["dataset/cat/c.png"])
+        .unwrap();
+    assert_eq!(cat_annotation["category_id"], 1);
+    assert_eq!(
+        cat_annotation["bbox"],
+        serde_json::json!([0.0, 0.0, 10.0, 40.0])
+    );
+    assert_eq!(cat_annotation["area"], 400.0);
+    assert_eq!(cat_annotation["iscrowd"], 0);
+    assert_eq!(
+        cat_annotation["segmentation"],
+        serde_json::json!([[0.0, 0.0, 10.0, 0.0, 10.0, 40.0, 0.0, 40.0]])
+    );
+    assert!(
+        annotations
+            .iter()
+            .filter(|ann| ann["image_id"] != ids["dataset/cat/c.png"])
+            .all(|ann| ann["category_id"] == 2)
+    );
+}
+
+#[test]
+fn test_cococrawl_categorize_by_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let dataset_dir = temp_dir.path().join("dataset");
+    fs::create_dir_all(dataset_dir.join("cats")).unwrap();
+    fs::create_dir_all(dataset_dir.join("dogs")).unwrap();
+    create_dummy_image(&dataset_dir.join("cats/a.png"), 30, 20);
+    create_dummy_image(&dataset_dir.join("dogs/b.png"), 10, 40);
+    create_dummy_image(&dataset_dir.join("dogs/c.png"), 10, 40);
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        dataset_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--categorize-by-dir".as_ref(),
+    ]);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    let names: Vec<&str> = coco["categories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|category| category["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["cats", "dogs"]);
+
+    let ids = image_ids_by_name(&output_path);
+    let annotations = coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 3);
+    for (file_name, image_id) in &ids {
+        let image_annotations: Vec<_> = annotations
+            .iter()
+            .filter(|ann| ann["image_id"] == *image_id)
+            .collect();
+        assert_eq!(image_annotations.len(), 1, "{}", file_name);
+        let expected_category = if file_name.contains("/cats/") { 1 } else { 2 };
+        assert_eq!(image_annotations[0]["category_id"], expected_category);
+    }
+    let cat_annotation = annotations
+        .iter()
+        .find(|ann| ann["image_id"] == ids["dataset/cats/a.png"])
+        .unwrap();
+    assert_eq!(cat_annotation["area"], 600.0);
+}
+
+#[test]
+fn test_cococrawl_info_and_license_flags() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+    create_dummy_image(&images_dir.join("b.png"), 10, 10);
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--description".as_ref(),
+        "Street scenes".as_ref(),
+        "--contributor".as_ref(),
+        "Field team".as_ref(),
+        "--url".as_ref(),
+        "https://example.com/dataset".as_ref(),
+        "--license-name".as_ref(),
+        "CC BY 4.0".as_ref(),
+        "--license-url".as_ref(),
+        "https://creativecommons.org/licenses/by/4.0/".as_ref(),
+    ]);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(coco["info"]["description"], "Street scenes");
+    assert_eq!(coco["info"]["contributor"], "Field team");
+    assert_eq!(coco["info"]["url"], "https://example.com/dataset");
+    assert_eq!(
+        coco["licenses"],
+        serde_json::json!([{
+            "id": 1,
+            "name": "CC BY 4.0",
+            "url": "https://creativecommons.org/licenses/by/4.0/"
+        }])
+    );
+    assert!(
+        coco["images"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|img| img["license"] == 1)
+    );
+}
+
+#[test]
+fn test_cococrawl_licenses_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("a.png"), 10, 10);
+
+    let licenses_path = temp_dir.path().join("licenses.json");
+    fs::write(
+        &licenses_path,
+        r#"[{"id": 3, "name": "MIT", "url": ""}, {"id": 7, "name": "Proprietary", "url": ""}]"#,
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("coco.json");
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--licenses-file".as_ref(),
+        licenses_path.as_os_str(),
+        "--default-license-id".as_ref(),
+        "7".as_ref(),
+    ]);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(coco["licenses"].as_array().unwrap().len(), 2);
+    assert_eq!(coco["images"][0]["license"], 7);
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(&images_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--licenses-file")
+        .arg(&licenses_path)
+        .arg("--default-license-id")
+        .arg("2")
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cococrawl_jobs() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    for i in 1..=20 {
+        create_dummy_image(&images_dir.join(format!("img{}.png", i)), i, 2 * i);
+    }
+
+    let crawl = |jobs: &[&str], name: &str| {
+        let output_path = temp_dir.path().join(name);
+        let mut args = vec![
+            images_dir.as_os_str(),
+            "-o".as_ref(),
+            output_path.as_os_str(),
+        ];
+        args.extend(jobs.iter().map(std::ffi::OsStr::new));
+        run_cococrawl(&args);
+        let coco: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        coco["images"].clone()
+    };
+
+    let sequential = crawl(&["--jobs", "1"], "sequential.json");
+    assert_eq!(sequential.as_array().unwrap().len(), 20);
+    assert_eq!(sequential, crawl(&[], "parallel.json"));
+
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(&images_dir)
+        .arg("--jobs")
+        .arg("0")
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cococrawl_dimension_filters() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir(&images_dir).unwrap();
+    create_dummy_image(&images_dir.join("small.png"), 10, 10);
+    create_dummy_image(&images_dir.join("large.png"), 1000, 1000);
+
+    let output_path = temp_dir.path().join("coco.json");
+    let output = run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--min-dim".as_ref(),
+        "100".as_ref(),
+    ]);
+    let ids = image_ids_by_name(&output_path);
+    assert_eq!(ids.len(), 1);
+    assert!(ids.contains_key("images/large.png"));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Skipped 1 image(s) outside the dimension bounds")
+    );
+
+    run_cococrawl(&[
+        images_dir.as_os_str(),
+        "-o".as_ref(),
+        output_path.as_os_str(),
+        "--max-pixels".as_ref(),
+        "100".as_ref(),
+    ]);
+    let ids = image_ids_by_name(&output_path);
+    assert_eq!(ids.keys().collect::<Vec<_>>(), vec!["images/small.png"]);
+}
+
+fn crawled_file_names(images_dir: &PathBuf, output_path: &PathBuf, args: &[&str]) -> Vec<String> {
+    let output = Command::new(get_binary_path("cococrawl"))
+        .arg(images_dir)
+        .arg("-o")
+        .arg(output_path)
+        .args(args)
+        .output()
+        .expect("Failed to execute cococrawl");
+    assert!(output.status.success(), "cococrawl failed: {:?}", output);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+    let mut file_names: Vec<String> = coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["file_name"].as_str().unwrap().to_string())
+        .collect();
+    file_names.sort();
+    file_names
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cococrawl_follow_symlinks() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    let elsewhere = temp_dir.path().join("elsewhere");
+    fs::create_dir(&images_dir).unwrap();
+    fs::create_dir(&elsewhere).unwrap();
+    create_dummy_image(&images_dir.join("local.jpg"), 10, 10);
+    create_dummy_image(&elsewhere.join("linked.jpg"), 10, 10);
+    std::os::unix::fs::symlink(&elsewhere, images_dir.join("linked_dir")).unwrap();
+    // a cycle back to the crawl root must not loop
+    std::os::unix::fs::symlink(&images_dir, images_dir.join("loop")).unwrap();
+    let output_path = temp_dir.path().join("coco.json");
+
+    assert_eq!(
+        crawled_file_names(&images_dir, &output_path, &[]),
+        vec!["images/local.jpg"]
+    );
+    // file names point at the link target
+    assert_eq!(
+        crawled_file_names(&images_dir, &output_path, &["--follow-symlinks"]),
+        vec!["elsewhere/linked.jpg", "images/local.jpg"]
+    );
+}
+
+#[test]
+fn test_cococrawl_max_depth() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    let nested = images_dir.join("nested");
+    fs::create_dir_all(&nested).unwrap();
+    create_dummy_image(&images_dir.join("top.jpg"), 10, 10);
+    create_dummy_image(&nested.join("deep.jpg"), 10, 10);
+    let output_path = temp_dir.path().join("coco.json");
+
+    assert_eq!(
+        crawled_file_names(&images_dir, &output_path, &["--max-depth", "1"]),
+        vec!["images/top.jpg"]
+    );
+    assert_eq!(
+        crawled_file_names(&images_dir, &output_path, &[]),
+        vec!["images/nested/deep.jpg", "images/top.jpg"]
+    );
+}
+
+#[test]
+fn test_cococrawl_deterministic_ids() {
+    let temp_dir = TempDir::new().unwrap();
+    let images_dir = temp_dir.path().join("images");
+    fs::create_dir_all(images_dir.join("b")).unwrap();
+    fs::create_dir_all(images_dir.join("a")).unwrap();
+    // created out of order so directory order and creation order disagree with name order
+    for name in ["z.jpg", "b/y.jpg", "m.jpg", "a/x.jpg", "a/c.jpg", "b/a.jpg"] {
+        create_dummy_image(&images_dir.join(name), 10, 10);
+    }
+
+    let first_path = temp_dir.path().join("first.json");
+    let second_path = temp_dir.path().join("second.json");
+    for output_path in [&first_path, &second_path] {
+        run_cococrawl(&[
+            images_dir.as_os_str(),
+            "-o".as_ref(),
+            output_path.as_os_str(),
+        ]);
+    }
+
+    let ids = image_ids_by_name(&first_path);
+    assert_eq!(ids, image_ids_by_name(&second_path));
+    let mut names_by_id: Vec<(i64, String)> =
+        ids.into_iter().map(|(name, id)| (id, name)).collect();
+    names_by_id.sort();
+    let names: Vec<String> = names_by_id.into_iter().map(|(_, name)| name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "images/a/c.jpg",
+            "images/a/x.jpg",
+            "images/b/a.jpg",
+            "images/b/y.jpg",
+            "images/m.jpg",
+            "images/z.jpg"
+        ]
+    );
+}
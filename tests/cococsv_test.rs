@@ -0,0 +1,83 @@
+// Integration tests for cococsv binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+#[test]
+fn test_cococsv_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 3, "segmentation": [],
+             "area": 200.0, "bbox": [10.0, 20.0, 10.0, 20.0], "iscrowd": 0},
+            {"id": 2, "image_id": 2, "caption": "two dogs, \"playing\"\non the beach"}
+        ],
+        "categories": [{"id": 3, "name": "dog", "supercategory": "animal"}]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let csv_path = temp_dir.path().join("ann.csv");
+
+    let output = Command::new(get_binary_path("cococsv"))
+        .arg("export")
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&csv_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let csv = fs::read_to_string(&csv_path).unwrap();
+    assert!(csv.contains("1,1,a.jpg,dog,10,20,10,20,200,0,\n"));
+
+    let output_path = temp_dir.path().join("out.json");
+    let output = Command::new(get_binary_path("cococsv"))
+        .arg("import")
+        .arg(&csv_path)
+        .arg("--images-from")
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(coco["images"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        coco["annotations"][0]["bbox"],
+        serde_json::json!([10.0, 20.0, 10.0, 20.0])
+    );
+    assert_eq!(coco["annotations"][0]["category_id"], 3);
+    assert_eq!(
+        coco["annotations"][1]["caption"],
+        "two dogs, \"playing\"\non the beach"
+    );
+
+    // an image id that the images file doesn't have
+    fs::write(&csv_path, csv.replace("\n1,1,a.jpg", "\n1,9,a.jpg")).unwrap();
+    let output = Command::new(get_binary_path("cococsv"))
+        .arg("import")
+        .arg(&csv_path)
+        .arg("--images-from")
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("image id 9"));
+}
@@ -0,0 +1,97 @@
+// Integration tests for cocodedupe binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// Two overlapping boxes and a disjoint one on each of two images
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "img2.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 400.0, "bbox": [10.0, 10.0, 20.0, 20.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 400.0, "bbox": [10.0, 11.0, 20.0, 20.0], "iscrowd": 0},
+            {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [60.0, 60.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 4, "image_id": 2, "category_id": 1, "segmentation": [],
+             "area": 90.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 5, "image_id": 2, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 6, "image_id": 2, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [50.0, 50.0, 10.0, 10.0], "iscrowd": 0}
+        ],
+        "categories": [{"id": 1, "name": "box", "supercategory": "thing"}]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cocodedupe_dry_run() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+    let output_path = temp_dir.path().join("deduped.json");
+
+    let output = Command::new(get_binary_path("cocodedupe"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Annotations 1 and 2 are duplicates"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Annotations 4 and 5 are duplicates"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("2 duplicate pair(s)"), "{}", stdout);
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn test_cocodedupe_removes_duplicates() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+    let output_path = temp_dir.path().join("deduped.json");
+
+    let output = Command::new(get_binary_path("cocodedupe"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .args(["--iou-threshold", "0.8"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let deduped: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    let ids: Vec<i64> = deduped["annotations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|annotation| annotation["id"].as_i64().unwrap())
+        .collect();
+    // the first of two equally large boxes and the larger box of image 2 stay
+    assert_eq!(ids, vec![1, 3, 5, 6]);
+}
@@ -0,0 +1,114 @@
+// Integration tests for cocodiff binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+const OLD_JSON: &str = r#"{
+    "images": [
+        {"id": 1, "width": 100, "height": 100, "file_name": "img1.png"},
+        {"id": 2, "width": 100, "height": 100, "file_name": "img2.png"}
+    ],
+    "annotations": [
+        {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+         "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+        {"id": 2, "image_id": 2, "category_id": 1, "segmentation": [],
+         "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0}
+    ],
+    "categories": [
+        {"id": 1, "name": "cat", "supercategory": "animal"}
+    ]
+}"#;
+
+/// img2 is resized and replaced by img3, img1 gets a second annotation and dogs are added
+const NEW_JSON: &str = r#"{
+    "images": [
+        {"id": 1, "width": 100, "height": 100, "file_name": "img1.png"},
+        {"id": 3, "width": 50, "height": 50, "file_name": "img3.png"}
+    ],
+    "annotations": [
+        {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+         "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+        {"id": 4, "image_id": 1, "category_id": 2, "segmentation": [],
+         "area": 100.0, "bbox": [40.0, 40.0, 10.0, 10.0], "iscrowd": 0},
+        {"id": 3, "image_id": 3, "category_id": 1, "segmentation": [],
+         "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0}
+    ],
+    "categories": [
+        {"id": 1, "name": "cat", "supercategory": "animal"},
+        {"id": 2, "name": "dog", "supercategory": "animal"}
+    ]
+}"#;
+
+fn run_cocodiff(temp_dir: &TempDir, old_json: &str, new_json: &str, args: &[&str]) -> Output {
+    let old_path = temp_dir.path().join("old.json");
+    let new_path = temp_dir.path().join("new.json");
+    fs::write(&old_path, old_json).unwrap();
+    fs::write(&new_path, new_json).unwrap();
+    Command::new(get_binary_path("cocodiff"))
+        .arg(old_path)
+        .arg(new_path)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cocodiff_identical() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocodiff(&temp_dir, OLD_JSON, OLD_JSON, &[]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Images: 0 added, 0 removed, 0 changed"));
+}
+
+#[test]
+fn test_cocodiff_text() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocodiff(&temp_dir, OLD_JSON, NEW_JSON, &[]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "+ image img3.png",
+            "- image img2.png",
+            "+ annotations 4 on image img1.png",
+            "+ category 2 \"dog\"",
+            "Images: 1 added, 1 removed, 0 changed. Annotations: 1 added, 0 removed. \
+             Categories: 1 added, 0 removed, 0 changed",
+        ]
+    );
+}
+
+#[test]
+fn test_cocodiff_json_by_id() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocodiff(&temp_dir, OLD_JSON, NEW_JSON, &["--by-id", "-f", "json"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["added_images"], serde_json::json!([3]));
+    assert_eq!(report["removed_images"], serde_json::json!([2]));
+    assert!(report["changed_images"].as_array().unwrap().is_empty());
+    assert_eq!(report["annotations"][0]["image"], 1);
+    assert_eq!(report["annotations"][0]["added"], serde_json::json!([4]));
+    assert_eq!(report["added_categories"][0]["name"], "dog");
+}
+
+#[test]
+fn test_cocodiff_unreadable_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocodiff(&temp_dir, OLD_JSON, "not json", &[]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
@@ -0,0 +1,146 @@
+// Integration tests for cocoeval binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// Two boxes of "cat" found with one false positive in between, and a "dog" box found at
+/// IoU 0.72
+fn write_fixture(temp_dir: &TempDir) -> (PathBuf, PathBuf) {
+    let gt_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "img1.png"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "img2.png"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [50.0, 50.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 3, "image_id": 2, "category_id": 2, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0}
+        ],
+        "categories": [
+            {"id": 1, "name": "cat", "supercategory": "animal"},
+            {"id": 2, "name": "dog, small", "supercategory": "animal"}
+        ]
+    }"#;
+    let predictions_json = r#"[
+        {"image_id": 1, "category_id": 1, "bbox": [0.0, 0.0, 10.0, 10.0], "score": 0.9},
+        {"image_id": 1, "category_id": 1, "bbox": [30.0, 30.0, 10.0, 10.0], "score": 0.8},
+        {"image_id": 1, "category_id": 1, "bbox": [50.0, 50.0, 10.0, 10.0], "score": 0.7},
+        {"image_id": 2, "category_id": 2, "bbox": [0.0, 0.0, 10.0, 7.2], "score": 0.9}
+    ]"#;
+    let gt_path = temp_dir.path().join("gt.json");
+    let predictions_path = temp_dir.path().join("predictions.json");
+    fs::write(&gt_path, gt_json).unwrap();
+    fs::write(&predictions_path, predictions_json).unwrap();
+    (gt_path, predictions_path)
+}
+
+fn run_cocoeval(temp_dir: &TempDir, extra_args: &[&str]) -> Output {
+    let (gt_path, predictions_path) = write_fixture(temp_dir);
+    Command::new(get_binary_path("cocoeval"))
+        .arg(gt_path)
+        .arg(predictions_path)
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+// cat AP is (51 + 50 * 2/3) / 101 at every threshold, dog AP is 1 up to IoU 0.7 and 0 above
+const CAT_AP: f64 = (51.0 + 50.0 * 2.0 / 3.0) / 101.0;
+
+#[test]
+fn test_cocoeval_table() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoeval(&temp_dir, &[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        " Average Precision  (AP) @[ IoU=0.50:0.95 | area=   all | maxDets=100 ] = 0.667"
+    );
+    assert_eq!(
+        lines[1],
+        " Average Precision  (AP) @[ IoU=0.50      | area=   all | maxDets=100 ] = 0.917"
+    );
+    assert!(stdout.contains("Per Category:"));
+    assert!(stdout.contains("cat"));
+    assert!(stdout.contains("dog, small"));
+}
+
+#[test]
+fn test_cocoeval_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoeval(&temp_dir, &["--json"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["iou_type"], "bbox");
+    let stats = report["stats"].as_array().unwrap();
+    assert_eq!(stats.len(), 12);
+    assert_eq!(stats[0]["name"], "AP");
+    let ap = stats[0]["value"].as_f64().unwrap();
+    assert!((ap - (CAT_AP + 0.5) / 2.0).abs() < 1e-6);
+    assert_eq!(stats[4]["name"], "APm");
+    assert_eq!(stats[4]["value"], -1.0);
+
+    let per_category = report["per_category"].as_array().unwrap();
+    assert_eq!(per_category.len(), 2);
+    assert_eq!(per_category[1]["category_id"], 2);
+    assert_eq!(per_category[1]["ap50"], 1.0);
+    assert_eq!(per_category[1]["ap75"], 0.0);
+}
+
+#[test]
+fn test_cocoeval_per_category_csv() {
+    let temp_dir = TempDir::new().unwrap();
+    let csv_path = temp_dir.path().join("per_category.csv");
+    let output = run_cocoeval(
+        &temp_dir,
+        &["--per-category-csv", csv_path.to_str().unwrap()],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let csv = fs::read_to_string(csv_path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "category_id,name,ap,ap50,ap75,ar");
+    assert_eq!(
+        lines[1],
+        format!("1,cat,{:.6},{:.6},{:.6},1.000000", CAT_AP, CAT_AP, CAT_AP)
+    );
+    assert_eq!(
+        lines[2],
+        "2,\"dog, small\",0.500000,1.000000,0.000000,0.500000"
+    );
+}
+
+#[test]
+fn test_cocoeval_unknown_image() {
+    let temp_dir = TempDir::new().unwrap();
+    let (gt_path, _) = write_fixture(&temp_dir);
+    let predictions_path = temp_dir.path().join("unknown.json");
+    fs::write(
+        &predictions_path,
+        r#"[{"image_id": 3, "category_id": 1, "bbox": [0, 0, 1, 1], "score": 0.5}]"#,
+    )
+    .unwrap();
+    let output = Command::new(get_binary_path("cocoeval"))
+        .arg(gt_path)
+        .arg(predictions_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("image 3"));
+}
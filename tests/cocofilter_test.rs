@@ -0,0 +1,169 @@
+// Integration tests for cocofilter binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+fn create_dummy_image(path: &PathBuf, width: u32, height: u32) {
+    use image::{ImageBuffer, Rgb};
+    let img = ImageBuffer::from_fn(width, height, |_x, _y| Rgb([255u8, 0u8, 0u8]));
+    img.save(path).unwrap();
+}
+
+fn create_test_coco(temp_dir: &TempDir) -> PathBuf {
+    create_dummy_image(&temp_dir.path().join("img1.jpg"), 100, 100);
+    create_dummy_image(&temp_dir.path().join("img2.jpg"), 100, 100);
+
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "img2.jpg"}
+        ],
+        "annotations": [
+            {
+                "id": 1,
+                "image_id": 1,
+                "category_id": 1,
+                "segmentation": [],
+                "area": 100.0,
+                "bbox": [10.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            },
+            {
+                "id": 2,
+                "image_id": 1,
+                "category_id": 2,
+                "segmentation": [],
+                "area": 100.0,
+                "bbox": [30.0, 30.0, 10.0, 10.0],
+                "iscrowd": 0
+            },
+            {
+                "id": 3,
+                "image_id": 2,
+                "category_id": 2,
+                "segmentation": [],
+                "area": 100.0,
+                "bbox": [50.0, 50.0, 10.0, 10.0],
+                "iscrowd": 0
+            }
+        ],
+        "categories": [
+            {"id": 1, "name": "person", "supercategory": "human"},
+            {"id": 2, "name": "car", "supercategory": "vehicle"}
+        ]
+    }"#;
+
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn read_output(path: &PathBuf) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_cocofilter_single_category() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco(&temp_dir);
+    let output_path = temp_dir.path().join("subset.json");
+
+    let output = Command::new(get_binary_path("cocofilter"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--categories")
+        .arg("person")
+        .output()
+        .expect("Failed to execute cocofilter");
+
+    assert!(output.status.success(), "cocofilter failed: {:?}", output);
+
+    let coco = read_output(&output_path);
+    let annotations = coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0]["id"], 1);
+
+    let categories = coco["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0]["name"], "person");
+
+    // without --drop-empty the now unannotated image is kept
+    assert_eq!(coco["images"].as_array().unwrap().len(), 2);
+    assert_eq!(coco["images"][0]["file_name"], "img1.jpg");
+}
+
+#[test]
+fn test_cocofilter_drop_empty() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco(&temp_dir);
+    let output_path = temp_dir.path().join("subset.json");
+
+    let output = Command::new(get_binary_path("cocofilter"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--categories")
+        .arg("person")
+        .arg("--drop-empty")
+        .output()
+        .expect("Failed to execute cocofilter");
+
+    assert!(output.status.success(), "cocofilter failed: {:?}", output);
+
+    let coco = read_output(&output_path);
+    let images = coco["images"].as_array().unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0]["id"], 1);
+}
+
+#[test]
+fn test_cocofilter_by_id() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco(&temp_dir);
+    let output_path = temp_dir.path().join("subset.json");
+
+    let output = Command::new(get_binary_path("cocofilter"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--categories")
+        .arg("1,2")
+        .arg("--by-id")
+        .output()
+        .expect("Failed to execute cocofilter");
+
+    assert!(output.status.success(), "cocofilter failed: {:?}", output);
+
+    let coco = read_output(&output_path);
+    assert_eq!(coco["annotations"].as_array().unwrap().len(), 3);
+    assert_eq!(coco["categories"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_cocofilter_unknown_category() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocofilter"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(temp_dir.path().join("subset.json"))
+        .arg("--categories")
+        .arg("bicycle")
+        .output()
+        .expect("Failed to execute cocofilter");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No category named \"bicycle\""));
+}
@@ -0,0 +1,101 @@
+// Integration tests for cocoprune binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// A common category with three annotations and a rare one with a single annotation on its
+/// own image
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "img2.jpg"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "img3.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [20.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 3, "image_id": 3, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [40.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 4, "image_id": 2, "category_id": 2, "segmentation": [],
+             "area": 100.0, "bbox": [60.0, 0.0, 10.0, 10.0], "iscrowd": 0}
+        ],
+        "categories": [
+            {"id": 1, "name": "common", "supercategory": "thing"},
+            {"id": 2, "name": "rare", "supercategory": "thing"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn run_cocoprune(temp_dir: &TempDir, extra_args: &[&str]) -> Output {
+    Command::new(get_binary_path("cocoprune"))
+        .arg(write_coco(temp_dir))
+        .arg("-o")
+        .arg(temp_dir.path().join("pruned.json"))
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+fn read_output(temp_dir: &TempDir) -> serde_json::Value {
+    let output_json = fs::read_to_string(temp_dir.path().join("pruned.json")).unwrap();
+    serde_json::from_str(&output_json).unwrap()
+}
+
+#[test]
+fn test_cocoprune_min_annotations() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoprune(&temp_dir, &["--min-annotations", "2"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Removed 1 of 2 categories"), "{}", stdout);
+
+    let output_coco = read_output(&temp_dir);
+    assert_eq!(output_coco["categories"].as_array().unwrap().len(), 1);
+    assert_eq!(output_coco["annotations"].as_array().unwrap().len(), 3);
+    // the rare category's image stays without --drop-empty-images
+    assert_eq!(output_coco["images"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_cocoprune_drop_empty_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoprune(
+        &temp_dir,
+        &["--min-annotations", "2", "--drop-empty-images"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Removed 1 image(s)"), "{}", stdout);
+
+    let output_coco = read_output(&temp_dir);
+    let image_ids: Vec<i64> = output_coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(image_ids, vec![1, 3]);
+}
+
+#[test]
+fn test_cocoprune_requires_an_operation() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoprune(&temp_dir, &[]);
+    assert!(!output.status.success());
+    assert!(!temp_dir.path().join("pruned.json").exists());
+}
@@ -0,0 +1,137 @@
+// Integration tests for cocoremap binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// Three categories with two, one and one annotations
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 2, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 2, "segmentation": [],
+             "area": 100.0, "bbox": [20.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 3, "image_id": 1, "category_id": 5, "segmentation": [],
+             "area": 100.0, "bbox": [40.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 4, "image_id": 1, "category_id": 9, "segmentation": [],
+             "area": 100.0, "bbox": [60.0, 0.0, 10.0, 10.0], "iscrowd": 0}
+        ],
+        "categories": [
+            {"id": 2, "name": "auto", "supercategory": "vehicle"},
+            {"id": 5, "name": "truck", "supercategory": "vehicle"},
+            {"id": 9, "name": "debug_class", "supercategory": "none"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn run_cocoremap(temp_dir: &TempDir, mapping: &str, extra_args: &[&str]) -> Output {
+    let mapping_path = temp_dir.path().join("mapping.json");
+    fs::write(&mapping_path, mapping).unwrap();
+    Command::new(get_binary_path("cocoremap"))
+        .arg(write_coco(temp_dir))
+        .arg("-o")
+        .arg(temp_dir.path().join("remapped.json"))
+        .arg("--map")
+        .arg(&mapping_path)
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+fn read_output(temp_dir: &TempDir) -> serde_json::Value {
+    let output_json = fs::read_to_string(temp_dir.path().join("remapped.json")).unwrap();
+    serde_json::from_str(&output_json).unwrap()
+}
+
+#[test]
+fn test_cocoremap_all_rules() {
+    let temp_dir = TempDir::new().unwrap();
+    let mapping = r#"{
+        "drop": ["debug_class"],
+        "merge": {"auto": ["truck"]},
+        "rename": {"auto": "car"}
+    }"#;
+    let output = run_cocoremap(&temp_dir, mapping, &["--contiguous"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("drop debug_class: 1 removed"), "{}", stdout);
+    assert!(stdout.contains("merge into auto: 1 moved"), "{}", stdout);
+    assert!(stdout.contains("rename auto -> car: 3 moved"), "{}", stdout);
+    let after = stdout.split("After:").nth(1).unwrap();
+    assert!(after.contains("car"));
+    assert!(!after.contains("truck"));
+
+    let output_coco = read_output(&temp_dir);
+    let categories = output_coco["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0]["id"], 1);
+    assert_eq!(categories[0]["name"], "car");
+    let annotations = output_coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 3);
+    assert!(annotations.iter().all(|ann| ann["category_id"] == 1));
+}
+
+#[test]
+fn test_cocoremap_contiguous_keeps_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocoremap(&temp_dir, "{}", &["--contiguous"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output_coco = read_output(&temp_dir);
+    let ids: Vec<(i64, String)> = output_coco["categories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|cat| {
+            (
+                cat["id"].as_i64().unwrap(),
+                cat["name"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            (1, "auto".to_string()),
+            (2, "truck".to_string()),
+            (3, "debug_class".to_string())
+        ]
+    );
+    let category_ids: Vec<i64> = output_coco["annotations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|ann| ann["category_id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(category_ids, vec![1, 1, 2, 3]);
+}
+
+#[test]
+fn test_cocoremap_unknown_category() {
+    let temp_dir = TempDir::new().unwrap();
+    for mapping in [
+        r#"{"drop": ["debug_class", "bicycle"]}"#,
+        r#"{"merge": {"auto": ["lorry"]}}"#,
+        r#"{"rename": {"cart": "car"}}"#,
+    ] {
+        let output = run_cocoremap(&temp_dir, mapping, &[]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No category named"), "{}", stderr);
+        assert!(!temp_dir.path().join("remapped.json").exists());
+    }
+}
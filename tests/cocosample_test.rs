@@ -0,0 +1,132 @@
+// Integration tests for cocosample binary
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+/// 20 images with a "common" annotation each, the first 4 also with a "rare" one
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let mut annotations = Vec::new();
+    for image_id in 1..=20 {
+        annotations.push(serde_json::json!({
+            "id": image_id * 10, "image_id": image_id, "category_id": 1, "segmentation": [],
+            "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0
+        }));
+        if image_id <= 4 {
+            annotations.push(serde_json::json!({
+                "id": image_id * 10 + 1, "image_id": image_id, "category_id": 2,
+                "segmentation": [], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0
+            }));
+        }
+    }
+    let images: Vec<serde_json::Value> = (1..=20)
+        .map(|id| serde_json::json!({"id": id, "width": 10, "height": 10, "file_name": "a.jpg"}))
+        .collect();
+    let coco_json = serde_json::json!({
+        "images": images,
+        "annotations": annotations,
+        "categories": [
+            {"id": 1, "name": "common", "supercategory": "thing"},
+            {"id": 2, "name": "rare", "supercategory": "thing"}
+        ]
+    });
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json.to_string()).unwrap();
+    coco_path
+}
+
+fn run_cocosample(temp_dir: &TempDir, extra_args: &[&str]) -> Output {
+    Command::new(get_binary_path("cocosample"))
+        .arg(write_coco(temp_dir))
+        .arg("-o")
+        .arg(temp_dir.path().join("sample.json"))
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+fn read_output(temp_dir: &TempDir) -> serde_json::Value {
+    let output_json = fs::read_to_string(temp_dir.path().join("sample.json")).unwrap();
+    serde_json::from_str(&output_json).unwrap()
+}
+
+/// Fails if an annotation refers to an image that isn't in the sample
+fn sampled_image_ids(sample: &serde_json::Value) -> Vec<i64> {
+    let image_ids: Vec<i64> = sample["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["id"].as_i64().unwrap())
+        .collect();
+    let image_set: HashSet<i64> = image_ids.iter().copied().collect();
+    for annotation in sample["annotations"].as_array().unwrap() {
+        assert!(image_set.contains(&annotation["image_id"].as_i64().unwrap()));
+    }
+    image_ids
+}
+
+#[test]
+fn test_cocosample_per_category() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocosample(&temp_dir, &["--per-category", "6", "--seed", "42"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip_while(|line| *line != "Per Category:")
+        .skip(2)
+        .take(2)
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(
+        rows,
+        vec![vec!["1", "common", "6", "20"], vec!["2", "rare", "4", "4"]]
+    );
+
+    let sample = read_output(&temp_dir);
+    let image_ids = sampled_image_ids(&sample);
+    assert_eq!(image_ids.len(), 6);
+    assert!((1..=4).all(|id| image_ids.contains(&id)));
+    assert_eq!(sample["categories"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_cocosample_images_is_reproducible() {
+    let temp_dir = TempDir::new().unwrap();
+    let output = run_cocosample(&temp_dir, &["--images", "5", "--seed", "7"]);
+    assert!(output.status.success(), "{:?}", output);
+    let first = sampled_image_ids(&read_output(&temp_dir));
+    assert_eq!(first.len(), 5);
+
+    run_cocosample(&temp_dir, &["--images", "5", "--seed", "7"]);
+    assert_eq!(sampled_image_ids(&read_output(&temp_dir)), first);
+
+    // an unseeded run prints the seed it drew
+    let output = run_cocosample(&temp_dir, &["--images", "5"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let seed = stderr
+        .trim()
+        .strip_prefix("Sampling with random seed ")
+        .expect("seed is printed");
+    let random = sampled_image_ids(&read_output(&temp_dir));
+    run_cocosample(&temp_dir, &["--images", "5", "--seed", seed]);
+    assert_eq!(sampled_image_ids(&read_output(&temp_dir)), random);
+}
+
+#[test]
+fn test_cocosample_requires_a_mode() {
+    let temp_dir = TempDir::new().unwrap();
+    assert!(!run_cocosample(&temp_dir, &[]).status.success());
+    let both = run_cocosample(&temp_dir, &["--images", "5", "--per-category", "5"]);
+    assert!(!both.status.success());
+}
@@ -0,0 +1,93 @@
+// Integration tests for cocostats binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+fn write_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 640, "height": 480, "file_name": "img1.jpg"},
+            {"id": 2, "width": 640, "height": 480, "file_name": "img2.jpg"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "img3.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 200.0, "bbox": [0.0, 0.0, 20.0, 10.0], "iscrowd": 0},
+            {"id": 3, "image_id": 2, "category_id": 2, "segmentation": [],
+             "area": 400.0, "bbox": [0.0, 0.0, 10.0, 40.0], "iscrowd": 0}
+        ],
+        "categories": [
+            {"id": 1, "name": "cat", "supercategory": "animal"},
+            {"id": 2, "name": "dog", "supercategory": "animal"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cocostats_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocostats"))
+        .arg(&coco_path)
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let histogram = stats["ann_per_image"].as_array().unwrap();
+    let total: u64 = histogram
+        .iter()
+        .map(|bin| bin["images"].as_u64().unwrap())
+        .sum();
+    assert_eq!(total, 3);
+    let images_with = |annotations: &str| {
+        histogram
+            .iter()
+            .find(|bin| bin["annotations"] == annotations)
+            .unwrap()["images"]
+            .as_u64()
+            .unwrap()
+    };
+    assert_eq!(images_with("0"), 1);
+    assert_eq!(images_with("1"), 1);
+    assert_eq!(images_with("2-5"), 1);
+
+    assert_eq!(stats["bbox_area"]["count"], 3);
+    assert_eq!(stats["bbox_area"]["quartiles"]["median"], 200.0);
+    assert_eq!(stats["bbox_area"]["quartiles"]["q1"], 150.0);
+    assert_eq!(stats["image_sizes"][0]["width"], 640);
+    assert_eq!(stats["image_sizes"][0]["images"], 2);
+    assert_eq!(stats["per_category"][0]["annotations"], 2);
+    assert_eq!(stats["per_category"][1]["annotations"], 1);
+}
+
+#[test]
+fn test_cocostats_text() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocostats"))
+        .arg(&coco_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Images by Annotation Count:"));
+    assert!(stdout.contains("640x480: 2"));
+}
@@ -0,0 +1,184 @@
+// Integration tests for cocovalidate binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+fn write_clean_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"}],
+        "annotations": [
+            {
+                "id": 1,
+                "image_id": 1,
+                "category_id": 1,
+                "segmentation": [[10.0, 10.0, 20.0, 10.0, 20.0, 20.0]],
+                "area": 50.0,
+                "bbox": [10.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            }
+        ],
+        "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+    }"#;
+    let coco_path = temp_dir.path().join("clean.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn write_broken_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"}],
+        "annotations": [
+            {
+                "id": 1,
+                "image_id": 2,
+                "category_id": 1,
+                "segmentation": [],
+                "area": 50.0,
+                "bbox": [10.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            },
+            {
+                "id": 1,
+                "image_id": 1,
+                "category_id": 5,
+                "segmentation": [],
+                "area": 50.0,
+                "bbox": [10.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            }
+        ],
+        "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+    }"#;
+    let coco_path = temp_dir.path().join("broken.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+fn write_out_of_bounds_coco(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "img1.jpg"}],
+        "annotations": [
+            {
+                "id": 1,
+                "image_id": 1,
+                "category_id": 1,
+                "segmentation": [],
+                "area": 50.0,
+                "bbox": [95.0, 10.0, 10.0, 10.0],
+                "iscrowd": 0
+            }
+        ],
+        "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+    }"#;
+    let coco_path = temp_dir.path().join("bounds.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cocovalidate_clean_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_clean_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&coco_path)
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert!(output.status.success(), "cocovalidate failed: {:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("0 error(s), 0 warning(s)"));
+}
+
+#[test]
+fn test_cocovalidate_broken_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_broken_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&coco_path)
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("error: duplicate annotation id 1"));
+    assert!(stdout.contains("error: annotation 1 references missing image id 2"));
+    assert!(stdout.contains("error: annotation 1 references missing category id 5"));
+    assert!(stdout.contains("3 error(s)"));
+}
+
+#[test]
+fn test_cocovalidate_multiple_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let clean_path = write_clean_coco(&temp_dir);
+    let broken_path = write_broken_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&clean_path)
+        .arg(&broken_path)
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 file(s) checked"));
+    assert!(stdout.contains("broken.json: error"));
+    assert!(!stdout.contains("clean.json: error"));
+}
+
+#[test]
+fn test_cocovalidate_json_format() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_broken_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&coco_path)
+        .arg("--format")
+        .arg("json")
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert!(!output.status.success());
+    let issues: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let issues = issues.as_array().unwrap();
+    assert_eq!(issues.len(), 3);
+    assert!(issues.iter().all(|issue| issue["severity"] == "error"));
+    assert!(
+        issues
+            .iter()
+            .any(|issue| issue["kind"] == "dangling_image_id" && issue["image_id"] == 2)
+    );
+}
+
+#[test]
+fn test_cocovalidate_warnings_as_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = write_out_of_bounds_coco(&temp_dir);
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&coco_path)
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("warning: annotation 1 bbox"));
+
+    let output = Command::new(get_binary_path("cocovalidate"))
+        .arg(&coco_path)
+        .arg("--warnings-as-errors")
+        .output()
+        .expect("Failed to execute cocovalidate");
+
+    assert!(!output.status.success());
+}
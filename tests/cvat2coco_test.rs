@@ -0,0 +1,123 @@
+// Integration tests for cvat2coco binary
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+const CVAT_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<annotations>
+  <version>1.1</version>
+  <meta>
+    <task>
+      <name>street</name>
+      <labels>
+        <label><name>car</name><attributes></attributes></label>
+        <label><name>person</name><attributes></attributes></label>
+        <label><name>face</name><attributes></attributes></label>
+      </labels>
+    </task>
+  </meta>
+  <image id="0" name="frames/0.jpg" width="640" height="480">
+    <box label="car" occluded="1" source="manual" xtl="10.00" ytl="20.00" xbr="110.00" ybr="70.00" z_order="0">
+      <attribute name="color">red</attribute>
+      <attribute name="parked">true</attribute>
+    </box>
+    <polygon label="person" occluded="0" source="manual" points="0.00,0.00;10.00,0.00;10.00,10.00" z_order="0">
+    </polygon>
+    <points label="face" occluded="0" source="manual" points="5.00,6.00;15.00,26.00" z_order="0">
+    </points>
+    <polyline label="car" occluded="0" source="manual" points="1.00,1.00;2.00,2.00" z_order="0">
+    </polyline>
+  </image>
+  <image id="1" name="frames/1.jpg" width="640" height="480">
+  </image>
+  <track id="3" label="car" source="manual">
+    <box frame="0" outside="0" occluded="0" keyframe="1" xtl="1.00" ytl="1.00" xbr="3.00" ybr="5.00" z_order="0">
+    </box>
+    <box frame="1" outside="1" occluded="0" keyframe="1" xtl="1.00" ytl="1.00" xbr="3.00" ybr="5.00" z_order="0">
+    </box>
+  </track>
+</annotations>
+"#;
+
+#[test]
+fn test_cvat2coco() {
+    let temp_dir = TempDir::new().unwrap();
+    let xml_path = temp_dir.path().join("annotations.xml");
+    fs::write(&xml_path, CVAT_XML).unwrap();
+    let output_path = temp_dir.path().join("coco.json");
+
+    let output = Command::new(get_binary_path("cvat2coco"))
+        .arg(&xml_path)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .expect("Failed to execute cvat2coco");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("skipped 1 <polyline>"));
+
+    let coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(coco["images"].as_array().unwrap().len(), 2);
+    assert_eq!(coco["images"][0]["file_name"], "frames/0.jpg");
+
+    let categories = coco["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 3);
+    assert_eq!(categories[0]["name"], "car");
+    assert_eq!(categories[2]["keypoints"], serde_json::json!(["1", "2"]));
+
+    let annotations = coco["annotations"].as_array().unwrap();
+    // the box shape, polygon, points and the track box inside the frame
+    assert_eq!(annotations.len(), 4);
+
+    let car = &annotations[0];
+    assert_eq!(car["category_id"], 1);
+    assert_eq!(car["bbox"], serde_json::json!([10.0, 20.0, 100.0, 50.0]));
+    assert_eq!(car["area"], 5000.0);
+    assert_eq!(
+        car["attributes"],
+        serde_json::json!({"color": "red", "parked": true, "occluded": true})
+    );
+
+    let person = &annotations[1];
+    assert_eq!(person["category_id"], 2);
+    assert_eq!(person["area"], 50.0);
+    assert!(person.get("attributes").is_none());
+
+    let face = &annotations[2];
+    assert_eq!(face["category_id"], 3);
+    assert_eq!(
+        face["keypoints"],
+        serde_json::json!([5.0, 6.0, 2.0, 15.0, 26.0, 2.0])
+    );
+    assert_eq!(face["num_keypoints"], 2);
+    assert_eq!(face["bbox"], serde_json::json!([5.0, 6.0, 10.0, 20.0]));
+
+    let track_box = &annotations[3];
+    assert_eq!(track_box["image_id"], 0);
+    assert_eq!(track_box["attributes"], serde_json::json!({"track_id": 3}));
+}
+
+#[test]
+fn test_cvat2coco_rejects_unknown_frame() {
+    let temp_dir = TempDir::new().unwrap();
+    let xml_path = temp_dir.path().join("annotations.xml");
+    fs::write(&xml_path, CVAT_XML.replace("frame=\"0\"", "frame=\"7\"")).unwrap();
+
+    let output = Command::new(get_binary_path("cvat2coco"))
+        .arg(&xml_path)
+        .arg("-o")
+        .arg(temp_dir.path().join("coco.json"))
+        .output()
+        .expect("Failed to execute cvat2coco");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("frame 7"));
+}
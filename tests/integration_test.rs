@@ -167,6 +167,435 @@ fn test_cococount_mixed_annotations() {
     assert!(stdout.contains("Image Captioning Annotations: 1"));
 }
 
+fn create_category_count_coco_file(temp_dir: &TempDir) -> PathBuf {
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"id": 3, "image_id": 2, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"id": 4, "image_id": 2, "category_id": 9, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"image_id": 2, "file_name": "b.png", "segments_info": [
+                {"id": 1, "category_id": 2, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+                {"id": 2, "category_id": 2, "area": 1, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0}
+            ]},
+            {"id": 6, "image_id": 1, "caption": "no category"}
+        ],
+        "categories": [
+            {"id": 1, "name": "zebra", "supercategory": "animal"},
+            {"id": 2, "name": "road", "supercategory": "stuff", "isthing": 0, "color": [0, 0, 0]},
+            {"id": 3, "name": "unused", "supercategory": "none"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("categories.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    coco_path
+}
+
+/// Rows of the per category table as (id, name, annotations, images)
+fn cococount_category_rows(coco_path: &PathBuf, extra_args: &[&str]) -> Vec<Vec<String>> {
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(coco_path)
+        .args(extra_args)
+        .output()
+        .expect("Failed to execute cococount");
+    assert!(output.status.success(), "cococount failed: {:?}", output);
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip_while(|line| *line != "Per Category:")
+        .skip(2)
+        .map(|line| line.split_whitespace().map(str::to_string).collect())
+        .collect()
+}
+
+#[test]
+fn test_cococount_per_category() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_category_count_coco_file(&temp_dir);
+
+    let rows = cococount_category_rows(&coco_path, &[]);
+    assert_eq!(
+        rows,
+        vec![
+            vec!["1", "zebra", "3", "2"],
+            vec!["2", "road", "2", "1"],
+            vec!["-", "unknown", "1", "1"],
+        ]
+    );
+
+    let rows = cococount_category_rows(&coco_path, &["--sort", "name", "--top", "2"]);
+    assert_eq!(
+        rows,
+        vec![
+            vec!["2", "road", "2", "1"],
+            vec!["-", "unknown", "1", "1"],
+            vec!["...", "1", "more"],
+        ]
+    );
+
+    let rows = cococount_category_rows(&coco_path, &["--sort", "id"]);
+    let ids: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+    assert_eq!(ids, vec!["1", "2", "-"]);
+}
+
+#[test]
+fn test_cococount_json_and_csv() {
+    let temp_dir = TempDir::new().unwrap();
+    let category_path = create_category_count_coco_file(&temp_dir);
+    let basic_path = create_test_coco_file(&temp_dir, "test.json");
+
+    let run = |format: &str| {
+        let output = Command::new(get_binary_path("cococount"))
+            .arg(&category_path)
+            .arg(&basic_path)
+            .arg("--format")
+            .arg(format)
+            .output()
+            .expect("Failed to execute cococount");
+        assert!(output.status.success(), "cococount failed: {:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&run("json")).unwrap();
+    let files = json.as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0]["file"], "categories.json");
+    assert_eq!(files[0]["images"], 2);
+    assert_eq!(files[0]["annotations"]["total"], 6);
+    assert_eq!(files[0]["annotations"]["panoptic_segmentation"], 1);
+    assert_eq!(files[0]["categories"]["total"], 3);
+    assert_eq!(files[0]["categories"]["panoptic_segmentation"], 1);
+    assert_eq!(files[0]["per_category"][0]["name"], "zebra");
+    assert_eq!(files[0]["per_category"][0]["images"], 2);
+    assert_eq!(files[0]["per_category"][2]["id"], serde_json::Value::Null);
+    assert_eq!(files[1]["file"], "test.json");
+
+    let csv = run("csv");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "file,category_id,category_name,annotations,images"
+    );
+    assert_eq!(lines[1], "categories.json,1,zebra,3,2");
+    assert_eq!(lines[3], "categories.json,,unknown,1,1");
+    assert_eq!(lines[4], "categories.json,,total,6,2");
+    assert_eq!(lines.last().unwrap(), &"test.json,,total,2,2");
+
+    let text = run("text");
+    assert!(text.contains("Coco File: categories.json"));
+    assert!(text.contains("Coco File: test.json"));
+    assert!(text.contains("Total: 2 files\nImages: 4\nAnnotations: 8"));
+}
+
+/// A cut down LVIS file: images without file names, annotations without iscrowd and
+/// categories with frequencies and stale counts
+fn create_lvis_coco_file(temp_dir: &TempDir) -> PathBuf {
+    let annotation = |id: i64, image_id: i64, category_id: i32| {
+        serde_json::json!({
+            "id": id, "image_id": image_id, "category_id": category_id, "area": 4.0,
+            "segmentation": [[0.0, 0.0, 2.0, 0.0, 2.0, 2.0, 0.0, 2.0]], "bbox": [0.0, 0.0, 2.0, 2.0]
+        })
+    };
+    let image = |id: i64, neg_category_ids: &[i32]| {
+        serde_json::json!({
+            "id": id, "width": 640, "height": 480, "date_captured": "2013-11-14 11:18:45",
+            "coco_url": format!("http://images.cocodataset.org/train2017/{:012}.jpg", id),
+            "neg_category_ids": neg_category_ids, "not_exhaustive_category_ids": []
+        })
+    };
+    let coco_json = serde_json::json!({
+        "images": [image(1, &[3]), image(2, &[2]), image(3, &[])],
+        "annotations": [annotation(1, 1, 1), annotation(2, 1, 1), annotation(3, 1, 2)],
+        "categories": [
+            {"id": 1, "name": "aerosol_can", "synset": "aerosol.n.02", "frequency": "r",
+             "image_count": 7, "instance_count": 9},
+            {"id": 2, "name": "air_conditioner", "synset": "air_conditioner.n.01",
+             "frequency": "f", "image_count": 364, "instance_count": 1081},
+            {"id": 3, "name": "airplane", "synset": "airplane.n.01", "frequency": "f",
+             "image_count": 1018, "instance_count": 2890}
+        ]
+    });
+    let coco_path = temp_dir.path().join("lvis.json");
+    fs::write(&coco_path, coco_json.to_string()).unwrap();
+    coco_path
+}
+
+#[test]
+fn test_cococount_lvis_frequency_groups() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_lvis_coco_file(&temp_dir);
+
+    let run = |format: &str| {
+        let output = Command::new(get_binary_path("cococount"))
+            .arg(&coco_path)
+            .arg("--format")
+            .arg(format)
+            .output()
+            .expect("Failed to execute cococount");
+        assert!(output.status.success(), "cococount failed: {:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let text = run("text");
+    assert!(text.contains("LVIS Frequency Groups:"));
+    assert!(text.contains("  Rare: 1 categories, 2 annotations"));
+    assert!(text.contains("  Common: 0 categories, 0 annotations"));
+    assert!(text.contains("  Frequent: 2 categories, 1 annotations"));
+
+    let json: serde_json::Value = serde_json::from_str(&run("json")).unwrap();
+    assert_eq!(json[0]["frequency_groups"]["frequent"]["categories"], 2);
+
+    // plain COCO files have no frequency groups
+    let basic_path = create_test_coco_file(&temp_dir, "test.json");
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(&basic_path)
+        .output()
+        .expect("Failed to execute cococount");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("LVIS"));
+}
+
+#[test]
+fn test_cococount_size_stats() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 640, "height": 480, "file_name": "a.jpg"},
+            {"id": 2, "width": 100, "height": 300, "file_name": "b.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 100.0, "bbox": [0.0, 0.0, 10.0, 10.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 300.0, "bbox": [0.0, 0.0, 10.0, 30.0], "iscrowd": 0},
+            {"id": 3, "image_id": 2, "category_id": 1, "segmentation": [[]], "area": 5000.0, "bbox": [0.0, 0.0, 50.0, 100.0], "iscrowd": 0},
+            {"image_id": 2, "file_name": "b.png", "segments_info": [
+                {"id": 1, "category_id": 2, "area": 20000, "bbox": [0.0, 0.0, 100.0, 200.0], "iscrowd": 0}
+            ]}
+        ],
+        "categories": [
+            {"id": 1, "name": "person", "supercategory": "human"},
+            {"id": 2, "name": "road", "supercategory": "stuff", "isthing": 0, "color": [0, 0, 0]}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("sizes.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(&coco_path)
+        .arg("--size-stats")
+        .arg("--format")
+        .arg("json")
+        .output()
+        .expect("Failed to execute cococount");
+    assert!(output.status.success(), "cococount failed: {:?}", output);
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stats = &json[0]["size_stats"];
+    assert_eq!(stats["overall"]["small"]["count"], 2);
+    assert_eq!(stats["overall"]["small"]["mean_area"], 200.0);
+    assert_eq!(stats["overall"]["small"]["median_area"], 200.0);
+    assert_eq!(stats["overall"]["medium"]["count"], 1);
+    assert_eq!(stats["overall"]["large"]["count"], 1);
+    assert_eq!(stats["per_category"][0]["name"], "person");
+    assert_eq!(stats["per_category"][0]["large"]["count"], 0);
+    assert_eq!(
+        stats["per_category"][0]["large"]["mean_area"],
+        serde_json::Value::Null
+    );
+    assert_eq!(stats["per_category"][1]["large"]["median_area"], 20000.0);
+
+    let resolution = &stats["resolution"];
+    assert_eq!(resolution["min_width"], 100);
+    assert_eq!(resolution["max_height"], 480);
+    assert_eq!(resolution["mean_width"], 370.0);
+    let aspect_ratios: Vec<u64> = resolution["aspect_ratios"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bin| bin["images"].as_u64().unwrap())
+        .collect();
+    assert_eq!(aspect_ratios, vec![1, 0, 0, 1, 0]);
+}
+
+#[test]
+fn test_cococount_coverage() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "b.jpg"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "c.jpg"},
+            {"id": 4, "width": 100, "height": 100, "file_name": "d.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 2, "caption": "one"},
+            {"id": 2, "image_id": 3, "caption": "one of three"},
+            {"id": 3, "image_id": 3, "caption": "two of three"},
+            {"id": 4, "image_id": 3, "caption": "three of three"},
+            {"id": 5, "image_id": 42, "caption": "no such image"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("coverage.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let run = |format: &str| {
+        let output = Command::new(get_binary_path("cococount"))
+            .arg(&coco_path)
+            .arg("--coverage")
+            .arg("--format")
+            .arg(format)
+            .output()
+            .expect("Failed to execute cococount");
+        assert!(output.status.success(), "cococount failed: {:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let text = run("text");
+    assert!(text.contains("Unannotated Images: 2 (50.0%)"));
+    assert!(text.contains("Dangling Annotations: 1"));
+    assert!(text.contains("Annotations per Image: min 0, median 0.5, mean 1.0, max 3"));
+
+    let json: serde_json::Value = serde_json::from_str(&run("json")).unwrap();
+    let coverage = &json[0]["coverage"];
+    assert_eq!(coverage["unannotated_images"], 2);
+    assert_eq!(coverage["dangling_annotations"], 1);
+    let histogram: Vec<u64> = coverage["histogram"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bin| bin["images"].as_u64().unwrap())
+        .collect();
+    assert_eq!(histogram, vec![2, 1, 1, 0, 0]);
+}
+
+#[test]
+fn test_cococount_keypoints() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "a.jpg"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0,
+             "keypoints": [10.0, 10.0, 2.0, 0.0, 0.0, 0.0], "num_keypoints": 1},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 1,
+             "keypoints": [10.0, 10.0, 1.0, 20.0, 20.0, 2.0], "num_keypoints": 2},
+            {"id": 3, "image_id": 1, "category_id": 1, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0,
+             "keypoints": [10.0, 10.0, 2.0], "num_keypoints": 1},
+            {"id": 4, "image_id": 1, "category_id": 9, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0,
+             "keypoints": [10.0, 10.0, 2.0], "num_keypoints": 1},
+            {"id": 5, "image_id": 1, "category_id": 2, "segmentation": [[]], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 1}
+        ],
+        "categories": [
+            {"id": 1, "name": "person", "supercategory": "human", "keypoints": ["nose", "left_eye"], "skeleton": [[1, 2]]},
+            {"id": 2, "name": "crowd", "supercategory": "human"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("keypoints.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let run = |format: &str| {
+        let output = Command::new(get_binary_path("cococount"))
+            .arg(&coco_path)
+            .arg("--keypoints")
+            .arg("--format")
+            .arg(format)
+            .output()
+            .expect("Failed to execute cococount");
+        assert!(output.status.success(), "cococount failed: {:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&run("json")).unwrap();
+    let stats = &json[0]["keypoints"];
+    assert_eq!(
+        stats["per_category"][0]["keypoints"],
+        serde_json::json!([
+            {"name": "nose", "visible": 1, "occluded": 1, "missing": 0},
+            {"name": "left_eye", "visible": 1, "occluded": 0, "missing": 1}
+        ])
+    );
+    assert_eq!(stats["length_mismatches"], serde_json::json!([3]));
+    assert_eq!(stats["unknown_category"], 1);
+    assert_eq!(stats["crowd"]["keypoint_detection"]["crowd"], 1);
+    assert_eq!(stats["crowd"]["keypoint_detection"]["total"], 4);
+    assert_eq!(stats["crowd"]["object_detection"]["crowd"], 1);
+
+    let text = run("text");
+    assert!(text.contains("person (id 1):"));
+    assert!(text.contains("Length Mismatches: 1"));
+    assert!(text.contains("Keypoint Detection: 1 of 4 (25.0%)"));
+}
+
+#[test]
+fn test_cococount_check_clean() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_file(&temp_dir, "test.json");
+
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(&coco_path)
+        .arg("--check")
+        .output()
+        .expect("Failed to execute cococount");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Issues: 0"));
+}
+
+#[test]
+fn test_cococount_check_dangling_annotation() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "test.jpg"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "caption": "A test image"},
+            {"id": 2, "image_id": 42, "caption": "No such image"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dangling.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(&coco_path)
+        .arg("--check")
+        .output()
+        .expect("Failed to execute cococount");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Issues: 1"));
+    assert!(stdout.contains("Dangling Annotations: 1"));
+    assert!(stdout.contains("Duplicate Image IDs: 0"));
+}
+
+#[test]
+fn test_cococount_check_fails_on_out_of_bounds_bbox() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "test.jpg"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 400.0,
+                "bbox": [90.0, 90.0, 20.0, 20.0], "iscrowd": 0}
+        ],
+        "categories": [{"id": 1, "name": "person", "supercategory": "human"}]
+    }"#;
+    let coco_path = temp_dir.path().join("out_of_bounds.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let output = Command::new(get_binary_path("cococount"))
+        .arg(&coco_path)
+        .arg("--check")
+        .output()
+        .expect("Failed to execute cococount");
+
+    // only a warning for cocovalidate, but --check fails on every issue
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Issues: 1"));
+    assert!(stdout.contains("Out of Bounds BBoxes: 1"));
+}
+
 // ========== COCOSPLIT TESTS ==========
 
 #[test]
@@ -193,6 +622,46 @@ fn test_cocosplit_with_count() {
     assert_eq!(split_coco["images"].as_array().unwrap().len(), 1);
 }
 
+#[test]
+fn test_cocosplit_count_exceeds_pool() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_file(&temp_dir, "test.json");
+    let output_path = temp_dir.path().join("split.json");
+
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("-c")
+        .arg("5")
+        .arg("--quiet")
+        .output()
+        .expect("Failed to execute cocosplit");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("requested 5 images but only 2 are available after filtering"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    let split_json = fs::read_to_string(&output_path).unwrap();
+    let split_coco: serde_json::Value = serde_json::from_str(&split_json).unwrap();
+    assert_eq!(split_coco["images"].as_array().unwrap().len(), 2);
+
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("-c")
+        .arg("5")
+        .arg("--strict")
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only 2 are available"));
+}
+
 #[test]
 fn test_cocosplit_all_images() {
     let temp_dir = TempDir::new().unwrap();
@@ -277,12 +746,12 @@ fn test_cocosplit_preserves_annotations() {
 #[test]
 fn test_cocosplit_annotated_only() {
     let temp_dir = TempDir::new().unwrap();
-    
+
     // Create actual image files
     create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
     create_dummy_image(&temp_dir.path().join("test2.jpg"), 100, 100);
     create_dummy_image(&temp_dir.path().join("test3.jpg"), 100, 100);
-    
+
     let coco_json = r#"{
         "images": [
             {"id": 1, "width": 100, "height": 100, "file_name": "test1.jpg"},
@@ -328,6 +797,471 @@ fn test_cocosplit_annotated_only() {
     );
 }
 
+fn create_pool_coco_file(temp_dir: &TempDir, image_count: usize) -> PathBuf {
+    create_dummy_image(&temp_dir.path().join("pool.jpg"), 10, 10);
+    let images: Vec<serde_json::Value> = (1..=image_count)
+        .map(|id| serde_json::json!({"id": id, "width": 10, "height": 10, "file_name": "pool.jpg"}))
+        .collect();
+    let coco_path = temp_dir.path().join("pool.json");
+    fs::write(
+        &coco_path,
+        serde_json::json!({"images": images, "annotations": []}).to_string(),
+    )
+    .unwrap();
+    coco_path
+}
+
+fn split_image_count(coco_path: &PathBuf, output_path: &PathBuf, percent: &str) -> usize {
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(coco_path)
+        .arg("-o")
+        .arg(output_path)
+        .arg("--percent")
+        .arg(percent)
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(output.status.success(), "cocosplit failed: {:?}", output);
+
+    let split_json = fs::read_to_string(output_path).unwrap();
+    let split_coco: serde_json::Value = serde_json::from_str(&split_json).unwrap();
+    split_coco["images"].as_array().unwrap().len()
+}
+
+#[test]
+fn test_cocosplit_percent() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_pool_coco_file(&temp_dir, 10);
+    let output_path = temp_dir.path().join("split.json");
+
+    assert_eq!(split_image_count(&coco_path, &output_path, "30"), 3);
+    assert_eq!(split_image_count(&coco_path, &output_path, "0"), 0);
+    assert_eq!(split_image_count(&coco_path, &output_path, "100"), 10);
+
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("--percent")
+        .arg("150")
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cocosplit_rest_partitions_pool() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_pool_coco_file(&temp_dir, 10);
+    let mut pool: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&coco_path).unwrap()).unwrap();
+    pool["annotations"] = (1..=10)
+        .map(|id| serde_json::json!({"id": id * 100, "image_id": id, "caption": "pool"}))
+        .collect();
+    fs::write(&coco_path, pool.to_string()).unwrap();
+
+    let blacklist_path = temp_dir.path().join("blacklist.json");
+    fs::write(
+        &blacklist_path,
+        serde_json::json!({
+            "images": [
+                {"id": 1, "width": 10, "height": 10, "file_name": "pool.jpg"},
+                {"id": 2, "width": 10, "height": 10, "file_name": "pool.jpg"}
+            ],
+            "annotations": []
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("split.json");
+    let rest_path = temp_dir.path().join("rest.json");
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("-b")
+        .arg(&blacklist_path)
+        .arg("--percent")
+        .arg("25")
+        .arg("--shuffle")
+        .arg("7")
+        .arg("--rest")
+        .arg(&rest_path)
+        .arg("--quiet")
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(output.status.success(), "cocosplit failed: {:?}", output);
+
+    let ids = |path: &PathBuf, key: &str, field: &str| -> Vec<i64> {
+        let coco: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        coco[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item[field].as_i64().unwrap())
+            .collect()
+    };
+    let split_ids = ids(&output_path, "images", "id");
+    let rest_ids = ids(&rest_path, "images", "id");
+    assert_eq!(split_ids.len(), 2);
+    assert_eq!(rest_ids.len(), 6);
+
+    let mut all_ids: Vec<i64> = split_ids.iter().chain(&rest_ids).copied().collect();
+    all_ids.sort();
+    assert_eq!(all_ids, (3..=10).collect::<Vec<i64>>());
+
+    // annotations follow their images
+    let mut rest_annotation_images = ids(&rest_path, "annotations", "image_id");
+    rest_annotation_images.sort();
+    let mut sorted_rest_ids = rest_ids.clone();
+    sorted_rest_ids.sort();
+    assert_eq!(rest_annotation_images, sorted_rest_ids);
+    assert_eq!(ids(&output_path, "annotations", "image_id").len(), 2);
+}
+
+/// Image ids of a 5 image split of a 50 image pool, and cocosplit's stderr
+fn shuffled_split(temp_dir: &TempDir, extra_args: &[&str]) -> (Vec<i64>, String) {
+    let coco_path = create_pool_coco_file(temp_dir, 50);
+    let output_path = temp_dir.path().join("split.json");
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("-c")
+        .arg("5")
+        .args(extra_args)
+        .arg("--quiet")
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(output.status.success(), "cocosplit failed: {:?}", output);
+
+    let split_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    let ids = split_coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["id"].as_i64().unwrap())
+        .collect();
+    (ids, String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+#[test]
+fn test_cocosplit_seed_is_reproducible() {
+    let temp_dir = TempDir::new().unwrap();
+    let (first, _) = shuffled_split(&temp_dir, &["--seed", "42"]);
+    let (second, _) = shuffled_split(&temp_dir, &["--seed", "42"]);
+    assert_eq!(first, second);
+    assert_ne!(first, (1..=5).collect::<Vec<i64>>(), "--seed shuffles");
+
+    // a bare --shuffle takes the seed, as does the old --shuffle <seed> form
+    assert_eq!(
+        shuffled_split(&temp_dir, &["--shuffle", "--seed", "42"]).0,
+        first
+    );
+    assert_eq!(shuffled_split(&temp_dir, &["--shuffle", "42"]).0, first);
+
+    // an unseeded run prints its seed even with --quiet, which reproduces it
+    let (random, stderr) = shuffled_split(&temp_dir, &["--shuffle"]);
+    let seed = stderr
+        .trim()
+        .strip_prefix("Shuffling with random seed ")
+        .expect("seed is printed");
+    assert_eq!(shuffled_split(&temp_dir, &["--seed", seed]).0, random);
+}
+
+fn split_ids_with_required_categories(extra_args: &[&str]) -> Vec<i64> {
+    let temp_dir = TempDir::new().unwrap();
+    create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
+
+    let annotation = |id: i64, image_id: i64, category_id: i32| {
+        serde_json::json!({
+            "id": id, "image_id": image_id, "category_id": category_id,
+            "segmentation": [], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0
+        })
+    };
+    let coco_json = serde_json::json!({
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "test1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "test1.jpg"},
+            {"id": 3, "width": 100, "height": 100, "file_name": "test1.jpg"}
+        ],
+        "annotations": [annotation(1, 1, 2), annotation(2, 2, 1), annotation(3, 2, 2)],
+        "categories": [
+            {"id": 1, "name": "dog", "supercategory": "animal"},
+            {"id": 2, "name": "cat", "supercategory": "animal"}
+        ]
+    });
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json.to_string()).unwrap();
+
+    let output_path = temp_dir.path().join("split.json");
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .args(extra_args)
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(output.status.success(), "cocosplit failed: {:?}", output);
+
+    let split_json = fs::read_to_string(&output_path).unwrap();
+    let split_coco: serde_json::Value = serde_json::from_str(&split_json).unwrap();
+    split_coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|img| img["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_cocosplit_require_category() {
+    assert_eq!(
+        split_ids_with_required_categories(&["--require-category", "dog"]),
+        vec![2]
+    );
+    assert_eq!(
+        split_ids_with_required_categories(&["--require-category", "2", "--by-id"]),
+        vec![1, 2]
+    );
+    assert_eq!(
+        split_ids_with_required_categories(&[
+            "--require-category",
+            "dog",
+            "--require-category",
+            "cat",
+            "--match-mode",
+            "all",
+        ]),
+        vec![2]
+    );
+}
+
+#[test]
+fn test_cocosplit_min_score() {
+    let temp_dir = TempDir::new().unwrap();
+    create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
+    create_dummy_image(&temp_dir.path().join("test2.jpg"), 100, 100);
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 100, "height": 100, "file_name": "test1.jpg"},
+            {"id": 2, "width": 100, "height": 100, "file_name": "test2.jpg"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [], "area": 1.0,
+             "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0, "score": 0.9},
+            {"id": 2, "image_id": 1, "category_id": 1, "segmentation": [], "area": 1.0,
+             "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0},
+            {"id": 3, "image_id": 2, "category_id": 1, "segmentation": [], "area": 1.0,
+             "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0, "score": 0.3}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let output_path = temp_dir.path().join("pseudo-labels.json");
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--min-score")
+        .arg("0.5")
+        .arg("--annotated-only")
+        .output()
+        .expect("Failed to execute cocosplit");
+    assert!(output.status.success());
+
+    let split_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    // image 2 loses its only annotation and with it its place in the split
+    let image_ids: Vec<i64> = split_coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| image["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(image_ids, vec![1]);
+    let mut annotation_ids: Vec<i64> = split_coco["annotations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|annotation| annotation["id"].as_i64().unwrap())
+        .collect();
+    annotation_ids.sort();
+    assert_eq!(annotation_ids, vec![1, 2]);
+}
+
+#[test]
+fn test_cocosplit_count_unit_annotations() {
+    let temp_dir = TempDir::new().unwrap();
+    create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
+
+    // image ids 1 to 5 with 3, 0, 2, 4 and 1 annotations
+    let annotation_counts = [3, 0, 2, 4, 1];
+    let images: Vec<serde_json::Value> = (1..=annotation_counts.len())
+        .map(|id| serde_json::json!({"id": id, "width": 100, "height": 100, "file_name": "test1.jpg"}))
+        .collect();
+    let annotations: Vec<serde_json::Value> = annotation_counts
+        .iter()
+        .enumerate()
+        .flat_map(|(index, &count)| std::iter::repeat_n(index + 1, count))
+        .enumerate()
+        .map(|(index, image_id)| {
+            serde_json::json!({
+                "id": index + 1, "image_id": image_id, "category_id": 1,
+                "segmentation": [], "area": 1.0, "bbox": [0.0, 0.0, 1.0, 1.0], "iscrowd": 0
+            })
+        })
+        .collect();
+    let coco_path = temp_dir.path().join("test.json");
+    fs::write(
+        &coco_path,
+        serde_json::json!({"images": images, "annotations": annotations}).to_string(),
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("split.json");
+    let split = |count: &str| -> (Vec<i64>, usize, String) {
+        let output = Command::new(get_binary_path("cocosplit"))
+            .arg(&coco_path)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--count")
+            .arg(count)
+            .arg("--count-unit")
+            .arg("annotations")
+            .output()
+            .expect("Failed to execute cocosplit");
+        assert!(output.status.success(), "cocosplit failed: {:?}", output);
+
+        let split_coco: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let image_ids = split_coco["images"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|image| image["id"].as_i64().unwrap())
+            .collect();
+        (
+            image_ids,
+            split_coco["annotations"].as_array().unwrap().len(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )
+    };
+
+    // 3 + 0 + 2 lands exactly on the target
+    let (image_ids, annotation_count, stderr) = split("5");
+    assert_eq!(image_ids, vec![1, 2, 3]);
+    assert_eq!(annotation_count, 5);
+    assert!(stderr.contains("Split has 5 annotation(s) on 3 image(s)"));
+
+    // the image that crosses the target is kept
+    let (image_ids, annotation_count, stderr) = split("6");
+    assert_eq!(image_ids, vec![1, 2, 3, 4]);
+    assert_eq!(annotation_count, 9);
+    assert!(stderr.contains("Split has 9 annotation(s) on 4 image(s)"));
+
+    // asking for more than there are takes everything and warns
+    let (image_ids, annotation_count, stderr) = split("20");
+    assert_eq!(image_ids.len(), 5);
+    assert_eq!(annotation_count, 10);
+    assert!(stderr.contains("requested 20 annotations but only 10 are available"));
+}
+
+#[test]
+fn test_cocosplit_lvis() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_lvis_coco_file(&temp_dir);
+    let output_path = temp_dir.path().join("split.json");
+    let rest_path = temp_dir.path().join("rest.json");
+
+    let split = |extra_args: &[&str]| -> serde_json::Value {
+        let output = Command::new(get_binary_path("cocosplit"))
+            .arg(&coco_path)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--annotated-only")
+            .args(extra_args)
+            .output()
+            .expect("Failed to execute cocosplit");
+        assert!(output.status.success(), "cocosplit failed: {:?}", output);
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap()
+    };
+    let image_ids = |coco: &serde_json::Value| -> Vec<i64> {
+        coco["images"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|image| image["id"].as_i64().unwrap())
+            .collect()
+    };
+
+    // image 2 has no annotations, only a verified negative category
+    assert_eq!(image_ids(&split(&[])), vec![1]);
+    let lvis_split = split(&[
+        "--lvis",
+        "--count",
+        "1",
+        "--rest",
+        rest_path.to_str().unwrap(),
+    ]);
+    assert_eq!(image_ids(&split(&["--lvis"])), vec![1, 2]);
+
+    let image = &lvis_split["images"][0];
+    assert_eq!(image["neg_category_ids"], serde_json::json!([3]));
+    assert_eq!(image["not_exhaustive_category_ids"], serde_json::json!([]));
+    assert_eq!(image["file_name"], "");
+
+    let counts = |coco: &serde_json::Value| -> Vec<(u64, u64)> {
+        coco["categories"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|category| {
+                (
+                    category["image_count"].as_u64().unwrap(),
+                    category["instance_count"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(counts(&lvis_split), vec![(1, 2), (1, 1), (0, 0)]);
+    assert_eq!(lvis_split["categories"][0]["frequency"], "r");
+
+    let rest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&rest_path).unwrap()).unwrap();
+    assert_eq!(image_ids(&rest), vec![2, 3]);
+    assert_eq!(
+        rest["images"][0]["neg_category_ids"],
+        serde_json::json!([2])
+    );
+    assert_eq!(counts(&rest), vec![(0, 0), (0, 0), (0, 0)]);
+}
+
+#[test]
+fn test_cocosplit_quiet_has_no_progress_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_file(&temp_dir, "test.json");
+
+    let output_path = temp_dir.path().join("split.json");
+    let output = Command::new(get_binary_path("cocosplit"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--annotated-only")
+        .arg("--quiet")
+        .output()
+        .expect("Failed to execute cocosplit");
+
+    assert!(output.status.success());
+    assert!(
+        output.stderr.is_empty(),
+        "unexpected stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output_path.exists());
+}
+
 // ========== COCOMERGE TESTS ==========
 
 #[test]
@@ -382,6 +1316,35 @@ fn test_cocomerge_with_reassign_ids() {
     assert_eq!(merged_coco["images"].as_array().unwrap().len(), 4);
 }
 
+#[test]
+fn test_cocomerge_summary() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = create_test_coco_file(&temp_dir, "coco2.json");
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco1_path)
+        .arg(&coco2_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--reassign-clashing-ids")
+        .output()
+        .expect("Failed to execute cocomerge");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Merged 2 file(s)"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert!(stderr.contains("Images: 2 kept, 2 reassigned, 0 dropped"));
+    assert!(stderr.contains("Annotations: 4 kept, 0 dropped"));
+    assert!(stderr.contains("Categories: 1 unified, 1 in total"));
+    assert!(stderr.contains("Licenses: 1 unified, 1 in total"));
+}
+
 #[test]
 fn test_cocomerge_deduplicates_categories() {
     let temp_dir = TempDir::new().unwrap();
@@ -426,6 +1389,58 @@ fn test_cocomerge_deduplicates_licenses() {
     assert_eq!(merged_coco["licenses"].as_array().unwrap().len(), 1);
 }
 
+#[test]
+fn test_cocomerge_unifies_categories_and_licenses_with_different_ids() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = create_test_coco_file(&temp_dir, "coco2.json");
+    // the same category and license under other ids in the second file
+    let coco2_json = fs::read_to_string(&coco2_path)
+        .unwrap()
+        .replace("\"category_id\": 1", "\"category_id\": 7")
+        .replace(
+            "\"id\": 1,\n                \"name\": \"person\"",
+            "\"id\": 7, \"name\": \"person\"",
+        )
+        .replace("\"license\": 0", "\"license\": 3")
+        .replace(
+            "\"id\": 0,\n                \"name\": \"Test License\"",
+            "\"id\": 3, \"name\": \"Test License\"",
+        );
+    assert!(coco2_json.contains("\"id\": 7, \"name\": \"person\""));
+    assert!(coco2_json.contains("\"id\": 3, \"name\": \"Test License\""));
+    fs::write(&coco2_path, coco2_json).unwrap();
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco1_path)
+        .arg(&coco2_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("-r")
+        .output()
+        .expect("Failed to execute cocomerge");
+    assert!(output.status.success());
+
+    let merged_json = fs::read_to_string(&output_path).unwrap();
+    let merged_coco: serde_json::Value = serde_json::from_str(&merged_json).unwrap();
+
+    // categories and licenses are matched on everything but their id, so the second file's
+    // annotations and images point at the first file's ids
+    let categories = merged_coco["categories"].as_array().unwrap();
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0]["id"], 1);
+    for annotation in merged_coco["annotations"].as_array().unwrap() {
+        assert_eq!(annotation["category_id"], 1);
+    }
+    let licenses = merged_coco["licenses"].as_array().unwrap();
+    assert_eq!(licenses.len(), 1);
+    assert_eq!(licenses[0]["id"], 0);
+    for image in merged_coco["images"].as_array().unwrap() {
+        assert_eq!(image["license"], 0);
+    }
+}
+
 #[test]
 fn test_cocomerge_single_file() {
     let temp_dir = TempDir::new().unwrap();
@@ -449,6 +1464,316 @@ fn test_cocomerge_single_file() {
     assert_eq!(merged_coco["annotations"].as_array().unwrap().len(), 2);
 }
 
+#[test]
+fn test_cocomerge_keep_info_concat() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = temp_dir.path().join("coco2.json");
+
+    let mut coco2: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&coco1_path).unwrap()).unwrap();
+    coco2["info"]["year"] = serde_json::json!(2018);
+    coco2["info"]["description"] = serde_json::json!("Second Dataset");
+    fs::write(&coco2_path, serde_json::to_string(&coco2).unwrap()).unwrap();
+
+    let output_path = temp_dir.path().join("merged.json");
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco1_path)
+        .arg(&coco2_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--keep-info")
+        .arg("concat")
+        .output()
+        .expect("Failed to execute cocomerge");
+
+    assert!(output.status.success());
+
+    let merged_json = fs::read_to_string(&output_path).unwrap();
+    let merged_coco: serde_json::Value = serde_json::from_str(&merged_json).unwrap();
+
+    assert_eq!(
+        merged_coco["info"]["description"],
+        "Test Dataset; Second Dataset"
+    );
+    // identical values are only kept once
+    assert_eq!(merged_coco["info"]["contributor"], "Test");
+    assert_eq!(merged_coco["info"]["year"], 2018);
+    assert_eq!(merged_coco["info"]["version"], "1.0.0");
+}
+
+#[test]
+fn test_cocomerge_prefix_by_source() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = create_test_coco_file(&temp_dir, "coco2.json");
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco1_path)
+        .arg(&coco2_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--reassign-clashing-ids")
+        .arg("--prefix-by-source")
+        .output()
+        .expect("Failed to execute cocomerge");
+
+    assert!(output.status.success());
+
+    let merged_json = fs::read_to_string(&output_path).unwrap();
+    let merged_coco: serde_json::Value = serde_json::from_str(&merged_json).unwrap();
+
+    let file_names: Vec<&str> = merged_coco["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|img| img["file_name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        file_names,
+        vec![
+            "coco1/test1.jpg",
+            "coco1/test2.jpg",
+            "coco2/test1.jpg",
+            "coco2/test2.jpg"
+        ]
+    );
+}
+
+#[test]
+fn test_cocomerge_deterministic_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = temp_dir.path().join("coco2.json");
+
+    // distinct categories and licenses with clashing ids so both sets hold several entries
+    let mut coco2: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&coco1_path).unwrap()).unwrap();
+    coco2["categories"] = serde_json::json!([
+        {"id": 1, "name": "dog", "supercategory": "animal"},
+        {"id": 2, "name": "cat", "supercategory": "animal"},
+        {"id": 3, "name": "bird", "supercategory": "animal"}
+    ]);
+    coco2["licenses"] = serde_json::json!([
+        {"id": 0, "name": "Other License", "url": ""},
+        {"id": 1, "name": "Third License", "url": ""}
+    ]);
+    fs::write(&coco2_path, serde_json::to_string(&coco2).unwrap()).unwrap();
+
+    let merge = |output_name: &str| {
+        let output_path = temp_dir.path().join(output_name);
+        let output = Command::new(get_binary_path("cocomerge"))
+            .arg(&coco1_path)
+            .arg(&coco2_path)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--reassign-clashing-ids")
+            .env("SOURCE_DATE_EPOCH", "1700000000")
+            .output()
+            .expect("Failed to execute cocomerge");
+        assert!(output.status.success());
+        // compared as strings so a failure shows readable JSON, equal strings are equal bytes
+        fs::read_to_string(&output_path).unwrap()
+    };
+
+    let first = merge("merged1.json");
+    assert_eq!(first, merge("merged2.json"));
+
+    let merged_coco: serde_json::Value = serde_json::from_str(&first).unwrap();
+    let ids = |key: &str| -> Vec<i64> {
+        merged_coco[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect()
+    };
+    assert_eq!(ids("categories"), vec![1, 2, 3, 4]);
+    assert_eq!(ids("licenses"), vec![0, 1, 2]);
+    assert_eq!(ids("images"), vec![1, 2, 3, 4]);
+    assert_eq!(ids("annotations"), vec![1, 2, 3, 4]);
+    assert_eq!(merged_coco["info"]["date_created"], "2023-11-14T22:13:20Z");
+}
+
+#[test]
+fn test_cocomerge_on_clash() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco1_path = create_test_coco_file(&temp_dir, "coco1.json");
+    let coco2_path = create_test_coco_file(&temp_dir, "coco2.json");
+    let output_path = temp_dir.path().join("merged.json");
+
+    let merge = |on_clash: &str| {
+        Command::new(get_binary_path("cocomerge"))
+            .arg(&coco1_path)
+            .arg(&coco2_path)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--on-clash")
+            .arg(on_clash)
+            .arg("--quiet")
+            .output()
+            .expect("Failed to execute cocomerge")
+    };
+
+    let output = merge("error");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("clashes"));
+    assert!(!output_path.exists());
+
+    let output = merge("ignore");
+    assert!(output.status.success());
+    // the summary is printed even with --quiet
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Dropped 2 image(s)"));
+
+    let output = merge("reassign");
+    assert!(output.status.success());
+    let merged_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(merged_coco["images"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_cocomerge_recompute_area() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_file(&temp_dir, "coco.json");
+    let coco_json = fs::read_to_string(&coco_path).unwrap();
+    let zeroed = coco_json.replace("\"area\": 50.0", "\"area\": 0.0");
+    fs::write(&coco_path, zeroed).unwrap();
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--recompute-area")
+        .output()
+        .expect("Failed to execute cocomerge");
+    assert!(output.status.success());
+
+    // the areas were zeroed, the segmentations are right triangles with 10px legs
+    let merged_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    for annotation in merged_coco["annotations"].as_array().unwrap() {
+        assert_eq!(annotation["area"], 50.0);
+    }
+}
+
+#[test]
+fn test_cocomerge_simplify_polygons() {
+    let temp_dir = TempDir::new().unwrap();
+    let coco_path = create_test_coco_file(&temp_dir, "coco.json");
+    let coco_json = fs::read_to_string(&coco_path).unwrap();
+    // the same triangle with a point in the middle of each edge
+    let dense = coco_json.replace(
+        "[[10.0, 10.0, 20.0, 10.0, 20.0, 20.0]]",
+        "[[10.0, 10.0, 15.0, 10.0, 20.0, 10.0, 20.0, 15.0, 20.0, 20.0, 15.0, 15.0]]",
+    );
+    fs::write(&coco_path, dense).unwrap();
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--simplify-polygons")
+        .arg("1.5")
+        .output()
+        .expect("Failed to execute cocomerge");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Polygon points removed: 3"));
+
+    let merged_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(
+        merged_coco["annotations"][0]["segmentation"],
+        serde_json::json!([[10.0, 10.0, 20.0, 10.0, 20.0, 20.0]])
+    );
+}
+
+#[test]
+fn test_cocomerge_id_space() {
+    let temp_dir = TempDir::new().unwrap();
+    create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
+    let coco_json = r#"{
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "test1.jpg"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0, 0, 10, 10], "iscrowd": 0},
+            {"id": 1, "image_id": 1, "caption": "a red square"}
+        ],
+        "categories": [{"id": 1, "name": "square", "supercategory": "shape"}]
+    }"#;
+    let coco_path = temp_dir.path().join("coco.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_path = temp_dir.path().join("merged.json");
+
+    let merged_ids = |id_space: &str| -> Vec<(bool, i64)> {
+        let output = Command::new(get_binary_path("cocomerge"))
+            .arg(&coco_path)
+            .arg("-o")
+            .arg(&output_path)
+            .arg("--id-space")
+            .arg(id_space)
+            .output()
+            .expect("Failed to execute cocomerge");
+        assert!(output.status.success());
+        let merged_coco: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        merged_coco["annotations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|annotation| {
+                (
+                    annotation.get("caption").is_some(),
+                    annotation["id"].as_i64().unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    // the caption clashes with the box in a single id space
+    assert_eq!(merged_ids("global"), vec![(false, 1), (true, 2)]);
+    assert_eq!(merged_ids("per-type"), vec![(false, 1), (true, 1)]);
+}
+
+#[test]
+fn test_cocomerge_keeps_extra_fields() {
+    let temp_dir = TempDir::new().unwrap();
+    create_dummy_image(&temp_dir.path().join("test1.jpg"), 100, 100);
+    let coco_json = r#"{
+        "info": {"year": 2024, "version": "1", "description": "", "contributor": "",
+                 "url": "", "date_created": "2024-01-01T00:00:00Z", "split": "train"},
+        "images": [{"id": 1, "width": 100, "height": 100, "file_name": "test1.jpg",
+                    "sensor": "cam0"}],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 1, "segmentation": [],
+             "area": 100.0, "bbox": [0, 0, 10, 10], "iscrowd": 0, "track_id": 12}
+        ],
+        "categories": [{"id": 1, "name": "square", "supercategory": "shape"}]
+    }"#;
+    let coco_path = temp_dir.path().join("coco.json");
+    fs::write(&coco_path, coco_json).unwrap();
+    let output_path = temp_dir.path().join("merged.json");
+
+    let output = Command::new(get_binary_path("cocomerge"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--keep-info")
+        .arg("first")
+        .output()
+        .expect("Failed to execute cocomerge");
+    assert!(output.status.success());
+
+    let merged_coco: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+    assert_eq!(merged_coco["info"]["split"], "train");
+    assert_eq!(merged_coco["images"][0]["sensor"], "cam0");
+    assert_eq!(merged_coco["annotations"][0]["track_id"], 12);
+}
+
 // ========== ERROR HANDLING TESTS ==========
 
 #[test]
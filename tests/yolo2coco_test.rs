@@ -0,0 +1,164 @@
+// Integration tests for yolo2coco binary
+use image::{ImageBuffer, Rgb};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+fn get_binary_path(name: &str) -> PathBuf {
+    let mut path = std::env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push(name);
+    path
+}
+
+fn write_image(path: &Path, width: u32, height: u32) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    ImageBuffer::from_fn(width, height, |_, _| Rgb([0u8, 128u8, 255u8]))
+        .save(path)
+        .unwrap();
+}
+
+fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn test_yolo2coco_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    write_image(&temp_dir.path().join("src/a.png"), 64, 48);
+    write_image(&temp_dir.path().join("src/b.png"), 30, 30);
+    let coco_json = r#"{
+        "images": [
+            {"id": 1, "width": 64, "height": 48, "file_name": "src/a.png"},
+            {"id": 2, "width": 30, "height": 30, "file_name": "src/b.png"}
+        ],
+        "annotations": [
+            {"id": 1, "image_id": 1, "category_id": 7, "segmentation": [],
+             "area": 300.0, "bbox": [3.0, 5.5, 20.0, 15.0], "iscrowd": 0},
+            {"id": 2, "image_id": 1, "category_id": 2, "segmentation": [],
+             "area": 64.0, "bbox": [40.0, 30.0, 8.0, 8.0], "iscrowd": 0}
+        ],
+        "categories": [
+            {"id": 2, "name": "cat", "supercategory": "animal"},
+            {"id": 7, "name": "traffic light", "supercategory": "outdoor"}
+        ]
+    }"#;
+    let coco_path = temp_dir.path().join("dataset.json");
+    fs::write(&coco_path, coco_json).unwrap();
+
+    let yolo_dir = temp_dir.path().join("yolo");
+    let output = Command::new(get_binary_path("coco2yolo"))
+        .arg(&coco_path)
+        .arg("-o")
+        .arg(&yolo_dir)
+        .arg("--copy-images")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let round_trip_path = temp_dir.path().join("round_trip.json");
+    let output = Command::new(get_binary_path("yolo2coco"))
+        .arg(&yolo_dir)
+        .arg("-o")
+        .arg(&round_trip_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let coco = read_json(&round_trip_path);
+    let images = coco["images"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0]["file_name"], "yolo/images/a.png");
+    assert_eq!(images[0]["width"], 64);
+    assert_eq!(images[1]["file_name"], "yolo/images/b.png");
+
+    // classes keep the order of the category ids, under new contiguous ids
+    let names: Vec<&str> = coco["categories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|category| category["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["cat", "traffic light"]);
+
+    let annotations = coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 2);
+    for (annotation, (category_id, bbox)) in annotations
+        .iter()
+        .zip([(2, [3.0, 5.5, 20.0, 15.0]), (1, [40.0, 30.0, 8.0, 8.0])])
+    {
+        assert_eq!(annotation["image_id"], images[0]["id"]);
+        assert_eq!(annotation["category_id"], category_id);
+        for (value, expected) in annotation["bbox"].as_array().unwrap().iter().zip(bbox) {
+            assert!(
+                (value.as_f64().unwrap() - expected).abs() < 0.01,
+                "{}",
+                annotation
+            );
+        }
+        let area = annotation["area"].as_f64().unwrap();
+        assert!((area - bbox[2] * bbox[3]).abs() < 0.1, "{}", annotation);
+        assert_eq!(annotation["segmentation"][0].as_array().unwrap().len(), 8);
+    }
+}
+
+#[test]
+fn test_yolo2coco_segmentation_and_names() {
+    let temp_dir = TempDir::new().unwrap();
+    let yolo_dir = temp_dir.path().join("yolo");
+    write_image(&yolo_dir.join("images/train/a.png"), 100, 50);
+    write_image(&yolo_dir.join("images/train/unlabeled.png"), 10, 10);
+    fs::create_dir_all(yolo_dir.join("labels/train")).unwrap();
+    fs::write(
+        yolo_dir.join("labels/train/a.txt"),
+        "1 0.1 0.2 0.5 0.2 0.5 0.6\n0 0.5 0.5 0.2 0.4\n\n",
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("coco.json");
+    let output = Command::new(get_binary_path("yolo2coco"))
+        .arg(&yolo_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--names")
+        .arg("car,person")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let coco = read_json(&output_path);
+    assert_eq!(coco["images"].as_array().unwrap().len(), 2);
+    assert_eq!(coco["categories"][1]["name"], "person");
+    let annotations = coco["annotations"].as_array().unwrap();
+    assert_eq!(annotations.len(), 2);
+    assert_eq!(annotations[0]["category_id"], 2);
+    let assert_close = |value: &serde_json::Value, expected: &[f64]| {
+        let values = value.as_array().unwrap();
+        assert_eq!(values.len(), expected.len(), "{}", value);
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value.as_f64().unwrap() - expected).abs() < 1e-3,
+                "{}",
+                value
+            );
+        }
+    };
+    assert_close(
+        &annotations[0]["segmentation"][0],
+        &[10.0, 10.0, 50.0, 10.0, 50.0, 30.0],
+    );
+    assert_close(&annotations[0]["bbox"], &[10.0, 10.0, 40.0, 20.0]);
+    assert!((annotations[0]["area"].as_f64().unwrap() - 400.0).abs() < 1e-2);
+
+    // no class names anywhere
+    let output = Command::new(get_binary_path("yolo2coco"))
+        .arg(&yolo_dir)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No class names"));
+}